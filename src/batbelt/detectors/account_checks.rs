@@ -0,0 +1,400 @@
+//! Context accounts of Solana entry points that nothing validates.
+//!
+//! Works on the `context_accounts_info` of the sonar: Anchor `UncheckedAccount`/`AccountInfo`
+//! fields without justification nor constraint, authority-like accounts that don't sign, and
+//! Pinocchio or native accounts without any check. Manual validations in the entry point
+//! function and its dependencies silence a hit.
+
+use crate::batbelt::metadata::functions_source_code_metadata::FunctionSourceCodeMetadata;
+use crate::batbelt::metadata::structs_source_code_metadata::StructSourceCodeMetadata;
+use crate::batbelt::metadata::{BatMetadata, BatMetadataParser};
+use crate::batbelt::parser::context_accounts_parser::CAAccountParser;
+use crate::batbelt::parser::entrypoint_parser::EntrypointParser;
+use crate::batbelt::parser::solana_account_parser::SolanaAccountType;
+use crate::config::ProjectType;
+
+use super::declaration_offset;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Name segments of the accounts expected to sign, e.g. `pool_authority` or `admin`.
+const AUTHORITY_NAMES: &[&str] = &["authority", "admin", "owner"];
+
+/// Wrappers the runtime loads without checking the signature.
+const NOT_SIGNER_WRAPPERS: &[&str] = &[
+    "UncheckedAccount",
+    "AccountInfo",
+    "AccountView",
+    "SystemAccount",
+];
+
+/// Tokens making a statement reading `account.key`-like fields a validation.
+const VALIDATION_TOKENS: &[&str] = &["==", "!=", "require", "assert", "check_id", "cmp"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, strum_macros::Display)]
+pub enum AccountCheckKind {
+    /// `UncheckedAccount`/`AccountInfo` without `/// CHECK`, constraint or manual validation
+    UncheckedAccount,
+    /// `authority`/`admin`/`owner` account that is not a `Signer` nor bound by `has_one`
+    AuthorityNotSigner,
+    /// Pinocchio or native account without any check
+    NoChecks,
+}
+
+impl AccountCheckKind {
+    /// Why a hit deserves a look, used as the finding candidate note.
+    pub fn description(&self) -> &'static str {
+        match self {
+            AccountCheckKind::UncheckedAccount => {
+                "unchecked account without CHECK justification, owner, address or seeds constraint"
+            }
+            AccountCheckKind::AuthorityNotSigner => {
+                "authority-like account neither required to sign nor validated with has_one"
+            }
+            AccountCheckKind::NoChecks => "account read without any signer, owner or key check",
+        }
+    }
+}
+
+/// A context account flagged for one entry point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountCheckHit {
+    pub kind: AccountCheckKind,
+    pub entry_point: String,
+    pub program_name: String,
+    pub account_name: String,
+    /// File and 1-based line of the account declaration
+    pub file_path: String,
+    pub line: usize,
+}
+
+impl AccountCheckHit {
+    /// `- [ ] ...` line of the hit, in the format of the finding candidates template.
+    pub fn candidate_line(&self) -> String {
+        format!(
+            "- [ ] [{:?}: `{}`]({}#L{}) in `{}`, {}",
+            self.kind,
+            self.account_name,
+            self.file_path,
+            self.line,
+            self.entry_point,
+            self.kind.description()
+        )
+    }
+}
+
+/// Flags the context accounts of every entry point of the metadata.
+pub fn detect_account_checks(
+    bat_metadata: &BatMetadata,
+    project_type: &ProjectType,
+) -> Vec<AccountCheckHit> {
+    bat_metadata
+        .entry_points
+        .iter()
+        .flat_map(|entry_point| {
+            let context_accounts = match bat_metadata
+                .source_code
+                .get_struct_by_id(entry_point.context_accounts_id.clone())
+            {
+                Ok(context_accounts) => context_accounts,
+                Err(_) => return vec![],
+            };
+            let mut functions = vec![];
+            if let Ok(entry_point_function) = bat_metadata
+                .source_code
+                .get_function_by_id(entry_point.entrypoint_function_id.clone())
+            {
+                functions.push(entry_point_function);
+                functions.extend(EntrypointParser::resolve_all_dependencies(
+                    &entry_point.entrypoint_function_id,
+                    bat_metadata,
+                ));
+            }
+            detect_entry_point_account_checks(
+                bat_metadata,
+                &entry_point.name,
+                &context_accounts,
+                &functions,
+                project_type,
+            )
+        })
+        .collect()
+}
+
+/// Flags the context accounts of one entry point, `functions` being the entry point function
+/// and its dependencies.
+pub fn detect_entry_point_account_checks(
+    bat_metadata: &BatMetadata,
+    entry_point_name: &str,
+    context_accounts: &StructSourceCodeMetadata,
+    functions: &[FunctionSourceCodeMetadata],
+    project_type: &ProjectType,
+) -> Vec<AccountCheckHit> {
+    let accounts = match bat_metadata
+        .get_context_accounts_metadata_by_struct_source_code_metadata_id(
+            context_accounts.metadata_id.clone(),
+        ) {
+        Ok(context_accounts_metadata) => context_accounts_metadata.context_accounts_info,
+        Err(_) => return vec![],
+    };
+    let handler_sources = functions
+        .iter()
+        .map(|function| {
+            function
+                .to_source_code_parser(None)
+                .get_source_code_content()
+        })
+        .collect::<Vec<_>>();
+    let struct_content = context_accounts
+        .to_source_code_parser(None)
+        .get_source_code_content();
+    missing_account_checks(
+        &accounts,
+        &handler_sources,
+        *project_type == ProjectType::Anchor,
+    )
+    .into_iter()
+    .map(|(account_name, kind)| AccountCheckHit {
+        line: context_accounts.start_line_index
+            + declaration_offset(&struct_content, &account_name),
+        kind,
+        entry_point: entry_point_name.to_string(),
+        program_name: context_accounts.program_name.clone(),
+        account_name,
+        file_path: context_accounts.path.clone(),
+    })
+    .collect()
+}
+
+/// Accounts of a context accounts struct missing their checks, with the reason.
+///
+/// `handler_sources` are searched for manual validations, `anchor` selects between the
+/// Anchor constraints and the Pinocchio/native check heuristics.
+pub fn missing_account_checks(
+    accounts: &[CAAccountParser],
+    handler_sources: &[String],
+    anchor: bool,
+) -> Vec<(String, AccountCheckKind)> {
+    let mut result = vec![];
+    for account in accounts {
+        let name = &account.account_name;
+        let validated_in_handler = is_validated_in_handler(name, handler_sources);
+        let bound_by_other_account = is_bound_by_other_account(name, accounts);
+        if anchor {
+            let unchecked = account.solana_account_type == SolanaAccountType::UncheckedAccount
+                || ["UncheckedAccount", "AccountInfo"]
+                    .contains(&account.account_wrapper_name.as_str());
+            let constrained = account.owner.is_some()
+                || account.is_pda
+                || !account.seeds.is_empty()
+                || account.validations.iter().any(|validation| {
+                    validation.starts_with("address")
+                        || validation.starts_with("owner")
+                        || validation.starts_with("constraint")
+                });
+            if unchecked
+                && !has_check_justification(account)
+                && !constrained
+                && !bound_by_other_account
+                && !validated_in_handler
+            {
+                result.push((name.clone(), AccountCheckKind::UncheckedAccount));
+            }
+        } else if account.validations.is_empty() && !validated_in_handler {
+            result.push((name.clone(), AccountCheckKind::NoChecks));
+        }
+
+        let is_signer = account.solana_account_type == SolanaAccountType::Signer
+            || account.account_wrapper_name == "Signer"
+            || account
+                .validations
+                .iter()
+                .any(|validation| validation.to_lowercase().contains("signer"))
+            || handler_sources
+                .iter()
+                .any(|source| field_access_regex(name, "is_signer").is_match(source));
+        if is_authority_name(name)
+            && NOT_SIGNER_WRAPPERS.contains(&account.account_wrapper_name.as_str())
+            && !is_signer
+            && !account.is_pda
+            && !bound_by_other_account
+        {
+            result.push((name.clone(), AccountCheckKind::AuthorityNotSigner));
+        }
+    }
+    result
+}
+
+fn is_authority_name(account_name: &str) -> bool {
+    account_name
+        .to_lowercase()
+        .split('_')
+        .any(|segment| AUTHORITY_NAMES.contains(&segment))
+}
+
+/// `name . field`, with the spacing of both the source and the token stream.
+fn field_access_regex(account_name: &str, field: &str) -> Regex {
+    Regex::new(&format!(
+        r"\b{}\s*\.\s*{}\b",
+        regex::escape(account_name),
+        field
+    ))
+    .unwrap()
+}
+
+/// true if the field of `account` carries a `/// CHECK` doc comment.
+fn has_check_justification(account: &CAAccountParser) -> bool {
+    match syn::parse_str::<syn::ItemStruct>(&account.content) {
+        Ok(item_struct) => item_struct
+            .fields
+            .iter()
+            .filter(|field| {
+                field
+                    .ident
+                    .as_ref()
+                    .map(|ident| *ident == account.account_name)
+                    .unwrap_or(false)
+            })
+            .any(|field| {
+                field.attrs.iter().any(|attr| {
+                    attr.path().is_ident("doc")
+                        && quote::ToTokens::to_token_stream(attr)
+                            .to_string()
+                            .contains("CHECK")
+                })
+            }),
+        // Regex fallback, content is the field itself
+        Err(_) => account.content.contains("CHECK"),
+    }
+}
+
+/// true if another account binds `account_name` with `has_one` or a `constraint`.
+fn is_bound_by_other_account(account_name: &str, accounts: &[CAAccountParser]) -> bool {
+    let key_access = field_access_regex(account_name, "key");
+    accounts
+        .iter()
+        .filter(|account| account.account_name != account_name)
+        .flat_map(|account| account.validations.iter())
+        .any(|validation| {
+            let validation = validation.split('@').next().unwrap_or_default().trim();
+            validation == format!("has_one = {}", account_name)
+                || (validation.starts_with("constraint") && key_access.is_match(validation))
+        })
+}
+
+/// true if a statement of the handler compares the key, owner or signer flag of the account.
+fn is_validated_in_handler(account_name: &str, handler_sources: &[String]) -> bool {
+    let access = Regex::new(&format!(
+        r"\b{}\s*\.\s*(key|owner|address|is_signer)\b",
+        regex::escape(account_name)
+    ))
+    .unwrap();
+    handler_sources
+        .iter()
+        .flat_map(|source| source.split(';'))
+        .any(|statement| {
+            access.is_match(statement)
+                && VALIDATION_TOKENS
+                    .iter()
+                    .any(|token| statement.contains(token))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::parser::pinocchio_context_accounts_parser::parse_pinocchio_context_accounts_from_source;
+    use crate::batbelt::parser::syn_context_accounts_parser::parse_context_accounts_from_source;
+
+    #[test]
+    fn test_missing_anchor_account_checks() {
+        let source = r#"
+            #[derive(Accounts)]
+            pub struct Withdraw<'info> {
+                #[account(mut, has_one = admin)]
+                pub pool: Account<'info, Pool>,
+                pub admin: UncheckedAccount<'info>,
+                pub authority: AccountInfo<'info>,
+                /// CHECK: only receives lamports
+                #[account(mut)]
+                pub receiver: UncheckedAccount<'info>,
+                #[account(mut)]
+                pub destination: UncheckedAccount<'info>,
+                pub oracle: UncheckedAccount<'info>,
+                #[account(address = pool.fee_receiver)]
+                pub fee_receiver: AccountInfo<'info>,
+                pub signer: Signer<'info>,
+            }
+        "#;
+        let parsed = parse_context_accounts_from_source(source).unwrap();
+        let accounts = parsed[0]
+            .accounts
+            .iter()
+            .map(|account| {
+                account.to_ca_account_parser(account.determine_solana_account_type(&[]), source)
+            })
+            .collect::<Vec<_>>();
+        let handler = "pub fn handler(ctx: Context<Withdraw>) -> Result<()> {\n    \
+            require_keys_eq!(ctx.accounts.oracle.key(), ORACLE_ID);\n    Ok(())\n}"
+            .to_string();
+
+        assert_eq!(
+            missing_account_checks(&accounts, &[handler], true),
+            vec![
+                ("authority".to_string(), AccountCheckKind::UncheckedAccount),
+                (
+                    "authority".to_string(),
+                    AccountCheckKind::AuthorityNotSigner
+                ),
+                (
+                    "destination".to_string(),
+                    AccountCheckKind::UncheckedAccount
+                ),
+            ]
+        );
+        assert_eq!(declaration_offset(source, "destination"), 11);
+    }
+
+    #[test]
+    fn test_missing_pinocchio_account_checks() {
+        let source = r#"
+            pub struct UpdateAccounts<'a> {
+                pub owner: &'a AccountView,
+                pub config: &'a AccountView,
+                pub vault: &'a AccountView,
+            }
+
+            impl<'a> TryFrom<&'a [AccountView]> for UpdateAccounts<'a> {
+                type Error = ProgramError;
+                fn try_from(accounts: &'a [AccountView]) -> Result<Self, Self::Error> {
+                    let [owner, config, vault] = accounts else {
+                        return Err(ProgramError::NotEnoughAccountKeys);
+                    };
+                    ProgramAccount::check(config)?;
+                    Ok(Self { owner, config, vault })
+                }
+            }
+        "#;
+        let parsed = parse_pinocchio_context_accounts_from_source(source).unwrap();
+        let accounts = parsed[0]
+            .accounts
+            .iter()
+            .map(|account| {
+                account.to_pinocchio_ca_account_parser(
+                    account.determine_pinocchio_solana_account_type(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let handler = "if vault.owner() != &crate::ID { return Err(ProgramError::IllegalOwner); }"
+            .to_string();
+
+        assert_eq!(
+            missing_account_checks(&accounts, &[handler], false),
+            vec![
+                ("owner".to_string(), AccountCheckKind::NoChecks),
+                ("owner".to_string(), AccountCheckKind::AuthorityNotSigner),
+            ]
+        );
+    }
+}
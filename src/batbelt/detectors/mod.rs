@@ -0,0 +1,39 @@
+pub mod account_checks;
+pub mod pda_seeds;
+
+use error_stack::{Result, ResultExt};
+use regex::Regex;
+
+use crate::batbelt::sonar::BatSonarError;
+use crate::batbelt::templates::notes_template::NoteTemplate;
+
+use self::account_checks::AccountCheckHit;
+use self::pda_seeds::PdaSeedIssue;
+
+/// Add the Solana hits and PDA seed issues to the auditor's `finding_candidates.md`, if the
+/// notes are initialized.
+pub fn save_finding_candidates(
+    hits: &[AccountCheckHit],
+    pda_issues: &[PdaSeedIssue],
+) -> Result<(), BatSonarError> {
+    let lines = hits
+        .iter()
+        .map(AccountCheckHit::candidate_line)
+        .chain(pda_issues.iter().map(PdaSeedIssue::candidate_line))
+        .collect::<Vec<_>>();
+    NoteTemplate::save_generated_finding_candidates(&lines).change_context(BatSonarError)
+}
+
+/// Offset from the first line of the struct to the declaration of `account_name`, either a
+/// field or a `next_account_info` binding.
+pub(crate) fn declaration_offset(struct_content: &str, account_name: &str) -> usize {
+    let declaration = Regex::new(&format!(
+        r"(\b{name}\s*:[^:]|\blet\s+(mut\s+)?{name}\b)",
+        name = regex::escape(account_name)
+    ))
+    .unwrap();
+    struct_content
+        .lines()
+        .position(|line| declaration.is_match(line))
+        .unwrap_or(0)
+}
@@ -0,0 +1,828 @@
+//! Catalogue of the PDA seed schemas of Solana programs, and the schemas that can collide.
+//!
+//! Seeds come from the `seeds = [...]` constraints of the context accounts. Each seed is
+//! classified by what it adds to the derivation (literal, pubkey, integer, string...), so the
+//! same PDA derived from different variables across instructions falls in a single schema.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use lazy_regex::regex;
+use serde::{Deserialize, Serialize};
+
+use crate::batbelt::metadata::{BatMetadata, BatMetadataParser};
+
+use super::declaration_offset;
+
+/// Account types that don't tell which PDA an account is, e.g. a `TokenAccount` vault.
+const GENERIC_ACCOUNT_TYPES: &[&str] = &[
+    "UncheckedAccount",
+    "AccountInfo",
+    "AccountView",
+    "SystemAccount",
+    "Signer",
+    "TokenAccount",
+    "Mint",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SeedComponent {
+    /// `b"pool"` or `"pool".as_bytes()`
+    Literal(String),
+    /// `POOL_SEED`, a constant whose value lives elsewhere
+    Constant(String),
+    /// `authority.key().as_ref()`, 32 bytes
+    Pubkey,
+    /// `index.to_le_bytes()`, fixed width
+    Integer,
+    /// `&[bump]`
+    Bump,
+    /// `name.as_bytes()`, variable length
+    String,
+    /// Any other expression, length unknown
+    Bytes,
+}
+
+impl SeedComponent {
+    pub fn from_seed(seed: &str) -> Self {
+        let seed: String = seed.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(captures) = regex!(r#"^&?b"([^"]*)"(\.as_ref\(\))?$"#).captures(&seed) {
+            return SeedComponent::Literal(captures[1].to_string());
+        }
+        if let Some(captures) = regex!(r#"^&?"([^"]*)"\.as_bytes\(\)$"#).captures(&seed) {
+            return SeedComponent::Literal(captures[1].to_string());
+        }
+        if regex!(r"^&?\[[\w.]*bump[\w.]*\]$").is_match(&seed) {
+            return SeedComponent::Bump;
+        }
+        if seed.contains(".key()")
+            || seed.contains(".key.")
+            || seed.ends_with(".key")
+            || regex!(r"(^|::)(ID|id\(\))(\.as_ref\(\))?$").is_match(&seed)
+        {
+            return SeedComponent::Pubkey;
+        }
+        if seed.contains("to_le_bytes()") || seed.contains("to_be_bytes()") {
+            return SeedComponent::Integer;
+        }
+        if let Some(captures) =
+            regex!(r"^&?(\w+::)*([A-Z][A-Z0-9_]*)(\.as_ref\(\)|\.as_bytes\(\))?$").captures(&seed)
+        {
+            return SeedComponent::Constant(captures[2].to_string());
+        }
+        if seed.ends_with(".as_bytes()") {
+            return SeedComponent::String;
+        }
+        SeedComponent::Bytes
+    }
+
+    /// true for the seeds fixed at compile time, which make the prefix of a schema.
+    fn is_prefix(&self) -> bool {
+        matches!(self, SeedComponent::Literal(_) | SeedComponent::Constant(_))
+    }
+
+    fn serialized(&self) -> SerializedSeed {
+        match self {
+            SeedComponent::Literal(value) => SerializedSeed::Literal(value.as_bytes().to_vec()),
+            SeedComponent::Constant(name) => SerializedSeed::Constant(name.clone()),
+            SeedComponent::Pubkey => SerializedSeed::Fixed(32),
+            SeedComponent::Integer => SerializedSeed::Integer,
+            SeedComponent::Bump => SerializedSeed::Fixed(1),
+            SeedComponent::String | SeedComponent::Bytes => SerializedSeed::Unbounded,
+        }
+    }
+}
+
+/// Byte widths an integer seed can serialize to, from `u8` to `u128`.
+const INTEGER_WIDTHS: &[usize] = &[1, 2, 4, 8, 16];
+
+/// What a seed adds to the bytes hashed into the address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SerializedSeed {
+    Literal(Vec<u8>),
+    /// A constant whose value lives elsewhere, only known to equal itself
+    Constant(String),
+    /// Any bytes of this length
+    Fixed(usize),
+    /// `to_le_bytes()` of an integer of unknown type
+    Integer,
+    Unbounded,
+}
+
+impl SerializedSeed {
+    /// The seed without its first `len` bytes, `None` when nothing is left.
+    fn skip(&self, len: usize) -> Option<SerializedSeed> {
+        match self {
+            SerializedSeed::Literal(bytes) if bytes.len() > len => {
+                Some(SerializedSeed::Literal(bytes[len..].to_vec()))
+            }
+            SerializedSeed::Fixed(width) if *width > len => {
+                Some(SerializedSeed::Fixed(width - len))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// true when two seed lists can serialize to the same bytes. Seeds are hashed as one
+/// concatenation, so `[b"pool", name]` and `[b"poolx", key]` line up when `name` starts
+/// with `x`: literals are compared byte by byte, fixed-width seeds take any bytes of their
+/// width, and an unbounded seed absorbs whatever is left.
+fn can_collide(left: &[SerializedSeed], right: &[SerializedSeed]) -> bool {
+    let with_head = |head: Option<SerializedSeed>, tail: &[SerializedSeed]| {
+        head.into_iter()
+            .chain(tail.iter().cloned())
+            .collect::<Vec<_>>()
+    };
+    match (left.first(), right.first()) {
+        (None, None) => true,
+        (None, Some(_)) | (Some(_), None) => left
+            .iter()
+            .chain(right)
+            .all(|seed| *seed == SerializedSeed::Unbounded),
+        (Some(SerializedSeed::Unbounded), _) | (_, Some(SerializedSeed::Unbounded)) => true,
+        (Some(SerializedSeed::Integer), _) => INTEGER_WIDTHS.iter().any(|width| {
+            can_collide(
+                &with_head(Some(SerializedSeed::Fixed(*width)), &left[1..]),
+                right,
+            )
+        }),
+        (_, Some(SerializedSeed::Integer)) => can_collide(right, left),
+        (Some(SerializedSeed::Constant(left_name)), Some(SerializedSeed::Constant(right_name))) => {
+            left_name == right_name && can_collide(&left[1..], &right[1..])
+        }
+        (Some(SerializedSeed::Constant(_)), _) | (_, Some(SerializedSeed::Constant(_))) => false,
+        (Some(left_seed), Some(right_seed)) => {
+            let len = |seed: &SerializedSeed| match seed {
+                SerializedSeed::Literal(bytes) => bytes.len(),
+                SerializedSeed::Fixed(width) => *width,
+                _ => 0,
+            };
+            let common = len(left_seed).min(len(right_seed));
+            if let (SerializedSeed::Literal(left_bytes), SerializedSeed::Literal(right_bytes)) =
+                (left_seed, right_seed)
+            {
+                if left_bytes[..common] != right_bytes[..common] {
+                    return false;
+                }
+            }
+            can_collide(
+                &with_head(left_seed.skip(common), &left[1..]),
+                &with_head(right_seed.skip(common), &right[1..]),
+            )
+        }
+    }
+}
+
+impl fmt::Display for SeedComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeedComponent::Literal(value) => write!(f, "b\"{}\"", value),
+            SeedComponent::Constant(name) => write!(f, "{}", name),
+            SeedComponent::Pubkey => write!(f, "Pubkey"),
+            SeedComponent::Integer => write!(f, "integer"),
+            SeedComponent::Bump => write!(f, "bump"),
+            SeedComponent::String => write!(f, "String"),
+            SeedComponent::Bytes => write!(f, "bytes"),
+        }
+    }
+}
+
+/// A PDA account of an entry point, with its seeds as written in the constraint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PdaUsage {
+    pub program_name: String,
+    pub entry_point: String,
+    pub account_name: String,
+    /// `Pool` for `Account<'info, Pool>`, the wrapper for unchecked accounts
+    pub account_type: String,
+    pub seeds: Vec<String>,
+    pub is_init: bool,
+    pub file_path: String,
+    pub line: usize,
+}
+
+/// Distinct layout of seeds in a program, with the accounts derived from it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PdaSchema {
+    pub program_name: String,
+    pub components: Vec<SeedComponent>,
+    pub account_types: BTreeSet<String>,
+    /// Seeds as written, e.g. `[b"pool", authority.key().as_ref()]`
+    pub seed_expressions: BTreeSet<String>,
+    pub init_entry_points: BTreeSet<String>,
+    pub use_entry_points: BTreeSet<String>,
+    /// First account derived from the schema
+    pub file_path: String,
+    pub line: usize,
+}
+
+impl PdaSchema {
+    /// `[b"pool", Pubkey, integer]`
+    pub fn signature(&self) -> String {
+        signature(&self.components)
+    }
+
+    fn prefix(&self) -> &[SeedComponent] {
+        let prefix_len = self
+            .components
+            .iter()
+            .take_while(|component| component.is_prefix())
+            .count();
+        &self.components[..prefix_len]
+    }
+
+    fn specific_account_types(&self) -> impl Iterator<Item = &String> {
+        self.account_types
+            .iter()
+            .filter(|account_type| !GENERIC_ACCOUNT_TYPES.contains(&account_type.as_str()))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, strum_macros::Display)]
+pub enum PdaSeedIssueKind {
+    /// Two adjacent variable-length seeds, `"ab" + "c"` and `"a" + "bc"` derive the same PDA
+    VariableLengthWithoutDelimiter,
+    /// Different account types whose literal prefixes match byte by byte (`b"pool"` and
+    /// `b"poolx"` too), with variable seeds that can serialize to the same bytes
+    SharedPrefix,
+    /// One account type derived from different schemas across instructions
+    InconsistentSeeds,
+}
+
+impl PdaSeedIssueKind {
+    /// Why an issue deserves a look, used as the finding candidate note.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PdaSeedIssueKind::VariableLengthWithoutDelimiter => {
+                "variable-length seeds without a delimiter can be shifted to derive the same address"
+            }
+            PdaSeedIssueKind::SharedPrefix => {
+                "account types sharing a seed prefix may be derived at the same address"
+            }
+            PdaSeedIssueKind::InconsistentSeeds => {
+                "same account type derived with different seeds across instructions"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PdaSeedIssue {
+    pub kind: PdaSeedIssueKind,
+    pub program_name: String,
+    pub account_types: Vec<String>,
+    /// Signatures of the schemas involved
+    pub schemas: Vec<String>,
+    pub file_path: String,
+    pub line: usize,
+}
+
+impl PdaSeedIssue {
+    /// `- [ ] ...` line of the issue, in the format of the finding candidates template.
+    pub fn candidate_line(&self) -> String {
+        format!(
+            "- [ ] [{:?}: `{}`]({}#L{}) for {}, {}",
+            self.kind,
+            self.schemas.join("` / `"),
+            self.file_path,
+            self.line,
+            quoted(&self.account_types),
+            self.kind.description()
+        )
+    }
+}
+
+/// Every seed schema of the programs, with the issues found across them.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct PdaCatalogue {
+    pub schemas: Vec<PdaSchema>,
+    pub issues: Vec<PdaSeedIssue>,
+}
+
+impl PdaCatalogue {
+    pub fn new(usages: &[PdaUsage]) -> Self {
+        let mut schemas: BTreeMap<(String, Vec<SeedComponent>), PdaSchema> = BTreeMap::new();
+        for usage in usages {
+            let components = usage
+                .seeds
+                .iter()
+                .map(|seed| SeedComponent::from_seed(seed))
+                .collect::<Vec<_>>();
+            let schema = schemas
+                .entry((usage.program_name.clone(), components.clone()))
+                .or_insert_with(|| PdaSchema {
+                    program_name: usage.program_name.clone(),
+                    components,
+                    account_types: BTreeSet::new(),
+                    seed_expressions: BTreeSet::new(),
+                    init_entry_points: BTreeSet::new(),
+                    use_entry_points: BTreeSet::new(),
+                    file_path: usage.file_path.clone(),
+                    line: usage.line,
+                });
+            schema.account_types.insert(usage.account_type.clone());
+            schema
+                .seed_expressions
+                .insert(format!("[{}]", usage.seeds.join(", ")));
+            if usage.is_init {
+                schema.init_entry_points.insert(usage.entry_point.clone());
+            } else {
+                schema.use_entry_points.insert(usage.entry_point.clone());
+            }
+        }
+        let schemas = schemas.into_values().collect::<Vec<_>>();
+        let issues = find_issues(&schemas);
+        Self { schemas, issues }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut content = String::from("# PDA seeds\n\n");
+        content.push_str("## Schemas\n\n");
+        if self.schemas.is_empty() {
+            content.push_str("- None detected\n");
+        }
+        for schema in &self.schemas {
+            content.push_str(&format!(
+                "- `{}` in `{}`: {}\n",
+                schema.signature(),
+                schema.program_name,
+                quoted(&schema.account_types.iter().cloned().collect::<Vec<_>>())
+            ));
+            for seeds in &schema.seed_expressions {
+                content.push_str(&format!("  - seeds: `{}`\n", seeds));
+            }
+            if !schema.init_entry_points.is_empty() {
+                content.push_str(&format!(
+                    "  - init by: {}\n",
+                    quoted(&schema.init_entry_points.iter().cloned().collect::<Vec<_>>())
+                ));
+            }
+            if !schema.use_entry_points.is_empty() {
+                content.push_str(&format!(
+                    "  - used by: {}\n",
+                    quoted(&schema.use_entry_points.iter().cloned().collect::<Vec<_>>())
+                ));
+            }
+        }
+
+        content.push_str("\n## Issues\n\n");
+        if self.issues.is_empty() {
+            content.push_str("- None detected\n");
+        }
+        for issue in &self.issues {
+            content.push_str(&format!(
+                "- {:?} in `{}`: `{}` for {}, {} ({}#L{})\n",
+                issue.kind,
+                issue.program_name,
+                issue.schemas.join("` / `"),
+                quoted(&issue.account_types),
+                issue.kind.description(),
+                issue.file_path,
+                issue.line
+            ));
+        }
+        content
+    }
+}
+
+/// PDA accounts of every entry point of the metadata.
+pub fn collect_pda_usages(bat_metadata: &BatMetadata) -> Vec<PdaUsage> {
+    let mut usages = vec![];
+    for entry_point in &bat_metadata.entry_points {
+        let context_accounts = match bat_metadata
+            .source_code
+            .get_struct_by_id(entry_point.context_accounts_id.clone())
+        {
+            Ok(context_accounts) => context_accounts,
+            Err(_) => continue,
+        };
+        let accounts = match bat_metadata
+            .get_context_accounts_metadata_by_struct_source_code_metadata_id(
+                context_accounts.metadata_id.clone(),
+            ) {
+            Ok(context_accounts_metadata) => context_accounts_metadata.context_accounts_info,
+            Err(_) => continue,
+        };
+        if accounts.iter().all(|account| account.seeds.is_empty()) {
+            continue;
+        }
+        let struct_content = context_accounts
+            .to_source_code_parser(None)
+            .get_source_code_content();
+        usages.extend(
+            accounts
+                .into_iter()
+                .filter(|account| !account.seeds.is_empty())
+                .map(|account| PdaUsage {
+                    program_name: context_accounts.program_name.clone(),
+                    entry_point: entry_point.name.clone(),
+                    line: context_accounts.start_line_index
+                        + declaration_offset(&struct_content, &account.account_name),
+                    account_name: account.account_name,
+                    account_type: account.account_struct_name,
+                    seeds: account.seeds,
+                    is_init: account.is_init,
+                    file_path: context_accounts.path.clone(),
+                }),
+        );
+    }
+    usages
+}
+
+fn find_issues(schemas: &[PdaSchema]) -> Vec<PdaSeedIssue> {
+    let mut issues = vec![];
+
+    for schema in schemas {
+        let unbounded = schema.components.windows(2).any(|pair| {
+            matches!(
+                (&pair[0], &pair[1]),
+                (SeedComponent::String, SeedComponent::String)
+                    | (SeedComponent::String, SeedComponent::Bytes)
+                    | (SeedComponent::Bytes, SeedComponent::String)
+            )
+        });
+        if unbounded {
+            issues.push(issue(
+                PdaSeedIssueKind::VariableLengthWithoutDelimiter,
+                &[schema],
+                schema.account_types.iter().cloned().collect(),
+            ));
+        }
+    }
+
+    // Schemas without a literal prefix only collide with the very same layout, the others
+    // when their prefixes and variable seeds line up byte by byte
+    let serialized = schemas
+        .iter()
+        .map(|schema| {
+            schema
+                .components
+                .iter()
+                .map(SeedComponent::serialized)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // Colliding schemas are grouped with the first one they collide with, transitively
+    let mut group_of: Vec<Option<usize>> = vec![None; schemas.len()];
+    for (left_idx, left) in schemas.iter().enumerate() {
+        for (right_idx, right) in schemas.iter().enumerate().skip(left_idx) {
+            let account_types = left
+                .specific_account_types()
+                .chain(right.specific_account_types())
+                .collect::<BTreeSet<_>>();
+            let comparable = left.program_name == right.program_name
+                && (left_idx == right_idx
+                    || (!left.prefix().is_empty() && !right.prefix().is_empty()));
+            if !comparable
+                || account_types.len() < 2
+                || !can_collide(&serialized[left_idx], &serialized[right_idx])
+            {
+                continue;
+            }
+            let merged = [group_of[left_idx], group_of[right_idx]];
+            let group = merged.iter().flatten().min().copied().unwrap_or(left_idx);
+            for (idx, group_idx) in group_of.iter_mut().enumerate() {
+                if idx == left_idx
+                    || idx == right_idx
+                    || (group_idx.is_some() && merged.contains(group_idx))
+                {
+                    *group_idx = Some(group);
+                }
+            }
+        }
+    }
+    let mut by_group: BTreeMap<usize, Vec<&PdaSchema>> = BTreeMap::new();
+    for (idx, group) in group_of.iter().enumerate() {
+        if let Some(group) = group {
+            by_group.entry(*group).or_default().push(&schemas[idx]);
+        }
+    }
+    for colliding in by_group.values() {
+        let account_types = colliding
+            .iter()
+            .flat_map(|schema| schema.specific_account_types())
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        issues.push(issue(
+            PdaSeedIssueKind::SharedPrefix,
+            colliding,
+            account_types.into_iter().collect(),
+        ));
+    }
+
+    let mut by_account_type: BTreeMap<(&str, &String), Vec<&PdaSchema>> = BTreeMap::new();
+    for schema in schemas {
+        for account_type in schema.specific_account_types() {
+            by_account_type
+                .entry((schema.program_name.as_str(), account_type))
+                .or_default()
+                .push(schema);
+        }
+    }
+    for ((_, account_type), group) in by_account_type {
+        if group.len() > 1 {
+            issues.push(issue(
+                PdaSeedIssueKind::InconsistentSeeds,
+                &group,
+                vec![account_type.clone()],
+            ));
+        }
+    }
+
+    issues
+}
+
+fn issue(
+    kind: PdaSeedIssueKind,
+    schemas: &[&PdaSchema],
+    account_types: Vec<String>,
+) -> PdaSeedIssue {
+    PdaSeedIssue {
+        kind,
+        program_name: schemas[0].program_name.clone(),
+        account_types,
+        schemas: schemas.iter().map(|schema| schema.signature()).collect(),
+        file_path: schemas[0].file_path.clone(),
+        line: schemas[0].line,
+    }
+}
+
+fn signature(components: &[SeedComponent]) -> String {
+    format!(
+        "[{}]",
+        components
+            .iter()
+            .map(|component| component.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(entry_point: &str, account_type: &str, seeds: &[&str], is_init: bool) -> PdaUsage {
+        PdaUsage {
+            program_name: "vault".to_string(),
+            entry_point: entry_point.to_string(),
+            account_name: account_type.to_lowercase(),
+            account_type: account_type.to_string(),
+            seeds: seeds.iter().map(|seed| seed.to_string()).collect(),
+            is_init,
+            file_path: format!("programs/vault/src/instructions/{}.rs", entry_point),
+            line: 10,
+        }
+    }
+
+    #[test]
+    fn test_seed_components() {
+        let components = [
+            "b\"pool\"",
+            "b \"pool\" . as_ref ()",
+            "\"config\".as_bytes()",
+            "POOL_SEED.as_ref()",
+            "crate::constants::VAULT_SEED",
+            "authority.key().as_ref()",
+            "ctx.accounts.user.key.as_ref()",
+            "crate::ID.as_ref()",
+            "index.to_le_bytes().as_ref()",
+            "&[bump]",
+            "&[ctx.bumps.pool]",
+            "name.as_bytes()",
+            "data.as_ref()",
+        ]
+        .iter()
+        .map(|seed| SeedComponent::from_seed(seed))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            vec![
+                SeedComponent::Literal("pool".to_string()),
+                SeedComponent::Literal("pool".to_string()),
+                SeedComponent::Literal("config".to_string()),
+                SeedComponent::Constant("POOL_SEED".to_string()),
+                SeedComponent::Constant("VAULT_SEED".to_string()),
+                SeedComponent::Pubkey,
+                SeedComponent::Pubkey,
+                SeedComponent::Pubkey,
+                SeedComponent::Integer,
+                SeedComponent::Bump,
+                SeedComponent::Bump,
+                SeedComponent::String,
+                SeedComponent::Bytes,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pda_catalogue_issues() {
+        let catalogue = PdaCatalogue::new(&[
+            usage(
+                "initialize",
+                "Pool",
+                &["b\"pool\"", "authority.key().as_ref()"],
+                true,
+            ),
+            usage(
+                "deposit",
+                "Pool",
+                &["b\"pool\"", "pool.authority.key().as_ref()"],
+                false,
+            ),
+            usage(
+                "withdraw",
+                "Pool",
+                &[
+                    "b\"pool\"",
+                    "admin.key().as_ref()",
+                    "pool.index.to_le_bytes().as_ref()",
+                ],
+                false,
+            ),
+            usage(
+                "open_position",
+                "Position",
+                &["b\"pool\"", "index.to_le_bytes().as_ref()"],
+                true,
+            ),
+            usage(
+                "create_profile",
+                "Profile",
+                &[
+                    "b\"profile\"",
+                    "first_name.as_bytes()",
+                    "last_name.as_bytes()",
+                ],
+                true,
+            ),
+            usage(
+                "deposit",
+                "TokenAccount",
+                &["b\"vault\"", "pool.key().as_ref()"],
+                false,
+            ),
+        ]);
+
+        let pool = &catalogue.schemas[0];
+        assert_eq!(pool.signature(), "[b\"pool\", Pubkey]");
+        assert_eq!(
+            pool.init_entry_points.iter().collect::<Vec<_>>(),
+            vec!["initialize"]
+        );
+        assert_eq!(
+            pool.use_entry_points.iter().collect::<Vec<_>>(),
+            vec!["deposit"]
+        );
+        assert_eq!(catalogue.schemas.len(), 5);
+
+        let issues = catalogue
+            .issues
+            .iter()
+            .map(|issue| (issue.kind, issue.account_types.join(",")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    PdaSeedIssueKind::VariableLengthWithoutDelimiter,
+                    "Profile".to_string()
+                ),
+                (PdaSeedIssueKind::InconsistentSeeds, "Pool".to_string()),
+            ]
+        );
+        assert_eq!(
+            catalogue.issues[1].schemas,
+            vec!["[b\"pool\", Pubkey]", "[b\"pool\", Pubkey, integer]"]
+        );
+    }
+
+    #[test]
+    fn test_shared_prefix_needs_matching_widths() {
+        let catalogue = PdaCatalogue::new(&[
+            usage(
+                "create_pool",
+                "Pool",
+                &["b\"pool\"", "mint.key().as_ref()"],
+                true,
+            ),
+            usage(
+                "open_position",
+                "Position",
+                &["b\"pool\"", "index.to_le_bytes().as_ref()"],
+                true,
+            ),
+            usage(
+                "create_reward",
+                "Reward",
+                &["b\"reward\"", "mint.key().as_ref()"],
+                true,
+            ),
+            usage(
+                "create_ticket",
+                "Ticket",
+                &["b\"reward\"", "name.as_bytes()"],
+                true,
+            ),
+            usage(
+                "create_vault",
+                "Vault",
+                &["b\"vault\"", "owner.key().as_ref()"],
+                true,
+            ),
+            usage(
+                "create_escrow",
+                "Escrow",
+                &["b\"vault\"", "seller.key().as_ref()"],
+                true,
+            ),
+        ]);
+
+        let issues = catalogue
+            .issues
+            .iter()
+            .filter(|issue| issue.kind == PdaSeedIssueKind::SharedPrefix)
+            .map(|issue| (issue.account_types.join(","), issue.schemas.join(" / ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "Reward,Ticket".to_string(),
+                    "[b\"reward\", Pubkey] / [b\"reward\", String]".to_string()
+                ),
+                (
+                    "Escrow,Vault".to_string(),
+                    "[b\"vault\", Pubkey]".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_prefix_compares_literal_bytes() {
+        let catalogue = PdaCatalogue::new(&[
+            usage(
+                "create_pool",
+                "Pool",
+                &["b\"pool\"", "name.as_bytes()"],
+                true,
+            ),
+            usage(
+                "create_pool_x",
+                "PoolX",
+                &["b\"poolx\"", "mint.key().as_ref()"],
+                true,
+            ),
+            usage(
+                "open_lot",
+                "Lot",
+                &["b\"lot\"", "index.to_le_bytes().as_ref()"],
+                true,
+            ),
+            usage("open_lot_x", "LotX", &["b\"lotx\"", "&[bump]"], true),
+            usage(
+                "open_stake",
+                "Stake",
+                &["b\"stake\"", "owner.key().as_ref()"],
+                true,
+            ),
+            // `x` plus a `u64` never makes the 32 bytes of a pubkey
+            usage(
+                "open_stake_x",
+                "StakeX",
+                &["b\"stakex\"", "index.to_le_bytes().as_ref()"],
+                true,
+            ),
+        ]);
+
+        let issues = catalogue
+            .issues
+            .iter()
+            .filter(|issue| issue.kind == PdaSeedIssueKind::SharedPrefix)
+            .map(|issue| (issue.account_types.join(","), issue.schemas.join(" / ")))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (
+                    "Lot,LotX".to_string(),
+                    "[b\"lot\", integer] / [b\"lotx\", bump]".to_string()
+                ),
+                (
+                    "Pool,PoolX".to_string(),
+                    "[b\"pool\", String] / [b\"poolx\", Pubkey]".to_string()
+                ),
+            ]
+        );
+    }
+}
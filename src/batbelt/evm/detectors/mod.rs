@@ -0,0 +1,395 @@
+pub mod solidity_footguns;
+
+use std::collections::HashSet;
+
+use error_stack::ResultExt;
+use solar_parse::ast;
+use solar_parse::interface::{Session, Span};
+
+use crate::batbelt::evm::metadata::bat_metadata::{
+    evm_function_id, EvmMetadataError, EvmMetadataResult,
+};
+use crate::batbelt::evm::parser::evm_file_parser::{
+    body_start_line, span_to_line, with_parsed_body,
+};
+use crate::batbelt::evm::parser::inheritance_resolver::InheritanceResolver;
+use crate::batbelt::evm::types::{
+    EvmContract, EvmContractType, EvmDetectorHit, EvmDetectorKind, EvmParam, StorageVariable,
+};
+use crate::batbelt::templates::notes_template::NoteTemplate;
+
+use self::solidity_footguns::{
+    EncodePackedCollision, InlineAssembly, Selfdestruct, TimestampComparison, TxOriginAuth,
+    UnboundedStorageLoop, UncheckedUserArithmetic, UserDelegatecall,
+};
+
+/// Snippets longer than this are cut, assembly blocks would flood the notes otherwise.
+const MAX_SNIPPET_LEN: usize = 80;
+
+/// A heuristic run over every in-scope function and modifier body.
+///
+/// Detectors only see the parsed body of one function or modifier at a time; register new ones in
+/// `default_detectors` or hand them to `EvmDetectorRunner::with_detectors`.
+pub trait EvmDetector: Send + Sync {
+    fn kind(&self) -> EvmDetectorKind;
+
+    /// Why a match deserves a look, used as the finding candidate note.
+    fn description(&self) -> &'static str;
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch>;
+}
+
+/// Location of a detector match, before it is tied to its function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectorMatch {
+    pub line: usize,
+    pub snippet: String,
+}
+
+/// Function or modifier being checked, with what detectors need to type its identifiers.
+pub struct DetectorContext<'a> {
+    pub sess: &'a Session,
+    pub params: &'a [EvmParam],
+    /// Named returns, none for a modifier
+    pub returns: &'a [EvmParam],
+    /// State variables of the contract and its bases
+    pub state_variables: Vec<&'a StorageVariable>,
+    first_line: usize,
+}
+
+impl DetectorContext<'_> {
+    /// 1-based line in the source file of `span`.
+    pub fn line(&self, span: Span) -> usize {
+        self.first_line + span_to_line(self.sess, span) - 1
+    }
+
+    pub fn matched(&self, span: Span) -> DetectorMatch {
+        let snippet = self
+            .sess
+            .source_map()
+            .span_to_snippet(span)
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default();
+        let snippet = match snippet.char_indices().nth(MAX_SNIPPET_LEN) {
+            Some((idx, _)) => format!("{}...", &snippet[..idx]),
+            None => snippet,
+        };
+        DetectorMatch {
+            line: self.line(span),
+            snippet,
+        }
+    }
+
+    /// true if `name` is a parameter of the function or modifier, i.e. supplied by the caller.
+    pub fn is_param(&self, name: &str) -> bool {
+        self.params.iter().any(|p| p.name == name)
+    }
+
+    /// Declared type of a parameter, named return or state variable.
+    pub fn variable_type(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .chain(self.returns.iter())
+            .find(|p| p.name == name)
+            .map(|p| p.type_name.as_str())
+            .or_else(|| self.state_variable(name).map(|v| v.type_name.as_str()))
+    }
+
+    pub fn state_variable(&self, name: &str) -> Option<&StorageVariable> {
+        // Parameters and named returns shadow state variables
+        if self
+            .params
+            .iter()
+            .chain(self.returns.iter())
+            .any(|p| p.name == name)
+        {
+            return None;
+        }
+        self.state_variables
+            .iter()
+            .copied()
+            .find(|v| v.name == name)
+    }
+}
+
+/// The detectors run by the sonar.
+pub fn default_detectors() -> Vec<Box<dyn EvmDetector>> {
+    vec![
+        Box::new(TxOriginAuth),
+        Box::new(UserDelegatecall),
+        Box::new(Selfdestruct),
+        Box::new(UncheckedUserArithmetic),
+        Box::new(InlineAssembly),
+        Box::new(TimestampComparison),
+        Box::new(UnboundedStorageLoop),
+        Box::new(EncodePackedCollision),
+    ]
+}
+
+/// Runs the detectors over the functions and modifiers defined by in-scope contracts.
+pub struct EvmDetectorRunner<'a> {
+    contracts: &'a [EvmContract],
+    inheritance_resolver: InheritanceResolver<'a>,
+    detectors: Vec<Box<dyn EvmDetector>>,
+}
+
+impl<'a> EvmDetectorRunner<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self::with_detectors(contracts, default_detectors())
+    }
+
+    pub fn with_detectors(
+        contracts: &'a [EvmContract],
+        detectors: Vec<Box<dyn EvmDetector>>,
+    ) -> Self {
+        Self {
+            contracts,
+            inheritance_resolver: InheritanceResolver::new(contracts),
+            detectors,
+        }
+    }
+
+    pub fn run(&self) -> Vec<EvmDetectorHit> {
+        let mut hits = Vec::new();
+        for contract in self
+            .contracts
+            .iter()
+            .filter(|c| !c.external && c.contract_type != EvmContractType::Interface)
+        {
+            let linearization = self.inheritance_resolver.linearize(&contract.name);
+            let state_variables: Vec<&StorageVariable> = self
+                .contracts
+                .iter()
+                .filter(|c| linearization.contains(&c.name))
+                .flat_map(|c| c.storage_variables.iter())
+                .collect();
+            for function in contract
+                .functions
+                .iter()
+                .filter(|f| !f.body_source.is_empty())
+            {
+                let body = CheckedBody {
+                    name: &function.name,
+                    params: &function.params,
+                    returns: &function.returns,
+                    body_source: &function.body_source,
+                    end_line: function.end_line,
+                };
+                hits.extend(self.check_body(contract, &body, &state_variables));
+            }
+            // `require(tx.origin == owner); _;` guards every function using the modifier
+            for modifier in contract
+                .modifiers
+                .iter()
+                .filter(|m| !m.body_source.is_empty())
+            {
+                let body = CheckedBody {
+                    name: &modifier.name,
+                    params: &modifier.params,
+                    returns: &[],
+                    body_source: &modifier.body_source,
+                    end_line: modifier.end_line,
+                };
+                hits.extend(self.check_body(contract, &body, &state_variables));
+            }
+        }
+        hits
+    }
+
+    fn check_body(
+        &self,
+        contract: &EvmContract,
+        body: &CheckedBody<'_>,
+        state_variables: &[&StorageVariable],
+    ) -> Vec<EvmDetectorHit> {
+        let first_line = body_start_line(body.body_source, body.end_line);
+        let matches = with_parsed_body(body.body_source, |sess, block| {
+            let cx = DetectorContext {
+                sess,
+                params: body.params,
+                returns: body.returns,
+                state_variables: state_variables.to_vec(),
+                first_line,
+            };
+            let mut matches: Vec<(EvmDetectorKind, DetectorMatch)> = Vec::new();
+            for detector in &self.detectors {
+                for found in detector.check(&cx, block) {
+                    // One hit per line, `a + b - c` is a single candidate
+                    let kind = detector.kind();
+                    if !matches
+                        .iter()
+                        .any(|(k, m)| *k == kind && m.line == found.line)
+                    {
+                        matches.push((kind, found));
+                    }
+                }
+            }
+            matches
+        })
+        .unwrap_or_default();
+
+        let function_metadata_id = evm_function_id(&contract.name, body.name, body.params);
+        matches
+            .into_iter()
+            .map(|(kind, found)| EvmDetectorHit {
+                kind,
+                function_metadata_id: function_metadata_id.clone(),
+                function: body.name.to_string(),
+                contract_name: contract.name.clone(),
+                file_path: contract.file_path.clone(),
+                line: found.line,
+                snippet: found.snippet,
+            })
+            .collect()
+    }
+}
+
+/// Function or modifier body handed to the detectors.
+struct CheckedBody<'a> {
+    name: &'a str,
+    params: &'a [EvmParam],
+    returns: &'a [EvmParam],
+    body_source: &'a str,
+    end_line: usize,
+}
+
+fn description(kind: EvmDetectorKind) -> &'static str {
+    default_detectors()
+        .into_iter()
+        .find(|d| d.kind() == kind)
+        .map(|d| d.description())
+        .unwrap_or_default()
+}
+
+/// `- [ ] ...` line of a hit, in the format of the finding candidates template.
+fn candidate_line(hit: &EvmDetectorHit) -> String {
+    format!(
+        "- [ ] [{:?}: `{}`]({}#L{}) in `{}`, {}",
+        hit.kind,
+        hit.snippet,
+        hit.file_path,
+        hit.line,
+        hit.function_metadata_id,
+        description(hit.kind)
+    )
+}
+
+/// Replace the generated block of `finding_candidates.md` with `hits`, under `## to review`.
+/// A pattern is listed once per function, whatever the lines it is found at.
+pub fn merge_finding_candidates(content: &str, hits: &[EvmDetectorHit]) -> String {
+    NoteTemplate::merge_generated_finding_candidates(content, &candidate_lines(hits))
+}
+
+/// Candidate lines of the hits, once per kind, file, function and snippet.
+fn candidate_lines(hits: &[EvmDetectorHit]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    hits.iter()
+        .filter(|hit| {
+            seen.insert((
+                hit.kind,
+                &hit.file_path,
+                &hit.function_metadata_id,
+                &hit.snippet,
+            ))
+        })
+        .map(candidate_line)
+        .collect()
+}
+
+/// Add the hits to the auditor's `finding_candidates.md`, if the notes are initialized.
+pub fn save_finding_candidates(hits: &[EvmDetectorHit]) -> EvmMetadataResult<()> {
+    let lines = candidate_lines(hits);
+    NoteTemplate::save_generated_finding_candidates(&lines).change_context(EvmMetadataError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::templates::notes_template::{CANDIDATES_END, CANDIDATES_START};
+
+    fn hit(kind: EvmDetectorKind, line: usize, snippet: &str) -> EvmDetectorHit {
+        EvmDetectorHit {
+            kind,
+            function_metadata_id: "Vault.withdraw(uint256)".to_string(),
+            function: "withdraw".to_string(),
+            contract_name: "Vault".to_string(),
+            file_path: "src/Vault.sol".to_string(),
+            line,
+            snippet: snippet.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_merge_finding_candidates_replaces_generated_block() {
+        let template = "# Finding candidates\n\n## accepted\n\n## rejected\n\n## to review\n\n- [ ] manual candidate\n";
+        let tx_origin = hit(EvmDetectorKind::TxOriginAuth, 12, "tx.origin == owner");
+        let assembly = hit(
+            EvmDetectorKind::InlineAssembly,
+            20,
+            "assembly { sstore(0, 1) }",
+        );
+
+        let first = merge_finding_candidates(template, &[tx_origin.clone(), assembly.clone()]);
+        assert!(first.contains(&format!(
+            "## to review\n\n{}\n{}\n{}\n{}\n\n- [ ] manual candidate\n",
+            CANDIDATES_START,
+            candidate_line(&tx_origin),
+            candidate_line(&assembly),
+            CANDIDATES_END
+        )));
+
+        // The auditor accepts the tx.origin candidate, a new scan only re-adds the other one
+        let accepted = first
+            .replacen(&format!("{}\n", candidate_line(&tx_origin)), "", 1)
+            .replace(
+                "## accepted\n",
+                &format!(
+                    "## accepted\n\n{}\n",
+                    candidate_line(&tx_origin).replacen("- [ ]", "- [x]", 1)
+                ),
+            );
+        let second = merge_finding_candidates(&accepted, &[tx_origin.clone(), assembly.clone()]);
+        assert_eq!(second.matches("TxOriginAuth").count(), 1);
+        assert_eq!(second.matches(CANDIDATES_START).count(), 1);
+        assert!(second.contains(&format!(
+            "{}\n{}\n{}",
+            CANDIDATES_START,
+            candidate_line(&assembly),
+            CANDIDATES_END
+        )));
+
+        // Nothing left to flag removes the block
+        let cleared = merge_finding_candidates(&second, &[]);
+        assert!(!cleared.contains(CANDIDATES_START));
+        assert!(cleared.ends_with("## to review\n\n- [ ] manual candidate\n"));
+    }
+
+    #[test]
+    fn test_merge_finding_candidates_ignores_lines() {
+        let template = "# Finding candidates\n\n## accepted\n\n## to review\n";
+        let tx_origin = hit(EvmDetectorKind::TxOriginAuth, 12, "tx.origin == owner");
+        let same_check = hit(EvmDetectorKind::TxOriginAuth, 30, "tx.origin == owner");
+
+        let first = merge_finding_candidates(template, &[tx_origin.clone(), same_check.clone()]);
+        assert_eq!(first.matches("TxOriginAuth").count(), 1);
+
+        // Accepted, then shifted by an unrelated edit: not re-added
+        let accepted = first
+            .replacen(&format!("{}\n", candidate_line(&tx_origin)), "", 1)
+            .replace(
+                "## accepted\n",
+                &format!(
+                    "## accepted\n\n{}\n",
+                    candidate_line(&tx_origin).replacen("- [ ]", "- [x]", 1)
+                ),
+            );
+        let shifted = hit(EvmDetectorKind::TxOriginAuth, 14, "tx.origin == owner");
+        let second = merge_finding_candidates(&accepted, &[shifted]);
+        assert_eq!(second.matches("TxOriginAuth").count(), 1);
+        assert!(!second.contains(CANDIDATES_START));
+    }
+}
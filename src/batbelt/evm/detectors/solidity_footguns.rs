@@ -0,0 +1,500 @@
+use std::ops::ControlFlow;
+
+use solar_parse::ast::{self, BinOpKind, Visit};
+
+use crate::batbelt::evm::parser::evm_file_parser::type_to_string;
+use crate::batbelt::evm::types::EvmDetectorKind;
+
+use super::{DetectorContext, DetectorMatch, EvmDetector};
+
+/// `tx.origin == owner`: any contract the owner interacts with can act on their behalf.
+pub struct TxOriginAuth;
+
+/// `target.delegatecall(data)` with `target` supplied by the caller.
+pub struct UserDelegatecall;
+
+/// `selfdestruct(recipient)`.
+pub struct Selfdestruct;
+
+/// `unchecked { balance += amount; }` with `amount` supplied by the caller.
+pub struct UncheckedUserArithmetic;
+
+/// `assembly { ... }`, skipped by every other analysis of the sonar.
+pub struct InlineAssembly;
+
+/// `block.timestamp > deadline`, which validators can nudge by a few seconds.
+pub struct TimestampComparison;
+
+/// `for (...; i < users.length; ...)` over a storage array anyone can grow.
+pub struct UnboundedStorageLoop;
+
+/// `abi.encodePacked(a, b)` with `a` and `b` dynamic: `("ab", "c")` and `("a", "bc")` collide.
+pub struct EncodePackedCollision;
+
+impl EvmDetector for TxOriginAuth {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::TxOriginAuth
+    }
+
+    fn description(&self) -> &'static str {
+        "`tx.origin` used for authorization, a contract called by the user can act on their behalf"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        Nodes::of_block(block)
+            .exprs
+            .iter()
+            .filter(|(expr, _)| match &expr.kind {
+                ast::ExprKind::Binary(lhs, op, rhs) => {
+                    matches!(op.kind, BinOpKind::Eq | BinOpKind::Ne)
+                        && (is_member(lhs, "tx", "origin") || is_member(rhs, "tx", "origin"))
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for UserDelegatecall {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::UserDelegatecall
+    }
+
+    fn description(&self) -> &'static str {
+        "`delegatecall` to a caller-supplied address runs arbitrary code on this contract's storage"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        Nodes::of_block(block)
+            .exprs
+            .iter()
+            .filter(|(expr, _)| {
+                let ast::ExprKind::Call(callee, _) = &expr.kind else {
+                    return false;
+                };
+                match &without_call_options(callee).kind {
+                    ast::ExprKind::Member(target, member) => {
+                        member.as_str() == "delegatecall"
+                            && any_expr(target, |e| is_ident(e, |name| cx.is_param(name)))
+                    }
+                    _ => false,
+                }
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for Selfdestruct {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::Selfdestruct
+    }
+
+    fn description(&self) -> &'static str {
+        "`selfdestruct` can remove the code or sweep the balance, check who can reach it"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        Nodes::of_block(block)
+            .exprs
+            .iter()
+            .filter(|(expr, _)| match &expr.kind {
+                ast::ExprKind::Call(callee, _) => {
+                    is_ident(callee, |name| name == "selfdestruct" || name == "suicide")
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for UncheckedUserArithmetic {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::UncheckedUserArithmetic
+    }
+
+    fn description(&self) -> &'static str {
+        "arithmetic on caller-supplied values inside `unchecked` can silently overflow"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        let user_controlled = |expr: &'ast ast::Expr<'ast>| {
+            any_expr(expr, |e| {
+                is_ident(e, |name| cx.is_param(name)) || is_member(e, "msg", "value")
+            })
+        };
+        Nodes::of_block(block)
+            .exprs
+            .iter()
+            .filter(|(_, unchecked)| *unchecked)
+            .filter(|(expr, _)| match &expr.kind {
+                ast::ExprKind::Binary(lhs, op, rhs) | ast::ExprKind::Assign(lhs, Some(op), rhs) => {
+                    is_overflowing(op.kind) && (user_controlled(lhs) || user_controlled(rhs))
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for InlineAssembly {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::InlineAssembly
+    }
+
+    fn description(&self) -> &'static str {
+        "inline assembly bypasses the compiler checks, review memory and storage accesses by hand"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        Nodes::of_block(block)
+            .stmts
+            .iter()
+            .filter(|stmt| matches!(stmt.kind, ast::StmtKind::Assembly(_)))
+            .map(|stmt| cx.matched(stmt.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for TimestampComparison {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::TimestampComparison
+    }
+
+    fn description(&self) -> &'static str {
+        "`block.timestamp` comparison, validators can shift it slightly and equality rarely holds"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        let timestamp = |expr: &'ast ast::Expr<'ast>| {
+            any_expr(expr, |e| {
+                is_member(e, "block", "timestamp") || is_ident(e, |name| name == "now")
+            })
+        };
+        Nodes::of_block(block)
+            .exprs
+            .iter()
+            .filter(|(expr, _)| match &expr.kind {
+                ast::ExprKind::Binary(lhs, op, rhs) => {
+                    is_comparison(op.kind) && (timestamp(lhs) || timestamp(rhs))
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for UnboundedStorageLoop {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::UnboundedStorageLoop
+    }
+
+    fn description(&self) -> &'static str {
+        "loop over a growing storage array can run out of gas and block the function"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        let storage_length = |expr: &'ast ast::Expr<'ast>| {
+            any_expr(expr, |e| match &e.kind {
+                ast::ExprKind::Member(base, member) if member.as_str() == "length" => {
+                    is_ident(base, |name| {
+                        cx.state_variable(name)
+                            .is_some_and(|v| v.type_name.ends_with("[]"))
+                    })
+                }
+                _ => false,
+            })
+        };
+        Nodes::of_block(block)
+            .stmts
+            .iter()
+            .filter(|stmt| match &stmt.kind {
+                ast::StmtKind::For {
+                    cond: Some(cond), ..
+                } => storage_length(cond),
+                ast::StmtKind::While(cond, _) | ast::StmtKind::DoWhile(_, cond) => {
+                    storage_length(cond)
+                }
+                _ => false,
+            })
+            .map(|stmt| cx.matched(stmt.span))
+            .collect()
+    }
+}
+
+impl EvmDetector for EncodePackedCollision {
+    fn kind(&self) -> EvmDetectorKind {
+        EvmDetectorKind::EncodePackedCollision
+    }
+
+    fn description(&self) -> &'static str {
+        "`abi.encodePacked` with several dynamic arguments, different inputs can hash the same"
+    }
+
+    fn check<'ast>(
+        &self,
+        cx: &DetectorContext<'_>,
+        block: &'ast ast::Block<'ast>,
+    ) -> Vec<DetectorMatch> {
+        let nodes = Nodes::of_block(block);
+        let locals: Vec<(String, String)> = nodes
+            .stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                ast::StmtKind::DeclSingle(var) => var
+                    .name
+                    .map(|name| (name.as_str().to_string(), type_to_string(cx.sess, &var.ty))),
+                _ => None,
+            })
+            .collect();
+        let is_dynamic = |expr: &'ast ast::Expr<'ast>| {
+            let ast::ExprKind::Ident(ident) = &expr.kind else {
+                return false;
+            };
+            let name = ident.as_str();
+            let type_name = locals
+                .iter()
+                .find(|(local, _)| local == name)
+                .map(|(_, type_name)| type_name.as_str())
+                .or_else(|| cx.variable_type(name));
+            type_name.is_some_and(|t| t == "string" || t == "bytes" || t.ends_with("[]"))
+        };
+        nodes
+            .exprs
+            .iter()
+            .filter(|(expr, _)| match &expr.kind {
+                ast::ExprKind::Call(callee, args) => {
+                    is_member(callee, "abi", "encodePacked")
+                        && args.exprs().filter(|arg| is_dynamic(arg)).count() > 1
+                }
+                _ => false,
+            })
+            .map(|(expr, _)| cx.matched(expr.span))
+            .collect()
+    }
+}
+
+/// Every statement and expression of a body, expressions flagged when inside `unchecked`.
+#[derive(Default)]
+struct Nodes<'ast> {
+    stmts: Vec<&'ast ast::Stmt<'ast>>,
+    exprs: Vec<(&'ast ast::Expr<'ast>, bool)>,
+    unchecked: usize,
+}
+
+impl<'ast> Nodes<'ast> {
+    fn of_block(block: &'ast ast::Block<'ast>) -> Self {
+        let mut nodes = Self::default();
+        let _ = nodes.visit_block(block);
+        nodes
+    }
+}
+
+impl<'ast> Visit<'ast> for Nodes<'ast> {
+    type BreakValue = ();
+
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        self.stmts.push(stmt);
+        if let ast::StmtKind::UncheckedBlock(_) = stmt.kind {
+            self.unchecked += 1;
+            let flow = self.walk_stmt(stmt);
+            self.unchecked -= 1;
+            return flow;
+        }
+        self.walk_stmt(stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        self.exprs.push((expr, self.unchecked > 0));
+        self.walk_expr(expr)
+    }
+}
+
+/// true if `expr` or any of its sub-expressions matches `predicate`.
+fn any_expr<'ast, F>(expr: &'ast ast::Expr<'ast>, predicate: F) -> bool
+where
+    F: Fn(&'ast ast::Expr<'ast>) -> bool,
+{
+    struct Finder<F> {
+        predicate: F,
+    }
+
+    impl<'ast, F: Fn(&'ast ast::Expr<'ast>) -> bool> Visit<'ast> for Finder<F> {
+        type BreakValue = ();
+
+        fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+            if (self.predicate)(expr) {
+                return ControlFlow::Break(());
+            }
+            self.walk_expr(expr)
+        }
+    }
+
+    Finder { predicate }.visit_expr(expr).is_break()
+}
+
+fn is_ident(expr: &ast::Expr<'_>, predicate: impl Fn(&str) -> bool) -> bool {
+    matches!(&expr.kind, ast::ExprKind::Ident(ident) if predicate(ident.as_str()))
+}
+
+/// true if `expr` is `base.member` (`tx.origin`, `block.timestamp`...).
+fn is_member(expr: &ast::Expr<'_>, base: &str, member: &str) -> bool {
+    match &expr.kind {
+        ast::ExprKind::Member(object, name) => {
+            name.as_str() == member && is_ident(object, |ident| ident == base)
+        }
+        _ => false,
+    }
+}
+
+/// `target.delegatecall{gas: g}` -> `target.delegatecall`.
+fn without_call_options<'e, 'ast>(expr: &'e ast::Expr<'ast>) -> &'e ast::Expr<'ast> {
+    match &expr.kind {
+        ast::ExprKind::CallOptions(callee, _) => callee,
+        _ => expr,
+    }
+}
+
+fn is_comparison(op: BinOpKind) -> bool {
+    matches!(
+        op,
+        BinOpKind::Lt
+            | BinOpKind::Le
+            | BinOpKind::Gt
+            | BinOpKind::Ge
+            | BinOpKind::Eq
+            | BinOpKind::Ne
+    )
+}
+
+/// Operators that wrap around instead of reverting inside `unchecked`.
+fn is_overflowing(op: BinOpKind) -> bool {
+    matches!(
+        op,
+        BinOpKind::Add | BinOpKind::Sub | BinOpKind::Mul | BinOpKind::Pow | BinOpKind::Shl
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::batbelt::evm::detectors::EvmDetectorRunner;
+    use crate::batbelt::evm::fixtures::{contract, function, modifier, param, state_variable};
+    use crate::batbelt::evm::types::{EvmContract, EvmContractType};
+
+    use super::*;
+
+    #[test]
+    fn test_default_detectors_flag_footguns() {
+        let body = r#"{
+    require(tx.origin == owner, "not owner");
+    (bool ok, ) = target.delegatecall(data);
+    unchecked {
+        balance += amount;
+        counter++;
+    }
+    assembly {
+        sstore(0, 1)
+    }
+    if (block.timestamp > deadline) {
+        selfdestruct(payable(owner));
+    }
+    for (uint256 i = 0; i < users.length; i++) {
+        total += i;
+    }
+    string memory suffix = "-v1";
+    bytes32 h = keccak256(abi.encodePacked(name, suffix, amount));
+    bytes32 safe = keccak256(abi.encodePacked(name, amount));
+    require(block.number > deadline);
+}"#;
+        let function = function("execute", "Vault", body)
+            .with_params(vec![
+                param("target", "address"),
+                param("data", "bytes"),
+                param("amount", "uint256"),
+                param("name", "string"),
+            ])
+            .at_line(10);
+        let contracts = vec![EvmContract {
+            storage_variables: vec![
+                state_variable("owner", "address"),
+                state_variable("balance", "uint256"),
+                state_variable("deadline", "uint256"),
+                state_variable("users", "address[]"),
+            ],
+            ..contract("Vault", EvmContractType::Contract, &[], vec![function])
+        }];
+
+        let hits: Vec<(EvmDetectorKind, usize)> = EvmDetectorRunner::new(&contracts)
+            .run()
+            .into_iter()
+            .map(|hit| (hit.kind, hit.line))
+            .collect();
+
+        assert_eq!(
+            hits,
+            vec![
+                (EvmDetectorKind::TxOriginAuth, 11),
+                (EvmDetectorKind::UserDelegatecall, 12),
+                (EvmDetectorKind::Selfdestruct, 21),
+                (EvmDetectorKind::UncheckedUserArithmetic, 14),
+                (EvmDetectorKind::InlineAssembly, 17),
+                (EvmDetectorKind::TimestampComparison, 20),
+                (EvmDetectorKind::UnboundedStorageLoop, 23),
+                (EvmDetectorKind::EncodePackedCollision, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_detectors_check_modifiers() {
+        let contracts = vec![EvmContract {
+            storage_variables: vec![state_variable("owner", "address")],
+            modifiers: vec![modifier(
+                "onlyOwner",
+                "Vault",
+                "    modifier onlyOwner() {\n        require(tx.origin == owner);\n        _;\n    }",
+            )],
+            ..contract("Vault", EvmContractType::Contract, &[], vec![])
+        }];
+
+        let hits = EvmDetectorRunner::new(&contracts).run();
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, EvmDetectorKind::TxOriginAuth);
+        assert_eq!(hits[0].function_metadata_id, "Vault.onlyOwner()");
+        assert_eq!(hits[0].function, "onlyOwner");
+        assert_eq!(hits[0].line, 2);
+    }
+}
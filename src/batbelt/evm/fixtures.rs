@@ -0,0 +1,128 @@
+//! Builders of parsed EVM items shared by the unit tests.
+
+use crate::batbelt::evm::types::{
+    EvmContract, EvmContractType, EvmFunction, EvmModifierDef, EvmModifierInvocation,
+    EvmMutability, EvmParam, EvmVisibility, StorageVariable,
+};
+
+pub fn param(name: &str, type_name: &str) -> EvmParam {
+    EvmParam {
+        name: name.to_string(),
+        type_name: type_name.to_string(),
+        storage_location: None,
+    }
+}
+
+/// Internal, non-constant state variable declared on line 2.
+pub fn state_variable(name: &str, type_name: &str) -> StorageVariable {
+    StorageVariable {
+        name: name.to_string(),
+        type_name: type_name.to_string(),
+        visibility: EvmVisibility::Internal,
+        is_constant: false,
+        is_immutable: false,
+        value: None,
+        line: 2,
+    }
+}
+
+/// Non-payable function starting on line 1, internal if its name starts with `_`.
+///
+/// `constructor`, `fallback` and `receive` get their flag set from the name.
+pub fn function(name: &str, contract_name: &str, body: &str) -> EvmFunction {
+    EvmFunction {
+        name: name.to_string(),
+        contract_name: contract_name.to_string(),
+        visibility: if name.starts_with('_') {
+            EvmVisibility::Internal
+        } else {
+            EvmVisibility::External
+        },
+        mutability: EvmMutability::NonPayable,
+        modifiers: vec![],
+        modifier_invocations: vec![],
+        params: vec![],
+        returns: vec![],
+        body_source: body.to_string(),
+        line: 1,
+        end_line: body.lines().count().max(1),
+        is_constructor: name == "constructor",
+        is_fallback: name == "fallback",
+        is_receive: name == "receive",
+        assembly: vec![],
+    }
+}
+
+/// Modifier without parameters starting on line 1.
+pub fn modifier(name: &str, contract_name: &str, body: &str) -> EvmModifierDef {
+    EvmModifierDef {
+        name: name.to_string(),
+        params: vec![],
+        body_source: body.to_string(),
+        line: 1,
+        end_line: body.lines().count().max(1),
+        contract_name: contract_name.to_string(),
+    }
+}
+
+/// In-scope contract declared on line 1 of `src/<name>.sol`.
+pub fn contract(
+    name: &str,
+    contract_type: EvmContractType,
+    bases: &[&str],
+    functions: Vec<EvmFunction>,
+) -> EvmContract {
+    EvmContract {
+        name: name.to_string(),
+        contract_type,
+        base_contracts: bases.iter().map(|b| b.to_string()).collect(),
+        functions,
+        modifiers: vec![],
+        storage_variables: vec![],
+        events: vec![],
+        structs: vec![],
+        enums: vec![],
+        errors: vec![],
+        udvts: vec![],
+        using_directives: vec![],
+        file_path: format!("src/{}.sol", name),
+        line: 1,
+        external: false,
+    }
+}
+
+impl EvmFunction {
+    /// Applies modifiers without arguments.
+    pub fn with_modifiers(self, modifiers: &[&str]) -> Self {
+        let modifiers = modifiers
+            .iter()
+            .map(|name| (*name, &[] as &[&str]))
+            .collect::<Vec<_>>();
+        self.with_modifier_args(&modifiers)
+    }
+
+    /// Applies modifiers with their arguments, `("onlyRole", &["MINTER_ROLE"])`.
+    pub fn with_modifier_args(mut self, modifiers: &[(&str, &[&str])]) -> Self {
+        self.modifiers = modifiers.iter().map(|(m, _)| m.to_string()).collect();
+        self.modifier_invocations = modifiers
+            .iter()
+            .map(|(m, args)| EvmModifierInvocation {
+                name: m.to_string(),
+                arguments: args.iter().map(|a| a.to_string()).collect(),
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_params(mut self, params: Vec<EvmParam>) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Moves the function so its body starts on `line`.
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.end_line = line + self.end_line - self.line;
+        self.line = line;
+        self
+    }
+}
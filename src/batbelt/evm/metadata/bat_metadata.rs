@@ -1,11 +1,24 @@
 use error_stack::{Report, ResultExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
 use std::{error::Error, fmt, fs};
 
+use crate::batbelt::evm::metadata::privilege_graph::EvmPrivilegeGraph;
+use crate::batbelt::evm::parser::access_control_resolver::AccessControlResolver;
+use crate::batbelt::evm::parser::call_resolver::CallResolver;
+use crate::batbelt::evm::parser::inheritance_resolver::InheritanceResolver;
+use crate::batbelt::evm::parser::proxy_resolver::SelectorRoute;
+use crate::batbelt::evm::parser::selector_resolver::{getter_keys, selector, SelectorResolver};
+use crate::batbelt::evm::parser::storage_access_resolver::StorageAccessResolver;
+use crate::batbelt::evm::parser::storage_layout_resolver::{StorageLayoutResolver, StorageSlot};
 use crate::batbelt::evm::types::{
-    AccessControlType, EvmContract, EvmContractType, EvmEvent, EvmModifierDef, EvmMutability,
-    EvmParam, EvmVisibility, StorageVariable,
+    function_signature, AccessControlType, EvmAssemblyOp, EvmContract, EvmContractType,
+    EvmCustomError, EvmDetectorHit, EvmEmittedEvent, EvmEnum, EvmEvent, EvmExternalCall,
+    EvmFileDefinitions, EvmFunction, EvmModifierDef, EvmMutability, EvmParam, EvmProxyInfo,
+    EvmStruct, EvmUdvt, EvmValidation, EvmVisibility, StorageVariable,
 };
+use crate::batbelt::path::BatFile;
 
 #[derive(Debug)]
 pub struct EvmMetadataError;
@@ -31,6 +44,28 @@ pub struct EvmBatMetadata {
     pub interfaces: Vec<InterfaceMetadata>,
     #[serde(default)]
     pub miro: MiroMetadataRef,
+    #[serde(default)]
+    pub selector_clashes: Vec<SelectorClash>,
+    /// Structs, enums, errors and value types declared outside any contract
+    #[serde(default)]
+    pub file_definitions: Vec<EvmFileDefinitions>,
+    /// Matches of the sonar detectors in in-scope functions
+    #[serde(default)]
+    pub detector_hits: Vec<EvmDetectorHit>,
+    /// Roles, the entry points they can call and who grants and administers them
+    #[serde(default)]
+    pub privileges: EvmPrivilegeGraph,
+    /// In-scope contract names declared in several files
+    #[serde(default)]
+    pub duplicate_contracts: Vec<DuplicateContract>,
+}
+
+/// Contract name declared in several files. Metadata IDs are keyed by contract name, so
+/// their functions and entry points resolve to the first declaration only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateContract {
+    pub name: String,
+    pub file_paths: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +83,23 @@ pub struct ContractMetadata {
     /// true if the contract comes from lib/ (external dependency)
     #[serde(default)]
     pub external: bool,
+    /// Getters generated by the compiler for public state variables
+    #[serde(default)]
+    pub public_getters: Vec<FunctionMetadata>,
+    /// Slot and offset of every state variable, inherited ones included
+    #[serde(default)]
+    pub storage_layout: Vec<StorageSlot>,
+    #[serde(default)]
+    pub structs: Vec<EvmStruct>,
+    #[serde(default)]
+    pub enums: Vec<EvmEnum>,
+    #[serde(default)]
+    pub errors: Vec<EvmCustomError>,
+    #[serde(default)]
+    pub udvts: Vec<EvmUdvt>,
+    /// Proxy pattern and upgradeability findings, filled by the sonar for in-scope contracts
+    #[serde(default)]
+    pub proxy: EvmProxyInfo,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +116,16 @@ pub struct FunctionMetadata {
     #[serde(default)]
     pub end_line: usize,
     pub is_constructor: bool,
+    #[serde(default)]
+    pub is_fallback: bool,
+    #[serde(default)]
+    pub is_receive: bool,
+    /// 4-byte selector (`0xa9059cbb`) of external/public functions and getters
+    #[serde(default)]
+    pub selector: Option<String>,
+    /// `sload`/`sstore`, calls and free-memory writes of the inline assembly
+    #[serde(default)]
+    pub assembly: Vec<EvmAssemblyOp>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,10 +137,16 @@ pub struct EntryPointMetadata {
     pub access_control: Vec<AccessControlType>,
     pub storage_reads: Vec<String>,
     pub storage_writes: Vec<String>,
-    pub external_calls: Vec<String>,
-    pub events_emitted: Vec<String>,
+    pub external_calls: Vec<EvmExternalCall>,
+    pub events_emitted: Vec<EvmEmittedEvent>,
+    /// `require`/`assert`/`revert` checks of the entry point, its modifiers and callees
+    #[serde(default)]
+    pub validations: Vec<EvmValidation>,
     pub modifiers: Vec<String>,
     pub dependencies: Vec<String>,
+    /// Foundry tests calling the entry point, directly or through handlers: `VaultTest.test_deposit`
+    #[serde(default)]
+    pub tests: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -94,6 +162,26 @@ pub struct InterfaceMetadata {
     pub functions: Vec<String>,
 }
 
+/// Functions of different contracts answering to the same selector.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelectorClash {
+    pub selector: String,
+    pub kind: SelectorClashKind,
+    /// Function metadata IDs sharing the selector, the proxy one first
+    pub functions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SelectorClashKind {
+    /// A proxy function shadows (or clashes with) a function of its implementation
+    ProxyImplementation,
+    /// Same as `ProxyImplementation`, for an upgradeable contract the proxy doesn't
+    /// reference: pin its implementation in Bat.toml `[proxy_implementations]` to confirm
+    ProxyImplementationCandidate,
+    /// Several diamond facets register the same selector
+    DiamondFacets,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MiroMetadataRef {
     pub frames: Vec<MiroFrameRef>,
@@ -112,10 +200,26 @@ pub struct MiroFrameRef {
     pub validations_image_id: String,
     #[serde(default)]
     pub dependency_image_ids: Vec<String>,
+    #[serde(default)]
+    pub external_call_note_ids: Vec<String>,
+    #[serde(default)]
+    pub struct_image_ids: Vec<String>,
 }
 
 impl EvmBatMetadata {
+    /// Read the metadata, refusing legacy IDs that would no longer match the parsed functions.
     pub fn read_metadata() -> EvmMetadataResult<Self> {
+        let metadata = Self::read_metadata_file()?;
+        if metadata.has_legacy_ids() {
+            return Err(Report::new(EvmMetadataError).attach_printable(format!(
+                "{} uses legacy function IDs, run `bat-cli sonar` to migrate it",
+                EVM_METADATA_FILE
+            )));
+        }
+        Ok(metadata)
+    }
+
+    fn read_metadata_file() -> EvmMetadataResult<Self> {
         let content = fs::read_to_string(EVM_METADATA_FILE).map_err(|e| {
             Report::new(EvmMetadataError)
                 .attach_printable(format!("Cannot read {}: {}", EVM_METADATA_FILE, e))
@@ -127,6 +231,22 @@ impl EvmBatMetadata {
         Ok(metadata)
     }
 
+    /// Rewrite legacy metadata IDs and move the code-overhaul files of the renamed entry
+    /// points. Returns the moved file paths, or `None` if the metadata was up to date.
+    pub fn migrate_metadata() -> EvmMetadataResult<Option<Vec<String>>> {
+        if !Path::new(EVM_METADATA_FILE).is_file() {
+            return Ok(None);
+        }
+        let mut metadata = Self::read_metadata_file()?;
+        let Some(renamed_entry_points) = metadata.migrate_legacy_ids() else {
+            return Ok(None);
+        };
+        // Files first: a failed move leaves the metadata legacy, so the migration can run again
+        let moved_files = rename_code_overhaul_files(&renamed_entry_points)?;
+        metadata.save_metadata()?;
+        Ok(Some(moved_files))
+    }
+
     pub fn save_metadata(&self) -> EvmMetadataResult<()> {
         let content = serde_json::to_string_pretty(self).map_err(|e| {
             Report::new(EvmMetadataError)
@@ -166,6 +286,87 @@ impl EvmBatMetadata {
         self.contracts.iter().find(|c| c.name == name)
     }
 
+    /// Collect all contracts in the inheritance chain (self + base_contracts, recursively).
+    pub fn get_inheritance_chain(&self, contract_name: &str) -> Vec<&ContractMetadata> {
+        let mut chain = Vec::new();
+        let mut visited: HashSet<String> = HashSet::new();
+        let mut queue: VecDeque<String> = VecDeque::new();
+        queue.push_back(contract_name.to_string());
+
+        while let Some(name) = queue.pop_front() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(c) = self.get_contract_by_name(&name) {
+                chain.push(c);
+                for base in &c.base_contracts {
+                    queue.push_back(base.clone());
+                }
+            }
+        }
+        chain
+    }
+
+    /// Struct definition named `name` as seen from `contract_name`: its inheritance chain
+    /// first, then any other contract, then file level. Returns the declaring file too.
+    pub fn find_struct(&self, contract_name: &str, name: &str) -> Option<(&str, &EvmStruct)> {
+        let name = name.rsplit('.').next().unwrap_or(name);
+        let chain = self.get_inheritance_chain(contract_name);
+        chain
+            .into_iter()
+            .chain(self.contracts.iter())
+            .find_map(|c| {
+                c.structs
+                    .iter()
+                    .find(|s| s.name == name)
+                    .map(|s| (c.file_path.as_str(), s))
+            })
+            .or_else(|| {
+                self.file_definitions.iter().find_map(|d| {
+                    d.structs
+                        .iter()
+                        .find(|s| s.name == name)
+                        .map(|s| (d.file_path.as_str(), s))
+                })
+            })
+    }
+
+    /// Structs an entry point touches through its parameters or the state variables it
+    /// reads and writes, including structs nested in their members.
+    pub fn get_entry_point_structs(&self, ep: &EntryPointMetadata) -> Vec<(&str, &EvmStruct)> {
+        let chain = self.get_inheritance_chain(&ep.contract_name);
+        let mut pending: VecDeque<String> = VecDeque::new();
+        if let Some(func) = self.get_function_by_id(&ep.function_metadata_id) {
+            pending.extend(func.params.iter().map(|p| p.type_name.clone()));
+        }
+        for name in ep.storage_reads.iter().chain(ep.storage_writes.iter()) {
+            if let Some(var) = chain
+                .iter()
+                .find_map(|c| c.state_variables.iter().find(|v| &v.name == name))
+            {
+                pending.push_back(var.type_name.clone());
+            }
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut structs = Vec::new();
+        while let Some(type_name) = pending.pop_front() {
+            for identifier in type_name
+                .split(|ch: char| !(ch.is_alphanumeric() || ch == '_' || ch == '.'))
+                .filter(|token| !token.is_empty())
+            {
+                let Some((path, item)) = self.find_struct(&ep.contract_name, identifier) else {
+                    continue;
+                };
+                if seen.insert(format!("{}:{}", path, item.name)) {
+                    pending.extend(item.members.iter().map(|m| m.type_name.clone()));
+                    structs.push((path, item));
+                }
+            }
+        }
+        structs
+    }
+
     pub fn get_function_by_id(&self, id: &str) -> Option<&FunctionMetadata> {
         self.contracts
             .iter()
@@ -173,13 +374,291 @@ impl EvmBatMetadata {
             .find(|f| f.metadata_id == id)
     }
 
+    /// Contract that calls through `interface_name` are followed into: `pinned` (from
+    /// `[interface_implementations]` in `Bat.toml`) or else the only in-scope implementation.
+    pub fn get_interface_implementation(
+        &self,
+        interface_name: &str,
+        pinned: Option<&str>,
+    ) -> Option<&ContractMetadata> {
+        if let Some(pinned) = pinned {
+            return self.get_contract_by_name(pinned);
+        }
+        let interface = self.interfaces.iter().find(|i| i.name == interface_name)?;
+        match interface.implemented_by.as_slice() {
+            [implementation] => self.get_contract_by_name(implementation),
+            _ => None,
+        }
+    }
+
+    /// Rewrite the callees made through an interface (`IVault.withdraw`) to the contract
+    /// executing them (`Vault.withdraw`), as picked by `get_interface_implementation`.
+    /// Returns the interfaces left as they are because several contracts implement them.
+    pub fn resolve_interface_callees(
+        &mut self,
+        interface_implementations: &BTreeMap<String, String>,
+    ) -> BTreeMap<String, Vec<String>> {
+        let mut ambiguous: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut implementations: HashMap<String, String> = HashMap::new();
+        for interface in &self.interfaces {
+            let pinned = interface_implementations.get(&interface.name);
+            match self.get_interface_implementation(&interface.name, pinned.map(|p| p.as_str())) {
+                Some(implementation) => {
+                    implementations.insert(interface.name.clone(), implementation.name.clone());
+                }
+                None if interface.implemented_by.len() > 1 => {
+                    ambiguous.insert(interface.name.clone(), interface.implemented_by.clone());
+                }
+                None => {}
+            }
+        }
+
+        for dependency in &mut self.function_dependencies {
+            let mut callees: Vec<String> = dependency
+                .callees
+                .iter()
+                .map(|callee| match callee.split_once('.') {
+                    Some((target, method)) if implementations.contains_key(target) => {
+                        format!("{}.{}", implementations[target], method)
+                    }
+                    _ => callee.clone(),
+                })
+                .collect();
+            callees.sort();
+            callees.dedup();
+            dependency.callees = callees;
+        }
+        ambiguous
+    }
+
+    /// Functions named `name` as executed by `contract_name`: those of the most-derived
+    /// contract of its inheritance chain defining them, skipping interface declarations.
+    pub fn find_functions_in_chain(
+        &self,
+        contract_name: &str,
+        name: &str,
+    ) -> Option<(&ContractMetadata, Vec<&FunctionMetadata>)> {
+        self.get_inheritance_chain(contract_name)
+            .into_iter()
+            .filter(|c| c.contract_type != EvmContractType::Interface)
+            .find_map(|c| {
+                let functions: Vec<&FunctionMetadata> =
+                    c.functions.iter().filter(|f| f.name == name).collect();
+                (!functions.is_empty()).then_some((c, functions))
+            })
+    }
+
+    /// Indexes of the entry points reached by calling `function` through a value of type
+    /// `target_type`: those of the contract itself, of the contracts inheriting it and of
+    /// its implementations. `None` matches every function.
+    pub fn entry_points_called_through(
+        &self,
+        target_type: &str,
+        function: Option<&str>,
+    ) -> Vec<usize> {
+        let implemented_by = self
+            .interfaces
+            .iter()
+            .find(|i| i.name == target_type)
+            .map(|i| i.implemented_by.as_slice())
+            .unwrap_or_default();
+        self.entry_points
+            .iter()
+            .enumerate()
+            .filter(|(_, ep)| {
+                function.is_none_or(|function| {
+                    self.get_function_by_id(&ep.function_metadata_id)
+                        .is_some_and(|f| f.name == function)
+                })
+            })
+            .filter(|(_, ep)| {
+                implemented_by.contains(&ep.contract_name)
+                    || self
+                        .get_inheritance_chain(&ep.contract_name)
+                        .iter()
+                        .any(|c| c.name == target_type)
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
     pub fn get_entry_point_by_name(&self, name: &str) -> Option<&EntryPointMetadata> {
         self.entry_points.iter().find(|ep| ep.name == name)
     }
 
-    /// Build metadata from parsed contracts.
-    pub fn from_contracts(contracts: Vec<EvmContract>) -> Self {
+    /// Selectors reachable on a deployed contract (inherited functions and public getters),
+    /// mapped to the most-derived function metadata ID answering to each of them.
+    pub fn get_selector_surface(&self, contract_name: &str) -> Vec<(String, String)> {
+        let mut surface: Vec<(String, String)> = Vec::new();
+        for contract in self.get_inheritance_chain(contract_name) {
+            for func in contract
+                .functions
+                .iter()
+                .chain(contract.public_getters.iter())
+            {
+                if let Some(selector) = &func.selector {
+                    if !surface.iter().any(|(s, _)| s == selector) {
+                        surface.push((selector.clone(), func.metadata_id.clone()));
+                    }
+                }
+            }
+        }
+        surface
+    }
+
+    /// Find selectors shared within each route: by a proxy and one of its implementation
+    /// candidates or facets, or by several facets of the same diamond.
+    pub fn find_selector_clashes(&self, routes: &[SelectorRoute]) -> Vec<SelectorClash> {
+        let mut clashes: Vec<SelectorClash> = Vec::new();
+        let mut push = |clash: SelectorClash| {
+            if !clashes.contains(&clash) {
+                clashes.push(clash);
+            }
+        };
+
+        for route in routes {
+            if let Some(proxy) = &route.proxy {
+                let proxy_surface = self.get_selector_surface(proxy);
+                for target in &route.targets {
+                    for (selector, target_function) in self.get_selector_surface(target) {
+                        if let Some((_, proxy_function)) =
+                            proxy_surface.iter().find(|(s, _)| *s == selector)
+                        {
+                            let kind = if route.candidate {
+                                SelectorClashKind::ProxyImplementationCandidate
+                            } else {
+                                SelectorClashKind::ProxyImplementation
+                            };
+                            push(SelectorClash {
+                                selector,
+                                kind,
+                                functions: vec![proxy_function.clone(), target_function],
+                            });
+                        }
+                    }
+                }
+            }
+            if !route.diamond {
+                continue;
+            }
+            let mut facet_selectors: Vec<(String, Vec<String>)> = Vec::new();
+            for facet in &route.targets {
+                for (selector, function) in self.get_selector_surface(facet) {
+                    match facet_selectors.iter_mut().find(|(s, _)| *s == selector) {
+                        Some((_, functions)) => functions.push(function),
+                        None => facet_selectors.push((selector, vec![function])),
+                    }
+                }
+            }
+            for (selector, functions) in facet_selectors {
+                if functions.len() > 1 {
+                    push(SelectorClash {
+                        selector,
+                        kind: SelectorClashKind::DiamondFacets,
+                        functions,
+                    });
+                }
+            }
+        }
+
+        clashes
+    }
+
+    /// Rewrite metadata produced before functions were keyed by signature
+    /// (`file_Contract_name`), which made overloaded functions collide.
+    /// Returns the (old, new) entry point names, or `None` if nothing changed.
+    fn has_legacy_ids(&self) -> bool {
+        self.contracts
+            .iter()
+            .flat_map(|c| c.functions.iter())
+            .any(|f| f.metadata_id != evm_function_id(&f.contract_name, &f.name, &f.params))
+    }
+
+    fn migrate_legacy_ids(&mut self) -> Option<Vec<(String, String)>> {
+        if !self.has_legacy_ids() {
+            return None;
+        }
+
+        // Legacy IDs of overloads are identical; they were emitted in declaration order,
+        // so the n-th occurrence of an ID maps to the n-th overload.
+        let mut new_ids: HashMap<String, Vec<(String, String, String)>> = HashMap::new();
+        for func in self
+            .contracts
+            .iter_mut()
+            .flat_map(|c| c.functions.iter_mut())
+        {
+            let raw_types = func
+                .params
+                .iter()
+                .map(|p| p.type_name.clone())
+                .collect::<Vec<_>>()
+                .join(",");
+            let legacy_id = std::mem::replace(
+                &mut func.metadata_id,
+                evm_function_id(&func.contract_name, &func.name, &func.params),
+            );
+            new_ids.entry(legacy_id).or_default().push((
+                func.metadata_id.clone(),
+                format!("{}({})", func.name, raw_types),
+                function_signature(&func.name, &func.params),
+            ));
+        }
+
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        for dep in &mut self.function_dependencies {
+            let n = occurrences
+                .entry(dep.function_metadata_id.clone())
+                .or_insert(0);
+            if let Some((new_id, _, _)) = new_ids
+                .get(&dep.function_metadata_id)
+                .and_then(|ids| ids.get(*n))
+            {
+                dep.function_metadata_id = new_id.clone();
+            }
+            *n += 1;
+        }
+
+        // Overloaded entry points were already named `Contract.name(raw types)`
+        let mut renamed = Vec::new();
+        for ep in &mut self.entry_points {
+            let Some(candidates) = new_ids.get(&ep.function_metadata_id) else {
+                continue;
+            };
+            let Some((new_id, _, signature)) = candidates
+                .iter()
+                .find(|(_, raw_sig, _)| ep.name == format!("{}.{}", ep.contract_name, raw_sig))
+                .or_else(|| candidates.first())
+            else {
+                continue;
+            };
+            let new_name = format!("{}.{}", ep.contract_name, signature);
+            ep.function_metadata_id = new_id.clone();
+            ep.metadata_id = format!("ep_{}", new_name);
+            if ep.name != new_name {
+                renamed.push((std::mem::replace(&mut ep.name, new_name.clone()), new_name));
+            }
+        }
+
+        for frame in &mut self.miro.frames {
+            if let Some((_, new_name)) = renamed
+                .iter()
+                .find(|(old_name, _)| *old_name == frame.entry_point_name)
+            {
+                frame.entry_point_name = new_name.clone();
+            }
+        }
+
+        Some(renamed)
+    }
+
+    /// Build metadata from parsed contracts and the file-level definitions they may use.
+    pub fn from_contracts(
+        contracts: Vec<EvmContract>,
+        file_definitions: Vec<EvmFileDefinitions>,
+    ) -> Self {
         let mut metadata = Self::default();
+        let selector_resolver = SelectorResolver::new(&contracts, &file_definitions);
+        let storage_layout_resolver = StorageLayoutResolver::new(&contracts, &file_definitions);
 
         for contract in &contracts {
             let contract_id = format!("{}_{}", contract.file_path, contract.name);
@@ -187,21 +666,62 @@ impl EvmBatMetadata {
             let functions: Vec<FunctionMetadata> = contract
                 .functions
                 .iter()
-                .map(|f| {
-                    let func_id = format!("{}_{}_{}", contract.file_path, contract.name, f.name);
-                    FunctionMetadata {
-                        metadata_id: func_id,
-                        name: f.name.clone(),
+                .map(|f| FunctionMetadata {
+                    metadata_id: evm_function_id(&contract.name, &f.name, &f.params),
+                    name: f.name.clone(),
+                    contract_name: contract.name.clone(),
+                    visibility: f.visibility.clone(),
+                    mutability: f.mutability.clone(),
+                    modifiers: f.modifiers.clone(),
+                    params: f.params.clone(),
+                    returns: f.returns.clone(),
+                    line: f.line,
+                    end_line: f.end_line,
+                    is_constructor: f.is_constructor,
+                    is_fallback: f.is_fallback,
+                    is_receive: f.is_receive,
+                    selector: selector_resolver
+                        .function_signature(f)
+                        .map(|sig| selector(&sig)),
+                    assembly: f.assembly.clone(),
+                })
+                .collect();
+
+            let public_getters: Vec<FunctionMetadata> = contract
+                .storage_variables
+                .iter()
+                .filter_map(|v| {
+                    let sig = selector_resolver.getter_signature(v, &contract.name)?;
+                    let (keys, value_type) = getter_keys(&v.type_name);
+                    let params: Vec<EvmParam> = keys
+                        .into_iter()
+                        .map(|key| EvmParam {
+                            name: String::new(),
+                            type_name: key,
+                            storage_location: None,
+                        })
+                        .collect();
+                    Some(FunctionMetadata {
+                        metadata_id: evm_function_id(&contract.name, &v.name, &params),
+                        name: v.name.clone(),
                         contract_name: contract.name.clone(),
-                        visibility: f.visibility.clone(),
-                        mutability: f.mutability.clone(),
-                        modifiers: f.modifiers.clone(),
-                        params: f.params.clone(),
-                        returns: f.returns.clone(),
-                        line: f.line,
-                        end_line: f.end_line,
-                        is_constructor: f.is_constructor,
-                    }
+                        visibility: EvmVisibility::Public,
+                        mutability: EvmMutability::View,
+                        modifiers: vec![],
+                        params,
+                        returns: vec![EvmParam {
+                            name: String::new(),
+                            type_name: value_type,
+                            storage_location: None,
+                        }],
+                        line: v.line,
+                        end_line: v.line,
+                        is_constructor: false,
+                        is_fallback: false,
+                        is_receive: false,
+                        selector: Some(selector(&sig)),
+                        assembly: vec![],
+                    })
                 })
                 .collect();
 
@@ -217,98 +737,689 @@ impl EvmBatMetadata {
                 modifiers: contract.modifiers.clone(),
                 line: contract.line,
                 external: contract.external,
+                public_getters,
+                storage_layout: match contract.contract_type {
+                    EvmContractType::Interface | EvmContractType::Library => vec![],
+                    _ => storage_layout_resolver.layout(&contract.name),
+                },
+                structs: contract.structs.clone(),
+                enums: contract.enums.clone(),
+                errors: contract.errors.clone(),
+                udvts: contract.udvts.clone(),
+                proxy: EvmProxyInfo::default(),
             };
 
             metadata.contracts.push(contract_metadata);
         }
 
-        // Build entry points from external/public functions (skip external/lib contracts)
-        for contract in &metadata.contracts.clone() {
+        let storage_access_resolver = StorageAccessResolver::new(&contracts);
+        let call_resolver = CallResolver::new(&contracts);
+        let access_control_resolver = AccessControlResolver::new(&contracts);
+
+        let inheritance_resolver = InheritanceResolver::new(&contracts);
+
+        // Abstract contracts are only audited through the contracts deriving from them,
+        // unless nothing in scope does.
+        let inherited_abstracts: HashSet<&str> = metadata
+            .contracts
+            .iter()
+            .filter(|c| !c.external && c.contract_type == EvmContractType::Contract)
+            .flat_map(|c| inheritance_resolver.linearize(&c.name))
+            .filter_map(|name| {
+                metadata
+                    .get_contract_by_name(&name)
+                    .filter(|c| c.contract_type == EvmContractType::Abstract)
+                    .map(|c| c.name.as_str())
+            })
+            .collect();
+
+        // Build entry points from the external/public functions reachable on each deployable
+        // contract, including inherited ones (skip external/lib contracts)
+        let mut entry_points = Vec::new();
+        for contract in &metadata.contracts {
             if contract.external {
                 continue;
             }
-            if matches!(
-                contract.contract_type,
-                EvmContractType::Interface | EvmContractType::Library
-            ) {
-                continue;
+            match contract.contract_type {
+                EvmContractType::Interface | EvmContractType::Library => continue,
+                EvmContractType::Abstract
+                    if inherited_abstracts.contains(contract.name.as_str()) =>
+                {
+                    continue
+                }
+                _ => {}
             }
 
-            // Detect overloaded function names within this contract
-            let ep_functions: Vec<_> = contract
-                .functions
-                .iter()
-                .filter(|f| {
+            // Most-derived implementation of every function, skipping bodiless interface
+            // declarations
+            let ep_functions: Vec<(&EvmFunction, &FunctionMetadata)> = inheritance_resolver
+                .get_all_functions(&contract.name)
+                .into_iter()
+                .filter(|(_, f)| {
                     matches!(
                         f.visibility,
                         EvmVisibility::External | EvmVisibility::Public
                     ) && !f.is_constructor
                 })
+                .filter_map(|(defined_in, f)| {
+                    let defining_contract = metadata.get_contract_by_name(defined_in)?;
+                    if defining_contract.contract_type == EvmContractType::Interface {
+                        return None;
+                    }
+                    let signature = function_signature(&f.name, &f.params);
+                    let func = defining_contract
+                        .functions
+                        .iter()
+                        .find(|fm| function_signature(&fm.name, &fm.params) == signature)?;
+                    Some((f, func))
+                })
                 .collect();
 
-            let mut name_counts: std::collections::HashMap<String, usize> =
-                std::collections::HashMap::new();
-            for func in &ep_functions {
-                *name_counts.entry(func.name.clone()).or_insert(0) += 1;
-            }
+            for (evm_function, func) in ep_functions {
+                let ep_name = format!(
+                    "{}.{}",
+                    contract.name,
+                    function_signature(&func.name, &func.params)
+                );
 
-            for func in &ep_functions {
-                // If overloaded, append param types to disambiguate
-                let ep_name = if name_counts.get(&func.name).copied().unwrap_or(0) > 1 {
-                    let param_types = func
-                        .params
-                        .iter()
-                        .map(|p| p.type_name.clone())
-                        .collect::<Vec<_>>()
-                        .join(",");
-                    format!("{}.{}({})", contract.name, func.name, param_types)
-                } else {
-                    format!("{}.{}", contract.name, func.name)
-                };
+                let storage_access = storage_access_resolver.resolve(&contract.name, evm_function);
+                let external_calls =
+                    call_resolver.resolve_external_calls(&contract.name, evm_function);
+                let events_emitted =
+                    call_resolver.resolve_emitted_events(&contract.name, evm_function);
+                let validations = call_resolver.resolve_validations(&contract.name, evm_function);
 
                 let ep = EntryPointMetadata {
-                    metadata_id: format!("ep_{}", func.metadata_id),
+                    metadata_id: format!("ep_{}", ep_name),
                     name: ep_name,
                     contract_name: contract.name.clone(),
                     function_metadata_id: func.metadata_id.clone(),
-                    access_control: detect_access_control(&func.modifiers),
-                    storage_reads: vec![],
-                    storage_writes: vec![],
-                    external_calls: vec![],
-                    events_emitted: vec![],
+                    access_control: access_control_resolver.resolve(&contract.name, evm_function),
+                    storage_reads: storage_access.reads,
+                    storage_writes: storage_access.writes,
+                    external_calls,
+                    events_emitted,
+                    validations,
                     modifiers: func.modifiers.clone(),
                     dependencies: vec![],
+                    tests: vec![],
                 };
-                metadata.entry_points.push(ep);
+                entry_points.push(ep);
             }
         }
+        metadata.entry_points = entry_points;
+
+        // In-scope contracts implementing each interface, by inheritance or by exposing
+        // every function of it (public getters included)
+        let mut interfaces = Vec::new();
+        for interface in contracts
+            .iter()
+            .filter(|c| c.contract_type == EvmContractType::Interface)
+        {
+            let functions: Vec<String> = inheritance_resolver
+                .get_all_functions(&interface.name)
+                .into_iter()
+                .map(|(_, f)| function_signature(&f.name, &f.params))
+                .collect();
+            let implemented_by = contracts
+                .iter()
+                .filter(|c| !c.external && c.contract_type == EvmContractType::Contract)
+                .filter(|c| {
+                    if inheritance_resolver.inherits_from(&c.name, &interface.name) {
+                        return true;
+                    }
+                    let chain = metadata.get_inheritance_chain(&c.name);
+                    let exposed: HashSet<String> = chain
+                        .iter()
+                        .flat_map(|c| c.functions.iter().chain(c.public_getters.iter()))
+                        .filter(|f| {
+                            matches!(
+                                f.visibility,
+                                EvmVisibility::External | EvmVisibility::Public
+                            )
+                        })
+                        .map(|f| function_signature(&f.name, &f.params))
+                        .collect();
+                    !functions.is_empty() && functions.iter().all(|f| exposed.contains(f))
+                })
+                .map(|c| c.name.clone())
+                .collect();
+            interfaces.push(InterfaceMetadata {
+                name: interface.name.clone(),
+                implemented_by,
+                functions,
+            });
+        }
+        metadata.interfaces = interfaces;
+        metadata.file_definitions = file_definitions;
+        metadata.duplicate_contracts = duplicate_contracts(&contracts);
 
         metadata
     }
 }
 
-fn detect_access_control(modifiers: &[String]) -> Vec<AccessControlType> {
-    let mut result = Vec::new();
-
-    for modifier in modifiers {
-        match modifier.as_str() {
-            "onlyOwner" => result.push(AccessControlType::OnlyOwner),
-            "onlyRole" => result.push(AccessControlType::RoleBased {
-                role: "DEFAULT_ADMIN_ROLE".to_string(),
-            }),
-            other => {
-                if other.starts_with("only") {
-                    result.push(AccessControlType::CustomModifier {
-                        name: other.to_string(),
-                    });
+/// Names of in-scope contracts declared in more than one file.
+fn duplicate_contracts(contracts: &[EvmContract]) -> Vec<DuplicateContract> {
+    let mut by_name: BTreeMap<&str, Vec<&EvmContract>> = BTreeMap::new();
+    for contract in contracts {
+        by_name.entry(&contract.name).or_default().push(contract);
+    }
+    by_name
+        .into_iter()
+        .filter(|(_, declarations)| {
+            declarations.len() > 1 && declarations.iter().any(|c| !c.external)
+        })
+        .map(|(name, declarations)| DuplicateContract {
+            name: name.to_string(),
+            file_paths: declarations.iter().map(|c| c.file_path.clone()).collect(),
+        })
+        .collect()
+}
+
+/// Canonical metadata ID for a Solidity function: `Contract.name(type1,type2)`.
+pub fn evm_function_id(contract_name: &str, name: &str, params: &[EvmParam]) -> String {
+    format!("{}.{}", contract_name, function_signature(name, params))
+}
+
+/// Parsed function whose metadata ID is `function_metadata_id`.
+pub fn find_evm_function<'a>(
+    contracts: &'a [EvmContract],
+    function_metadata_id: &str,
+) -> Option<&'a EvmFunction> {
+    contracts.iter().find_map(|c| {
+        c.functions
+            .iter()
+            .find(|f| evm_function_id(&c.name, &f.name, &f.params) == function_metadata_id)
+    })
+}
+
+/// Move code-overhaul files of renamed entry points, wherever they are in the workflow.
+/// Returns the old and new path of every moved file.
+fn rename_code_overhaul_files(
+    renamed_entry_points: &[(String, String)],
+) -> EvmMetadataResult<Vec<String>> {
+    let mut moved_files = Vec::new();
+    let mut failures = Vec::new();
+    for (old_name, new_name) in renamed_entry_points {
+        let bat_files = |file_name: &String| {
+            [
+                BatFile::CodeOverhaulToReview {
+                    file_name: file_name.clone(),
+                    program_name: None,
+                },
+                BatFile::CodeOverhaulStarted {
+                    file_name: file_name.clone(),
+                    program_name: None,
+                },
+                BatFile::CodeOverhaulFinished {
+                    file_name: file_name.clone(),
+                    program_name: None,
+                },
+            ]
+        };
+        for (old_file, new_file) in bat_files(old_name).iter().zip(bat_files(new_name).iter()) {
+            if let (Ok(old_path), Ok(new_path)) =
+                (old_file.get_path(false), new_file.get_path(false))
+            {
+                if !Path::new(&old_path).is_file() {
+                    continue;
+                }
+                match fs::rename(&old_path, &new_path) {
+                    Ok(()) => moved_files.extend([old_path, new_path]),
+                    Err(e) => {
+                        failures.push(format!("Cannot move {} to {}: {}", old_path, new_path, e))
+                    }
                 }
             }
         }
     }
+    if failures.is_empty() {
+        return Ok(moved_files);
+    }
+    let mut report = Report::new(EvmMetadataError);
+    for failure in failures {
+        report = report.attach_printable(failure);
+    }
+    Err(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{self, contract, param, state_variable};
+
+    fn function(name: &str, contract_name: &str, line: usize) -> EvmFunction {
+        let body = format!("    function {}() external {{\n    }}", name);
+        fixtures::function(name, contract_name, &body).at_line(line)
+    }
+
+    #[test]
+    fn test_entry_points_include_inherited_functions() {
+        let contracts = vec![
+            contract(
+                "IVault",
+                EvmContractType::Interface,
+                &[],
+                vec![
+                    function("deposit", "IVault", 3),
+                    function("sweep", "IVault", 4),
+                ],
+            ),
+            contract(
+                "BaseVault",
+                EvmContractType::Abstract,
+                &["IVault"],
+                // Flattened sources may declare several functions on one line
+                vec![
+                    function("deposit", "BaseVault", 5),
+                    function("withdraw", "BaseVault", 10),
+                    function("pause", "BaseVault", 10),
+                ],
+            ),
+            contract(
+                "Vault",
+                EvmContractType::Contract,
+                &["BaseVault"],
+                vec![function("deposit", "Vault", 7)],
+            ),
+        ];
+
+        let metadata = EvmBatMetadata::from_contracts(contracts, vec![]);
+        let mut entry_points: Vec<(&str, &str)> = metadata
+            .entry_points
+            .iter()
+            .map(|ep| (ep.name.as_str(), ep.function_metadata_id.as_str()))
+            .collect();
+        entry_points.sort();
+
+        assert_eq!(
+            entry_points,
+            vec![
+                ("Vault.deposit()", "Vault.deposit()"),
+                ("Vault.pause()", "BaseVault.pause()"),
+                ("Vault.withdraw()", "BaseVault.withdraw()"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interface_implementations() {
+        let contracts = vec![
+            contract(
+                "IVault",
+                EvmContractType::Interface,
+                &[],
+                vec![function("withdraw", "IVault", 3)],
+            ),
+            contract(
+                "IOracle",
+                EvmContractType::Interface,
+                &[],
+                vec![function("price", "IOracle", 3)],
+            ),
+            contract(
+                "BaseVault",
+                EvmContractType::Abstract,
+                &["IVault"],
+                vec![function("withdraw", "BaseVault", 5)],
+            ),
+            contract("Vault", EvmContractType::Contract, &["BaseVault"], vec![]),
+            // Implements IOracle without inheriting it
+            contract(
+                "ChainlinkOracle",
+                EvmContractType::Contract,
+                &[],
+                vec![function("price", "ChainlinkOracle", 4)],
+            ),
+            contract(
+                "TwapOracle",
+                EvmContractType::Contract,
+                &[],
+                vec![function("price", "TwapOracle", 4)],
+            ),
+        ];
+
+        let mut metadata = EvmBatMetadata::from_contracts(contracts, vec![]);
+        let implemented_by = |name: &str| {
+            metadata
+                .interfaces
+                .iter()
+                .find(|i| i.name == name)
+                .map(|i| i.implemented_by.clone())
+                .unwrap()
+        };
+        assert_eq!(implemented_by("IVault"), vec!["Vault"]);
+        assert_eq!(
+            implemented_by("IOracle"),
+            vec!["ChainlinkOracle", "TwapOracle"]
+        );
+
+        let implementation = |name: &str, pinned: Option<&str>| {
+            metadata
+                .get_interface_implementation(name, pinned)
+                .map(|c| c.name.as_str())
+        };
+        assert_eq!(implementation("IVault", None), Some("Vault"));
+        assert_eq!(implementation("IOracle", None), None);
+        assert_eq!(
+            implementation("IOracle", Some("TwapOracle")),
+            Some("TwapOracle")
+        );
+
+        let (defining_contract, functions) = metadata
+            .find_functions_in_chain("Vault", "withdraw")
+            .unwrap();
+        assert_eq!(defining_contract.name, "BaseVault");
+        assert_eq!(functions[0].metadata_id, "BaseVault.withdraw()");
+
+        metadata.function_dependencies = vec![FunctionDependency {
+            function_metadata_id: "Router.route()".to_string(),
+            callees: vec![
+                "IOracle.price".to_string(),
+                "IVault.withdraw".to_string(),
+                "_pull".to_string(),
+            ],
+        }];
+        let ambiguous = metadata.resolve_interface_callees(&BTreeMap::new());
+        assert_eq!(
+            metadata.function_dependencies[0].callees,
+            vec!["IOracle.price", "Vault.withdraw", "_pull"]
+        );
+        assert_eq!(
+            ambiguous,
+            BTreeMap::from([(
+                "IOracle".to_string(),
+                vec!["ChainlinkOracle".to_string(), "TwapOracle".to_string()]
+            )])
+        );
+    }
+
+    #[test]
+    fn test_function_ids_use_canonical_signatures() {
+        let mut transfer = function("transfer", "Token", 3);
+        transfer.params = vec![
+            param("to", "address payable"),
+            EvmParam {
+                storage_location: Some("calldata".to_string()),
+                ..param("amounts", "uint[]")
+            },
+        ];
+        assert_eq!(
+            evm_function_id("Token", &transfer.name, &transfer.params),
+            "Token.transfer(address,uint256[])"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_contract_names_are_reported() {
+        let mock = EvmContract {
+            file_path: "src/mocks/Vault.sol".to_string(),
+            ..contract("Vault", EvmContractType::Contract, &[], vec![])
+        };
+        let external = EvmContract {
+            file_path: "lib/token/src/IERC20.sol".to_string(),
+            external: true,
+            ..contract("IERC20", EvmContractType::Interface, &[], vec![])
+        };
+        let metadata = EvmBatMetadata::from_contracts(
+            vec![
+                contract(
+                    "Vault",
+                    EvmContractType::Contract,
+                    &[],
+                    vec![function("deposit", "Vault", 3)],
+                ),
+                mock,
+                external.clone(),
+                EvmContract {
+                    file_path: "lib/other/src/IERC20.sol".to_string(),
+                    ..external
+                },
+            ],
+            vec![],
+        );
+
+        assert_eq!(
+            metadata.duplicate_contracts,
+            vec![DuplicateContract {
+                name: "Vault".to_string(),
+                file_paths: vec![
+                    "src/Vault.sol".to_string(),
+                    "src/mocks/Vault.sol".to_string()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_migrate_legacy_ids_disambiguates_overloads() {
+        let mut by_amount = function("deposit", "Vault", 3);
+        by_amount.params = vec![param("x", "uint")];
+        let mut by_receiver = function("deposit", "Vault", 8);
+        by_receiver.params = vec![param("x", "address")];
+        let mut metadata = EvmBatMetadata::from_contracts(
+            vec![contract(
+                "Vault",
+                EvmContractType::Contract,
+                &[],
+                vec![by_amount, by_receiver],
+            )],
+            vec![],
+        );
+
+        // Rewind to the legacy layout
+        let legacy_id = "src/Vault.sol_Vault_deposit".to_string();
+        for func in &mut metadata.contracts[0].functions {
+            func.metadata_id = legacy_id.clone();
+        }
+        metadata.function_dependencies = vec![
+            FunctionDependency {
+                function_metadata_id: legacy_id.clone(),
+                callees: vec!["_mintShares".to_string()],
+            },
+            FunctionDependency {
+                function_metadata_id: legacy_id.clone(),
+                callees: vec!["_pull".to_string()],
+            },
+        ];
+        metadata.entry_points[0].name = "Vault.deposit(uint)".to_string();
+        metadata.entry_points[1].name = "Vault.deposit(address)".to_string();
+        for ep in &mut metadata.entry_points {
+            ep.function_metadata_id = legacy_id.clone();
+        }
+        metadata.miro.frames.push(MiroFrameRef {
+            entry_point_name: "Vault.deposit(uint)".to_string(),
+            frame_id: "1".to_string(),
+            frame_url: String::new(),
+            images_deployed: false,
+            entry_point_image_id: String::new(),
+            validations_image_id: String::new(),
+            dependency_image_ids: vec![],
+            external_call_note_ids: vec![],
+            struct_image_ids: vec![],
+        });
+
+        assert!(metadata.has_legacy_ids());
+        let renamed = metadata.migrate_legacy_ids().unwrap();
+        assert_eq!(
+            renamed,
+            vec![(
+                "Vault.deposit(uint)".to_string(),
+                "Vault.deposit(uint256)".to_string()
+            )]
+        );
+        assert_eq!(
+            metadata.entry_points[0].function_metadata_id,
+            "Vault.deposit(uint256)"
+        );
+        assert_eq!(
+            metadata.function_dependencies[1].function_metadata_id,
+            "Vault.deposit(address)"
+        );
+        assert_eq!(
+            metadata.miro.frames[0].entry_point_name,
+            "Vault.deposit(uint256)"
+        );
+        assert!(!metadata.has_legacy_ids());
+        assert!(metadata.migrate_legacy_ids().is_none());
+    }
 
-    if result.is_empty() {
-        result.push(AccessControlType::None);
+    #[test]
+    fn test_entry_point_structs_follow_params_storage_and_members() {
+        let mut open = function("open", "Vault", 10);
+        open.params = vec![param("amount", "uint256")];
+        open.body_source = "    function open(uint256 amount) external {\n        positions[msg.sender].size = amount;\n    }".to_string();
+        open.end_line = 12;
+
+        let mut vault = contract("Vault", EvmContractType::Contract, &[], vec![open]);
+        vault.storage_variables.push(StorageVariable {
+            line: 8,
+            ..state_variable("positions", "mapping(address => Position)")
+        });
+        vault.structs.push(EvmStruct {
+            name: "Position".to_string(),
+            members: vec![param("size", "uint256"), param("fee", "Fee")],
+            line: 3,
+            end_line: 6,
+        });
+        vault.structs.push(EvmStruct {
+            name: "Unused".to_string(),
+            members: vec![param("flag", "bool")],
+            line: 7,
+            end_line: 7,
+        });
+        let file_definitions = vec![EvmFileDefinitions {
+            file_path: "src/Types.sol".to_string(),
+            structs: vec![EvmStruct {
+                name: "Fee".to_string(),
+                members: vec![param("bps", "uint16")],
+                line: 4,
+                end_line: 6,
+            }],
+            ..Default::default()
+        }];
+
+        let metadata = EvmBatMetadata::from_contracts(vec![vault], file_definitions);
+        let ep = metadata
+            .get_entry_point_by_name("Vault.open(uint256)")
+            .unwrap();
+        assert_eq!(ep.storage_writes, vec!["positions".to_string()]);
+
+        let structs: Vec<(&str, &str)> = metadata
+            .get_entry_point_structs(ep)
+            .into_iter()
+            .map(|(path, s)| (path, s.name.as_str()))
+            .collect();
+        assert_eq!(
+            structs,
+            vec![("src/Vault.sol", "Position"), ("src/Types.sol", "Fee")]
+        );
     }
 
-    result
+    #[test]
+    fn test_find_selector_clashes() {
+        let address_param = || param("implementation", "address");
+        let mut proxy_upgrade = function("upgradeTo", "VaultProxy", 3);
+        proxy_upgrade.params = vec![address_param()];
+        let mut staking_upgrade = function("upgradeTo", "StakingProxy", 3);
+        staking_upgrade.params = vec![address_param()];
+        let mut impl_upgrade = function("upgradeTo", "Vault", 3);
+        impl_upgrade.params = vec![address_param()];
+
+        let contracts = vec![
+            contract(
+                "VaultProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![proxy_upgrade],
+            ),
+            contract(
+                "Vault",
+                EvmContractType::Contract,
+                &[],
+                vec![impl_upgrade, function("deposit", "Vault", 8)],
+            ),
+            contract(
+                "OwnershipFacet",
+                EvmContractType::Contract,
+                &[],
+                vec![function("owner", "OwnershipFacet", 3)],
+            ),
+            contract(
+                "AdminFacet",
+                EvmContractType::Contract,
+                &[],
+                vec![function("owner", "AdminFacet", 3)],
+            ),
+            contract(
+                "StakingProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![staking_upgrade],
+            ),
+        ];
+        let metadata = EvmBatMetadata::from_contracts(contracts, vec![]);
+        assert_eq!(
+            metadata.contracts[1].functions[0].selector.as_deref(),
+            Some("0x3659cfe6")
+        );
+
+        let clashes = metadata.find_selector_clashes(&[
+            SelectorRoute {
+                proxy: Some("VaultProxy".to_string()),
+                diamond: false,
+                targets: vec!["Vault".to_string()],
+                candidate: false,
+            },
+            SelectorRoute {
+                proxy: None,
+                diamond: true,
+                targets: vec!["OwnershipFacet".to_string(), "AdminFacet".to_string()],
+                candidate: false,
+            },
+            // Facets of another diamond never meet the ones above
+            SelectorRoute {
+                proxy: None,
+                diamond: true,
+                targets: vec!["OwnershipFacet".to_string(), "Vault".to_string()],
+                candidate: false,
+            },
+            // A proxy that doesn't reference the implementation
+            SelectorRoute {
+                proxy: Some("StakingProxy".to_string()),
+                diamond: false,
+                targets: vec!["Vault".to_string()],
+                candidate: true,
+            },
+        ]);
+        assert_eq!(
+            clashes,
+            vec![
+                SelectorClash {
+                    selector: "0x3659cfe6".to_string(),
+                    kind: SelectorClashKind::ProxyImplementation,
+                    functions: vec![
+                        "VaultProxy.upgradeTo(address)".to_string(),
+                        "Vault.upgradeTo(address)".to_string()
+                    ],
+                },
+                SelectorClash {
+                    selector: "0x8da5cb5b".to_string(),
+                    kind: SelectorClashKind::DiamondFacets,
+                    functions: vec![
+                        "OwnershipFacet.owner()".to_string(),
+                        "AdminFacet.owner()".to_string()
+                    ],
+                },
+                SelectorClash {
+                    selector: "0x3659cfe6".to_string(),
+                    kind: SelectorClashKind::ProxyImplementationCandidate,
+                    functions: vec![
+                        "StakingProxy.upgradeTo(address)".to_string(),
+                        "Vault.upgradeTo(address)".to_string()
+                    ],
+                },
+            ]
+        );
+    }
 }
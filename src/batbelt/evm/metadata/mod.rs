@@ -1 +1,3 @@
 pub mod bat_metadata;
+pub mod privilege_graph;
+pub mod reentrancy_report;
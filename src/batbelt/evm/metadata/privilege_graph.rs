@@ -0,0 +1,526 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::batbelt::evm::metadata::bat_metadata::{find_evm_function, EvmBatMetadata};
+use crate::batbelt::evm::parser::role_resolver::RoleResolver;
+use crate::batbelt::evm::types::{
+    AccessControlType, EvmContract, EvmRoleChange, EvmRoleChangeKind, StorageVariable,
+};
+
+/// Pseudo-role checked by `onlyOwner` and held by the `Ownable` owner.
+pub const OWNER_ROLE: &str = "owner";
+
+/// Admin of every `AccessControl` role without an explicit `_setRoleAdmin`.
+pub const DEFAULT_ADMIN_ROLE: &str = "DEFAULT_ADMIN_ROLE";
+
+/// Roles of the in-scope contracts: who can call what, who grants and revokes each role
+/// and which role administers which.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvmPrivilegeGraph {
+    pub roles: Vec<EvmRole>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmRole {
+    /// Deployed contract holding the role
+    pub contract_name: String,
+    /// Role constant, `owner`, or the custom check restricting the caller
+    pub name: String,
+    /// Role administering this one
+    pub admin: Option<String>,
+    /// Entry points only the role can call
+    pub entry_points: Vec<String>,
+    /// Entry points and constructors granting the role
+    pub granted_by: Vec<String>,
+    /// Entry points and constructors revoking the role
+    pub revoked_by: Vec<String>,
+}
+
+impl EvmRole {
+    fn new(contract_name: &str, name: &str) -> Self {
+        Self {
+            contract_name: contract_name.to_string(),
+            name: name.to_string(),
+            admin: None,
+            entry_points: vec![],
+            granted_by: vec![],
+            revoked_by: vec![],
+        }
+    }
+}
+
+impl EvmPrivilegeGraph {
+    pub fn new(contracts: &[EvmContract], metadata: &EvmBatMetadata) -> Self {
+        let resolver = RoleResolver::new(contracts);
+        let mut roles: BTreeMap<(String, String), EvmRole> = BTreeMap::new();
+
+        let mut contract_names: Vec<&str> = metadata
+            .entry_points
+            .iter()
+            .map(|ep| ep.contract_name.as_str())
+            .collect();
+        contract_names.sort();
+        contract_names.dedup();
+
+        for contract_name in contract_names {
+            let has_role_admins = resolver.has_role_admins(contract_name);
+            let chain: Vec<String> = metadata
+                .get_inheritance_chain(contract_name)
+                .iter()
+                .map(|base| base.name.clone())
+                .collect();
+            let state_variables: Vec<&StorageVariable> = contracts
+                .iter()
+                .filter(|c| chain.contains(&c.name))
+                .flat_map(|c| c.storage_variables.iter())
+                .collect();
+            // Roles kept by `AccessControl`, as opposed to `owner` and custom checks
+            let mut managed: Vec<String> = Vec::new();
+            for name in resolver.declared_roles(contract_name) {
+                role(&mut roles, contract_name, &name);
+                push_unique(&mut managed, &name);
+            }
+
+            // Entry points restricted to a role
+            let entry_points: Vec<_> = metadata
+                .entry_points
+                .iter()
+                .filter(|ep| ep.contract_name == contract_name)
+                .collect();
+            for ep in &entry_points {
+                for access_control in &ep.access_control {
+                    if let Some(name) = role_name(access_control, &state_variables) {
+                        if matches!(access_control, AccessControlType::RoleBased { .. }) {
+                            push_unique(&mut managed, &name);
+                        }
+                        push_unique(
+                            &mut role(&mut roles, contract_name, &name).entry_points,
+                            &ep.name,
+                        );
+                    }
+                }
+            }
+
+            // Role changes of the entry points and of the constructors of the chain
+            let mut changes: Vec<(String, Vec<String>, EvmRoleChange)> = Vec::new();
+            for ep in &entry_points {
+                let Some(function) = find_evm_function(contracts, &ep.function_metadata_id) else {
+                    continue;
+                };
+                let params: Vec<String> = function.params.iter().map(|p| p.name.clone()).collect();
+                for change in resolver.role_changes(contract_name, function) {
+                    changes.push((ep.name.clone(), params.clone(), change));
+                }
+            }
+            for base in metadata.get_inheritance_chain(contract_name) {
+                let constructor = contracts
+                    .iter()
+                    .filter(|c| c.name == base.name)
+                    .flat_map(|c| c.functions.iter())
+                    .find(|f| f.is_constructor);
+                let Some(constructor) = constructor else {
+                    continue;
+                };
+                let label = format!("{}.constructor", base.name);
+                for change in resolver.role_changes(contract_name, constructor) {
+                    changes.push((label.clone(), vec![], change));
+                }
+            }
+
+            let mut any_role_grants: Vec<String> = Vec::new();
+            let mut any_role_revokes: Vec<String> = Vec::new();
+            for (changed_by, params, change) in changes {
+                // `grantRole(role, account)`: the caller of the entry point picks the role
+                let chosen_by_caller = change.role.as_ref().is_some_and(|r| params.contains(r));
+                match (change.kind, change.role) {
+                    (EvmRoleChangeKind::Grant, Some(_)) if chosen_by_caller => {
+                        push_unique(&mut any_role_grants, &changed_by)
+                    }
+                    (EvmRoleChangeKind::Revoke, Some(_)) if chosen_by_caller => {
+                        push_unique(&mut any_role_revokes, &changed_by)
+                    }
+                    (EvmRoleChangeKind::Grant, Some(name)) => {
+                        push_unique(&mut managed, &name);
+                        push_unique(
+                            &mut role(&mut roles, contract_name, &name).granted_by,
+                            &changed_by,
+                        )
+                    }
+                    (EvmRoleChangeKind::Revoke, Some(name)) => {
+                        push_unique(&mut managed, &name);
+                        push_unique(
+                            &mut role(&mut roles, contract_name, &name).revoked_by,
+                            &changed_by,
+                        )
+                    }
+                    (EvmRoleChangeKind::SetAdmin, Some(name)) if !chosen_by_caller => {
+                        if let Some(admin) = change.admin_role {
+                            push_unique(&mut managed, &name);
+                            push_unique(&mut managed, &admin);
+                            role(&mut roles, contract_name, &admin);
+                            role(&mut roles, contract_name, &name).admin = Some(admin);
+                        }
+                    }
+                    (EvmRoleChangeKind::TransferOwnership, _) => push_unique(
+                        &mut role(&mut roles, contract_name, OWNER_ROLE).granted_by,
+                        &changed_by,
+                    ),
+                    (EvmRoleChangeKind::RenounceOwnership, _) => push_unique(
+                        &mut role(&mut roles, contract_name, OWNER_ROLE).revoked_by,
+                        &changed_by,
+                    ),
+                    _ => {}
+                }
+            }
+
+            if has_role_admins && !managed.is_empty() {
+                role(&mut roles, contract_name, DEFAULT_ADMIN_ROLE);
+                push_unique(&mut managed, DEFAULT_ADMIN_ROLE);
+                for name in managed {
+                    let managed_role = role(&mut roles, contract_name, &name);
+                    if managed_role.admin.is_none() {
+                        managed_role.admin = Some(DEFAULT_ADMIN_ROLE.to_string());
+                    }
+                    for changed_by in &any_role_grants {
+                        push_unique(&mut managed_role.granted_by, changed_by);
+                    }
+                    for changed_by in &any_role_revokes {
+                        push_unique(&mut managed_role.revoked_by, changed_by);
+                    }
+                }
+            }
+        }
+
+        Self {
+            roles: roles.into_values().collect(),
+        }
+    }
+
+    /// Mermaid flowchart: roles administer roles and call entry points, entry points grant
+    /// and revoke roles. One subgraph per contract.
+    pub fn to_mermaid(&self) -> String {
+        let mut content = String::from("flowchart LR\n");
+        let mut node_ids: HashMap<(String, String), String> = HashMap::new();
+        let mut node = |content: &mut String, contract_name: &str, label: &str, is_role: bool| {
+            let key = (contract_name.to_string(), label.to_string());
+            if let Some(id) = node_ids.get(&key) {
+                return id.clone();
+            }
+            let id = format!("n{}", node_ids.len());
+            let label = label.replace('"', "#quot;");
+            if is_role {
+                content.push_str(&format!("        {}([\"{}\"])\n", id, label));
+            } else {
+                content.push_str(&format!("        {}[\"{}\"]\n", id, label));
+            }
+            node_ids.insert(key, id.clone());
+            id
+        };
+
+        let mut by_contract: BTreeMap<&str, Vec<&EvmRole>> = BTreeMap::new();
+        for role in &self.roles {
+            by_contract
+                .entry(role.contract_name.as_str())
+                .or_default()
+                .push(role);
+        }
+        for (contract_name, roles) in by_contract {
+            content.push_str(&format!("    subgraph {}\n", contract_name));
+            let mut edges: Vec<String> = Vec::new();
+            let prefix = format!("{}.", contract_name);
+            let short = |name: &str| name.strip_prefix(&prefix).unwrap_or(name).to_string();
+            for role in roles {
+                let role_id = node(&mut content, contract_name, &role.name, true);
+                if let Some(admin) = &role.admin {
+                    let admin_id = node(&mut content, contract_name, admin, true);
+                    edges.push(format!("{} -->|administers| {}", admin_id, role_id));
+                }
+                for ep in &role.entry_points {
+                    let ep_id = node(&mut content, contract_name, &short(ep), false);
+                    edges.push(format!("{} --> {}", role_id, ep_id));
+                }
+                for ep in &role.granted_by {
+                    let ep_id = node(&mut content, contract_name, &short(ep), false);
+                    edges.push(format!("{} -.->|grants| {}", ep_id, role_id));
+                }
+                for ep in &role.revoked_by {
+                    let ep_id = node(&mut content, contract_name, &short(ep), false);
+                    edges.push(format!("{} -.->|revokes| {}", ep_id, role_id));
+                }
+            }
+            for edge in edges {
+                content.push_str(&format!("        {}\n", edge));
+            }
+            content.push_str("    end\n");
+        }
+        content
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut content = String::from("# Privilege graph\n\n");
+        content.push_str("| Contract | Role | Admin | Entry points | Granted by | Revoked by |\n");
+        content.push_str("| --- | --- | --- | --- | --- | --- |\n");
+        for role in &self.roles {
+            content.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} | {} |\n",
+                role.contract_name,
+                role.name,
+                role.admin
+                    .as_ref()
+                    .map(|admin| format!("`{}`", admin))
+                    .unwrap_or_else(|| "-".to_string()),
+                quoted(&role.entry_points),
+                quoted(&role.granted_by),
+                quoted(&role.revoked_by)
+            ));
+        }
+        content.push_str("\n```mermaid\n");
+        content.push_str(&self.to_mermaid());
+        content.push_str("```\n");
+        content
+    }
+}
+
+fn role<'r>(
+    roles: &'r mut BTreeMap<(String, String), EvmRole>,
+    contract_name: &str,
+    name: &str,
+) -> &'r mut EvmRole {
+    roles
+        .entry((contract_name.to_string(), name.to_string()))
+        .or_insert_with(|| EvmRole::new(contract_name, name))
+}
+
+/// Role checked by an access control, `None` for unrestricted functions and for the
+/// `onlyRole(getRoleAdmin(role))` check of `grantRole`, which depends on the role granted.
+///
+/// `msg.sender` comparisons and authority calls only name a role when they read one of the
+/// contract's `state_variables` (`guardian`, `authorized[msg.sender]`) or pass one of its
+/// constants (`hasAnyRole(msg.sender, KEEPER_ROLE)`).
+fn role_name(
+    access_control: &AccessControlType,
+    state_variables: &[&StorageVariable],
+) -> Option<String> {
+    match access_control {
+        AccessControlType::OnlyOwner => Some(OWNER_ROLE.to_string()),
+        AccessControlType::RoleBased { role } if role.starts_with("getRoleAdmin(") => None,
+        AccessControlType::RoleBased { role } => Some(role.clone()),
+        AccessControlType::CustomModifier { name } => Some(name.clone()),
+        AccessControlType::RequireMsgSender { compared_to } => {
+            let base = compared_to
+                .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .next()
+                .unwrap_or_default();
+            let reads_state = !compared_to[base.len()..].starts_with('(');
+            state_variables
+                .iter()
+                .any(|v| v.name == base && !v.is_constant && reads_state)
+                .then(|| compared_to.clone())
+        }
+        AccessControlType::Authority { check } => check
+            .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .find(|identifier| {
+                state_variables
+                    .iter()
+                    .any(|v| v.name == *identifier && v.is_constant)
+            })
+            .map(|constant| constant.to_string()),
+        AccessControlType::None => None,
+    }
+}
+
+fn push_unique(names: &mut Vec<String>, name: &str) {
+    if !names.iter().any(|n| n == name) {
+        names.push(name.to_string());
+    }
+}
+
+fn quoted(names: &[String]) -> String {
+    if names.is_empty() {
+        return "-".to_string();
+    }
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, function, modifier, param, state_variable};
+    use crate::batbelt::evm::types::{
+        EvmContractType, EvmModifierDef, EvmVisibility, StorageVariable,
+    };
+
+    /// name, admin, entry points and granters of a role
+    type RoleSummary<'a> = (&'a str, Option<&'a str>, Vec<&'a str>, Vec<&'a str>);
+
+    fn role_constant(name: &str) -> StorageVariable {
+        StorageVariable {
+            visibility: EvmVisibility::Public,
+            is_constant: true,
+            ..state_variable(name, "bytes32")
+        }
+    }
+
+    #[test]
+    fn test_privilege_graph() {
+        let role_params = || vec![param("role", "bytes32"), param("account", "address")];
+        let mut access_control = contract(
+            "AccessControl",
+            EvmContractType::Abstract,
+            &[],
+            vec![
+                function(
+                    "grantRole",
+                    "AccessControl",
+                    "    function grantRole(bytes32 role, address account) public onlyRole(getRoleAdmin(role)) {\n        _grantRole(role, account);\n    }",
+                )
+                .with_params(role_params())
+                .with_modifier_args(&[("onlyRole", &["getRoleAdmin(role)"])])
+                .at_line(20),
+                function(
+                    "getRoleAdmin",
+                    "AccessControl",
+                    "    function getRoleAdmin(bytes32 role) public view returns (bytes32) {\n    }",
+                )
+                .with_params(vec![param("role", "bytes32")])
+                .at_line(30),
+                function(
+                    "_grantRole",
+                    "AccessControl",
+                    "    function _grantRole(bytes32 role, address account) internal {\n    }",
+                )
+                .with_params(role_params())
+                .at_line(40),
+                function(
+                    "_setRoleAdmin",
+                    "AccessControl",
+                    "    function _setRoleAdmin(bytes32 role, bytes32 adminRole) internal {\n    }",
+                )
+                .with_params(vec![param("role", "bytes32"), param("adminRole", "bytes32")])
+                .at_line(50),
+            ],
+        );
+        access_control
+            .storage_variables
+            .push(role_constant("DEFAULT_ADMIN_ROLE"));
+        access_control.modifiers.push(EvmModifierDef {
+            params: vec![param("role", "bytes32")],
+            ..modifier(
+                "onlyRole",
+                "AccessControl",
+                "    modifier onlyRole(bytes32 role) {\n        _checkRole(role);\n        _;\n    }",
+            )
+        });
+
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            &["AccessControl"],
+            vec![
+                function(
+                    "constructor",
+                    "Vault",
+                    "    constructor() {\n        _grantRole(DEFAULT_ADMIN_ROLE, msg.sender);\n        _setRoleAdmin(MINTER_ROLE, PAUSER_ROLE);\n    }",
+                )
+                .at_line(60),
+                function(
+                    "mint",
+                    "Vault",
+                    "    function mint(uint256 amount) external onlyRole(MINTER_ROLE) {\n    }",
+                )
+                .with_params(vec![param("amount", "uint256")])
+                .with_modifier_args(&[("onlyRole", &["MINTER_ROLE"])])
+                .at_line(70),
+                function(
+                    "setFee",
+                    "Vault",
+                    "    function setFee() external {\n        require(msg.sender == treasury);\n    }",
+                )
+                .at_line(80),
+                function(
+                    "sweep",
+                    "Vault",
+                    "    function sweep() external {\n        require(isAuthorized(msg.sender, msg.sig));\n    }",
+                )
+                .at_line(90),
+                function(
+                    "harvest",
+                    "Vault",
+                    "    function harvest() external {\n        require(hasAnyRole(msg.sender, KEEPER_ROLE));\n    }",
+                )
+                .at_line(100),
+                function(
+                    "skim",
+                    "Vault",
+                    "    function skim() external {\n        require(msg.sender == pendingTreasury());\n    }",
+                )
+                .at_line(110),
+            ],
+        );
+        vault
+            .storage_variables
+            .push(state_variable("treasury", "address"));
+        vault.storage_variables.push(role_constant("KEEPER_ROLE"));
+        vault.storage_variables.push(role_constant("MINTER_ROLE"));
+        vault.storage_variables.push(role_constant("PAUSER_ROLE"));
+
+        let contracts = vec![access_control, vault];
+        let metadata = EvmBatMetadata::from_contracts(contracts.clone(), vec![]);
+        let graph = EvmPrivilegeGraph::new(&contracts, &metadata);
+
+        let summary: Vec<RoleSummary> = graph
+            .roles
+            .iter()
+            .map(|role| {
+                (
+                    role.name.as_str(),
+                    role.admin.as_deref(),
+                    role.entry_points.iter().map(|s| s.as_str()).collect(),
+                    role.granted_by.iter().map(|s| s.as_str()).collect(),
+                )
+            })
+            .collect();
+        let grant_role = "Vault.grantRole(bytes32,address)";
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "DEFAULT_ADMIN_ROLE",
+                    Some("DEFAULT_ADMIN_ROLE"),
+                    vec![],
+                    vec!["Vault.constructor", grant_role]
+                ),
+                (
+                    "KEEPER_ROLE",
+                    Some("DEFAULT_ADMIN_ROLE"),
+                    vec!["Vault.harvest()"],
+                    vec![grant_role]
+                ),
+                (
+                    "MINTER_ROLE",
+                    Some("PAUSER_ROLE"),
+                    vec!["Vault.mint(uint256)"],
+                    vec![grant_role]
+                ),
+                (
+                    "PAUSER_ROLE",
+                    Some("DEFAULT_ADMIN_ROLE"),
+                    vec![],
+                    vec![grant_role]
+                ),
+                ("treasury", None, vec!["Vault.setFee()"], vec![]),
+            ]
+        );
+
+        let mermaid = graph.to_mermaid();
+        assert!(mermaid.starts_with("flowchart LR\n    subgraph Vault\n"));
+        assert!(mermaid.contains("([\"MINTER_ROLE\"])"));
+        assert!(mermaid.contains("[\"mint(uint256)\"]"));
+        assert!(mermaid.contains("-->|administers|"));
+        assert!(mermaid.contains("-.->|grants|"));
+    }
+}
@@ -0,0 +1,294 @@
+use error_stack::{Report, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::batbelt::evm::metadata::bat_metadata::{
+    find_evm_function, EntryPointMetadata, EvmBatMetadata, EvmMetadataError, EvmMetadataResult,
+};
+use crate::batbelt::evm::parser::reentrancy_resolver::ReentrancyResolver;
+use crate::batbelt::evm::types::{EvmContract, EvmExternalCall, EvmStateWrite};
+use crate::batbelt::path::BatFile;
+
+/// Written next to `BatMetadata.json`.
+pub const EVM_REENTRANCY_FILE: &str = "BatReentrancy.json";
+
+/// Modifiers (lowercased) acting as a reentrancy lock, besides any `*reentran*` one.
+const REENTRANCY_LOCKS: &[&str] = &["lock", "mutex", "noreentry"];
+
+/// Reentrancy surface of the in-scope entry points.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvmReentrancyReport {
+    /// Entry points writing state after an external call on the same path
+    pub writes_after_calls: Vec<WriteAfterCall>,
+    /// Entry points forwarding value without a reentrancy guard
+    pub unguarded_value_calls: Vec<UnguardedValueCall>,
+    /// State shared by an entry point handing control out and entry points that can
+    /// modify it from the callback
+    pub cross_function: Vec<CrossFunctionGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WriteAfterCall {
+    pub entry_point: String,
+    pub guarded: bool,
+    pub call: EvmExternalCall,
+    pub writes: Vec<EvmStateWrite>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnguardedValueCall {
+    pub entry_point: String,
+    pub calls: Vec<EvmExternalCall>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrossFunctionGroup {
+    pub contract_name: String,
+    pub variable: String,
+    /// Entry points accessing `variable` and making a reentrant call
+    pub callers: Vec<String>,
+    /// Other entry points writing `variable` that the callback can reach
+    pub reentered: Vec<String>,
+}
+
+impl EvmReentrancyReport {
+    pub fn new(contracts: &[EvmContract], metadata: &EvmBatMetadata) -> Self {
+        let resolver = ReentrancyResolver::new(contracts);
+        let mut report = Self::default();
+
+        // Entry points handing control out through a reentrant call
+        let mut callers: Vec<&EntryPointMetadata> = Vec::new();
+        for ep in &metadata.entry_points {
+            let Some(function) = find_evm_function(contracts, &ep.function_metadata_id) else {
+                continue;
+            };
+            let guarded = is_guarded(ep);
+            for (call, writes) in resolver.writes_after_calls(&ep.contract_name, function) {
+                report.writes_after_calls.push(WriteAfterCall {
+                    entry_point: ep.name.clone(),
+                    guarded,
+                    call,
+                    writes,
+                });
+            }
+
+            let value_calls: Vec<EvmExternalCall> = ep
+                .external_calls
+                .iter()
+                .filter(|call| call.forwards_value)
+                .cloned()
+                .collect();
+            if !guarded && !value_calls.is_empty() {
+                report.unguarded_value_calls.push(UnguardedValueCall {
+                    entry_point: ep.name.clone(),
+                    calls: value_calls,
+                });
+            }
+
+            if ep
+                .external_calls
+                .iter()
+                .any(|call| resolver.can_reenter(call))
+            {
+                callers.push(ep);
+            }
+        }
+
+        // A callback can enter any other entry point of the contract, unless both hold the lock
+        let mut groups: BTreeMap<(String, String), CrossFunctionGroup> = BTreeMap::new();
+        for caller in &callers {
+            let accessed = caller.storage_reads.iter().chain(&caller.storage_writes);
+            for variable in accessed {
+                let reentered: Vec<String> = metadata
+                    .entry_points
+                    .iter()
+                    .filter(|ep| ep.contract_name == caller.contract_name)
+                    .filter(|ep| ep.metadata_id != caller.metadata_id)
+                    .filter(|ep| ep.storage_writes.contains(variable))
+                    .filter(|ep| !(is_guarded(caller) && is_guarded(ep)))
+                    .map(|ep| ep.name.clone())
+                    .collect();
+                if reentered.is_empty() {
+                    continue;
+                }
+                let group = groups
+                    .entry((caller.contract_name.clone(), variable.clone()))
+                    .or_insert_with(|| CrossFunctionGroup {
+                        contract_name: caller.contract_name.clone(),
+                        variable: variable.clone(),
+                        callers: vec![],
+                        reentered: vec![],
+                    });
+                if !group.callers.contains(&caller.name) {
+                    group.callers.push(caller.name.clone());
+                }
+                for name in reentered {
+                    if !group.reentered.contains(&name) {
+                        group.reentered.push(name);
+                    }
+                }
+            }
+        }
+        report.cross_function = groups.into_values().collect();
+
+        report
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut content = String::from("# Reentrancy surface\n\n");
+
+        content.push_str("## State writes after external calls\n\n");
+        if self.writes_after_calls.is_empty() {
+            content.push_str("- None detected\n");
+        }
+        for entry in &self.writes_after_calls {
+            let guard = if entry.guarded { " (guarded)" } else { "" };
+            content.push_str(&format!(
+                "- `{}`{}: `{}.{}` ({:?}) in `{}` at line {}, then writes:\n",
+                entry.entry_point,
+                guard,
+                entry.call.target_type,
+                entry.call.function,
+                entry.call.kind,
+                entry.call.caller,
+                entry.call.line
+            ));
+            for write in &entry.writes {
+                content.push_str(&format!(
+                    "  - `{}` in `{}` at line {}\n",
+                    write.variable, write.caller, write.line
+                ));
+            }
+        }
+
+        content.push_str("\n## Value-forwarding calls without `nonReentrant`\n\n");
+        if self.unguarded_value_calls.is_empty() {
+            content.push_str("- None detected\n");
+        }
+        for entry in &self.unguarded_value_calls {
+            let calls = entry
+                .calls
+                .iter()
+                .map(|call| {
+                    format!(
+                        "`{}.{}` in `{}` at line {}",
+                        call.target_type, call.function, call.caller, call.line
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            content.push_str(&format!("- `{}`: {}\n", entry.entry_point, calls));
+        }
+
+        content.push_str("\n## Cross-function reentrancy\n\n");
+        if self.cross_function.is_empty() {
+            content.push_str("- None detected\n");
+        }
+        for group in &self.cross_function {
+            content.push_str(&format!(
+                "- `{}.{}`: calls out from {}; written by {}\n",
+                group.contract_name,
+                group.variable,
+                quoted(&group.callers),
+                quoted(&group.reentered)
+            ));
+        }
+        content
+    }
+
+    /// Write the JSON next to `BatMetadata.json` and the markdown to the notes folder.
+    pub fn save(&self) -> EvmMetadataResult<()> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| {
+            Report::new(EvmMetadataError)
+                .attach_printable(format!("Cannot serialize reentrancy report: {}", e))
+        })?;
+        fs::write(EVM_REENTRANCY_FILE, content).map_err(|e| {
+            Report::new(EvmMetadataError)
+                .attach_printable(format!("Cannot write {}: {}", EVM_REENTRANCY_FILE, e))
+        })?;
+        BatFile::ReentrancyReport
+            .write_content(false, &self.to_markdown())
+            .change_context(EvmMetadataError)
+    }
+}
+
+/// true if the entry point holds a reentrancy lock (`nonReentrant`, Uniswap's `lock`...).
+fn is_guarded(ep: &EntryPointMetadata) -> bool {
+    ep.modifiers.iter().any(|modifier| {
+        let modifier = modifier.to_lowercase();
+        modifier.contains("reentran") || REENTRANCY_LOCKS.contains(&modifier.as_str())
+    })
+}
+
+fn quoted(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, function, modifier, param, state_variable};
+    use crate::batbelt::evm::types::{EvmContractType, EvmFunction, EvmModifierDef};
+
+    fn vault_function(name: &str, modifiers: &[&str], body: &str, line: usize) -> EvmFunction {
+        function(name, "Vault", body)
+            .with_params(vec![param("amount", "uint256")])
+            .with_modifiers(modifiers)
+            .at_line(line)
+    }
+
+    #[test]
+    fn test_report_groups_cross_function_reentrancy() {
+        let withdraw = vault_function(
+            "withdraw",
+            &[],
+            "{\n    payable(msg.sender).call{value: amount}(\"\");\n    balances[msg.sender] -= amount;\n}",
+            10,
+        );
+        let transfer = vault_function(
+            "transfer",
+            &["nonReentrant"],
+            "{\n    balances[msg.sender] -= amount;\n}",
+            20,
+        );
+        let contracts = vec![EvmContract {
+            modifiers: vec![EvmModifierDef {
+                line: 3,
+                end_line: 5,
+                ..modifier("nonReentrant", "Vault", "{\n    _;\n}")
+            }],
+            storage_variables: vec![state_variable("balances", "mapping(address => uint256)")],
+            ..contract(
+                "Vault",
+                EvmContractType::Contract,
+                &[],
+                vec![withdraw, transfer],
+            )
+        }];
+        let metadata = EvmBatMetadata::from_contracts(contracts.clone(), vec![]);
+
+        let report = EvmReentrancyReport::new(&contracts, &metadata);
+
+        assert_eq!(report.writes_after_calls.len(), 1);
+        assert_eq!(
+            report.writes_after_calls[0].entry_point,
+            "Vault.withdraw(uint256)"
+        );
+        assert_eq!(report.writes_after_calls[0].writes[0].line, 12);
+        assert_eq!(report.unguarded_value_calls.len(), 1);
+        assert_eq!(
+            report.cross_function,
+            vec![CrossFunctionGroup {
+                contract_name: "Vault".to_string(),
+                variable: "balances".to_string(),
+                callers: vec!["Vault.withdraw(uint256)".to_string()],
+                reentered: vec!["Vault.transfer(uint256)".to_string()],
+            }]
+        );
+    }
+}
@@ -2,18 +2,22 @@ use colored::Colorize;
 use error_stack::{Report, ResultExt};
 use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use serde_json::{json, Value};
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::{error::Error, fmt};
 use tokio::task::JoinSet;
 
 use crate::batbelt::bat_dialoguer::BatDialoguer;
-use crate::batbelt::evm::metadata::bat_metadata::{EvmBatMetadata, MiroFrameRef};
+use crate::batbelt::evm::metadata::bat_metadata::{
+    EntryPointMetadata, EvmBatMetadata, MiroFrameRef,
+};
+use crate::batbelt::evm::types::{EvmContractType, ExternalCallKind};
 use crate::batbelt::miro::connector::create_connector_with_color;
 use crate::batbelt::miro::frame::{
     MiroFrame, MIRO_BOARD_COLUMNS, MIRO_FRAME_HEIGHT, MIRO_FRAME_WIDTH, MIRO_INITIAL_X,
     MIRO_INITIAL_Y,
 };
-use crate::batbelt::miro::MiroConfig;
+use crate::batbelt::miro::sticky_note::MiroStickyNote;
+use crate::batbelt::miro::{MiroColor, MiroConfig};
 use crate::batbelt::parser::source_code_parser::{SourceCodeParser, SourceCodeScreenshotOptions};
 use crate::config::BatConfig;
 
@@ -134,6 +138,8 @@ pub async fn deploy_co_frames() -> EvmMiroResult<()> {
                     entry_point_image_id: String::new(),
                     validations_image_id: String::new(),
                     dependency_image_ids: vec![],
+                    external_call_note_ids: vec![],
+                    struct_image_ids: vec![],
                 });
             }
         })
@@ -330,31 +336,6 @@ fn find_function_end_line(file_path: &str, start_line: usize) -> usize {
     (start_line + 20).min(total)
 }
 
-/// Collect all contracts in the inheritance chain (self + base_contracts, recursively).
-fn collect_inheritance_chain<'a>(
-    evm_metadata: &'a EvmBatMetadata,
-    contract_name: &str,
-) -> Vec<&'a crate::batbelt::evm::metadata::bat_metadata::ContractMetadata> {
-    let mut chain = Vec::new();
-    let mut visited: HashSet<String> = HashSet::new();
-    let mut queue: VecDeque<String> = VecDeque::new();
-    queue.push_back(contract_name.to_string());
-
-    while let Some(name) = queue.pop_front() {
-        if visited.contains(&name) {
-            continue;
-        }
-        visited.insert(name.clone());
-        if let Some(c) = evm_metadata.get_contract_by_name(&name) {
-            chain.push(c);
-            for base in &c.base_contracts {
-                queue.push_back(base.clone());
-            }
-        }
-    }
-    chain
-}
-
 /// Resolve direct dependencies for a function using pre-computed metadata from sonar
 /// + modifier resolution from the inheritance chain.
 /// Returns (metadata_id, name, file_path, line, end_line).
@@ -363,15 +344,28 @@ fn resolve_evm_function_deps(
     contract_name: &str,
     func_metadata_id: &str,
     func_modifiers: &[String],
-    _func_lines: &[String],
-    _contract_file_path: &str,
+    interface_implementations: &BTreeMap<String, String>,
+    ambiguous_interfaces: &mut BTreeMap<String, Vec<String>>,
 ) -> Vec<(String, String, String, usize, usize)> {
     let mut deps: Vec<(String, String, String, usize, usize)> = Vec::new();
     let mut seen_names: HashSet<String> = HashSet::new();
     let mut seen_ids: HashSet<String> = HashSet::new();
 
     // Collect all contracts in the inheritance chain
-    let chain = collect_inheritance_chain(evm_metadata, contract_name);
+    let mut chain = evm_metadata.get_inheritance_chain(contract_name);
+    // A library or implementation function (reached through `Target.fn` below) calls into
+    // its own contract
+    if let Some(owner) = evm_metadata.contracts.iter().find(|c| {
+        c.functions
+            .iter()
+            .any(|f| f.metadata_id == func_metadata_id)
+    }) {
+        for c in evm_metadata.get_inheritance_chain(&owner.name) {
+            if !chain.iter().any(|known| known.name == c.name) {
+                chain.push(c);
+            }
+        }
+    }
 
     // 1. Resolve modifiers as dependencies (search whole inheritance chain)
     for mod_name in func_modifiers {
@@ -431,6 +425,65 @@ fn resolve_evm_function_deps(
         }
     }
 
+    // 3. Calls into other contracts: libraries (`SafeERC20.safeTransfer`, or
+    // `token.safeTransfer` resolved through `using for` during sonar), and interfaces
+    // followed into their implementation (`IVault(vault).withdraw` into `Vault.withdraw`)
+    for called_name in &called_names {
+        let Some((target_name, method)) = called_name.split_once('.') else {
+            continue;
+        };
+        let Some(target) = evm_metadata.get_contract_by_name(target_name) else {
+            continue;
+        };
+        let executed_by = match target.contract_type {
+            EvmContractType::Interface => {
+                let pinned = interface_implementations.get(target_name);
+                match evm_metadata
+                    .get_interface_implementation(target_name, pinned.map(|p| p.as_str()))
+                {
+                    Some(implementation) => implementation,
+                    None => {
+                        let implemented_by = evm_metadata
+                            .interfaces
+                            .iter()
+                            .find(|i| i.name == target_name)
+                            .map(|i| i.implemented_by.clone())
+                            .unwrap_or_default();
+                        if implemented_by.len() > 1 && pinned.is_none() {
+                            ambiguous_interfaces.insert(target_name.to_string(), implemented_by);
+                        }
+                        continue;
+                    }
+                }
+            }
+            _ => target,
+        };
+        let Some((defining_contract, functions)) =
+            evm_metadata.find_functions_in_chain(&executed_by.name, method)
+        else {
+            continue;
+        };
+        for func_meta in functions {
+            if func_meta.metadata_id == func_metadata_id
+                || !seen_ids.insert(func_meta.metadata_id.clone())
+            {
+                continue;
+            }
+            let end = if func_meta.end_line > 0 {
+                func_meta.end_line
+            } else {
+                find_function_end_line(&defining_contract.file_path, func_meta.line)
+            };
+            deps.push((
+                func_meta.metadata_id.clone(),
+                format!("{}.{}", defining_contract.name, func_meta.name),
+                defining_contract.file_path.clone(),
+                func_meta.line,
+                end,
+            ));
+        }
+    }
+
     deps
 }
 
@@ -714,14 +767,54 @@ pub async fn deploy_source_code_screenshots(selected_miro_frame: MiroFrame) -> E
     Ok(())
 }
 
+/// Deploy one sticky note per external call of the entry point, so every trust
+/// boundary is visible next to the entry point screenshot.
+async fn deploy_external_call_notes(
+    co_miro_frame: &MiroFrame,
+    ep: &EntryPointMetadata,
+) -> EvmMiroResult<Vec<String>> {
+    let mut note_ids = Vec::new();
+    for (idx, call) in ep.external_calls.iter().enumerate() {
+        let color = if call.forwards_value || call.kind == ExternalCallKind::DelegateCall {
+            MiroColor::Red
+        } else if matches!(
+            call.kind,
+            ExternalCallKind::StaticCall | ExternalCallKind::Library
+        ) {
+            MiroColor::LightYellow
+        } else {
+            MiroColor::Orange
+        };
+        let content = format!(
+            "External call ({:?}):<br> <strong>{}.{}</strong><br>{}:{}",
+            call.kind, call.target_type, call.function, call.caller, call.line
+        );
+        let mut note = MiroStickyNote::new(
+            &content,
+            color,
+            &co_miro_frame.item_id,
+            300,
+            (150 + idx * 270) as i64,
+            374,
+            0,
+        );
+        note.deploy().await.change_context(EvmMiroError)?;
+        note_ids.push(note.item_id);
+    }
+    Ok(note_ids)
+}
+
 /// Deploy code-overhaul screenshots for a single EVM entry point into its Miro frame.
 ///
-/// Deploys: entry point screenshot, validations screenshot (with header),
-/// and BFS dependency screenshots (modifiers + internal calls) with connectors.
+/// Deploys: entry point screenshot, external call sticky notes, validations screenshot
+/// (with header), and BFS dependency screenshots (modifiers + internal calls) with connectors.
 pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()> {
     MiroConfig::check_miro_enabled().change_context(EvmMiroError)?;
 
     let evm_metadata = EvmBatMetadata::read_metadata().change_context(EvmMiroError)?;
+    let interface_implementations = BatConfig::get_config()
+        .change_context(EvmMiroError)?
+        .interface_implementations;
 
     // Find frame ref
     let frame_ref = evm_metadata
@@ -762,19 +855,20 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
         })?
         .clone();
 
-    let contract = evm_metadata
-        .get_contract_by_name(&ep.contract_name)
+    let func = evm_metadata
+        .get_function_by_id(&ep.function_metadata_id)
         .ok_or_else(|| {
             Report::new(EvmMiroError)
-                .attach_printable(format!("Contract '{}' not found", ep.contract_name))
+                .attach_printable(format!("Function '{}' not found", ep.function_metadata_id))
         })?
         .clone();
 
-    let func = evm_metadata
-        .get_function_by_id(&ep.function_metadata_id)
+    // Inherited entry points live in the base contract's file
+    let contract = evm_metadata
+        .get_contract_by_name(&func.contract_name)
         .ok_or_else(|| {
             Report::new(EvmMiroError)
-                .attach_printable(format!("Function '{}' not found", ep.function_metadata_id))
+                .attach_printable(format!("Contract '{}' not found", func.contract_name))
         })?
         .clone();
 
@@ -815,6 +909,8 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
 
     let entry_point_image_id = ep_image.item_id.clone();
 
+    let external_call_note_ids = deploy_external_call_notes(&co_miro_frame, &ep).await?;
+
     // 2. BFS deployment of dependency screenshots
     // Resolve deps from function body + modifiers (not from function_dependencies which may be empty)
     // Track both IDs and names to prevent deploying virtual parent versions of overridden functions
@@ -830,24 +926,12 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
         entry_point_image_id.clone(),
     );
 
-    let mut bfs_queue: VecDeque<(String, String, Vec<String>, Vec<String>, String)> =
-        VecDeque::new();
-
-    // Read body lines for the entry point function
-    let ep_body_lines: Vec<String> = {
-        let file_content = std::fs::read_to_string(&contract.file_path).unwrap_or_default();
-        let lines: Vec<&str> = file_content.lines().collect();
-        let start = if func.line > 0 { func.line - 1 } else { 0 };
-        let end = ep_end_line.min(lines.len());
-        lines[start..end].iter().map(|s| s.to_string()).collect()
-    };
+    let mut bfs_queue: VecDeque<(String, String, Vec<String>)> = VecDeque::new();
 
     bfs_queue.push_back((
         ep.function_metadata_id.clone(),
         func.name.clone(),
         func.modifiers.clone(),
-        ep_body_lines,
-        contract.file_path.clone(),
     ));
 
     let mut dependency_image_ids: Vec<String> = Vec::new();
@@ -867,18 +951,17 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
         ("#888800", "olive"),
     ];
     let mut color_index: usize = 0;
+    let mut ambiguous_interfaces: BTreeMap<String, Vec<String>> = BTreeMap::new();
 
-    while let Some((caller_id, caller_name, caller_modifiers, caller_body, caller_file)) =
-        bfs_queue.pop_front()
-    {
+    while let Some((caller_id, caller_name, caller_modifiers)) = bfs_queue.pop_front() {
         // Resolve deps dynamically from body + modifiers
         let new_dep_functions = resolve_evm_function_deps(
             &evm_metadata,
             &ep.contract_name,
             &caller_id,
             &caller_modifiers,
-            &caller_body,
-            &caller_file,
+            &interface_implementations,
+            &mut ambiguous_interfaces,
         );
 
         // Filter already deployed (by ID or by name to prevent virtual/override duplicates)
@@ -953,22 +1036,57 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
             deployed_function_names.insert(base_name.to_string());
             dependency_image_ids.push(dep_image.item_id.clone());
 
-            // Read body of dep for further BFS (only for non-modifier deps)
-            let dep_body: Vec<String> = {
-                let fc = std::fs::read_to_string(dep_path).unwrap_or_default();
-                let lines: Vec<&str> = fc.lines().collect();
-                let s = if *dep_line > 0 { dep_line - 1 } else { 0 };
-                let e = (*dep_end).min(lines.len());
-                lines[s..e].iter().map(|l| l.to_string()).collect()
-            };
             // Dep functions don't have modifiers in this context (we'd need to look them up)
-            bfs_queue.push_back((
-                dep_id.clone(),
-                dep_name.clone(),
-                vec![],
-                dep_body,
-                dep_path.clone(),
-            ));
+            bfs_queue.push_back((dep_id.clone(), dep_name.clone(), vec![]));
+        }
+    }
+    for (interface, implemented_by) in &ambiguous_interfaces {
+        println!(
+            "  {} has several implementations ({}), pin one in Bat.toml [interface_implementations] to follow its calls",
+            interface.yellow(),
+            implemented_by.join(", ")
+        );
+    }
+
+    // 3. Structs touched by the entry point (parameters and accessed state), one column
+    // to the right of the dependencies
+    let touched_structs = evm_metadata.get_entry_point_structs(&ep);
+    let mut struct_image_ids: Vec<String> = Vec::new();
+    if !touched_structs.is_empty() {
+        const STRUCTS_START_X: i64 = 3600;
+        const STRUCTS_START_Y: i64 = 260;
+
+        let prompt = format!(
+            "Press Enter to deploy {} structs used by `{}`",
+            touched_structs.len(),
+            func.name
+        );
+        BatDialoguer::input_with_default(prompt, "".to_string()).change_context(EvmMiroError)?;
+
+        for (idx, (struct_path, evm_struct)) in touched_structs.iter().enumerate() {
+            let struct_sc = SourceCodeParser::new(
+                format!("struct_{}.js", evm_struct.name),
+                struct_path.to_string(),
+                evm_struct.line,
+                evm_struct.end_line.max(evm_struct.line),
+            );
+            let struct_image = struct_sc
+                .deploy_screenshot_to_miro_frame(
+                    co_miro_frame.clone(),
+                    STRUCTS_START_X + (idx as i64) * CASCADE_STEP,
+                    STRUCTS_START_Y + (idx as i64) * CASCADE_STEP,
+                    SourceCodeScreenshotOptions {
+                        include_path: true,
+                        offset_to_start_line: true,
+                        filter_comments: false,
+                        font_size: Some(16),
+                        filters: None,
+                        show_line_number: true,
+                    },
+                )
+                .await
+                .change_context(EvmMiroError)?;
+            struct_image_ids.push(struct_image.item_id.clone());
         }
     }
 
@@ -984,6 +1102,8 @@ pub async fn deploy_co_screenshots(entry_point_name: &str) -> EvmMiroResult<()>
             frame.entry_point_image_id = entry_point_image_id.clone();
             frame.validations_image_id = String::new();
             frame.dependency_image_ids = dependency_image_ids.clone();
+            frame.external_call_note_ids = external_call_note_ids.clone();
+            frame.struct_image_ids = struct_image_ids.clone();
         }
     })
     .change_context(EvmMiroError)?;
@@ -1,3 +1,6 @@
+pub mod detectors;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod metadata;
 pub mod miro;
 pub mod parser;
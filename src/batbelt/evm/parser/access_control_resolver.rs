@@ -0,0 +1,542 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use lazy_regex::regex;
+
+use solar_parse::{
+    ast::{self, Visit},
+    interface::Session,
+};
+
+use crate::batbelt::evm::types::{
+    AccessControlType, EvmContract, EvmFunction, EvmModifierDef, EvmModifierInvocation, EvmParam,
+};
+
+use super::call_resolver::{is_builtin, CallResolver};
+use super::evm_file_parser::{expr_to_string, with_parsed_body};
+
+/// Expressions evaluating to the caller of the current call.
+const SENDER_EXPRESSIONS: &[&str] = &["msg.sender", "_msgSender()"];
+
+/// Expressions reading the owner of an `Ownable`-like contract.
+const OWNER_EXPRESSIONS: &[&str] = &["owner()", "owner", "_owner"];
+
+/// Functions returning whether an account is authorized, such as `hasRole(ROLE, account)`,
+/// Solmate's `isAuthorized(user, sig)` or an `Authority`'s `canCall(user, target, sig)`.
+const AUTH_CHECK_FUNCTIONS: &[&str] = &[
+    "hasRole",
+    "hasAnyRole",
+    "hasAllRoles",
+    "isAuthorized",
+    "canCall",
+    "isAllowed",
+    "isOwner",
+    "isAdmin",
+    "isOperator",
+    "contains",
+];
+
+/// Modifiers recognised by name when their definition is not available.
+const AUTH_MODIFIERS: &[&str] = &["auth", "requiresAuth", "restricted", "authorized"];
+
+/// How deep internal calls are followed from a modifier or function body.
+const MAX_CALL_DEPTH: usize = 6;
+
+/// Detects the access control enforced on a function by analysing its modifiers' bodies and
+/// its own body: `msg.sender` comparisons, `_checkOwner()`, `_checkRole(ROLE)`,
+/// `hasRole(ROLE, msg.sender)` and authority checks, followed through internal calls.
+pub struct AccessControlResolver<'a> {
+    call_resolver: CallResolver<'a>,
+}
+
+impl<'a> AccessControlResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self {
+            call_resolver: CallResolver::new(contracts),
+        }
+    }
+
+    /// Access control of `function` as executed by `contract_name`. Returns
+    /// `[AccessControlType::None]` if nothing restricts the caller.
+    pub fn resolve(&self, contract_name: &str, function: &EvmFunction) -> Vec<AccessControlType> {
+        let invocations: Vec<EvmModifierInvocation> = if function.modifier_invocations.is_empty() {
+            function
+                .modifiers
+                .iter()
+                .map(|name| EvmModifierInvocation {
+                    name: name.clone(),
+                    arguments: vec![],
+                })
+                .collect()
+        } else {
+            function.modifier_invocations.clone()
+        };
+
+        let mut result: Vec<AccessControlType> = Vec::new();
+        for invocation in &invocations {
+            let checks = match self
+                .call_resolver
+                .resolve_modifier(contract_name, &invocation.name)
+            {
+                Some(modifier) => {
+                    self.modifier_checks(contract_name, modifier, &invocation.arguments)
+                }
+                None => access_control_from_name(invocation).into_iter().collect(),
+            };
+            push_unique(&mut result, checks);
+        }
+
+        let mut visited = HashSet::new();
+        let body_checks = self.body_checks(
+            contract_name,
+            &function.body_source,
+            &HashMap::new(),
+            0,
+            &mut visited,
+        );
+        push_unique(&mut result, body_checks);
+
+        if result.is_empty() {
+            result.push(AccessControlType::None);
+        }
+        result
+    }
+
+    /// Checks performed by `modifier` when invoked with `arguments` (empty to keep the
+    /// parameter names).
+    pub fn modifier_checks(
+        &self,
+        contract_name: &str,
+        modifier: &EvmModifierDef,
+        arguments: &[String],
+    ) -> Vec<AccessControlType> {
+        let bindings = bind_arguments(&modifier.params, arguments);
+        let mut visited = HashSet::new();
+        self.body_checks(
+            contract_name,
+            &modifier.body_source,
+            &bindings,
+            0,
+            &mut visited,
+        )
+    }
+
+    fn body_checks(
+        &self,
+        contract_name: &str,
+        body_source: &str,
+        bindings: &HashMap<String, String>,
+        depth: usize,
+        visited: &mut HashSet<*const EvmFunction>,
+    ) -> Vec<AccessControlType> {
+        let (mut checks, calls) = with_parsed_body(body_source, |sess, block| {
+            let mut visitor = AccessControlVisitor {
+                sess,
+                bindings,
+                checks: Vec::new(),
+                calls: Vec::new(),
+            };
+            let _ = visitor.visit_block(block);
+            (visitor.checks, visitor.calls)
+        })
+        .unwrap_or_default();
+
+        if depth >= MAX_CALL_DEPTH {
+            return checks;
+        }
+        let lookup_order = self.call_resolver.lookup_order(contract_name);
+        for (name, arguments) in calls {
+            let callee = lookup_order.iter().find_map(|c| {
+                c.functions
+                    .iter()
+                    .find(|f| f.name == name && f.params.len() == arguments.len())
+            });
+            let Some(callee) = callee else {
+                continue;
+            };
+            if !visited.insert(callee as *const EvmFunction) {
+                continue;
+            }
+            let callee_bindings = bind_arguments(&callee.params, &arguments);
+            let callee_checks = self.body_checks(
+                contract_name,
+                &callee.body_source,
+                &callee_bindings,
+                depth + 1,
+                visited,
+            );
+            push_unique(&mut checks, callee_checks);
+        }
+        checks
+    }
+}
+
+/// Name-based fallback for modifiers whose definition is not available.
+fn access_control_from_name(invocation: &EvmModifierInvocation) -> Option<AccessControlType> {
+    let name = invocation.name.as_str();
+    match name {
+        "onlyOwner" => Some(AccessControlType::OnlyOwner),
+        "onlyRole" => Some(AccessControlType::RoleBased {
+            role: invocation
+                .arguments
+                .first()
+                .cloned()
+                .unwrap_or_else(|| "DEFAULT_ADMIN_ROLE".to_string()),
+        }),
+        "onlyInitializing" => None,
+        _ if name.starts_with("only") || AUTH_MODIFIERS.contains(&name) => {
+            Some(AccessControlType::CustomModifier {
+                name: name.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Map parameter names to the argument expressions they receive.
+fn bind_arguments(params: &[EvmParam], arguments: &[String]) -> HashMap<String, String> {
+    params
+        .iter()
+        .zip(arguments.iter())
+        .filter(|(param, _)| !param.name.is_empty())
+        .map(|(param, argument)| (param.name.clone(), argument.clone()))
+        .collect()
+}
+
+/// Replace the identifiers of `text` bound in `bindings`, leaving member names untouched:
+/// with `role => MINTER_ROLE`, `hasRole(role, msg.sender)` becomes
+/// `hasRole(MINTER_ROLE, msg.sender)`.
+fn substitute(text: &str, bindings: &HashMap<String, String>) -> String {
+    if bindings.is_empty() {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut identifier = String::new();
+    let mut previous: Option<char> = None;
+    let mut after_dot = false;
+    let flush = |identifier: &mut String, after_dot: bool, result: &mut String| {
+        match bindings.get(identifier.as_str()) {
+            Some(value) if !after_dot => result.push_str(value),
+            _ => result.push_str(identifier),
+        }
+        identifier.clear();
+    };
+    for ch in text.chars() {
+        if ch.is_alphanumeric() || ch == '_' || ch == '$' {
+            if identifier.is_empty() {
+                after_dot = previous == Some('.');
+            }
+            identifier.push(ch);
+        } else {
+            if !identifier.is_empty() {
+                flush(&mut identifier, after_dot, &mut result);
+            }
+            result.push(ch);
+        }
+        previous = Some(ch);
+    }
+    if !identifier.is_empty() {
+        flush(&mut identifier, after_dot, &mut result);
+    }
+    result
+}
+
+fn push_unique(result: &mut Vec<AccessControlType>, checks: Vec<AccessControlType>) {
+    for check in checks {
+        if !result.contains(&check) {
+            result.push(check);
+        }
+    }
+}
+
+struct AccessControlVisitor<'v> {
+    sess: &'v Session,
+    bindings: &'v HashMap<String, String>,
+    checks: Vec<AccessControlType>,
+    /// Internal calls `(name, arguments)` to follow, arguments already substituted
+    calls: Vec<(String, Vec<String>)>,
+}
+
+impl AccessControlVisitor<'_> {
+    fn text(&self, expr: &ast::Expr<'_>) -> String {
+        substitute(&expr_to_string(self.sess, expr), self.bindings)
+    }
+
+    fn push(&mut self, check: AccessControlType) {
+        if !self.checks.contains(&check) {
+            self.checks.push(check);
+        }
+    }
+
+    /// Access checks in a condition that must evaluate to `required` for the call to go on:
+    /// `true` for `require(condition)`, `false` for `if (condition) revert ...`.
+    fn check_condition(&mut self, expr: &ast::Expr<'_>, required: bool) {
+        match &expr.kind {
+            ast::ExprKind::Unary(op, inner) if op.kind == ast::UnOpKind::Not => {
+                self.check_condition(inner, !required)
+            }
+            ast::ExprKind::Tuple(items) if items.len() == 1 => {
+                if let Some(inner) = items[0].as_deref().unspan() {
+                    self.check_condition(inner, required);
+                }
+            }
+            ast::ExprKind::Binary(lhs, op, rhs) => match op.kind {
+                // Both operands must hold for `a && b`, and both must fail for `!(a || b)`;
+                // either one alone decides `a || b` or `!(a && b)`
+                ast::BinOpKind::And | ast::BinOpKind::Or
+                    if (op.kind == ast::BinOpKind::And) == required =>
+                {
+                    self.check_condition(lhs, required);
+                    self.check_condition(rhs, required);
+                }
+                // `msg.sender == x` must hold, or `msg.sender != x` must fail: anything else
+                // is a deny-list
+                ast::BinOpKind::Eq | ast::BinOpKind::Ne
+                    if (op.kind == ast::BinOpKind::Eq) == required =>
+                {
+                    let (lhs, rhs) = (self.text(lhs), self.text(rhs));
+                    let compared_to = if is_sender(&lhs) {
+                        rhs
+                    } else if is_sender(&rhs) {
+                        lhs
+                    } else {
+                        return;
+                    };
+                    if OWNER_EXPRESSIONS.contains(&compared_to.as_str()) {
+                        self.push(AccessControlType::OnlyOwner);
+                    } else if is_stored_address(&compared_to) {
+                        self.push(AccessControlType::RequireMsgSender { compared_to });
+                    }
+                }
+                _ => {}
+            },
+            ast::ExprKind::Call(callee, args) if required => {
+                let arguments: Vec<String> = args.exprs().map(|arg| self.text(arg)).collect();
+                let callee_text = expr_to_string(self.sess, callee);
+                let name = callee_text.rsplit('.').next().unwrap_or(&callee_text);
+                if !AUTH_CHECK_FUNCTIONS.contains(&name)
+                    || !arguments.iter().any(|arg| is_sender(arg))
+                {
+                    return;
+                }
+                if name == "hasRole" && arguments.len() == 2 && is_sender(&arguments[1]) {
+                    self.push(AccessControlType::RoleBased {
+                        role: arguments[0].clone(),
+                    });
+                } else {
+                    self.push(AccessControlType::Authority {
+                        check: self.text(expr),
+                    });
+                }
+            }
+            // `require(authorized[msg.sender])`: membership of an allow-list
+            ast::ExprKind::Index(_, ast::IndexKind::Index(Some(index)))
+                if required && is_sender(&self.text(index)) =>
+            {
+                self.push(AccessControlType::RequireMsgSender {
+                    compared_to: self.text(expr),
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for AccessControlVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::StmtKind::If(condition, then, otherwise) = &stmt.kind {
+            if always_reverts(then) {
+                self.check_condition(condition, false);
+            } else if otherwise.as_deref().is_some_and(always_reverts) {
+                self.check_condition(condition, true);
+            }
+        }
+        self.walk_stmt(stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, args) = &expr.kind {
+            if let ast::ExprKind::Ident(ident) = &callee.kind {
+                let arguments: Vec<String> = args.exprs().map(|arg| self.text(arg)).collect();
+                match ident.as_str() {
+                    "require" | "assert" => {
+                        if let Some(condition) = args.exprs().next() {
+                            self.check_condition(condition, true);
+                        }
+                    }
+                    "_checkOwner" => self.push(AccessControlType::OnlyOwner),
+                    "_checkRole" if !arguments.is_empty() => {
+                        self.push(AccessControlType::RoleBased {
+                            role: arguments[0].clone(),
+                        })
+                    }
+                    name if !is_builtin(name) => {
+                        self.calls.push((name.to_string(), arguments));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.walk_expr(expr)
+    }
+}
+
+fn is_sender(text: &str) -> bool {
+    SENDER_EXPRESSIONS.contains(&text)
+}
+
+/// Whether `text` reads an address kept by the contract (`guardian`, `roles.admin`,
+/// `admins[id]`, `pendingOwner()`) rather than `tx.origin`, a literal or a cast.
+fn is_stored_address(text: &str) -> bool {
+    regex!(r"^[A-Za-z_$][\w$]*(\.[A-Za-z_$][\w$]*|\[[^\]]*\])*(\(\))?$").is_match(text)
+        && !text.starts_with("tx.")
+        && !text.starts_with("msg.")
+}
+
+/// Whether `stmt` is a revert or a block ending in one.
+fn always_reverts(stmt: &ast::Stmt<'_>) -> bool {
+    match &stmt.kind {
+        ast::StmtKind::Revert(..) => true,
+        ast::StmtKind::Expr(expr) => matches!(
+            &expr.kind,
+            ast::ExprKind::Call(callee, _)
+                if matches!(&callee.kind, ast::ExprKind::Ident(i) if i.as_str() == "revert")
+        ),
+        ast::StmtKind::Block(block) | ast::StmtKind::UncheckedBlock(block) => {
+            block.stmts.last().is_some_and(always_reverts)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, function, modifier, param};
+    use crate::batbelt::evm::types::EvmContractType;
+
+    fn vault() -> EvmContract {
+        let functions = vec![
+            function("mint", "Vault", "    function mint() external onlyRole(MINTER_ROLE) {\n    }").with_modifier_args(&[("onlyRole", &["MINTER_ROLE"])]),
+            function("setFee", "Vault", "    function setFee() external onlyOwner {\n    }").with_modifiers(&["onlyOwner"]),
+            function("sweep", "Vault", "    function sweep() external requiresAuth {\n    }").with_modifiers(&["requiresAuth"]),
+            function("claim", "Vault", "    function claim() external whenAuthorized {\n    }").with_modifiers(&["whenAuthorized"]),
+            function("pause", "Vault", "    function pause() external {\n        require(msg.sender == guardian, \"guardian\");\n        paused = true;\n    }"),
+            function("setLimit", "Vault", "    function setLimit() external {\n        _requireRole(LIMIT_ROLE);\n    }"),
+            function("_requireRole", "Vault", "    function _requireRole(bytes32 role) internal view {\n        if (!hasRole(role, _msgSender())) revert Unauthorized();\n    }").with_params(vec![param("role", "bytes32")]),
+            function("deposit", "Vault", "    function deposit() external nonReentrant {\n        balances[msg.sender] += msg.value;\n    }").with_modifiers(&["nonReentrant"]),
+            function("pull", "Vault", "    function pull(uint256 amount) external {\n        require(token.transferFrom(msg.sender, address(this), amount));\n    }"),
+            function("trade", "Vault", "    function trade() external {\n        require(!blacklisted[msg.sender], \"blacklisted\");\n    }"),
+            function("withdraw", "Vault", "    function withdraw(uint256 amount) external {\n        require(balances[msg.sender] >= amount);\n        balances[msg.sender] -= amount;\n    }"),
+            function("play", "Vault", "    function play() external {\n        require(msg.sender == tx.origin, \"no contracts\");\n    }"),
+            function("poke", "Vault", "    function poke() external {\n        if (msg.sender != guardian) {\n            emit Poked(msg.sender);\n        }\n    }"),
+            function("upgrade", "Vault", "    function upgrade() external authorizeUpgradeHook noAuthNeeded {\n    }").with_modifiers(&["authorizeUpgradeHook", "noAuthNeeded"]),
+            function("unlock", "Vault", "    function unlock() external {\n        require(msg.sender == owner || block.timestamp > unlockTime);\n    }"),
+            function("release", "Vault", "    function release() external {\n        require(!(msg.sender != guardian && block.timestamp < releaseTime));\n    }"),
+            function("lock", "Vault", "    function lock() external {\n        if (msg.sender != owner || locked) revert Locked();\n    }"),
+            function("freeze", "Vault", "    function freeze() external {\n        require(!(msg.sender != guardian || frozen));\n    }"),
+        ];
+        let modifiers = vec![
+            EvmModifierDef {
+                params: vec![param("role", "bytes32")],
+                ..modifier("onlyRole", "Vault", "    modifier onlyRole(bytes32 role) {\n        _checkRole(role);\n        _;\n    }")
+            },
+            modifier("onlyOwner", "Vault", "    modifier onlyOwner() {\n        _checkOwner();\n        _;\n    }"),
+            modifier("requiresAuth", "Vault", "    modifier requiresAuth() virtual {\n        require(isAuthorized(msg.sender, msg.sig), \"UNAUTHORIZED\");\n        _;\n    }"),
+            modifier("whenAuthorized", "Vault", "    modifier whenAuthorized() {\n        if (!authorized[msg.sender]) revert NotAuthorized();\n        _;\n    }"),
+            modifier("nonReentrant", "Vault", "    modifier nonReentrant() {\n        require(locked == 1);\n        locked = 2;\n        _;\n        locked = 1;\n    }"),
+            modifier("authorizeUpgradeHook", "Vault", "    modifier authorizeUpgradeHook() {\n        _;\n        emit Upgraded();\n    }"),
+        ];
+        EvmContract {
+            modifiers,
+            ..contract("Vault", EvmContractType::Contract, &[], functions)
+        }
+    }
+
+    #[test]
+    fn test_resolve_access_control_from_bodies() {
+        let contracts = vec![vault()];
+        let resolver = AccessControlResolver::new(&contracts);
+        let resolve = |name: &str| {
+            let function = contracts[0]
+                .functions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap();
+            resolver.resolve("Vault", function)
+        };
+
+        assert_eq!(
+            resolve("mint"),
+            vec![AccessControlType::RoleBased {
+                role: "MINTER_ROLE".to_string()
+            }]
+        );
+        assert_eq!(resolve("setFee"), vec![AccessControlType::OnlyOwner]);
+        assert_eq!(
+            resolve("sweep"),
+            vec![AccessControlType::Authority {
+                check: "isAuthorized(msg.sender, msg.sig)".to_string()
+            }]
+        );
+        assert_eq!(
+            resolve("claim"),
+            vec![AccessControlType::RequireMsgSender {
+                compared_to: "authorized[msg.sender]".to_string()
+            }]
+        );
+        assert_eq!(
+            resolve("pause"),
+            vec![AccessControlType::RequireMsgSender {
+                compared_to: "guardian".to_string()
+            }]
+        );
+        assert_eq!(
+            resolve("setLimit"),
+            vec![AccessControlType::RoleBased {
+                role: "LIMIT_ROLE".to_string()
+            }]
+        );
+        assert_eq!(resolve("deposit"), vec![AccessControlType::None]);
+        assert_eq!(resolve("lock"), vec![AccessControlType::OnlyOwner]);
+        assert_eq!(
+            resolve("freeze"),
+            vec![AccessControlType::RequireMsgSender {
+                compared_to: "guardian".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_access_control_ignores_non_auth_checks() {
+        let contracts = vec![vault()];
+        let resolver = AccessControlResolver::new(&contracts);
+        for name in [
+            "pull", "trade", "withdraw", "play", "poke", "upgrade", "unlock", "release",
+        ] {
+            let function = contracts[0]
+                .functions
+                .iter()
+                .find(|f| f.name == name)
+                .unwrap();
+            assert_eq!(
+                resolver.resolve("Vault", function),
+                vec![AccessControlType::None],
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_substitute_skips_member_names() {
+        let bindings: HashMap<String, String> =
+            [("role".to_string(), "MINTER_ROLE".to_string())].into();
+        assert_eq!(
+            substitute("hasRole(role, msg.sender) && roles.role", &bindings),
+            "hasRole(MINTER_ROLE, msg.sender) && roles.role"
+        );
+    }
+}
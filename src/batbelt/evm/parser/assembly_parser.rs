@@ -0,0 +1,177 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use solar_parse::{
+    ast::{self, yul, Visit},
+    interface::Session,
+};
+
+use crate::batbelt::evm::types::{EvmAssemblyOp, EvmAssemblyOpKind};
+
+use super::evm_file_parser::span_to_line;
+
+/// Offset of the free-memory pointer.
+const FREE_MEMORY_POINTER: &[&str] = &["0x40", "64"];
+
+/// Collect the `sload`/`sstore`, `call`/`delegatecall`/`staticcall` and free-memory `mstore`
+/// operations of the inline `assembly` blocks of `block`.
+///
+/// `first_line` is the file line of the first line known to `sess`, so that bodies parsed
+/// on their own (see `with_parsed_body`) report file lines too.
+pub fn parse_assembly_ops<'ast>(
+    sess: &Session,
+    block: &'ast ast::Block<'ast>,
+    first_line: usize,
+) -> Vec<EvmAssemblyOp> {
+    let mut visitor = AssemblyVisitor {
+        sess,
+        first_line,
+        free_memory_vars: HashSet::new(),
+        ops: Vec::new(),
+    };
+    let _ = visitor.visit_block(block);
+    visitor.ops
+}
+
+struct AssemblyVisitor<'v> {
+    sess: &'v Session,
+    first_line: usize,
+    /// Yul variables holding `mload(0x40)`
+    free_memory_vars: HashSet<String>,
+    ops: Vec<EvmAssemblyOp>,
+}
+
+impl AssemblyVisitor<'_> {
+    fn snippet(&self, expr: &yul::Expr<'_>) -> String {
+        self.sess
+            .source_map()
+            .span_to_snippet(expr.span)
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
+
+    /// true if `expr` is `mload(0x40)` or a variable that holds it, possibly offset.
+    fn is_free_memory(&self, expr: &yul::Expr<'_>) -> bool {
+        match &expr.kind {
+            yul::ExprKind::Path(path) => self.free_memory_vars.contains(
+                &path
+                    .segments()
+                    .last()
+                    .map(|s| s.as_str().to_string())
+                    .unwrap_or_default(),
+            ),
+            yul::ExprKind::Call(call) => match call.name.as_str() {
+                "mload" => call.arguments.first().is_some_and(|offset| {
+                    FREE_MEMORY_POINTER.contains(&self.snippet(offset).as_str())
+                }),
+                "add" | "sub" => call.arguments.iter().any(|arg| self.is_free_memory(arg)),
+                _ => false,
+            },
+            yul::ExprKind::Lit(_) => false,
+        }
+    }
+
+    fn track_assignment(&mut self, names: Vec<String>, value: Option<&yul::Expr<'_>>) {
+        let holds_free_memory = value.is_some_and(|value| self.is_free_memory(value));
+        for name in names {
+            if holds_free_memory {
+                self.free_memory_vars.insert(name);
+            } else {
+                self.free_memory_vars.remove(&name);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for AssemblyVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_yul_stmt(&mut self, stmt: &'ast yul::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        match &stmt.kind {
+            yul::StmtKind::VarDecl(names, value) => {
+                let names = names.iter().map(|n| n.as_str().to_string()).collect();
+                self.track_assignment(names, value.as_ref());
+            }
+            yul::StmtKind::AssignSingle(path, value) => {
+                let names = path
+                    .segments()
+                    .iter()
+                    .map(|s| s.as_str().to_string())
+                    .collect();
+                self.track_assignment(names, Some(value));
+            }
+            _ => {}
+        }
+        self.walk_yul_stmt(stmt)
+    }
+
+    fn visit_yul_expr(&mut self, expr: &'ast yul::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let yul::ExprKind::Call(call) = &expr.kind {
+            let kind = match call.name.as_str() {
+                "sload" => Some(EvmAssemblyOpKind::Sload),
+                "sstore" => Some(EvmAssemblyOpKind::Sstore),
+                "call" => Some(EvmAssemblyOpKind::Call),
+                "delegatecall" => Some(EvmAssemblyOpKind::DelegateCall),
+                "staticcall" => Some(EvmAssemblyOpKind::StaticCall),
+                "mstore" => call
+                    .arguments
+                    .first()
+                    .filter(|offset| {
+                        FREE_MEMORY_POINTER.contains(&self.snippet(offset).as_str())
+                            || self.is_free_memory(offset)
+                    })
+                    .map(|_| EvmAssemblyOpKind::FreeMemoryMstore),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                self.ops.push(EvmAssemblyOp {
+                    kind,
+                    arguments: call.arguments.iter().map(|arg| self.snippet(arg)).collect(),
+                    line: self.first_line + span_to_line(self.sess, expr.span) - 1,
+                });
+            }
+        }
+        self.walk_yul_expr(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::parser::evm_file_parser::with_parsed_body;
+
+    #[test]
+    fn test_parse_assembly_ops() {
+        let body = r#"{
+    uint256 amount = 1;
+    assembly {
+        let bal := sload(balances.slot)
+        sstore(_ADMIN_SLOT, caller())
+        let ptr := mload(0x40)
+        mstore(ptr, amount)
+        mstore(0x40, add(ptr, 0x20))
+        mstore(0, 1)
+        let ok := delegatecall(gas(), impl, ptr, 0x20, 0, 0)
+    }
+}"#;
+        let ops =
+            with_parsed_body(body, |sess, block| parse_assembly_ops(sess, block, 10)).unwrap();
+        let kinds: Vec<(EvmAssemblyOpKind, usize)> =
+            ops.iter().map(|op| (op.kind.clone(), op.line)).collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (EvmAssemblyOpKind::Sload, 13),
+                (EvmAssemblyOpKind::Sstore, 14),
+                (EvmAssemblyOpKind::FreeMemoryMstore, 16),
+                (EvmAssemblyOpKind::FreeMemoryMstore, 17),
+                (EvmAssemblyOpKind::DelegateCall, 19),
+            ]
+        );
+        assert_eq!(ops[0].slot_name(), Some("balances"));
+        assert_eq!(ops[1].slot_name(), Some("_ADMIN_SLOT"));
+        assert_eq!(ops[1].arguments, vec!["_ADMIN_SLOT", "caller()"]);
+        assert_eq!(ops[4].call_target(), Some("impl"));
+    }
+}
@@ -1,12 +1,20 @@
 use std::collections::HashMap;
+use std::ops::ControlFlow;
 
 use solar_parse::{
-    ast,
-    interface::{source_map::FileName, Session},
-    Parser,
+    ast::{self, Visit},
+    interface::{Session, Span},
 };
 
-use crate::batbelt::evm::types::{EvmContract, EvmFunction};
+use crate::batbelt::evm::types::{
+    EvmAssemblyOp, EvmAssemblyOpKind, EvmContract, EvmContractType, EvmEmittedEvent,
+    EvmExternalCall, EvmFunction, EvmModifierDef, EvmParam, EvmUsingDirective, EvmValidation,
+    EvmValidationKind, ExternalCallKind,
+};
+
+use super::assembly_parser::parse_assembly_ops;
+use super::evm_file_parser::{body_start_line, span_to_line, type_to_string, with_parsed_body};
+use super::inheritance_resolver::InheritanceResolver;
 
 /// Represents a resolved function call.
 #[derive(Debug, Clone)]
@@ -19,15 +27,126 @@ pub struct ResolvedCall {
     pub is_super: bool,
 }
 
+/// A function or modifier body executed as part of an entry point.
+pub struct ReachableBody<'a> {
+    pub name: &'a str,
+    pub body_source: &'a str,
+    pub end_line: usize,
+    /// Parameters and named returns, which shadow state variables
+    pub params: Vec<&'a EvmParam>,
+    pub is_modifier: bool,
+}
+
+impl ReachableBody<'_> {
+    /// 1-based line in the source file of the first body line.
+    pub fn first_line(&self) -> usize {
+        body_start_line(self.body_source, self.end_line)
+    }
+}
+
 /// Resolves function calls within and across contracts.
 pub struct CallResolver<'a> {
     contracts_by_name: HashMap<String, &'a EvmContract>,
+    inheritance_resolver: InheritanceResolver<'a>,
 }
 
 impl<'a> CallResolver<'a> {
     pub fn new(contracts: &'a [EvmContract]) -> Self {
         let contracts_by_name = contracts.iter().map(|c| (c.name.clone(), c)).collect();
-        Self { contracts_by_name }
+        Self {
+            contracts_by_name,
+            inheritance_resolver: InheritanceResolver::new(contracts),
+        }
+    }
+
+    /// Contracts in the inheritance chain of `contract_name`, most derived first.
+    pub fn lookup_order(&self, contract_name: &str) -> Vec<&'a EvmContract> {
+        self.inheritance_resolver
+            .linearize(contract_name)
+            .iter()
+            .rev()
+            .filter_map(|name| self.contracts_by_name.get(name.as_str()).copied())
+            .collect()
+    }
+
+    /// Resolve a modifier used by a function of `contract_name` to its most-derived definition.
+    pub fn resolve_modifier(
+        &self,
+        contract_name: &str,
+        modifier_name: &str,
+    ) -> Option<&'a EvmModifierDef> {
+        self.lookup_order(contract_name)
+            .into_iter()
+            .find_map(|c| c.modifiers.iter().find(|m| m.name == modifier_name))
+    }
+
+    /// Collect `function` and every internal function it or its modifiers transitively
+    /// call, as executed by a deployed `contract_name` (virtual calls resolve to the
+    /// most-derived override).
+    ///
+    /// Calls to other contracts are not followed, since they run in a different storage context.
+    /// Overloads are over-approximated: every overload with a matching name is included.
+    pub fn reachable_functions(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<&'a EvmFunction> {
+        let lookup_order = self.lookup_order(contract_name);
+        let mut reachable: Vec<&'a EvmFunction> = vec![function];
+        let mut queue: Vec<&'a EvmFunction> = vec![function];
+
+        while let Some(current) = queue.pop() {
+            // `onlyOwner` -> `_checkOwner()`, `nonReentrant` -> `_nonReentrantBefore()`
+            let callers = std::iter::once((&current.body_source, &current.contract_name)).chain(
+                current
+                    .modifiers
+                    .iter()
+                    .filter_map(|name| self.resolve_modifier(contract_name, name))
+                    .map(|modifier| (&modifier.body_source, &modifier.contract_name)),
+            );
+            let callee_names: Vec<(String, &String)> = callers
+                .flat_map(|(body_source, caller_contract)| {
+                    extract_calls_from_source(body_source)
+                        .into_iter()
+                        .map(move |callee_name| (callee_name, caller_contract))
+                })
+                .collect();
+            for (callee_name, caller_contract) in callee_names {
+                let (search_from, method) = match callee_name.split_once('.') {
+                    Some(("super", method)) => {
+                        let caller_pos = lookup_order
+                            .iter()
+                            .position(|c| c.name == *caller_contract)
+                            .unwrap_or(0);
+                        (caller_pos + 1, method)
+                    }
+                    Some(_) => continue,
+                    None => (0, callee_name.as_str()),
+                };
+                if is_builtin(method) {
+                    continue;
+                }
+
+                let defining_contract = lookup_order
+                    .iter()
+                    .skip(search_from)
+                    .find(|c| c.functions.iter().any(|f| f.name == method));
+                if let Some(defining_contract) = defining_contract {
+                    for callee in defining_contract
+                        .functions
+                        .iter()
+                        .filter(|f| f.name == method)
+                    {
+                        if !reachable.iter().any(|r| std::ptr::eq(*r, callee)) {
+                            reachable.push(callee);
+                            queue.push(callee);
+                        }
+                    }
+                }
+            }
+        }
+
+        reachable
     }
 
     /// Resolve all calls in a function's body using AST-based extraction.
@@ -38,8 +157,7 @@ impl<'a> CallResolver<'a> {
             return Vec::new();
         }
 
-        // Try AST-based extraction first
-        let call_names = extract_calls_from_source(body);
+        let call_names = self.callee_names(contract_name, function);
 
         let mut calls = Vec::new();
 
@@ -86,6 +204,312 @@ impl<'a> CallResolver<'a> {
         calls
     }
 
+    /// Names called by `function`, as returned by `extract_calls_from_source`, with member
+    /// calls on typed receivers rewritten to the contract or library they land in:
+    /// `amount.mulDiv` becomes `FixedPointMathLib.mulDiv` (`using for`), `vault.withdraw`
+    /// and `IVault(vault).withdraw` become `IVault.withdraw`.
+    pub fn callee_names(&self, contract_name: &str, function: &EvmFunction) -> Vec<String> {
+        let names = extract_calls_from_source(&function.body_source);
+        let body = ReachableBody {
+            name: &function.name,
+            body_source: &function.body_source,
+            end_line: function.end_line,
+            params: function
+                .params
+                .iter()
+                .chain(function.returns.iter())
+                .collect(),
+            is_modifier: false,
+        };
+        let typed_calls: Vec<(Option<String>, EvmExternalCall)> = self
+            .external_calls_with_receivers(
+                contract_name,
+                &body,
+                &self.state_var_types(contract_name),
+            )
+            .into_iter()
+            .filter(|(_, call)| {
+                matches!(
+                    call.kind,
+                    ExternalCallKind::Library | ExternalCallKind::HighLevel
+                )
+            })
+            .collect();
+
+        let mut names: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                let (receiver, method) = match name.split_once('.') {
+                    Some((receiver, method))
+                        if receiver != "super"
+                            && !self.contracts_by_name.contains_key(receiver) =>
+                    {
+                        (receiver, method)
+                    }
+                    _ => return name,
+                };
+                // Only the calls made on that same receiver, other receivers may share the
+                // method name: `token.transfer` and `recipient.transfer`
+                typed_calls
+                    .iter()
+                    .find(|(call_receiver, call)| {
+                        call_receiver.as_deref() == Some(receiver) && call.function == method
+                    })
+                    .map(|(_, call)| format!("{}.{}", call.target_type, method))
+                    .unwrap_or(name)
+            })
+            .chain(
+                typed_calls
+                    .iter()
+                    .map(|(_, call)| format!("{}.{}", call.target_type, call.function)),
+            )
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// `using for` directives that apply to the bodies executed by `contract_name`.
+    ///
+    /// Directives are not inherited, but inherited bodies still see the ones of the contract
+    /// declaring them, so the whole inheritance chain is included, plus the global ones.
+    fn using_directives(&self, contract_name: &str) -> Vec<&'a EvmUsingDirective> {
+        let mut directives: Vec<&'a EvmUsingDirective> = self
+            .lookup_order(contract_name)
+            .into_iter()
+            .flat_map(|c| c.using_directives.iter())
+            .collect();
+        for directive in self
+            .contracts_by_name
+            .values()
+            .flat_map(|c| c.using_directives.iter())
+            .filter(|d| d.global)
+        {
+            if !directives.contains(&directive) {
+                directives.push(directive);
+            }
+        }
+        directives
+    }
+
+    /// Bodies executed when `function` runs on `contract_name`: the function itself,
+    /// its transitive internal callees, and the modifiers applied to each of them.
+    pub fn reachable_bodies(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<ReachableBody<'a>> {
+        let mut bodies: Vec<ReachableBody<'a>> = Vec::new();
+        for reachable in self.reachable_functions(contract_name, function) {
+            bodies.push(ReachableBody {
+                name: &reachable.name,
+                body_source: &reachable.body_source,
+                end_line: reachable.end_line,
+                params: reachable
+                    .params
+                    .iter()
+                    .chain(reachable.returns.iter())
+                    .collect(),
+                is_modifier: false,
+            });
+
+            for modifier_name in &reachable.modifiers {
+                let modifier = match self.resolve_modifier(contract_name, modifier_name) {
+                    Some(modifier) => modifier,
+                    None => continue,
+                };
+                let seen = bodies
+                    .iter()
+                    .any(|b| std::ptr::eq(b.body_source, modifier.body_source.as_str()));
+                if !seen {
+                    bodies.push(ReachableBody {
+                        name: &modifier.name,
+                        body_source: &modifier.body_source,
+                        end_line: modifier.end_line,
+                        params: modifier.params.iter().collect(),
+                        is_modifier: true,
+                    });
+                }
+            }
+        }
+        bodies
+    }
+
+    /// Collect the external calls (trust boundaries) made by `function`, its modifiers and
+    /// its transitive internal callees, as executed by `contract_name`.
+    pub fn resolve_external_calls(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<EvmExternalCall> {
+        let state_var_types = self.state_var_types(contract_name);
+
+        let mut calls: Vec<EvmExternalCall> = Vec::new();
+        for body in self.reachable_bodies(contract_name, function) {
+            for call in self.external_calls_in_body(contract_name, &body, &state_var_types) {
+                if !calls.contains(&call) {
+                    calls.push(call);
+                }
+            }
+        }
+        calls
+    }
+
+    /// External calls of a single body, in source order.
+    pub fn body_external_calls(
+        &self,
+        contract_name: &str,
+        body: &ReachableBody<'_>,
+    ) -> Vec<EvmExternalCall> {
+        self.external_calls_in_body(contract_name, body, &self.state_var_types(contract_name))
+    }
+
+    fn state_var_types(&self, contract_name: &str) -> HashMap<String, String> {
+        self.lookup_order(contract_name)
+            .iter()
+            .rev()
+            .flat_map(|c| c.storage_variables.iter())
+            .map(|v| (v.name.clone(), v.type_name.clone()))
+            .collect()
+    }
+
+    fn external_calls_in_body(
+        &self,
+        contract_name: &str,
+        body: &ReachableBody<'_>,
+        state_var_types: &HashMap<String, String>,
+    ) -> Vec<EvmExternalCall> {
+        self.external_calls_with_receivers(contract_name, body, state_var_types)
+            .into_iter()
+            .map(|(_, call)| call)
+            .collect()
+    }
+
+    /// External calls of `body`, each with the identifier it is made on if any.
+    fn external_calls_with_receivers(
+        &self,
+        contract_name: &str,
+        body: &ReachableBody<'_>,
+        state_var_types: &HashMap<String, String>,
+    ) -> Vec<(Option<String>, EvmExternalCall)> {
+        let mut var_types = state_var_types.clone();
+        for param in body.params.iter().filter(|p| !p.name.is_empty()) {
+            var_types.insert(param.name.clone(), param.type_name.clone());
+        }
+        let first_line = body.first_line();
+
+        let using_directives = self.using_directives(contract_name);
+
+        with_parsed_body(body.body_source, |sess, block| {
+            let mut visitor = ExternalCallVisitor {
+                sess,
+                contracts_by_name: &self.contracts_by_name,
+                using_directives,
+                contract_name,
+                caller: body.name,
+                first_line,
+                var_types,
+                receiver: None,
+                calls: Vec::new(),
+            };
+            let _ = visitor.visit_block(block);
+            let mut calls = visitor.calls;
+            calls.extend(
+                parse_assembly_ops(sess, block, first_line)
+                    .into_iter()
+                    .filter_map(|op| assembly_call(&op, body.name, &visitor.var_types))
+                    .map(|call| (None, call)),
+            );
+            calls.sort_by_key(|(_, call)| call.line);
+            calls
+        })
+        .unwrap_or_default()
+    }
+
+    /// Collect the `emit X(...)` statements of `function`, its modifiers and its transitive
+    /// internal callees, matched to the `EvmEvent` declared in the inheritance chain.
+    pub fn resolve_emitted_events(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<EvmEmittedEvent> {
+        let lookup_order = self.lookup_order(contract_name);
+        let mut events: Vec<EvmEmittedEvent> = Vec::new();
+
+        for body in self.reachable_bodies(contract_name, function) {
+            let first_line = body.first_line();
+            let emits = with_parsed_body(body.body_source, |sess, block| {
+                let mut visitor = EmitVisitor {
+                    sess,
+                    emits: Vec::new(),
+                };
+                let _ = visitor.visit_block(block);
+                visitor.emits
+            })
+            .unwrap_or_default();
+
+            for (path, rel_line) in emits {
+                let (qualifier, name) = match path.split_last() {
+                    Some((name, qualifier)) => (qualifier.last(), name.clone()),
+                    None => continue,
+                };
+                // `emit IVault.Deposit(...)` names the declaring contract explicitly
+                let declared_in = match qualifier {
+                    Some(qualifier) => self
+                        .contracts_by_name
+                        .get(qualifier.as_str())
+                        .filter(|c| c.events.iter().any(|e| e.name == name))
+                        .map(|c| c.name.clone()),
+                    None => lookup_order
+                        .iter()
+                        .find(|c| c.events.iter().any(|e| e.name == name))
+                        .map(|c| c.name.clone()),
+                };
+                let event = EvmEmittedEvent {
+                    name,
+                    declared_in,
+                    caller: body.name.to_string(),
+                    line: first_line + rel_line - 1,
+                };
+                if !events.contains(&event) {
+                    events.push(event);
+                }
+            }
+        }
+        events
+    }
+
+    /// Collect the `require`, `assert`, `if (...) revert` and `revert` checks of `function`,
+    /// its modifiers and its transitive internal callees.
+    pub fn resolve_validations(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<EvmValidation> {
+        let mut validations: Vec<EvmValidation> = Vec::new();
+        for body in self.reachable_bodies(contract_name, function) {
+            let first_line = body.first_line();
+            let found = with_parsed_body(body.body_source, |sess, block| {
+                let mut visitor = ValidationVisitor {
+                    sess,
+                    caller: body.name,
+                    first_line,
+                    validations: Vec::new(),
+                };
+                let _ = visitor.visit_block(block);
+                visitor.validations
+            })
+            .unwrap_or_default();
+
+            for validation in found {
+                if !validations.contains(&validation) {
+                    validations.push(validation);
+                }
+            }
+        }
+        validations
+    }
+
     fn resolve_single_call(
         &self,
         contract_name: &str,
@@ -106,66 +530,434 @@ impl<'a> CallResolver<'a> {
             }
         }
 
-        // Check inherited functions
-        if let Some(contract) = self.contracts_by_name.get(contract_name) {
-            for base_name in &contract.base_contracts {
-                if let Some(base) = self.contracts_by_name.get(base_name.as_str()) {
-                    if base.functions.iter().any(|f| f.name == callee_name) {
-                        return Some(ResolvedCall {
-                            caller_contract: contract_name.to_string(),
-                            caller_function: caller_function.to_string(),
-                            callee_contract: base_name.clone(),
-                            callee_function: callee_name.to_string(),
-                            is_external: false,
-                            is_super: false,
-                        });
+        // Check inherited functions (transitively, most derived base first)
+        self.lookup_order(contract_name)
+            .into_iter()
+            .skip(1)
+            .find(|base| base.functions.iter().any(|f| f.name == callee_name))
+            .map(|base| ResolvedCall {
+                caller_contract: contract_name.to_string(),
+                caller_function: caller_function.to_string(),
+                callee_contract: base.name.clone(),
+                callee_function: callee_name.to_string(),
+                is_external: false,
+                is_super: false,
+            })
+    }
+}
+
+/// AST visitor that classifies member calls leaving the executing contract.
+struct ExternalCallVisitor<'v> {
+    sess: &'v Session,
+    contracts_by_name: &'v HashMap<String, &'v EvmContract>,
+    using_directives: Vec<&'v EvmUsingDirective>,
+    contract_name: &'v str,
+    caller: &'v str,
+    /// 1-based line in the source file of the first body line
+    first_line: usize,
+    /// Declared type per variable name (state variables, params, locals)
+    var_types: HashMap<String, String>,
+    /// Identifier the member call being visited is made on, `token` in `token.transfer()`
+    receiver: Option<String>,
+    /// Calls with the identifier they are made on
+    calls: Vec<(Option<String>, EvmExternalCall)>,
+}
+
+impl ExternalCallVisitor<'_> {
+    /// Library whose `method` is attached to `receiver_type` by a `using for` directive.
+    fn using_for_library(&self, receiver_type: Option<&str>, method: &str) -> Option<String> {
+        self.using_directives
+            .iter()
+            .filter(|d| d.applies_to(receiver_type))
+            .filter_map(|d| d.library_for(method))
+            .filter_map(|library| self.contracts_by_name.get(library))
+            .find(|library| library.functions.iter().any(|f| f.name == method))
+            .map(|library| library.name.clone())
+    }
+
+    /// Best-effort static type of an expression used as call receiver.
+    fn expr_type(&self, expr: &ast::Expr<'_>) -> Option<String> {
+        match &expr.kind {
+            ast::ExprKind::Ident(ident) => match ident.as_str() {
+                "this" => Some(self.contract_name.to_string()),
+                name => self.var_types.get(name).cloned(),
+            },
+            // Casts: `IERC20(token)`, `address(x)`
+            ast::ExprKind::Call(callee, _) => match &callee.kind {
+                ast::ExprKind::Ident(ident)
+                    if self.contracts_by_name.contains_key(ident.as_str()) =>
+                {
+                    Some(ident.as_str().to_string())
+                }
+                ast::ExprKind::Type(ty) => Some(type_to_string(self.sess, ty)),
+                _ => None,
+            },
+            ast::ExprKind::Payable(_) => Some("address payable".to_string()),
+            ast::ExprKind::Member(base, member) => match (&base.kind, member.as_str()) {
+                (ast::ExprKind::Ident(base), "sender" | "origin" | "coinbase")
+                    if matches!(base.as_str(), "msg" | "tx" | "block") =>
+                {
+                    Some("address".to_string())
+                }
+                _ => None,
+            },
+            ast::ExprKind::Index(base, _) => self.expr_type(base).map(|t| element_type(&t)),
+            _ => None,
+        }
+    }
+
+    fn record(
+        &mut self,
+        span: ast::Span,
+        kind: ExternalCallKind,
+        target_type: String,
+        function: &str,
+        forwards_value: bool,
+    ) {
+        let call = EvmExternalCall {
+            kind,
+            target_type,
+            function: function.to_string(),
+            forwards_value,
+            caller: self.caller.to_string(),
+            line: self.first_line + span_to_line(self.sess, span) - 1,
+        };
+        self.calls.push((self.receiver.clone(), call));
+    }
+}
+
+impl<'ast> Visit<'ast> for ExternalCallVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_variable_definition(
+        &mut self,
+        var: &'ast ast::VariableDefinition<'ast>,
+    ) -> ControlFlow<Self::BreakValue> {
+        if let Some(name) = var.name {
+            self.var_types.insert(
+                name.as_str().to_string(),
+                type_to_string(self.sess, &var.ty),
+            );
+        }
+        self.walk_variable_definition(var)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, _) = &expr.kind {
+            // `target.method(...)` or `target.method{value: v}(...)`
+            let (member, options) = match &callee.kind {
+                ast::ExprKind::CallOptions(inner, options) => (&**inner, Some(options)),
+                _ => (&**callee, None),
+            };
+            if let ast::ExprKind::Member(target, method) = &member.kind {
+                let method = method.as_str();
+                self.receiver = match &target.kind {
+                    ast::ExprKind::Ident(ident) => Some(ident.as_str().to_string()),
+                    _ => None,
+                };
+                let forwards_value = options
+                    .map(|opts| opts.iter().any(|arg| arg.name.as_str() == "value"))
+                    .unwrap_or(false);
+
+                let library = match &target.kind {
+                    ast::ExprKind::Ident(ident) => self
+                        .contracts_by_name
+                        .get(ident.as_str())
+                        .filter(|c| c.contract_type == EvmContractType::Library)
+                        .map(|c| c.name.clone()),
+                    _ => None,
+                };
+                let target_type = self.expr_type(target);
+                let target_contract = target_type
+                    .as_deref()
+                    .and_then(|t| self.contracts_by_name.get(t));
+                // `token.safeTransfer(...)` with `using SafeERC20 for IERC20`
+                let library =
+                    library.or_else(|| self.using_for_library(target_type.as_deref(), method));
+
+                if let Some(library) = library {
+                    self.record(expr.span, ExternalCallKind::Library, library, method, false);
+                } else if let Some(contract) = target_contract {
+                    let kind = if contract.contract_type == EvmContractType::Library {
+                        ExternalCallKind::Library
+                    } else {
+                        ExternalCallKind::HighLevel
+                    };
+                    let name = contract.name.clone();
+                    self.record(expr.span, kind, name, method, forwards_value);
+                } else {
+                    // `call`/`delegatecall`/`staticcall` only exist on addresses, but an
+                    // unknown receiver of `transfer`/`send` may as well be a token
+                    let native_transfer = target_type
+                        .as_deref()
+                        .is_some_and(|t| t.starts_with("address"));
+                    let address_type = target_type.unwrap_or_else(|| "address".to_string());
+                    match method {
+                        "call" => self.record(
+                            expr.span,
+                            ExternalCallKind::Call,
+                            address_type,
+                            method,
+                            forwards_value,
+                        ),
+                        "delegatecall" => self.record(
+                            expr.span,
+                            ExternalCallKind::DelegateCall,
+                            address_type,
+                            method,
+                            false,
+                        ),
+                        "staticcall" => self.record(
+                            expr.span,
+                            ExternalCallKind::StaticCall,
+                            address_type,
+                            method,
+                            false,
+                        ),
+                        "transfer" | "send" if native_transfer => {
+                            let kind = if method == "transfer" {
+                                ExternalCallKind::Transfer
+                            } else {
+                                ExternalCallKind::Send
+                            };
+                            self.record(expr.span, kind, address_type, method, true);
+                        }
+                        _ => {}
                     }
                 }
             }
         }
+        self.walk_expr(expr)
+    }
+}
+
+/// External call made with the `call`/`delegatecall`/`staticcall` opcodes.
+fn assembly_call(
+    op: &EvmAssemblyOp,
+    caller: &str,
+    var_types: &HashMap<String, String>,
+) -> Option<EvmExternalCall> {
+    let (kind, function) = match op.kind {
+        EvmAssemblyOpKind::Call => (ExternalCallKind::Call, "call"),
+        EvmAssemblyOpKind::DelegateCall => (ExternalCallKind::DelegateCall, "delegatecall"),
+        EvmAssemblyOpKind::StaticCall => (ExternalCallKind::StaticCall, "staticcall"),
+        _ => return None,
+    };
+    let target_type = op
+        .call_target()
+        .and_then(|target| var_types.get(target))
+        .cloned()
+        .unwrap_or_else(|| "address".to_string());
+    let forwards_value =
+        op.kind == EvmAssemblyOpKind::Call && op.arguments.get(2).is_some_and(|value| value != "0");
+    Some(EvmExternalCall {
+        kind,
+        target_type,
+        function: function.to_string(),
+        forwards_value,
+        caller: caller.to_string(),
+        line: op.line,
+    })
+}
+
+/// AST visitor that collects `emit` statements as (path segments, relative line).
+struct EmitVisitor<'v> {
+    sess: &'v Session,
+    emits: Vec<(Vec<String>, usize)>,
+}
+
+impl<'ast> Visit<'ast> for EmitVisitor<'_> {
+    type BreakValue = ();
 
-        None
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::StmtKind::Emit(path, _) = &stmt.kind {
+            let segments = path
+                .segments()
+                .iter()
+                .map(|s| s.as_str().to_string())
+                .collect();
+            self.emits
+                .push((segments, span_to_line(self.sess, stmt.span)));
+        }
+        self.walk_stmt(stmt)
     }
 }
 
-/// Extract function call names from Solidity source code using AST.
-/// Falls back to regex if AST parsing fails.
-pub fn extract_calls_from_source(source: &str) -> Vec<String> {
-    // Wrap in a dummy function so it parses as a valid Solidity file
-    let wrapped = format!("contract _C {{ function _f() {{ {} }} }}", source);
+struct ValidationVisitor<'v> {
+    sess: &'v Session,
+    caller: &'v str,
+    first_line: usize,
+    validations: Vec<EvmValidation>,
+}
 
-    let sess = Session::builder().with_silent_emitter(None).build();
+impl ValidationVisitor<'_> {
+    fn snippet(&self, span: Span) -> String {
+        self.sess
+            .source_map()
+            .span_to_snippet(span)
+            .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+            .unwrap_or_default()
+    }
 
-    let result = sess.enter(|| -> Option<Vec<String>> {
-        let arena = ast::Arena::new();
-        let mut parser = Parser::from_source_code(
-            &sess,
-            &arena,
-            FileName::Custom("call_resolver".into()),
-            wrapped.clone(),
-        )
-        .ok()?;
+    fn push(
+        &mut self,
+        kind: EvmValidationKind,
+        condition: String,
+        error: Option<String>,
+        span: Span,
+    ) {
+        self.validations.push(EvmValidation {
+            kind,
+            condition,
+            error,
+            caller: self.caller.to_string(),
+            line: self.first_line + span_to_line(self.sess, span) - 1,
+        });
+    }
 
-        let file = parser.parse_file().map_err(|e| e.emit()).ok()?;
+    /// Revert reason of a statement that always reverts: `revert E(...)`, `revert("...")`,
+    /// or a block ending in one. The outer `Option` is `None` if it does not revert.
+    fn reverts(&self, stmt: &ast::Stmt<'_>) -> Option<Option<String>> {
+        match &stmt.kind {
+            ast::StmtKind::Revert(path, args) => {
+                Some(Some(self.snippet(path.span().to(args.span))))
+            }
+            ast::StmtKind::Expr(expr) => match &expr.kind {
+                ast::ExprKind::Call(callee, args) if matches!(&callee.kind, ast::ExprKind::Ident(i) if i.as_str() == "revert") => {
+                    Some(args.exprs().next().map(|e| self.snippet(e.span)))
+                }
+                _ => None,
+            },
+            ast::StmtKind::Block(block) | ast::StmtKind::UncheckedBlock(block) => {
+                block.stmts.last().and_then(|last| self.reverts(last))
+            }
+            _ => None,
+        }
+    }
+}
 
-        let mut calls = Vec::new();
-        // Navigate: file > contract > function > body > stmts
-        for item in file.items.iter() {
-            if let ast::ItemKind::Contract(c) = &item.kind {
-                for body_item in c.body.iter() {
-                    if let ast::ItemKind::Function(f) = &body_item.kind {
-                        if let Some(block) = &f.body {
-                            for stmt in block.stmts.iter() {
-                                extract_calls_from_stmt(&stmt.kind, &mut calls);
-                            }
-                        }
+impl<'ast> ValidationVisitor<'_> {
+    /// Visit the statements a reverting block runs before its final revert.
+    fn visit_before_revert(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<()> {
+        if let ast::StmtKind::Block(block) | ast::StmtKind::UncheckedBlock(block) = &stmt.kind {
+            if let Some((_, before)) = block.stmts.split_last() {
+                for inner in before {
+                    self.visit_stmt(inner)?;
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl<'ast> Visit<'ast> for ValidationVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        match &stmt.kind {
+            ast::StmtKind::If(condition, then, otherwise) => {
+                let condition_text = self.snippet(condition.span);
+                if let Some(error) = self.reverts(then) {
+                    self.push(
+                        EvmValidationKind::IfRevert,
+                        condition_text,
+                        error,
+                        stmt.span,
+                    );
+                    self.visit_expr(condition)?;
+                    self.visit_before_revert(then)?;
+                    if let Some(otherwise) = otherwise {
+                        self.visit_stmt(otherwise)?;
+                    }
+                    return ControlFlow::Continue(());
+                }
+                // `if (ok) { ... } else revert E();` reverts when the condition fails
+                if let Some((otherwise, error)) = otherwise
+                    .as_ref()
+                    .and_then(|o| self.reverts(o).map(|error| (o, error)))
+                {
+                    let negated = format!("!({})", condition_text);
+                    self.push(EvmValidationKind::IfRevert, negated, error, stmt.span);
+                    self.visit_expr(condition)?;
+                    self.visit_stmt(then)?;
+                    self.visit_before_revert(otherwise)?;
+                    return ControlFlow::Continue(());
+                }
+            }
+            ast::StmtKind::Revert(..) => {
+                let error = self.reverts(stmt).flatten();
+                self.push(EvmValidationKind::Revert, String::new(), error, stmt.span);
+                return ControlFlow::Continue(());
+            }
+            _ => {}
+        }
+        self.walk_stmt(stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, args) = &expr.kind {
+            if let ast::ExprKind::Ident(ident) = &callee.kind {
+                let mut exprs = args.exprs();
+                match ident.as_str() {
+                    "require" | "assert" => {
+                        let kind = if ident.as_str() == "require" {
+                            EvmValidationKind::Require
+                        } else {
+                            EvmValidationKind::Assert
+                        };
+                        let condition = exprs.next().map(|e| self.snippet(e.span));
+                        let error = exprs.next().map(|e| self.snippet(e.span));
+                        self.push(kind, condition.unwrap_or_default(), error, expr.span);
                     }
+                    "revert" => {
+                        let error = exprs.next().map(|e| self.snippet(e.span));
+                        self.push(EvmValidationKind::Revert, String::new(), error, expr.span);
+                    }
+                    _ => {}
                 }
             }
         }
+        self.walk_expr(expr)
+    }
+}
+
+/// Value type of a mapping or element type of an array type string.
+fn element_type(type_name: &str) -> String {
+    let type_name = type_name.trim();
+    if let Some(inner) = type_name
+        .strip_prefix("mapping")
+        .and_then(|t| t.trim().strip_prefix('('))
+        .and_then(|t| t.strip_suffix(')'))
+    {
+        let value = inner
+            .split_once("=>")
+            .map(|(_, value)| value.trim())
+            .unwrap_or(inner);
+        // Drop named mapping values: `mapping(address user => IVault vault)`
+        return match value.rsplit_once(' ') {
+            Some((ty, name)) if !value.starts_with("mapping") && name != "payable" => {
+                ty.trim().to_string()
+            }
+            _ => value.to_string(),
+        };
+    }
+    match type_name.rfind('[') {
+        Some(pos) if type_name.ends_with(']') => type_name[..pos].trim().to_string(),
+        _ => type_name.to_string(),
+    }
+}
+
+/// Extract function call names from Solidity source code using AST.
+/// Falls back to regex if AST parsing fails.
+pub fn extract_calls_from_source(source: &str) -> Vec<String> {
+    let result = with_parsed_body(source, |_sess, block| {
+        let mut calls = Vec::new();
+        for stmt in block.stmts.iter() {
+            extract_calls_from_stmt(&stmt.kind, &mut calls);
+        }
         calls.sort();
         calls.dedup();
-        Some(calls)
+        calls
     });
 
     result.unwrap_or_else(|| extract_calls_regex(source))
@@ -341,7 +1133,8 @@ fn extract_calls_regex(source: &str) -> Vec<String> {
     calls
 }
 
-fn is_builtin(name: &str) -> bool {
+/// Solidity builtins and elementary type conversions, which never resolve to user code.
+pub fn is_builtin(name: &str) -> bool {
     matches!(
         name,
         "require"
@@ -384,3 +1177,430 @@ fn is_builtin(name: &str) -> bool {
             | "length"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{self, param, state_variable};
+    use crate::batbelt::evm::types::EvmEvent;
+
+    fn contract(
+        name: &str,
+        contract_type: EvmContractType,
+        functions: Vec<EvmFunction>,
+    ) -> EvmContract {
+        fixtures::contract(name, contract_type, &[], functions)
+    }
+
+    /// Function taking `address target` whose body ends on `end_line`.
+    fn function(name: &str, contract_name: &str, body: &str, end_line: usize) -> EvmFunction {
+        fixtures::function(name, contract_name, body)
+            .with_params(vec![param("target", "address")])
+            .at_line(end_line + 1 - body.lines().count())
+    }
+
+    #[test]
+    fn test_resolve_external_calls_classification() {
+        let body = "    function exit(address target) external {\n        token.transfer(target, 1);\n        payable(msg.sender).transfer(2);\n        (bool ok, ) = target.call{value: 3}(\"\");\n        target.delegatecall(\"\");\n        MathLib.mulDiv(1, 2, 3);\n    }";
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![function("exit", "Vault", body, 16)],
+        );
+        vault
+            .storage_variables
+            .push(state_variable("token", "IERC20"));
+        let contracts = vec![
+            vault,
+            contract("IERC20", EvmContractType::Interface, vec![]),
+            contract("MathLib", EvmContractType::Library, vec![]),
+        ];
+
+        let resolver = CallResolver::new(&contracts);
+        let calls = resolver.resolve_external_calls("Vault", &contracts[0].functions[0]);
+        let summary: Vec<(ExternalCallKind, &str, &str, bool, usize)> = calls
+            .iter()
+            .map(|c| {
+                (
+                    c.kind.clone(),
+                    c.target_type.as_str(),
+                    c.function.as_str(),
+                    c.forwards_value,
+                    c.line,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (ExternalCallKind::HighLevel, "IERC20", "transfer", false, 11),
+                (
+                    ExternalCallKind::Transfer,
+                    "address payable",
+                    "transfer",
+                    true,
+                    12
+                ),
+                (ExternalCallKind::Call, "address", "call", true, 13),
+                (
+                    ExternalCallKind::DelegateCall,
+                    "address",
+                    "delegatecall",
+                    false,
+                    14
+                ),
+                (ExternalCallKind::Library, "MathLib", "mulDiv", false, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_receiver_transfer_is_not_a_native_call() {
+        let body = "    function exit(address target) external {\n        cfg.token.transfer(target, 1);\n        asset().transfer(target, 2);\n        payable(target).send(3);\n    }";
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![function("exit", "Vault", body, 14)],
+        );
+        vault
+            .storage_variables
+            .push(state_variable("cfg", "Config"));
+        let contracts = vec![vault];
+
+        let resolver = CallResolver::new(&contracts);
+        let calls = resolver.resolve_external_calls("Vault", &contracts[0].functions[0]);
+        let summary: Vec<(ExternalCallKind, &str, usize)> = calls
+            .iter()
+            .map(|c| (c.kind.clone(), c.target_type.as_str(), c.line))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![(ExternalCallKind::Send, "address payable", 13)]
+        );
+    }
+
+    #[test]
+    fn test_callee_names_match_the_receiver() {
+        let body = "    function exit(address target) external {\n        address payable recipient = payable(target);\n        recipient.transfer(1);\n        IERC20(target).transfer(target, 2);\n        payable(target).transfer(3);\n        share.transfer(target, 4);\n    }";
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![function("exit", "Vault", body, 16)],
+        );
+        vault
+            .storage_variables
+            .push(state_variable("share", "IShare"));
+        let contracts = vec![
+            vault,
+            contract("IERC20", EvmContractType::Interface, vec![]),
+            contract("IShare", EvmContractType::Interface, vec![]),
+        ];
+
+        let resolver = CallResolver::new(&contracts);
+        assert_eq!(
+            resolver.callee_names("Vault", &contracts[0].functions[0]),
+            vec!["IERC20.transfer", "IShare.transfer", "recipient.transfer"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_using_for_calls() {
+        let body = "    function exit(address target) external {\n        uint256 shares = balance.mulDiv(2, 3);\n        token.safeTransfer(target, shares);\n        token.transfer(target, 1);\n        _burn(shares);\n    }";
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![
+                function("exit", "Vault", body, 16),
+                function("_burn", "Vault", "    function _burn() internal {}", 17),
+            ],
+        );
+        for (name, type_name) in [("token", "IERC20"), ("balance", "uint")] {
+            vault
+                .storage_variables
+                .push(state_variable(name, type_name));
+        }
+        vault.using_directives.push(EvmUsingDirective {
+            library: Some("SafeERC20".to_string()),
+            functions: vec![],
+            type_name: Some("IERC20".to_string()),
+            global: false,
+            line: 3,
+        });
+        let mut math = contract(
+            "FixedPointMathLib",
+            EvmContractType::Library,
+            vec![function("mulDiv", "FixedPointMathLib", "    {}", 5)],
+        );
+        math.using_directives.push(EvmUsingDirective {
+            library: None,
+            functions: vec!["FixedPointMathLib.mulDiv".to_string()],
+            type_name: Some("uint256".to_string()),
+            global: true,
+            line: 1,
+        });
+        let contracts = vec![
+            vault,
+            contract("IERC20", EvmContractType::Interface, vec![]),
+            contract(
+                "SafeERC20",
+                EvmContractType::Library,
+                vec![function("safeTransfer", "SafeERC20", "    {}", 5)],
+            ),
+            math,
+        ];
+
+        let resolver = CallResolver::new(&contracts);
+        let function = &contracts[0].functions[0];
+        let calls: Vec<(ExternalCallKind, String, String)> = resolver
+            .resolve_external_calls("Vault", function)
+            .into_iter()
+            .map(|c| (c.kind, c.target_type, c.function))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                (
+                    ExternalCallKind::Library,
+                    "FixedPointMathLib".to_string(),
+                    "mulDiv".to_string()
+                ),
+                (
+                    ExternalCallKind::Library,
+                    "SafeERC20".to_string(),
+                    "safeTransfer".to_string()
+                ),
+                (
+                    ExternalCallKind::HighLevel,
+                    "IERC20".to_string(),
+                    "transfer".to_string()
+                ),
+            ]
+        );
+
+        assert_eq!(
+            resolver.callee_names("Vault", function),
+            vec![
+                "FixedPointMathLib.mulDiv",
+                "IERC20.transfer",
+                "SafeERC20.safeTransfer",
+                "_burn",
+            ]
+        );
+        let resolved: Vec<(String, String, bool)> = resolver
+            .resolve_calls("Vault", function)
+            .into_iter()
+            .map(|c| (c.callee_contract, c.callee_function, c.is_external))
+            .collect();
+        assert_eq!(
+            resolved,
+            vec![
+                ("FixedPointMathLib".to_string(), "mulDiv".to_string(), true),
+                ("IERC20".to_string(), "transfer".to_string(), true),
+                ("SafeERC20".to_string(), "safeTransfer".to_string(), true),
+                ("Vault".to_string(), "_burn".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_emitted_events_through_callees() {
+        let ep_body = "    function deposit(address target) external {\n        _deposit(target);\n        emit IVault.Synced();\n    }";
+        let internal_body = "    function _deposit(address target) internal {\n        if (target != address(0)) {\n            emit Deposit(target);\n        }\n    }";
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![
+                function("deposit", "Vault", ep_body, 10),
+                function("_deposit", "Vault", internal_body, 20),
+            ],
+        );
+        vault.base_contracts.push("IVault".to_string());
+        let mut ivault = contract("IVault", EvmContractType::Interface, vec![]);
+        for name in ["Deposit", "Synced"] {
+            ivault.events.push(EvmEvent {
+                name: name.to_string(),
+                params: vec![],
+                is_anonymous: false,
+                line: 3,
+            });
+        }
+        let contracts = vec![vault, ivault];
+
+        let resolver = CallResolver::new(&contracts);
+        let events = resolver.resolve_emitted_events("Vault", &contracts[0].functions[0]);
+        assert_eq!(
+            events,
+            vec![
+                EvmEmittedEvent {
+                    name: "Synced".to_string(),
+                    declared_in: Some("IVault".to_string()),
+                    caller: "deposit".to_string(),
+                    line: 9,
+                },
+                EvmEmittedEvent {
+                    name: "Deposit".to_string(),
+                    declared_in: Some("IVault".to_string()),
+                    caller: "_deposit".to_string(),
+                    line: 18,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_validations_in_modifiers_and_callees() {
+        let ep_body = "    function withdraw(address target) external onlyOwner {\n        require(target != address(0), \"zero target\");\n        _withdraw(target);\n    }";
+        let internal_body = "    function _withdraw(address target) internal {\n        if (paused) {\n            revert Paused();\n        }\n        if (balance > 0) {\n            balance = 0;\n        } else revert(\"empty\");\n        assert(balance == 0);\n    }";
+        let mut withdraw = function("withdraw", "Vault", ep_body, 13);
+        withdraw.modifiers.push("onlyOwner".to_string());
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![withdraw, function("_withdraw", "Vault", internal_body, 30)],
+        );
+        vault.modifiers.push(EvmModifierDef {
+            name: "onlyOwner".to_string(),
+            params: vec![],
+            body_source: "    modifier onlyOwner() {\n        if (msg.sender != owner) revert Unauthorized(msg.sender);\n        _;\n    }".to_string(),
+            line: 4,
+            end_line: 7,
+            contract_name: "Vault".to_string(),
+        });
+        let contracts = vec![vault];
+
+        let resolver = CallResolver::new(&contracts);
+        let validations = resolver.resolve_validations("Vault", &contracts[0].functions[0]);
+        let summary: Vec<(EvmValidationKind, &str, Option<&str>, &str, usize)> = validations
+            .iter()
+            .map(|v| {
+                (
+                    v.kind.clone(),
+                    v.condition.as_str(),
+                    v.error.as_deref(),
+                    v.caller.as_str(),
+                    v.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    EvmValidationKind::Require,
+                    "target != address(0)",
+                    Some("\"zero target\""),
+                    "withdraw",
+                    11,
+                ),
+                (
+                    EvmValidationKind::IfRevert,
+                    "msg.sender != owner",
+                    Some("Unauthorized(msg.sender)"),
+                    "onlyOwner",
+                    5,
+                ),
+                (
+                    EvmValidationKind::IfRevert,
+                    "paused",
+                    Some("Paused()"),
+                    "_withdraw",
+                    23,
+                ),
+                (
+                    EvmValidationKind::IfRevert,
+                    "!(balance > 0)",
+                    Some("\"empty\""),
+                    "_withdraw",
+                    26,
+                ),
+                (
+                    EvmValidationKind::Assert,
+                    "balance == 0",
+                    None,
+                    "_withdraw",
+                    29
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_validations_in_modifier_helpers() {
+        let ep_body = "    function pause() external onlyOwner whenNotPaused {\n        paused = true;\n    }";
+        let check_owner = "    function _checkOwner() internal view {\n        if (owner != msg.sender) {\n            revert OwnableUnauthorizedAccount(msg.sender);\n        }\n    }";
+        let require_not_paused =
+            "    function _requireNotPaused() internal view {\n        require(!paused, \"paused\");\n    }";
+        let mut pause = function("pause", "Vault", ep_body, 12);
+        pause.modifiers = vec!["onlyOwner".to_string(), "whenNotPaused".to_string()];
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            vec![
+                pause,
+                function("_checkOwner", "Vault", check_owner, 20),
+                function("_requireNotPaused", "Vault", require_not_paused, 25),
+            ],
+        );
+        vault.modifiers = vec![
+            fixtures::modifier(
+                "onlyOwner",
+                "Vault",
+                "    modifier onlyOwner() {\n        _checkOwner();\n        _;\n    }",
+            ),
+            fixtures::modifier(
+                "whenNotPaused",
+                "Vault",
+                "    modifier whenNotPaused() {\n        _requireNotPaused();\n        _;\n    }",
+            ),
+        ];
+        let contracts = vec![vault];
+
+        let resolver = CallResolver::new(&contracts);
+        let validations = resolver.resolve_validations("Vault", &contracts[0].functions[0]);
+        let mut summary: Vec<(EvmValidationKind, &str, &str, usize)> = validations
+            .iter()
+            .map(|v| {
+                (
+                    v.kind.clone(),
+                    v.condition.as_str(),
+                    v.caller.as_str(),
+                    v.line,
+                )
+            })
+            .collect();
+        summary.sort_by_key(|(_, _, caller, _)| caller.to_string());
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    EvmValidationKind::IfRevert,
+                    "owner != msg.sender",
+                    "_checkOwner",
+                    17
+                ),
+                (
+                    EvmValidationKind::Require,
+                    "!paused",
+                    "_requireNotPaused",
+                    24
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_element_type() {
+        assert_eq!(element_type("mapping(address => IVault)"), "IVault");
+        assert_eq!(
+            element_type("mapping(address => mapping(uint256 => bool))"),
+            "mapping(uint256 => bool)"
+        );
+        assert_eq!(
+            element_type("mapping(address user => IVault vault)"),
+            "IVault"
+        );
+        assert_eq!(element_type("IStrategy[]"), "IStrategy");
+    }
+}
@@ -1,7 +1,8 @@
 use solar_parse::{ast, interface::Session};
 
 use crate::batbelt::evm::types::{
-    EvmContract, EvmContractType, EvmEvent, EvmFunction, EvmModifierDef, StorageVariable,
+    EvmContract, EvmContractType, EvmCustomError, EvmEnum, EvmEvent, EvmFunction, EvmModifierDef,
+    EvmStruct, EvmUdvt, EvmUsingDirective, StorageVariable,
 };
 
 use super::event_parser::parse_event_definition;
@@ -9,6 +10,10 @@ use super::evm_file_parser::span_to_line;
 use super::function_parser::parse_function_definition;
 use super::modifier_parser::parse_modifier_definition;
 use super::storage_parser::parse_variable_definition;
+use super::type_parser::{
+    parse_enum_definition, parse_error_definition, parse_struct_definition, parse_udvt_definition,
+    parse_using_directive,
+};
 
 /// Parse an ItemContract AST node into our EvmContract type.
 pub fn parse_contract_definition(
@@ -36,6 +41,11 @@ pub fn parse_contract_definition(
     let mut modifiers: Vec<EvmModifierDef> = Vec::new();
     let mut storage_variables: Vec<StorageVariable> = Vec::new();
     let mut events: Vec<EvmEvent> = Vec::new();
+    let mut structs: Vec<EvmStruct> = Vec::new();
+    let mut enums: Vec<EvmEnum> = Vec::new();
+    let mut errors: Vec<EvmCustomError> = Vec::new();
+    let mut udvts: Vec<EvmUdvt> = Vec::new();
+    let mut using_directives: Vec<EvmUsingDirective> = Vec::new();
 
     for item in contract.body.iter() {
         match &item.kind {
@@ -54,14 +64,27 @@ pub fn parse_contract_definition(
             ast::ItemKind::Event(event) => {
                 events.push(parse_event_definition(sess, event));
             }
+            ast::ItemKind::Struct(def) => {
+                structs.push(parse_struct_definition(sess, def, item.span));
+            }
+            ast::ItemKind::Enum(def) => {
+                enums.push(parse_enum_definition(sess, def, item.span));
+            }
+            ast::ItemKind::Error(def) => {
+                errors.push(parse_error_definition(sess, def, item.span));
+            }
+            ast::ItemKind::Udvt(def) => {
+                udvts.push(parse_udvt_definition(sess, def, item.span));
+            }
+            ast::ItemKind::Using(def) => {
+                using_directives.push(parse_using_directive(sess, def, item.span));
+            }
             _ => {}
         }
     }
 
     let line = span_to_line(sess, contract.name.span);
 
-    let external = file_path.contains("/lib/");
-
     EvmContract {
         name,
         contract_type,
@@ -70,8 +93,14 @@ pub fn parse_contract_definition(
         modifiers,
         storage_variables,
         events,
+        structs,
+        enums,
+        errors,
+        udvts,
+        using_directives,
         file_path: file_path.to_string(),
         line,
-        external,
+        // Set by the sonar, which knows the dependency directories
+        external: false,
     }
 }
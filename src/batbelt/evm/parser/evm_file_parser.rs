@@ -10,9 +10,13 @@ use solar_parse::{
 };
 
 use super::{EvmParserError, EvmParserResult};
-use crate::batbelt::evm::types::{EvmFile, EvmImport, ImportSymbol};
+use crate::batbelt::evm::types::{EvmFile, EvmFileDefinitions, EvmImport, ImportSymbol};
 
 use super::contract_parser::parse_contract_definition;
+use super::type_parser::{
+    parse_enum_definition, parse_error_definition, parse_struct_definition, parse_udvt_definition,
+    parse_using_directive,
+};
 
 /// Parse a single .sol file into a `EvmFile` structure.
 pub fn parse_sol_file(file_path: &str) -> EvmParserResult<EvmFile> {
@@ -44,6 +48,10 @@ pub fn parse_sol_file(file_path: &str) -> EvmParserResult<EvmFile> {
             path: file_path.to_string(),
             imports: Vec::new(),
             contracts: Vec::new(),
+            definitions: EvmFileDefinitions {
+                file_path: file_path.to_string(),
+                ..Default::default()
+            },
             pragma: None,
         };
 
@@ -73,10 +81,37 @@ pub fn parse_sol_file(file_path: &str) -> EvmParserResult<EvmFile> {
                         parse_contract_definition(&sess, contract_def, file_path, &source);
                     sol_file.contracts.push(contract);
                 }
+                ast::ItemKind::Struct(def) => {
+                    let definition = parse_struct_definition(&sess, def, item.span);
+                    sol_file.definitions.structs.push(definition);
+                }
+                ast::ItemKind::Enum(def) => {
+                    let definition = parse_enum_definition(&sess, def, item.span);
+                    sol_file.definitions.enums.push(definition);
+                }
+                ast::ItemKind::Error(def) => {
+                    let definition = parse_error_definition(&sess, def, item.span);
+                    sol_file.definitions.errors.push(definition);
+                }
+                ast::ItemKind::Udvt(def) => {
+                    let definition = parse_udvt_definition(&sess, def, item.span);
+                    sol_file.definitions.udvts.push(definition);
+                }
+                ast::ItemKind::Using(def) => {
+                    let directive = parse_using_directive(&sess, def, item.span);
+                    sol_file.definitions.using_directives.push(directive);
+                }
                 _ => {}
             }
         }
 
+        // File-level directives apply to every contract of the file, wherever they appear
+        for contract in &mut sol_file.contracts {
+            contract
+                .using_directives
+                .extend(sol_file.definitions.using_directives.iter().cloned());
+        }
+
         Ok(sol_file)
     });
 
@@ -143,6 +178,14 @@ pub fn type_to_string(sess: &Session, ty: &ast::Type<'_>) -> String {
         .unwrap_or_else(|_| format!("{:?}", ty.kind))
 }
 
+/// Source text of an expression, with whitespace runs collapsed to a single space.
+pub fn expr_to_string(sess: &Session, expr: &ast::Expr<'_>) -> String {
+    sess.source_map()
+        .span_to_snippet(expr.span)
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default()
+}
+
 /// Extract source text between two 1-based line numbers (inclusive).
 pub fn extract_source_by_lines(source: &str, start_line: usize, end_line: usize) -> String {
     let lines: Vec<&str> = source.lines().collect();
@@ -150,3 +193,57 @@ pub fn extract_source_by_lines(source: &str, start_line: usize, end_line: usize)
     let end = end_line.min(lines.len());
     lines[start..end].join("\n")
 }
+
+/// Parse a function or modifier `body_source` with solar-parse and run `f` over its block.
+///
+/// `body_source` starts at the line holding the opening brace, so the function header
+/// (if on the same line) is stripped before wrapping the block in a dummy contract.
+/// Line numbers reported by the session are relative to the first line of `body_source`.
+pub fn with_parsed_body<R, F>(body_source: &str, f: F) -> Option<R>
+where
+    R: Send,
+    F: for<'ast> FnOnce(&Session, &'ast ast::Block<'ast>) -> R + Send,
+{
+    let brace = body_source.find('{')?;
+    let wrapped = format!(
+        "contract _C {{ function _f() {{ {} }} }}",
+        &body_source[brace..]
+    );
+
+    let sess = Session::builder().with_silent_emitter(None).build();
+
+    sess.enter(|| -> Option<R> {
+        let arena = ast::Arena::new();
+        let mut parser = Parser::from_source_code(
+            &sess,
+            &arena,
+            solar_parse::interface::source_map::FileName::Custom("function_body".into()),
+            wrapped,
+        )
+        .ok()?;
+
+        let file = parser.parse_file().map_err(|e| e.emit()).ok()?;
+
+        for item in file.items.iter() {
+            if let ast::ItemKind::Contract(c) = &item.kind {
+                for body_item in c.body.iter() {
+                    if let ast::ItemKind::Function(func) = &body_item.kind {
+                        if let Some(block) = &func.body {
+                            return Some(f(&sess, block));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    })
+}
+
+/// 1-based line in the original file where `body_source` starts, given the line where it ends.
+pub fn body_start_line(body_source: &str, end_line: usize) -> usize {
+    let body_lines = body_source.lines().count();
+    if body_lines == 0 || end_line < body_lines {
+        return end_line;
+    }
+    end_line + 1 - body_lines
+}
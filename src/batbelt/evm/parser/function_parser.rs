@@ -1,9 +1,13 @@
 use solar_parse::{ast, interface::Session};
 
-use crate::batbelt::evm::types::{EvmFunction, EvmMutability, EvmParam, EvmVisibility};
+use crate::batbelt::evm::types::{
+    EvmFunction, EvmModifierInvocation, EvmMutability, EvmParam, EvmVisibility,
+};
 
+use super::assembly_parser::parse_assembly_ops;
 use super::evm_file_parser::{
-    extract_source_by_lines, span_to_end_line, span_to_line, type_to_string,
+    expr_to_string, extract_source_by_lines, span_to_end_line, span_to_line, type_to_string,
+    with_parsed_body,
 };
 
 /// Parse an ItemFunction AST node into our EvmFunction type.
@@ -56,6 +60,20 @@ pub fn parse_function_definition(
         .map(|m| m.name.last().as_str().to_string())
         .collect();
 
+    let modifier_invocations: Vec<EvmModifierInvocation> = func
+        .header
+        .modifiers
+        .iter()
+        .map(|m| EvmModifierInvocation {
+            name: m.name.last().as_str().to_string(),
+            arguments: m
+                .arguments
+                .exprs()
+                .map(|arg| expr_to_string(sess, arg))
+                .collect(),
+        })
+        .collect();
+
     let params: Vec<EvmParam> = func
         .header
         .parameters
@@ -82,6 +100,18 @@ pub fn parse_function_definition(
         })
         .unwrap_or_default();
 
+    // Yul is rare enough that reparsing the body beats threading the file AST lifetime
+    let assembly = match &func.body {
+        Some(block) if body_source.contains("assembly") => {
+            let first_line = span_to_line(sess, block.span);
+            with_parsed_body(&body_source, |body_sess, body| {
+                parse_assembly_ops(body_sess, body, first_line)
+            })
+            .unwrap_or_default()
+        }
+        _ => vec![],
+    };
+
     // Use full function span: from header start to body end
     let full_span = func.header.span.to(func.body_span);
     let line = span_to_line(sess, full_span);
@@ -93,6 +123,7 @@ pub fn parse_function_definition(
         visibility,
         mutability,
         modifiers,
+        modifier_invocations,
         params,
         returns,
         body_source,
@@ -101,6 +132,7 @@ pub fn parse_function_definition(
         is_constructor: func.kind == ast::FunctionKind::Constructor,
         is_fallback: func.kind == ast::FunctionKind::Fallback,
         is_receive: func.kind == ast::FunctionKind::Receive,
+        assembly,
     }
 }
 
@@ -1,38 +1,59 @@
-use error_stack::{Report, ResultExt};
+use error_stack::Report;
+use lazy_regex::regex;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::{EvmParserError, EvmParserResult};
 
-/// Resolves Solidity import paths using Foundry remappings.
+/// Hardhat config file names, in lookup order.
+pub const HARDHAT_CONFIG_FILES: &[&str] = &["hardhat.config.ts", "hardhat.config.js"];
+
+/// Resolves Solidity import paths for Foundry, Hardhat and plain solc layouts.
 pub struct ImportResolver {
     /// Map from remapping prefix to replacement path.
     remappings: HashMap<String, String>,
-    /// Base source directory (from foundry.toml `src`).
+    /// Base source directory (foundry.toml `src`, Hardhat `paths.sources`).
     src_dir: PathBuf,
+    /// Dependency directories (foundry.toml `libs`, `lib/`, `node_modules/`).
+    lib_dirs: Vec<PathBuf>,
+    /// Solidity test directory (foundry.toml `test`, `test/` by default).
+    test_dir: PathBuf,
     /// Project root directory.
     root_dir: PathBuf,
 }
 
 impl ImportResolver {
-    /// Create a new resolver by reading foundry.toml and remappings.txt.
+    /// Create a new resolver by reading foundry.toml, remappings.txt and the Hardhat config.
     pub fn new(project_root: &str) -> EvmParserResult<Self> {
         let root_dir = PathBuf::from(project_root);
-        let src_dir = Self::detect_src_dir(&root_dir)?;
-        let remappings = Self::load_remappings(&root_dir)?;
+        let foundry_profile = Self::load_foundry_profile(&root_dir)?;
+        let src_dir = Self::detect_src_dir(&root_dir, foundry_profile.as_ref());
+        let lib_dirs = Self::detect_lib_dirs(&root_dir, foundry_profile.as_ref());
+        let test_dir = root_dir.join(
+            foundry_profile
+                .as_ref()
+                .and_then(|profile| profile.get("test"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("test"),
+        );
+        let remappings = Self::load_remappings(&root_dir, foundry_profile.as_ref());
 
         Ok(Self {
             remappings,
             src_dir,
+            lib_dirs,
+            test_dir,
             root_dir,
         })
     }
 
     /// Resolve an import path to an absolute file path.
     pub fn resolve(&self, import_path: &str, from_file: &str) -> Option<PathBuf> {
-        // Try remappings first
-        for (prefix, replacement) in &self.remappings {
+        // Try remappings first, longest prefix wins
+        let mut remappings: Vec<(&String, &String)> = self.remappings.iter().collect();
+        remappings.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        for (prefix, replacement) in remappings {
             if import_path.starts_with(prefix.as_str()) {
                 let resolved = import_path.replacen(prefix.as_str(), replacement.as_str(), 1);
                 let full_path = self.root_dir.join(&resolved);
@@ -61,46 +82,93 @@ impl ImportResolver {
             return Some(from_src);
         }
 
-        // Try lib/ (common for Foundry dependencies)
-        let from_lib = self.root_dir.join("lib").join(import_path);
-        if from_lib.is_file() {
-            return Some(from_lib);
+        // Try dependency dirs: `lib/forge-std/...`, `node_modules/@scope/package/...`
+        self.lib_dirs
+            .iter()
+            .map(|lib_dir| lib_dir.join(import_path))
+            .find(|path| path.is_file())
+    }
+
+    /// `[profile.default]` table of foundry.toml, if the project has one.
+    fn load_foundry_profile(root: &Path) -> EvmParserResult<Option<toml::Table>> {
+        let foundry_toml_path = root.join("foundry.toml");
+        if !foundry_toml_path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&foundry_toml_path).map_err(|e| {
+            Report::new(EvmParserError).attach_printable(format!("Cannot read foundry.toml: {}", e))
+        })?;
+        let table: toml::Table = content.parse().map_err(|e| {
+            Report::new(EvmParserError)
+                .attach_printable(format!("Cannot parse foundry.toml: {}", e))
+        })?;
+        let profile = table
+            .get("profile")
+            .and_then(|p| p.get("default"))
+            .and_then(|p| p.as_table())
+            .cloned()
+            .unwrap_or_default();
+        Ok(Some(profile))
+    }
+
+    fn detect_src_dir(root: &Path, foundry_profile: Option<&toml::Table>) -> PathBuf {
+        if let Some(profile) = foundry_profile {
+            let src = profile.get("src").and_then(|v| v.as_str()).unwrap_or("src");
+            return root.join(src);
         }
 
-        // Try node_modules (Hardhat-style)
-        let from_node_modules = self.root_dir.join("node_modules").join(import_path);
-        if from_node_modules.is_file() {
-            return Some(from_node_modules);
+        if let Some(sources) = Self::hardhat_sources(root) {
+            return root.join(sources);
         }
 
-        None
+        // Plain solc repositories: Hardhat-style `contracts/`, then Foundry-style `src/`
+        ["contracts", "src"]
+            .iter()
+            .map(|dir| root.join(dir))
+            .find(|dir| dir.is_dir())
+            .unwrap_or_else(|| root.to_path_buf())
     }
 
-    fn detect_src_dir(root: &Path) -> EvmParserResult<PathBuf> {
-        let foundry_toml_path = root.join("foundry.toml");
-        if foundry_toml_path.is_file() {
-            let content = fs::read_to_string(&foundry_toml_path).map_err(|e| {
-                Report::new(EvmParserError)
-                    .attach_printable(format!("Cannot read foundry.toml: {}", e))
-            })?;
+    /// `paths.sources` of the Hardhat config (`contracts` by default), if there is one.
+    fn hardhat_sources(root: &Path) -> Option<String> {
+        let config = HARDHAT_CONFIG_FILES
+            .iter()
+            .map(|name| root.join(name))
+            .find(|path| path.is_file())?;
+        let content = fs::read_to_string(config).unwrap_or_default();
+        let sources = regex!(r#"sources\s*:\s*["'`]([^"'`]+)["'`]"#)
+            .captures(&content)
+            .map(|captures| captures[1].trim_start_matches("./").to_string())
+            .unwrap_or_else(|| "contracts".to_string());
+        Some(sources)
+    }
 
-            // Simple TOML parsing for src field
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if trimmed.starts_with("src") {
-                    if let Some(value) = trimmed.split('=').nth(1) {
-                        let src = value.trim().trim_matches('"').trim_matches('\'');
-                        return Ok(root.join(src));
-                    }
-                }
-            }
+    fn detect_lib_dirs(root: &Path, foundry_profile: Option<&toml::Table>) -> Vec<PathBuf> {
+        let configured: Option<Vec<String>> = foundry_profile
+            .and_then(|profile| profile.get("libs"))
+            .and_then(|libs| libs.as_array())
+            .map(|libs| {
+                libs.iter()
+                    .filter_map(|lib| lib.as_str().map(|s| s.to_string()))
+                    .collect()
+            });
+        let mut lib_dirs: Vec<PathBuf> = configured
+            .unwrap_or_else(|| vec!["lib".to_string()])
+            .iter()
+            .map(|lib| root.join(lib))
+            .collect();
+        let node_modules = root.join("node_modules");
+        if node_modules.is_dir() && !lib_dirs.contains(&node_modules) {
+            lib_dirs.push(node_modules);
         }
-
-        // Default Foundry src directory
-        Ok(root.join("src"))
+        lib_dirs.retain(|dir| dir.is_dir());
+        lib_dirs
     }
 
-    fn load_remappings(root: &Path) -> EvmParserResult<HashMap<String, String>> {
+    fn load_remappings(
+        root: &Path,
+        foundry_profile: Option<&toml::Table>,
+    ) -> HashMap<String, String> {
         let mut remappings = HashMap::new();
 
         // Load from remappings.txt
@@ -112,44 +180,142 @@ impl ImportResolver {
                 if line.is_empty() || line.starts_with('#') {
                     continue;
                 }
-                if let Some((prefix, replacement)) = line.split_once('=') {
-                    remappings.insert(prefix.to_string(), replacement.to_string());
+                if let Some((prefix, replacement)) = parse_remapping(line) {
+                    remappings.insert(prefix, replacement);
                 }
             }
         }
 
-        // Also check foundry.toml for inline remappings
-        let foundry_toml = root.join("foundry.toml");
-        if foundry_toml.is_file() {
-            let content = fs::read_to_string(&foundry_toml).unwrap_or_default();
-            let mut in_remappings = false;
-            for line in content.lines() {
-                let trimmed = line.trim();
-                if trimmed.starts_with("remappings") && trimmed.contains('[') {
-                    in_remappings = true;
-                    continue;
-                }
-                if in_remappings {
-                    if trimmed.contains(']') {
-                        in_remappings = false;
-                        continue;
-                    }
-                    let cleaned = trimmed.trim_matches(|c| c == '"' || c == '\'' || c == ',');
-                    if let Some((prefix, replacement)) = cleaned.split_once('=') {
-                        remappings.insert(prefix.to_string(), replacement.to_string());
-                    }
-                }
+        // foundry.toml remappings take precedence over remappings.txt
+        let inline = foundry_profile
+            .and_then(|profile| profile.get("remappings"))
+            .and_then(|remappings| remappings.as_array());
+        for remapping in inline.into_iter().flatten().filter_map(|r| r.as_str()) {
+            if let Some((prefix, replacement)) = parse_remapping(remapping) {
+                remappings.insert(prefix, replacement);
             }
         }
 
-        Ok(remappings)
+        remappings
     }
 
     pub fn get_src_dir(&self) -> &Path {
         &self.src_dir
     }
 
+    pub fn get_lib_dirs(&self) -> &[PathBuf] {
+        &self.lib_dirs
+    }
+
+    pub fn get_test_dir(&self) -> &Path {
+        &self.test_dir
+    }
+
     pub fn get_root_dir(&self) -> &Path {
         &self.root_dir
     }
+
+    /// true if `path` points into one of the dependency directories rather than the audited
+    /// sources. Paths are compared relative to the project root, so `src/lib/Math.sol` is
+    /// in scope even though `lib/` is a dependency directory.
+    pub fn is_dependency_path(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        let relative = path.strip_prefix(&self.root_dir).unwrap_or(path);
+        self.lib_dirs.iter().any(|lib_dir| {
+            let lib_dir = lib_dir.strip_prefix(&self.root_dir).unwrap_or(lib_dir);
+            relative.starts_with(lib_dir)
+        })
+    }
+}
+
+/// Split `[context:]prefix=target` into `(prefix, target)`; the context is ignored.
+fn parse_remapping(remapping: &str) -> Option<(String, String)> {
+    let (prefix, replacement) = remapping.trim().split_once('=')?;
+    let prefix = prefix.rsplit_once(':').map(|(_, p)| p).unwrap_or(prefix);
+    Some((prefix.trim().to_string(), replacement.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Project named after the test, so tests running in parallel don't share it.
+    fn project(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!(
+            "bat-import-resolver-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_hardhat_layout_resolves_node_modules() {
+        let root = project(
+            "hardhat",
+            &[
+                (
+                    "hardhat.config.ts",
+                    "export default { solidity: \"0.8.20\", paths: { sources: \"./solidity\" } };",
+                ),
+                ("solidity/Vault.sol", ""),
+                (
+                    "node_modules/@openzeppelin/contracts/access/Ownable.sol",
+                    "",
+                ),
+            ],
+        );
+        let resolver = ImportResolver::new(root.to_str().unwrap()).unwrap();
+
+        assert_eq!(resolver.get_src_dir(), root.join("solidity"));
+        assert_eq!(
+            resolver.resolve(
+                "@openzeppelin/contracts/access/Ownable.sol",
+                root.join("solidity/Vault.sol").to_str().unwrap()
+            ),
+            Some(root.join("node_modules/@openzeppelin/contracts/access/Ownable.sol"))
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_foundry_toml_src_libs_and_remappings() {
+        let root = project("foundry", &[
+            (
+                "foundry.toml",
+                "[profile.default]\nsrc = \"contracts\"\ntest = \"tests\"\nlibs = [\"dependencies\"]\nremappings = [\"@oz/=dependencies/openzeppelin/\", \"src/:solmate/=dependencies/solmate/src/\"]\n",
+            ),
+            ("contracts/Vault.sol", ""),
+            ("contracts/lib/Math.sol", ""),
+            ("lib/forge-std/src/Test.sol", ""),
+            ("dependencies/openzeppelin/token/ERC20.sol", ""),
+            ("dependencies/solmate/src/auth/Auth.sol", ""),
+        ]);
+        let resolver = ImportResolver::new(root.to_str().unwrap()).unwrap();
+        let from = root.join("contracts/Vault.sol");
+        let from = from.to_str().unwrap();
+
+        assert_eq!(resolver.get_src_dir(), root.join("contracts"));
+        assert_eq!(resolver.get_lib_dirs(), [root.join("dependencies")]);
+        assert_eq!(resolver.get_test_dir(), root.join("tests"));
+        assert_eq!(
+            resolver.resolve("@oz/token/ERC20.sol", from),
+            Some(root.join("dependencies/openzeppelin/token/ERC20.sol"))
+        );
+        assert_eq!(
+            resolver.resolve("solmate/auth/Auth.sol", from),
+            Some(root.join("dependencies/solmate/src/auth/Auth.sol"))
+        );
+        let path = |path: &str| root.join(path).to_string_lossy().to_string();
+        assert!(resolver.is_dependency_path(&path("dependencies/openzeppelin/token/ERC20.sol")));
+        assert!(!resolver.is_dependency_path(&path("contracts/lib/Math.sol")));
+        assert!(!resolver.is_dependency_path(&path("lib/forge-std/src/Test.sol")));
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -3,7 +3,6 @@ use std::collections::HashMap;
 use crate::batbelt::evm::types::EvmContract;
 
 /// Resolves C3 linearization for Solidity inheritance.
-/// Returns contracts in linearized order (most derived first).
 pub struct InheritanceResolver<'a> {
     contracts_by_name: HashMap<String, &'a EvmContract>,
 }
@@ -16,28 +15,54 @@ impl<'a> InheritanceResolver<'a> {
     }
 
     /// Get C3 linearization for a contract.
-    /// Returns list of contract names from most derived (self) to most base.
+    /// Returns list of contract names from most base to most derived (self last), which is
+    /// also the order solc lays out state variables in.
     pub fn linearize(&self, contract_name: &str) -> Vec<String> {
-        let mut result = Vec::new();
-        let mut visited = Vec::new();
-        self.c3_linearize(contract_name, &mut result, &mut visited);
+        let mut in_progress = Vec::new();
+        let mut result = self.c3_linearize(contract_name, &mut in_progress);
+        result.reverse();
         result
     }
 
-    fn c3_linearize(&self, name: &str, result: &mut Vec<String>, visited: &mut Vec<String>) {
-        if visited.contains(&name.to_string()) {
-            return;
-        }
-        visited.push(name.to_string());
+    /// Solidity C3: `L(C) = C + merge(L(Bn), ..., L(B1), [Bn, ..., B1])` for `C is B1, ..., Bn`.
+    /// Returns names from most derived to most base.
+    fn c3_linearize(&self, name: &str, in_progress: &mut Vec<String>) -> Vec<String> {
+        let bases: Vec<String> = match self.contracts_by_name.get(name) {
+            Some(contract) if !in_progress.iter().any(|n| n == name) => {
+                contract.base_contracts.iter().rev().cloned().collect()
+            }
+            _ => return vec![name.to_string()],
+        };
+
+        in_progress.push(name.to_string());
+        let mut sequences: Vec<Vec<String>> = bases
+            .iter()
+            .map(|base| self.c3_linearize(base, in_progress))
+            .collect();
+        sequences.push(bases);
+        in_progress.pop();
 
-        if let Some(contract) = self.contracts_by_name.get(name) {
-            // Process bases in reverse order (rightmost base is most base-like)
-            for base_name in contract.base_contracts.iter().rev() {
-                self.c3_linearize(base_name, result, visited);
+        let mut result = vec![name.to_string()];
+        loop {
+            sequences.retain(|seq| !seq.is_empty());
+            if sequences.is_empty() {
+                break;
+            }
+            // First head not appearing in the tail of any sequence; inconsistent hierarchies
+            // (rejected by solc) fall back to the first head
+            let head = sequences
+                .iter()
+                .map(|seq| seq[0].clone())
+                .find(|head| !sequences.iter().any(|seq| seq[1..].contains(head)))
+                .unwrap_or_else(|| sequences[0][0].clone());
+            for seq in sequences.iter_mut() {
+                seq.retain(|n| *n != head);
+            }
+            if !result.contains(&head) {
+                result.push(head);
             }
         }
-
-        result.push(name.to_string());
+        result
     }
 
     /// Get all inherited functions for a contract (including from bases).
@@ -45,7 +70,7 @@ impl<'a> InheritanceResolver<'a> {
     pub fn get_all_functions(
         &self,
         contract_name: &str,
-    ) -> Vec<(&str, &crate::batbelt::evm::types::EvmFunction)> {
+    ) -> Vec<(&'a str, &'a crate::batbelt::evm::types::EvmFunction)> {
         let linearization = self.linearize(contract_name);
         let mut result = Vec::new();
         let mut seen_signatures: Vec<String> = Vec::new();
@@ -54,7 +79,13 @@ impl<'a> InheritanceResolver<'a> {
         for name in linearization.iter().rev() {
             if let Some(contract) = self.contracts_by_name.get(name.as_str()) {
                 for func in &contract.functions {
-                    let sig = format!("{}({})", func.name, func.params.len());
+                    let param_types = func
+                        .params
+                        .iter()
+                        .map(|p| p.type_name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    let sig = format!("{}({})", func.name, param_types);
                     if !seen_signatures.contains(&sig) {
                         seen_signatures.push(sig);
                         result.push((contract.name.as_str(), func));
@@ -72,3 +103,30 @@ impl<'a> InheritanceResolver<'a> {
         linearization.contains(&base_name.to_string()) && contract_name != base_name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures;
+    use crate::batbelt::evm::types::EvmContractType;
+
+    fn contract(name: &str, bases: &[&str]) -> EvmContract {
+        fixtures::contract(name, EvmContractType::Contract, bases, vec![])
+    }
+
+    #[test]
+    fn test_linearize_multiple_inheritance() {
+        let contracts = vec![
+            contract("Context", &[]),
+            contract("Ownable", &["Context"]),
+            contract("Pausable", &["Context"]),
+            contract("Vault", &["Ownable", "Pausable"]),
+        ];
+        let resolver = InheritanceResolver::new(&contracts);
+        assert_eq!(
+            resolver.linearize("Vault"),
+            vec!["Context", "Ownable", "Pausable", "Vault"]
+        );
+        assert!(resolver.inherits_from("Vault", "Context"));
+    }
+}
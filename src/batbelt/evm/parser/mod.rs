@@ -1,3 +1,5 @@
+pub mod access_control_resolver;
+pub mod assembly_parser;
 pub mod call_resolver;
 pub mod contract_parser;
 pub mod event_parser;
@@ -6,7 +8,16 @@ pub mod function_parser;
 pub mod import_resolver;
 pub mod inheritance_resolver;
 pub mod modifier_parser;
+pub mod proxy_resolver;
+pub mod reentrancy_resolver;
+pub mod role_resolver;
+pub mod selector_resolver;
+pub mod storage_access_resolver;
+pub mod storage_layout_resolver;
 pub mod storage_parser;
+pub mod test_resolver;
+pub mod type_parser;
+pub mod type_resolver;
 
 use std::{error::Error, fmt};
 
@@ -0,0 +1,689 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+use crate::batbelt::evm::types::{
+    AccessControlType, EvmContract, EvmContractType, EvmFunction, EvmProxyFinding,
+    EvmProxyFindingKind, EvmProxyInfo, EvmProxyKind, EvmVisibility, ExternalCallKind,
+};
+
+use super::access_control_resolver::AccessControlResolver;
+use super::call_resolver::CallResolver;
+use super::inheritance_resolver::InheritanceResolver;
+use super::storage_access_resolver::StorageAccessResolver;
+
+/// EIP-1967 slots as `(name, value, preimage)`; contracts declare them either way.
+const EIP1967_SLOTS: &[(&str, &str, &str)] = &[
+    (
+        "implementation",
+        "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc",
+        "eip1967.proxy.implementation",
+    ),
+    (
+        "admin",
+        "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103",
+        "eip1967.proxy.admin",
+    ),
+    (
+        "beacon",
+        "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50",
+        "eip1967.proxy.beacon",
+    ),
+];
+
+/// Storage slot preimage of the EIP-2535 reference implementation.
+const DIAMOND_STORAGE_PREIMAGE: &str = "diamond.standard.diamond.storage";
+
+/// Interfaces of the EIP-2535 facets managing the diamond itself.
+const DIAMOND_FACET_INTERFACES: &[&str] = &["IDiamondCut", "IDiamondLoupe"];
+
+/// Modifiers that make an initializer callable only once.
+const INITIALIZER_MODIFIERS: &[&str] = &["initializer", "reinitializer", "onlyInitializing"];
+
+/// Contracts whose selectors a proxy dispatches: the upgradeable implementations it may
+/// point to, or the facets of a diamond.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectorRoute {
+    /// Proxy dispatching the selectors; `None` for facets whose diamond is not in scope
+    pub proxy: Option<String>,
+    pub diamond: bool,
+    /// Implementations or facets
+    pub targets: Vec<String>,
+    /// The proxy neither references nor is pinned to an implementation, so every
+    /// upgradeable contract in scope may be behind it
+    pub candidate: bool,
+}
+
+/// Classifies contracts as Transparent, UUPS, Beacon or Diamond proxies from their
+/// inheritance, `delegatecall` usage and EIP-1967 slots, and flags unsafe initializers and
+/// upgrade authorizations.
+pub struct ProxyResolver<'a> {
+    contracts: &'a [EvmContract],
+    inheritance_resolver: InheritanceResolver<'a>,
+    call_resolver: CallResolver<'a>,
+    access_control_resolver: AccessControlResolver<'a>,
+    storage_access_resolver: StorageAccessResolver<'a>,
+}
+
+impl<'a> ProxyResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self {
+            contracts,
+            inheritance_resolver: InheritanceResolver::new(contracts),
+            call_resolver: CallResolver::new(contracts),
+            access_control_resolver: AccessControlResolver::new(contracts),
+            storage_access_resolver: StorageAccessResolver::new(contracts),
+        }
+    }
+
+    pub fn resolve(&self, contract_name: &str) -> EvmProxyInfo {
+        let Some(contract) = self.contracts.iter().find(|c| c.name == contract_name) else {
+            return EvmProxyInfo::default();
+        };
+        let linearization = self.inheritance_resolver.linearize(contract_name);
+        let functions = self.inheritance_resolver.get_all_functions(contract_name);
+        let inherits = |base: &str| linearization.iter().any(|name| name == base);
+        let defines = |name: &str| functions.iter().any(|(_, f)| f.name == name);
+
+        let sources = self.sources(&linearization, &functions);
+        let eip1967_slots: Vec<String> = EIP1967_SLOTS
+            .iter()
+            .filter(|(_, value, preimage)| {
+                sources.iter().any(|source| {
+                    let source = source.to_lowercase();
+                    source.contains(value) || source.contains(preimage)
+                })
+            })
+            .map(|(name, _, _)| name.to_string())
+            .collect();
+        let delegates = self.delegates(contract_name, &functions);
+        let has_slot = |slot: &str| eip1967_slots.iter().any(|s| s == slot);
+
+        let kind = if inherits("Diamond")
+            || (delegates
+                && (defines("diamondCut")
+                    || sources
+                        .iter()
+                        .any(|source| source.contains(DIAMOND_STORAGE_PREIMAGE))))
+        {
+            EvmProxyKind::Diamond
+        } else if inherits("BeaconProxy") {
+            EvmProxyKind::Beacon
+        } else if inherits("TransparentUpgradeableProxy") {
+            EvmProxyKind::Transparent
+        } else if inherits("UUPSUpgradeable")
+            || defines("_authorizeUpgrade")
+            || defines("proxiableUUID")
+            || inherits("ERC1967Proxy")
+        {
+            EvmProxyKind::Uups
+        } else if delegates && has_slot("beacon") {
+            EvmProxyKind::Beacon
+        } else if delegates && has_slot("admin") {
+            EvmProxyKind::Transparent
+        } else if delegates {
+            EvmProxyKind::Custom
+        } else {
+            EvmProxyKind::Plain
+        };
+
+        let initializable =
+            inherits("Initializable") || functions.iter().any(|(_, f)| is_initializer_guarded(f));
+
+        let mut info = EvmProxyInfo {
+            kind,
+            initializable,
+            eip1967_slots,
+            findings: vec![],
+        };
+        if contract.contract_type == EvmContractType::Contract {
+            info.findings = self.findings(contract, &info, delegates, &linearization, &functions);
+        }
+        info
+    }
+
+    /// Selector routes of the in-scope `proxy_names`. Facets are the deployable contracts
+    /// implementing `IDiamondCut`/`IDiamondLoupe`, using `LibDiamond`, or named where the
+    /// project builds a `FacetCut`. The implementation of any other proxy is the one pinned
+    /// in `pinned_implementations`, else the initializable or UUPS contracts it references
+    /// (constructor or initializer argument type, `new Vault()`...), else all of them as
+    /// candidates.
+    pub fn selector_routes(
+        &self,
+        proxy_names: &[String],
+        pinned_implementations: &BTreeMap<String, String>,
+    ) -> Vec<SelectorRoute> {
+        let deployable: Vec<&EvmContract> = self
+            .contracts
+            .iter()
+            .filter(|c| {
+                !c.external
+                    && c.contract_type == EvmContractType::Contract
+                    && !proxy_names.contains(&c.name)
+            })
+            .collect();
+        let cut_sources: Vec<&str> = self
+            .contracts
+            .iter()
+            .filter(|c| !c.external)
+            .flat_map(|c| c.functions.iter())
+            .map(|f| f.body_source.as_str())
+            .filter(|body| body.contains("FacetCut") || body.contains("diamondCut("))
+            .collect();
+        let facets: Vec<String> = deployable
+            .iter()
+            .filter(|c| self.is_facet(c, &cut_sources))
+            .map(|c| c.name.clone())
+            .collect();
+        let implementations: Vec<String> = deployable
+            .iter()
+            .filter(|c| !facets.contains(&c.name))
+            .filter(|c| {
+                let info = self.resolve(&c.name);
+                info.initializable || info.kind == EvmProxyKind::Uups
+            })
+            .map(|c| c.name.clone())
+            .collect();
+
+        let mut routes: Vec<SelectorRoute> = proxy_names
+            .iter()
+            .map(|proxy| {
+                let diamond = self.resolve(proxy).kind == EvmProxyKind::Diamond;
+                let (targets, candidate) = if diamond {
+                    (facets.clone(), false)
+                } else if let Some(pinned) = pinned_implementations.get(proxy) {
+                    (vec![pinned.clone()], false)
+                } else {
+                    let referenced = self.referenced_implementations(proxy, &implementations);
+                    if referenced.is_empty() {
+                        (implementations.clone(), true)
+                    } else {
+                        (referenced, false)
+                    }
+                };
+                SelectorRoute {
+                    proxy: Some(proxy.clone()),
+                    diamond,
+                    targets,
+                    candidate,
+                }
+            })
+            .collect();
+        if !facets.is_empty() && !routes.iter().any(|route| route.diamond) {
+            routes.push(SelectorRoute {
+                proxy: None,
+                diamond: true,
+                targets: facets,
+                candidate: false,
+            });
+        }
+        routes
+    }
+
+    /// `implementations` named, directly or through an interface they inherit, by the
+    /// in-scope code of `proxy`: parameter and state variable types or function bodies.
+    fn referenced_implementations(&self, proxy: &str, implementations: &[String]) -> Vec<String> {
+        let linearization = self.inheritance_resolver.linearize(proxy);
+        let proxy_sources: Vec<&str> = self
+            .contracts
+            .iter()
+            .filter(|c| !c.external && linearization.contains(&c.name))
+            .flat_map(|c| {
+                let functions = c.functions.iter().flat_map(|f| {
+                    f.params
+                        .iter()
+                        .map(|p| p.type_name.as_str())
+                        .chain(std::iter::once(f.body_source.as_str()))
+                });
+                let variables = c.storage_variables.iter().map(|v| v.type_name.as_str());
+                functions.chain(variables)
+            })
+            .collect();
+        let names = |source: &str, name: &str| {
+            Regex::new(&format!(r"\b{}\b", regex::escape(name)))
+                .unwrap()
+                .is_match(source)
+        };
+        implementations
+            .iter()
+            .filter(|implementation| {
+                let interfaces = self
+                    .inheritance_resolver
+                    .linearize(implementation)
+                    .into_iter()
+                    .filter(|name| {
+                        self.contracts.iter().any(|c| {
+                            c.name == *name && c.contract_type == EvmContractType::Interface
+                        })
+                    });
+                let aliases: Vec<String> = std::iter::once(implementation.to_string())
+                    .chain(interfaces)
+                    .collect();
+                proxy_sources
+                    .iter()
+                    .any(|source| aliases.iter().any(|alias| names(source, alias)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn is_facet(&self, contract: &EvmContract, cut_sources: &[&str]) -> bool {
+        let linearization = self.inheritance_resolver.linearize(&contract.name);
+        if linearization
+            .iter()
+            .any(|name| DIAMOND_FACET_INTERFACES.contains(&name.as_str()))
+        {
+            return true;
+        }
+        if self
+            .inheritance_resolver
+            .get_all_functions(&contract.name)
+            .iter()
+            .any(|(_, f)| f.body_source.contains("LibDiamond."))
+        {
+            return true;
+        }
+        let named = Regex::new(&format!(r"\b{}\b", regex::escape(&contract.name))).unwrap();
+        cut_sources.iter().any(|source| named.is_match(source))
+    }
+
+    fn findings(
+        &self,
+        contract: &EvmContract,
+        info: &EvmProxyInfo,
+        is_proxy: bool,
+        linearization: &[String],
+        functions: &[(&str, &'a EvmFunction)],
+    ) -> Vec<EvmProxyFinding> {
+        let mut findings = Vec::new();
+
+        // Only implementations run behind a proxy and must lock their own initializers
+        if info.initializable && !is_proxy {
+            let constructor = functions.iter().find(|(_, f)| f.is_constructor);
+            // Base constructors run too, so any of them may lock the initializers
+            let disables = self
+                .contracts
+                .iter()
+                .filter(|c| linearization.contains(&c.name))
+                .flat_map(|c| c.functions.iter())
+                .filter(|f| f.is_constructor)
+                .any(|f| f.body_source.contains("_disableInitializers"));
+            if !disables {
+                findings.push(EvmProxyFinding {
+                    kind: EvmProxyFindingKind::MissingDisableInitializers,
+                    function: "constructor".to_string(),
+                    contract_name: constructor
+                        .map(|(name, _)| name.to_string())
+                        .unwrap_or_else(|| contract.name.clone()),
+                    line: constructor.map(|(_, f)| f.line).unwrap_or(contract.line),
+                });
+            }
+        }
+
+        for (defined_in, function) in functions {
+            let is_entry_point = matches!(
+                function.visibility,
+                EvmVisibility::External | EvmVisibility::Public
+            );
+            let unprotected = if is_entry_point && function.name.starts_with("initialize") {
+                !is_initializer_guarded(function)
+                    && !self.guarded_by_state_flag(&contract.name, function)
+                    && !self.restricts_caller(&contract.name, function)
+            } else if function.name == "_authorizeUpgrade" {
+                !self.restricts_caller(&contract.name, function)
+            } else {
+                continue;
+            };
+            if !unprotected {
+                continue;
+            }
+            let kind = if function.name == "_authorizeUpgrade" {
+                EvmProxyFindingKind::UnprotectedAuthorizeUpgrade
+            } else {
+                EvmProxyFindingKind::UnprotectedInitialize
+            };
+            findings.push(EvmProxyFinding {
+                kind,
+                function: function.name.clone(),
+                contract_name: defined_in.to_string(),
+                line: function.line,
+            });
+        }
+        findings
+    }
+
+    fn restricts_caller(&self, contract_name: &str, function: &EvmFunction) -> bool {
+        self.access_control_resolver
+            .resolve(contract_name, function)
+            .iter()
+            .any(|check| *check != AccessControlType::None)
+    }
+
+    /// true if `function` checks a `bool` state variable in a `require` or `if (...) revert`
+    /// and also assigns it, like a hand-rolled `initialized` flag.
+    fn guarded_by_state_flag(&self, contract_name: &str, function: &'a EvmFunction) -> bool {
+        let flags: Vec<&str> = self
+            .call_resolver
+            .lookup_order(contract_name)
+            .iter()
+            .flat_map(|c| c.storage_variables.iter())
+            .filter(|v| v.type_name == "bool" && !v.is_constant && !v.is_immutable)
+            .map(|v| v.name.as_str())
+            .collect();
+        let access = self
+            .storage_access_resolver
+            .resolve(contract_name, function);
+        let written_flags: Vec<&str> = flags
+            .into_iter()
+            .filter(|flag| access.writes.iter().any(|w| w == flag))
+            .collect();
+        if written_flags.is_empty() {
+            return false;
+        }
+        self.call_resolver
+            .resolve_validations(contract_name, function)
+            .iter()
+            .any(|validation| {
+                validation
+                    .condition
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                    .any(|identifier| written_flags.contains(&identifier))
+            })
+    }
+
+    /// true if the contract has a fallback and forwards calls with `delegatecall`.
+    fn delegates(&self, contract_name: &str, functions: &[(&str, &EvmFunction)]) -> bool {
+        if !functions.iter().any(|(_, f)| f.is_fallback) {
+            return false;
+        }
+        // Inline assembly `delegatecall`s are resolved as external calls too
+        functions.iter().any(|(_, f)| {
+            self.call_resolver
+                .resolve_external_calls(contract_name, f)
+                .iter()
+                .any(|call| call.kind == ExternalCallKind::DelegateCall)
+        })
+    }
+
+    /// Constant initializers and function bodies of the whole linearization.
+    fn sources(&self, linearization: &[String], functions: &[(&str, &EvmFunction)]) -> Vec<String> {
+        let constants = self
+            .contracts
+            .iter()
+            .filter(|c| linearization.contains(&c.name))
+            .flat_map(|c| c.storage_variables.iter())
+            .filter_map(|v| v.value.clone());
+        let bodies = functions.iter().map(|(_, f)| f.body_source.clone());
+        constants.chain(bodies).collect()
+    }
+}
+
+fn is_initializer_guarded(function: &EvmFunction) -> bool {
+    function
+        .modifiers
+        .iter()
+        .any(|m| INITIALIZER_MODIFIERS.contains(&m.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, function, param, state_variable};
+    use crate::batbelt::evm::types::StorageVariable;
+
+    #[test]
+    fn test_uups_implementation_findings() {
+        let contracts = vec![
+            contract("Initializable", EvmContractType::Abstract, &[], vec![]),
+            contract(
+                "UUPSUpgradeable",
+                EvmContractType::Abstract,
+                &["Initializable"],
+                vec![],
+            ),
+            contract(
+                "Vault",
+                EvmContractType::Contract,
+                &["UUPSUpgradeable"],
+                vec![
+                    function("constructor", "Vault", "{}"),
+                    function("initialize", "Vault", "{ owner = msg.sender; }"),
+                    function("_authorizeUpgrade", "Vault", "{}"),
+                ],
+            ),
+        ];
+        let info = ProxyResolver::new(&contracts).resolve("Vault");
+
+        assert_eq!(info.kind, EvmProxyKind::Uups);
+        assert!(info.initializable);
+        let kinds: Vec<EvmProxyFindingKind> = info.findings.into_iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EvmProxyFindingKind::MissingDisableInitializers,
+                EvmProxyFindingKind::UnprotectedInitialize,
+                EvmProxyFindingKind::UnprotectedAuthorizeUpgrade,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_selector_routes_of_diamond_and_proxy() {
+        let delegating_fallback = |contract_name: &str| {
+            function(
+                "fallback",
+                contract_name,
+                "{ assembly { let result := delegatecall(gas(), facet, 0, calldatasize(), 0, 0) } }",
+            )
+        };
+        let contracts = vec![
+            contract("IDiamondCut", EvmContractType::Interface, &[], vec![]),
+            contract(
+                "Diamond",
+                EvmContractType::Contract,
+                &[],
+                vec![
+                    delegating_fallback("Diamond"),
+                    function("diamondCut", "Diamond", "{ LibDiamond.diamondCut(cut); }"),
+                ],
+            ),
+            contract(
+                "DiamondCutFacet",
+                EvmContractType::Contract,
+                &["IDiamondCut"],
+                vec![],
+            ),
+            contract(
+                "OwnershipFacet",
+                EvmContractType::Contract,
+                &[],
+                vec![function(
+                    "owner",
+                    "OwnershipFacet",
+                    "{ return LibDiamond.contractOwner(); }",
+                )],
+            ),
+            contract("Vaults", EvmContractType::Contract, &[], vec![]),
+            contract(
+                "DiamondInit",
+                EvmContractType::Contract,
+                &[],
+                vec![function(
+                    "init",
+                    "DiamondInit",
+                    "{ cut[0] = IDiamondCut.FacetCut(address(new Vaults()), Add, selectors); }",
+                )],
+            ),
+            // Named like a facet, but nothing cuts it into the diamond
+            contract("PriceFacet", EvmContractType::Contract, &[], vec![]),
+            contract(
+                "VaultProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![delegating_fallback("VaultProxy")],
+            ),
+            contract(
+                "Vault",
+                EvmContractType::Contract,
+                &[],
+                vec![function("initialize", "Vault", "{}").with_modifiers(&["initializer"])],
+            ),
+        ];
+        let routes = ProxyResolver::new(&contracts).selector_routes(
+            &["Diamond".to_string(), "VaultProxy".to_string()],
+            &BTreeMap::new(),
+        );
+
+        assert_eq!(
+            routes,
+            vec![
+                SelectorRoute {
+                    proxy: Some("Diamond".to_string()),
+                    diamond: true,
+                    targets: vec![
+                        "DiamondCutFacet".to_string(),
+                        "OwnershipFacet".to_string(),
+                        "Vaults".to_string(),
+                    ],
+                    candidate: false,
+                },
+                SelectorRoute {
+                    proxy: Some("VaultProxy".to_string()),
+                    diamond: false,
+                    targets: vec!["Vault".to_string()],
+                    candidate: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_selector_routes_bind_proxies_to_their_implementations() {
+        let delegating_fallback = |contract_name: &str| {
+            function(
+                "fallback",
+                contract_name,
+                "{ assembly { let result := delegatecall(gas(), sload(0), 0, calldatasize(), 0, 0) } }",
+            )
+        };
+        let initializable = |name: &str| {
+            contract(
+                name,
+                EvmContractType::Contract,
+                &[],
+                vec![function("initialize", name, "{}").with_modifiers(&["initializer"])],
+            )
+        };
+        let contracts = vec![
+            contract(
+                "VaultProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![
+                    delegating_fallback("VaultProxy"),
+                    function("constructor", "VaultProxy", "{}")
+                        .with_params(vec![param("implementation", "Vault")]),
+                ],
+            ),
+            contract(
+                "StakingProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![delegating_fallback("StakingProxy")],
+            ),
+            contract(
+                "RewardsProxy",
+                EvmContractType::Contract,
+                &[],
+                vec![delegating_fallback("RewardsProxy")],
+            ),
+            initializable("Vault"),
+            initializable("Staking"),
+        ];
+        let pinned = BTreeMap::from([("StakingProxy".to_string(), "Staking".to_string())]);
+        let routes = ProxyResolver::new(&contracts).selector_routes(
+            &[
+                "VaultProxy".to_string(),
+                "StakingProxy".to_string(),
+                "RewardsProxy".to_string(),
+            ],
+            &pinned,
+        );
+
+        let targets: Vec<(&str, Vec<&str>, bool)> = routes
+            .iter()
+            .map(|route| {
+                (
+                    route.proxy.as_deref().unwrap_or_default(),
+                    route.targets.iter().map(String::as_str).collect(),
+                    route.candidate,
+                )
+            })
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                ("VaultProxy", vec!["Vault"], false),
+                ("StakingProxy", vec!["Staking"], false),
+                ("RewardsProxy", vec!["Vault", "Staking"], true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_custom_proxy_with_eip1967_admin_slot_is_transparent() {
+        let mut proxy = contract(
+            "VaultProxy",
+            EvmContractType::Contract,
+            &[],
+            vec![function(
+                "fallback",
+                "VaultProxy",
+                "{ assembly { let result := delegatecall(gas(), impl, 0, calldatasize(), 0, 0) } }",
+            )],
+        );
+        proxy.storage_variables.push(StorageVariable {
+            is_constant: true,
+            value: Some("bytes32(uint256(keccak256(\"eip1967.proxy.admin\")) - 1)".to_string()),
+            ..state_variable("ADMIN_SLOT", "bytes32")
+        });
+        let contracts = vec![proxy];
+        let info = ProxyResolver::new(&contracts).resolve("VaultProxy");
+
+        assert_eq!(info.kind, EvmProxyKind::Transparent);
+        assert_eq!(info.eip1967_slots, vec!["admin".to_string()]);
+        assert!(info.findings.is_empty());
+    }
+
+    #[test]
+    fn test_initializer_guards_come_from_modifiers_and_state_flags() {
+        let mut vault = contract(
+            "Vault",
+            EvmContractType::Contract,
+            &[],
+            vec![
+                function("initialize", "Vault", "{\n    require(!initialized, \"done\");\n    initialized = true;\n    owner = msg.sender;\n}"),
+                function("initializeFees", "Vault", "{\n    if (paused) revert Paused();\n    fee = 10;\n}"),
+                function("initializeDeposit", "Vault", "{\n    require(token.transferFrom(msg.sender, address(this), amount));\n}"),
+                function("initializeV2", "Vault", "{ version = 2; }").with_modifier_args(&[("reinitializer", &["2"])]),
+            ],
+        );
+        vault.storage_variables = vec![
+            state_variable("initialized", "bool"),
+            state_variable("paused", "bool"),
+            state_variable("owner", "address"),
+            state_variable("fee", "uint256"),
+            state_variable("version", "uint256"),
+        ];
+        let contracts = vec![vault];
+        let info = ProxyResolver::new(&contracts).resolve("Vault");
+
+        let unprotected: Vec<&str> = info
+            .findings
+            .iter()
+            .filter(|f| f.kind == EvmProxyFindingKind::UnprotectedInitialize)
+            .map(|f| f.function.as_str())
+            .collect();
+        assert_eq!(unprotected, vec!["initializeFees", "initializeDeposit"]);
+    }
+}
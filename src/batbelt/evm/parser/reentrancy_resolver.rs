@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+
+use solar_parse::ast::{self, Visit};
+
+use crate::batbelt::evm::types::{
+    EvmContract, EvmExternalCall, EvmFunction, EvmModifierDef, EvmMutability, EvmStateWrite,
+    ExternalCallKind,
+};
+
+use super::call_resolver::{is_builtin, CallResolver, ReachableBody};
+use super::evm_file_parser::{span_to_end_line, span_to_line, with_parsed_body};
+use super::storage_access_resolver::StorageAccessResolver;
+
+/// How deep internal calls are inlined into an entry point trace.
+const MAX_CALL_DEPTH: usize = 8;
+
+/// Arm of an `if` statement enclosing a trace step, `branch` is unique within a trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BranchArm {
+    pub branch: usize,
+    pub is_else: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceAction {
+    Call(EvmExternalCall),
+    Write(EvmStateWrite),
+}
+
+/// A step of an entry point execution that matters for reentrancy, with the `if`/`else`
+/// arms it runs in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    pub action: TraceAction,
+    pub arms: Vec<BranchArm>,
+}
+
+impl TraceStep {
+    /// Whether `later` runs on the same path as `self`: both are in the same arm of every
+    /// `if` enclosing them, or `later` is past its join point.
+    fn shares_path_with(&self, later: &TraceStep) -> bool {
+        self.arms.iter().all(|arm| {
+            later
+                .arms
+                .iter()
+                .all(|other| other.branch != arm.branch || other.is_else == arm.is_else)
+        })
+    }
+}
+
+/// Item of a single body, before internal calls are inlined.
+enum BodyItem {
+    Call(EvmExternalCall),
+    Write(EvmStateWrite),
+    Internal {
+        name: String,
+        is_super: bool,
+        arguments: usize,
+        line: usize,
+    },
+}
+
+/// Items of a single body with the arms enclosing each of them, branch ids local to the
+/// body.
+struct Body {
+    items: Vec<(BodyItem, Vec<BranchArm>)>,
+    /// Line of the `_;` of a modifier and the arms enclosing it
+    placeholder: Option<(usize, Vec<BranchArm>)>,
+    branches: usize,
+}
+
+impl Body {
+    /// Gives the branches of this inlining of the body ids unique within the trace, nested
+    /// in the arms of the call site.
+    fn instantiate(mut self, context: &[BranchArm], next_branch: &mut usize) -> Self {
+        let offset = *next_branch;
+        *next_branch += self.branches;
+        let nest = |arms: &mut Vec<BranchArm>| {
+            for arm in arms.iter_mut() {
+                arm.branch += offset;
+            }
+            arms.splice(0..0, context.iter().copied());
+        };
+        for (_, arms) in self.items.iter_mut() {
+            nest(arms);
+        }
+        if let Some((_, arms)) = self.placeholder.as_mut() {
+            nest(arms);
+        }
+        self
+    }
+}
+
+/// Steps of a trace being built and the next unused branch id.
+#[derive(Default)]
+struct Trace {
+    steps: Vec<TraceStep>,
+    next_branch: usize,
+}
+
+impl BodyItem {
+    /// Within a line, calls run before the assignment receiving their result.
+    fn sort_key(&self) -> (usize, u8) {
+        match self {
+            BodyItem::Call(call) => (call.line, 0),
+            BodyItem::Internal { line, .. } => (*line, 1),
+            BodyItem::Write(write) => (write.line, 2),
+        }
+    }
+}
+
+/// Orders the external calls and state writes of an entry point as they execute: modifier
+/// code before `_;`, the function body with its internal callees inlined at their call
+/// site, then modifier code after `_;`.
+pub struct ReentrancyResolver<'a> {
+    contracts_by_name: HashMap<&'a str, &'a EvmContract>,
+    call_resolver: CallResolver<'a>,
+    storage_access_resolver: StorageAccessResolver<'a>,
+}
+
+impl<'a> ReentrancyResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self {
+            contracts_by_name: contracts.iter().map(|c| (c.name.as_str(), c)).collect(),
+            call_resolver: CallResolver::new(contracts),
+            storage_access_resolver: StorageAccessResolver::new(contracts),
+        }
+    }
+
+    /// External calls and state writes of `function` as executed by `contract_name`.
+    pub fn trace(&self, contract_name: &str, function: &'a EvmFunction) -> Vec<TraceStep> {
+        let mut trace = Trace::default();
+        self.trace_function(contract_name, function, &[], &mut Vec::new(), &mut trace);
+        trace.steps
+    }
+
+    /// Calls able to hand control to another contract that may call back: low-level `call`s
+    /// and interface calls to functions not declared `view`/`pure`.
+    pub fn can_reenter(&self, call: &EvmExternalCall) -> bool {
+        match call.kind {
+            ExternalCallKind::Call => true,
+            ExternalCallKind::HighLevel => {
+                let declared: Vec<&EvmFunction> = self
+                    .contracts_by_name
+                    .get(call.target_type.as_str())
+                    .map(|c| {
+                        c.functions
+                            .iter()
+                            .filter(|f| f.name == call.function)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                declared.is_empty()
+                    || declared
+                        .iter()
+                        .any(|f| !matches!(f.mutability, EvmMutability::View | EvmMutability::Pure))
+            }
+            _ => false,
+        }
+    }
+
+    /// Every reentrant call of the trace with the state writes executed after it on the
+    /// same path.
+    pub fn writes_after_calls(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<(EvmExternalCall, Vec<EvmStateWrite>)> {
+        let steps = self.trace(contract_name, function);
+        steps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, step)| match &step.action {
+                TraceAction::Call(call) if self.can_reenter(call) => {
+                    let mut writes: Vec<EvmStateWrite> = Vec::new();
+                    for later in &steps[idx + 1..] {
+                        if let TraceAction::Write(write) = &later.action {
+                            if step.shares_path_with(later) && !writes.contains(write) {
+                                writes.push(write.clone());
+                            }
+                        }
+                    }
+                    (!writes.is_empty()).then(|| (call.clone(), writes))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Appends the steps of `function`, called from within the `context` arms.
+    fn trace_function(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+        context: &[BranchArm],
+        stack: &mut Vec<&'a EvmFunction>,
+        trace: &mut Trace,
+    ) {
+        if stack.len() >= MAX_CALL_DEPTH || stack.iter().any(|f| std::ptr::eq(*f, function)) {
+            return;
+        }
+        stack.push(function);
+
+        let modifiers: Vec<(Body, usize)> = function
+            .modifiers
+            .iter()
+            .filter_map(|name| self.call_resolver.resolve_modifier(contract_name, name))
+            .map(|modifier| {
+                let body = self
+                    .modifier_body(contract_name, modifier)
+                    .instantiate(context, &mut trace.next_branch);
+                let placeholder = body
+                    .placeholder
+                    .as_ref()
+                    .map(|(line, _)| *line)
+                    .unwrap_or(modifier.end_line);
+                (body, placeholder)
+            })
+            .collect();
+
+        // The function body runs wherever the `_;` of its modifiers is
+        let mut body_context = context.to_vec();
+        for (body, _) in &modifiers {
+            for arm in body.placeholder.iter().flat_map(|(_, arms)| arms) {
+                if !body_context.contains(arm) {
+                    body_context.push(*arm);
+                }
+            }
+        }
+
+        for (body, placeholder) in &modifiers {
+            let before = body
+                .items
+                .iter()
+                .filter(|(item, _)| item.sort_key().0 <= *placeholder);
+            self.emit(contract_name, function, before, stack, trace);
+        }
+        let body = ReachableBody {
+            name: &function.name,
+            body_source: &function.body_source,
+            end_line: function.end_line,
+            params: function
+                .params
+                .iter()
+                .chain(function.returns.iter())
+                .collect(),
+            is_modifier: false,
+        };
+        let body = self
+            .body_items(contract_name, &body)
+            .instantiate(&body_context, &mut trace.next_branch);
+        self.emit(contract_name, function, body.items.iter(), stack, trace);
+        for (body, placeholder) in modifiers.iter().rev() {
+            let after = body
+                .items
+                .iter()
+                .filter(|(item, _)| item.sort_key().0 > *placeholder);
+            self.emit(contract_name, function, after, stack, trace);
+        }
+
+        stack.pop();
+    }
+
+    fn emit<'i>(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+        items: impl Iterator<Item = &'i (BodyItem, Vec<BranchArm>)>,
+        stack: &mut Vec<&'a EvmFunction>,
+        trace: &mut Trace,
+    ) {
+        for (item, arms) in items {
+            let action = match item {
+                BodyItem::Call(call) => TraceAction::Call(call.clone()),
+                BodyItem::Write(write) => TraceAction::Write(write.clone()),
+                BodyItem::Internal {
+                    name,
+                    is_super,
+                    arguments,
+                    ..
+                } => {
+                    if let Some(callee) =
+                        self.resolve_internal(contract_name, function, name, *is_super, *arguments)
+                    {
+                        self.trace_function(contract_name, callee, arms, stack, trace);
+                    }
+                    continue;
+                }
+            };
+            trace.steps.push(TraceStep {
+                action,
+                arms: arms.clone(),
+            });
+        }
+    }
+
+    fn modifier_body(&self, contract_name: &str, modifier: &'a EvmModifierDef) -> Body {
+        let body = ReachableBody {
+            name: &modifier.name,
+            body_source: &modifier.body_source,
+            end_line: modifier.end_line,
+            params: modifier.params.iter().collect(),
+            is_modifier: true,
+        };
+        self.body_items(contract_name, &body)
+    }
+
+    fn body_items(&self, contract_name: &str, body: &ReachableBody<'_>) -> Body {
+        let mut items: Vec<BodyItem> = self
+            .call_resolver
+            .body_external_calls(contract_name, body)
+            .into_iter()
+            .map(BodyItem::Call)
+            .collect();
+        items.extend(
+            self.storage_access_resolver
+                .body_writes(contract_name, body)
+                .into_iter()
+                .map(|(variable, line)| {
+                    BodyItem::Write(EvmStateWrite {
+                        variable,
+                        caller: body.name.to_string(),
+                        line,
+                    })
+                }),
+        );
+
+        let first_line = body.first_line();
+        let (internal_calls, placeholder, arms) =
+            with_parsed_body(body.body_source, |sess, block| {
+                let mut visitor = InternalCallVisitor {
+                    sess,
+                    first_line,
+                    calls: Vec::new(),
+                    placeholder: None,
+                    arms: Vec::new(),
+                };
+                let _ = visitor.visit_block(block);
+                (visitor.calls, visitor.placeholder, visitor.arms)
+            })
+            .unwrap_or_default();
+        items.extend(internal_calls);
+        items.sort_by_key(|item| item.sort_key());
+
+        Body {
+            items: items
+                .into_iter()
+                .map(|item| {
+                    let item_arms = arms_at(&arms, item.sort_key().0);
+                    (item, item_arms)
+                })
+                .collect(),
+            placeholder: placeholder.map(|line| (line, arms_at(&arms, line))),
+            branches: arms
+                .iter()
+                .map(|lines| lines.arm.branch + 1)
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Most-derived function `name` visible from `caller`, preferring the overload taking
+    /// `arguments` arguments.
+    fn resolve_internal(
+        &self,
+        contract_name: &str,
+        caller: &EvmFunction,
+        name: &str,
+        is_super: bool,
+        arguments: usize,
+    ) -> Option<&'a EvmFunction> {
+        let lookup_order = self.call_resolver.lookup_order(contract_name);
+        let search_from = if is_super {
+            lookup_order
+                .iter()
+                .position(|c| c.name == caller.contract_name)
+                .map(|pos| pos + 1)
+                .unwrap_or(0)
+        } else {
+            0
+        };
+        let candidates: Vec<&'a EvmFunction> = lookup_order
+            .into_iter()
+            .skip(search_from)
+            .map(|c| {
+                c.functions
+                    .iter()
+                    .filter(|f| f.name == name && !f.body_source.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .find(|functions| !functions.is_empty())?;
+        candidates
+            .iter()
+            .find(|f| f.params.len() == arguments)
+            .or_else(|| candidates.first())
+            .copied()
+    }
+}
+
+/// Lines spanned by the statements of an `if` arm.
+struct ArmLines {
+    arm: BranchArm,
+    first: usize,
+    last: usize,
+}
+
+/// Arms enclosing `line`. Statements sharing a line with the `else` arm are taken as
+/// part of it.
+fn arms_at(arms: &[ArmLines], line: usize) -> Vec<BranchArm> {
+    let mut enclosing: Vec<BranchArm> = Vec::new();
+    for lines in arms.iter().filter(|l| (l.first..=l.last).contains(&line)) {
+        match enclosing
+            .iter_mut()
+            .find(|arm| arm.branch == lines.arm.branch)
+        {
+            Some(arm) => arm.is_else |= lines.arm.is_else,
+            None => enclosing.push(lines.arm),
+        }
+    }
+    enclosing
+}
+
+/// Collects internal calls (`_update(...)`, `super._update(...)`), the `_;` placeholder
+/// and the lines of every `if`/`else` arm.
+struct InternalCallVisitor<'v> {
+    sess: &'v solar_parse::interface::Session,
+    first_line: usize,
+    calls: Vec<BodyItem>,
+    placeholder: Option<usize>,
+    arms: Vec<ArmLines>,
+}
+
+impl InternalCallVisitor<'_> {
+    fn line(&self, span: ast::Span) -> usize {
+        self.first_line + span_to_line(self.sess, span) - 1
+    }
+
+    fn end_line(&self, span: ast::Span) -> usize {
+        self.first_line + span_to_end_line(self.sess, span) - 1
+    }
+
+    /// Records the lines of the statements of an arm, not its braces, so a condition on
+    /// the line of the `{` stays outside of it.
+    fn push_arm(&mut self, stmt: &ast::Stmt<'_>, branch: usize, is_else: bool) {
+        let span = match &stmt.kind {
+            ast::StmtKind::Block(block) | ast::StmtKind::UncheckedBlock(block) => {
+                match (block.stmts.first(), block.stmts.last()) {
+                    (Some(first), Some(last)) => first.span.to(last.span),
+                    _ => return,
+                }
+            }
+            _ => stmt.span,
+        };
+        self.arms.push(ArmLines {
+            arm: BranchArm { branch, is_else },
+            first: self.line(span),
+            last: self.end_line(span),
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for InternalCallVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_stmt(&mut self, stmt: &'ast ast::Stmt<'ast>) -> ControlFlow<Self::BreakValue> {
+        match &stmt.kind {
+            ast::StmtKind::Placeholder => self.placeholder = Some(self.line(stmt.span)),
+            ast::StmtKind::If(_, then, otherwise) => {
+                let branch = self
+                    .arms
+                    .last()
+                    .map(|lines| lines.arm.branch + 1)
+                    .unwrap_or(0);
+                self.push_arm(then, branch, false);
+                if let Some(otherwise) = otherwise {
+                    self.push_arm(otherwise, branch, true);
+                }
+            }
+            _ => {}
+        }
+        self.walk_stmt(stmt)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, args) = &expr.kind {
+            let target = match &callee.kind {
+                ast::ExprKind::Ident(ident) => Some((ident.as_str().to_string(), false)),
+                ast::ExprKind::Member(base, method) => match &base.kind {
+                    ast::ExprKind::Ident(base) if base.as_str() == "super" => {
+                        Some((method.as_str().to_string(), true))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some((name, is_super)) = target.filter(|(name, _)| !is_builtin(name)) {
+                self.calls.push(BodyItem::Internal {
+                    name,
+                    is_super,
+                    arguments: args.exprs().count(),
+                    line: self.line(expr.span),
+                });
+            }
+        }
+        self.walk_expr(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, function, modifier, param, state_variable};
+    use crate::batbelt::evm::types::EvmContractType;
+
+    fn vault() -> EvmContract {
+        let withdraw = function(
+            "withdraw",
+            "Vault",
+            "{\n    (bool ok, ) = msg.sender.call{value: amount}(\"\");\n    _debit(amount);\n}",
+        )
+        .with_params(vec![param("amount", "uint256")])
+        .with_modifiers(&["checked"])
+        .at_line(10);
+        let debit = function(
+            "_debit",
+            "Vault",
+            "{\n    balances[msg.sender] -= amount;\n}",
+        )
+        .with_params(vec![param("amount", "uint256")])
+        .at_line(20);
+        EvmContract {
+            modifiers: vec![EvmModifierDef {
+                line: 3,
+                end_line: 6,
+                ..modifier(
+                    "checked",
+                    "Vault",
+                    "{\n    _;\n    lastCheck = block.number;\n}",
+                )
+            }],
+            storage_variables: ["balances", "lastCheck"]
+                .iter()
+                .map(|name| state_variable(name, "mapping(address => uint256)"))
+                .collect(),
+            ..contract(
+                "Vault",
+                EvmContractType::Contract,
+                &[],
+                vec![withdraw, debit],
+            )
+        }
+    }
+
+    #[test]
+    fn test_writes_after_call_follow_callees_and_modifiers() {
+        let contracts = vec![vault()];
+        let resolver = ReentrancyResolver::new(&contracts);
+        let withdraw = &contracts[0].functions[0];
+
+        let findings = resolver.writes_after_calls("Vault", withdraw);
+
+        assert_eq!(findings.len(), 1);
+        let (call, writes) = &findings[0];
+        assert_eq!(call.kind, ExternalCallKind::Call);
+        assert_eq!(call.line, 11);
+        let writes: Vec<(&str, &str, usize)> = writes
+            .iter()
+            .map(|w| (w.variable.as_str(), w.caller.as_str(), w.line))
+            .collect();
+        assert_eq!(
+            writes,
+            vec![("balances", "_debit", 21), ("lastCheck", "checked", 5)]
+        );
+    }
+
+    #[test]
+    fn test_writes_in_another_arm_are_not_after_the_call() {
+        let claim = function(
+            "claim",
+            "Vault",
+            "{\n    if (native) {\n        (bool ok, ) = msg.sender.call{value: amount}(\"\");\n    } else {\n        balances[msg.sender] -= amount;\n    }\n}",
+        )
+        .with_params(vec![param("amount", "uint256")])
+        .at_line(10);
+        let settle = function(
+            "settle",
+            "Vault",
+            "{\n    if (native) {\n        (bool ok, ) = msg.sender.call{value: amount}(\"\");\n        balances[msg.sender] -= amount;\n    } else {\n        lastCheck = amount;\n    }\n    lastCheck = 0;\n}",
+        )
+        .with_params(vec![param("amount", "uint256")])
+        .at_line(20);
+        let contracts = vec![EvmContract {
+            storage_variables: ["balances", "lastCheck"]
+                .iter()
+                .map(|name| state_variable(name, "mapping(address => uint256)"))
+                .collect(),
+            ..contract("Vault", EvmContractType::Contract, &[], vec![claim, settle])
+        }];
+        let resolver = ReentrancyResolver::new(&contracts);
+
+        assert!(resolver
+            .writes_after_calls("Vault", &contracts[0].functions[0])
+            .is_empty());
+
+        let findings = resolver.writes_after_calls("Vault", &contracts[0].functions[1]);
+        assert_eq!(findings.len(), 1);
+        let writes: Vec<(&str, usize)> = findings[0]
+            .1
+            .iter()
+            .map(|w| (w.variable.as_str(), w.line))
+            .collect();
+        assert_eq!(writes, vec![("balances", 23), ("lastCheck", 27)]);
+    }
+}
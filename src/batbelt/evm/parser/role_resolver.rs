@@ -0,0 +1,153 @@
+use std::ops::ControlFlow;
+
+use solar_parse::{
+    ast::{self, Visit},
+    interface::Session,
+};
+
+use crate::batbelt::evm::types::{EvmContract, EvmFunction, EvmRoleChange, EvmRoleChangeKind};
+
+use super::call_resolver::CallResolver;
+use super::evm_file_parser::{expr_to_string, span_to_line, with_parsed_body};
+
+/// Finds the roles declared by a contract and the calls granting, revoking and
+/// administering them (or moving ownership) in the bodies executed by a function.
+pub struct RoleResolver<'a> {
+    call_resolver: CallResolver<'a>,
+}
+
+impl<'a> RoleResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self {
+            call_resolver: CallResolver::new(contracts),
+        }
+    }
+
+    /// `bytes32` constants and immutables named `*ROLE` in the inheritance chain of
+    /// `contract_name`, `DEFAULT_ADMIN_ROLE` included.
+    pub fn declared_roles(&self, contract_name: &str) -> Vec<String> {
+        let mut roles: Vec<String> = Vec::new();
+        for contract in self.call_resolver.lookup_order(contract_name).iter().rev() {
+            for var in &contract.storage_variables {
+                if (var.is_constant || var.is_immutable)
+                    && var.type_name == "bytes32"
+                    && var.name.ends_with("ROLE")
+                    && !roles.contains(&var.name)
+                {
+                    roles.push(var.name.clone());
+                }
+            }
+        }
+        roles
+    }
+
+    /// true if the inheritance chain of `contract_name` keeps per-role admins, like
+    /// OpenZeppelin's `AccessControl`.
+    pub fn has_role_admins(&self, contract_name: &str) -> bool {
+        self.call_resolver
+            .lookup_order(contract_name)
+            .iter()
+            .flat_map(|c| c.functions.iter())
+            .any(|f| f.name == "getRoleAdmin" || f.name == "_setRoleAdmin")
+    }
+
+    /// Role and ownership changes made by `function` as executed by `contract_name`,
+    /// modifiers and internal callees included.
+    ///
+    /// Role arguments are kept as written. Those bound to the parameters of a callee or
+    /// modifier are dropped, since the concrete role is the one passed by its caller;
+    /// those bound to the parameters of `function` itself are kept, as the caller of the
+    /// entry point chooses the role.
+    pub fn role_changes(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+    ) -> Vec<EvmRoleChange> {
+        let mut changes: Vec<EvmRoleChange> = Vec::new();
+        for (index, body) in self
+            .call_resolver
+            .reachable_bodies(contract_name, function)
+            .iter()
+            .enumerate()
+        {
+            let first_line = body.first_line();
+            let found = with_parsed_body(body.body_source, |sess, block| {
+                let mut visitor = RoleChangeVisitor {
+                    sess,
+                    caller: body.name,
+                    first_line,
+                    changes: Vec::new(),
+                };
+                let _ = visitor.visit_block(block);
+                visitor.changes
+            })
+            .unwrap_or_default();
+
+            for change in found {
+                let bound_to_param = change
+                    .role
+                    .as_ref()
+                    .is_some_and(|role| body.params.iter().any(|param| &param.name == role));
+                if (index > 0 && bound_to_param) || changes.contains(&change) {
+                    continue;
+                }
+                changes.push(change);
+            }
+        }
+        changes
+    }
+}
+
+struct RoleChangeVisitor<'v> {
+    sess: &'v Session,
+    caller: &'v str,
+    /// 1-based line in the source file of the first body line
+    first_line: usize,
+    changes: Vec<EvmRoleChange>,
+}
+
+impl<'ast> Visit<'ast> for RoleChangeVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, args) = &expr.kind {
+            if let ast::ExprKind::Ident(ident) = &callee.kind {
+                let arguments: Vec<String> = args
+                    .exprs()
+                    .map(|arg| expr_to_string(self.sess, arg))
+                    .collect();
+                let (kind, role, admin_role) = match (ident.as_str(), arguments.as_slice()) {
+                    ("_grantRole" | "grantRole" | "_setupRole", [role, ..]) => {
+                        (EvmRoleChangeKind::Grant, Some(role.clone()), None)
+                    }
+                    ("_revokeRole" | "revokeRole" | "renounceRole", [role, ..]) => {
+                        (EvmRoleChangeKind::Revoke, Some(role.clone()), None)
+                    }
+                    ("_setRoleAdmin", [role, admin_role, ..]) => (
+                        EvmRoleChangeKind::SetAdmin,
+                        Some(role.clone()),
+                        Some(admin_role.clone()),
+                    ),
+                    ("_transferOwnership" | "transferOwnership" | "_setOwner", [new_owner])
+                        if new_owner == "address(0)" =>
+                    {
+                        (EvmRoleChangeKind::RenounceOwnership, None, None)
+                    }
+                    ("_transferOwnership" | "transferOwnership" | "_setOwner", [_]) => {
+                        (EvmRoleChangeKind::TransferOwnership, None, None)
+                    }
+                    ("renounceOwnership", []) => (EvmRoleChangeKind::RenounceOwnership, None, None),
+                    _ => return self.walk_expr(expr),
+                };
+                self.changes.push(EvmRoleChange {
+                    kind,
+                    role,
+                    admin_role,
+                    caller: self.caller.to_string(),
+                    line: self.first_line + span_to_line(self.sess, expr.span) - 1,
+                });
+            }
+        }
+        self.walk_expr(expr)
+    }
+}
@@ -0,0 +1,228 @@
+use sha3::{Digest, Keccak256};
+
+use crate::batbelt::evm::types::{
+    EvmContract, EvmFileDefinitions, EvmFunction, EvmParam, EvmVisibility, StorageVariable,
+};
+
+use super::type_resolver::TypeResolver;
+
+/// Computes ABI signatures and 4-byte selectors, resolving contract types to `address`,
+/// enums to `uint8`, structs to tuples and value types to their underlying type.
+pub struct SelectorResolver<'a> {
+    type_resolver: TypeResolver<'a>,
+}
+
+impl<'a> SelectorResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract], file_definitions: &'a [EvmFileDefinitions]) -> Self {
+        Self {
+            type_resolver: TypeResolver::new(contracts, file_definitions),
+        }
+    }
+
+    /// ABI signature (`transfer(address,uint256)`) of an externally callable function.
+    /// Returns `None` for internal/private functions, constructors, fallback and receive.
+    pub fn function_signature(&self, function: &EvmFunction) -> Option<String> {
+        if function.is_constructor || function.is_fallback || function.is_receive {
+            return None;
+        }
+        if !matches!(
+            function.visibility,
+            EvmVisibility::External | EvmVisibility::Public
+        ) {
+            return None;
+        }
+        Some(self.signature(&function.name, &function.params, &function.contract_name))
+    }
+
+    /// ABI signature of the getter the compiler generates for a public state variable,
+    /// with one argument per mapping key or array index.
+    pub fn getter_signature(&self, variable: &StorageVariable, context: &str) -> Option<String> {
+        if variable.visibility != EvmVisibility::Public {
+            return None;
+        }
+        let (keys, _) = getter_keys(&variable.type_name);
+        let keys = keys
+            .iter()
+            .map(|key| self.abi_type(key, context))
+            .collect::<Vec<_>>()
+            .join(",");
+        Some(format!("{}({})", variable.name, keys))
+    }
+
+    /// ABI signature of `name(params)`, resolving user-defined types as seen from the
+    /// contract `context`.
+    pub fn signature(&self, name: &str, params: &[EvmParam], context: &str) -> String {
+        let types = params
+            .iter()
+            .map(|p| self.abi_type(&p.canonical_type(), context))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{}({})", name, types)
+    }
+
+    /// ABI type of a canonical source type: contracts and interfaces are encoded as
+    /// `address`, array dimensions are preserved.
+    pub fn abi_type(&self, type_name: &str, context: &str) -> String {
+        let param = EvmParam {
+            name: String::new(),
+            type_name: type_name.to_string(),
+            storage_location: None,
+        };
+        let canonical = param.canonical_type();
+        let (base, dims) = canonical.split_at(canonical.find('[').unwrap_or(canonical.len()));
+        let base = if self.type_resolver.is_contract_type(base) {
+            "address".to_string()
+        } else if let Some(item) = self.type_resolver.find_struct(base, context) {
+            let members = item
+                .members
+                .iter()
+                .map(|m| self.abi_type(&m.canonical_type(), context))
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("({})", members)
+        } else if self.type_resolver.find_enum(base, context).is_some() {
+            "uint8".to_string()
+        } else if let Some(item) = self.type_resolver.find_udvt(base, context) {
+            self.abi_type(&item.underlying_type, context)
+        } else {
+            base.to_string()
+        };
+        format!("{}{}", base, dims)
+    }
+}
+
+/// First 4 bytes of the keccak256 hash of `signature`, as `0x`-prefixed hex.
+pub fn selector(signature: &str) -> String {
+    let hash = Keccak256::digest(signature.as_bytes());
+    format!(
+        "0x{}",
+        hash[..4]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    )
+}
+
+/// Getter arguments of a state variable type and the type finally returned:
+/// `mapping(address => uint256[])` yields `([address, uint256], uint256)`.
+pub fn getter_keys(type_name: &str) -> (Vec<String>, String) {
+    let mut keys = Vec::new();
+    let mut current = type_name.trim().to_string();
+    loop {
+        if let Some(inner) = current
+            .strip_prefix("mapping")
+            .and_then(|t| t.trim().strip_prefix('('))
+            .and_then(|t| t.strip_suffix(')'))
+        {
+            let Some((key, value)) = inner.split_once("=>") else {
+                break;
+            };
+            keys.push(strip_name(key));
+            current = strip_name(value);
+            continue;
+        }
+        match current.rfind('[') {
+            Some(pos) if current.ends_with(']') => {
+                keys.push("uint256".to_string());
+                current = current[..pos].trim().to_string();
+            }
+            _ => break,
+        }
+    }
+    (keys, current)
+}
+
+/// Drop the optional name of a mapping key or value: `address user` → `address`.
+fn strip_name(part: &str) -> String {
+    let part = part.trim();
+    if part.starts_with("mapping") {
+        return part.to_string();
+    }
+    match part.rsplit_once(' ') {
+        Some((ty, name)) if name != "payable" && !name.ends_with(']') => ty.trim().to_string(),
+        _ => part.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{contract, param, state_variable};
+    use crate::batbelt::evm::types::{EvmContractType, EvmEnum, EvmStruct, EvmUdvt};
+
+    #[test]
+    fn test_selector_matches_known_values() {
+        assert_eq!(selector("transfer(address,uint256)"), "0xa9059cbb");
+        assert_eq!(selector("balanceOf(address)"), "0x70a08231");
+        assert_eq!(selector("owner()"), "0x8da5cb5b");
+    }
+
+    #[test]
+    fn test_getter_signature_and_contract_types() {
+        let token = contract("IERC20", EvmContractType::Interface, &[], vec![]);
+        let contracts = vec![token];
+        let resolver = SelectorResolver::new(&contracts, &[]);
+
+        let allowances = StorageVariable {
+            visibility: EvmVisibility::Public,
+            ..state_variable(
+                "allowance",
+                "mapping(address owner => mapping(address => uint))",
+            )
+        };
+        let signature = resolver.getter_signature(&allowances, "Vault").unwrap();
+        assert_eq!(signature, "allowance(address,address)");
+        assert_eq!(selector(&signature), "0xdd62ed3e");
+
+        let params = vec![EvmParam {
+            storage_location: Some("calldata".to_string()),
+            ..param("tokens", "IERC20[]")
+        }];
+        assert_eq!(
+            resolver.signature("sweep", &params, "Vault"),
+            "sweep(address[])"
+        );
+    }
+
+    #[test]
+    fn test_signature_encodes_user_defined_types() {
+        let vault = EvmContract {
+            structs: vec![EvmStruct {
+                name: "Order".to_string(),
+                members: vec![param("maker", "address"), param("amount", "Price")],
+                line: 4,
+                end_line: 7,
+            }],
+            enums: vec![EvmEnum {
+                name: "Side".to_string(),
+                variants: vec!["Buy".to_string(), "Sell".to_string()],
+                line: 8,
+                end_line: 8,
+            }],
+            line: 3,
+            ..contract("Vault", EvmContractType::Contract, &[], vec![])
+        };
+        let file_definitions = vec![EvmFileDefinitions {
+            file_path: "src/Types.sol".to_string(),
+            udvts: vec![EvmUdvt {
+                name: "Price".to_string(),
+                underlying_type: "uint".to_string(),
+                line: 3,
+                end_line: 3,
+            }],
+            ..Default::default()
+        }];
+        let contracts = vec![vault];
+        let resolver = SelectorResolver::new(&contracts, &file_definitions);
+
+        let params = vec![
+            param("orders", "Order[]"),
+            param("side", "Vault.Side"),
+            param("limit", "Price"),
+        ];
+        assert_eq!(
+            resolver.signature("fill", &params, "Vault"),
+            "fill((address,uint256)[],uint8,uint256)"
+        );
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use solar_parse::{
+    ast::{self, Visit},
+    interface::Span,
+};
+
+use crate::batbelt::evm::types::{EvmAssemblyOpKind, EvmContract, EvmFunction, EvmParam};
+
+use super::assembly_parser::parse_assembly_ops;
+use super::call_resolver::{CallResolver, ReachableBody};
+use super::evm_file_parser::{span_to_line, with_parsed_body};
+
+/// State variables read and written by an entry point (including its internal callees).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StorageAccess {
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+/// Resolves which `StorageVariable`s of the linearized contract an entry point touches.
+pub struct StorageAccessResolver<'a> {
+    call_resolver: CallResolver<'a>,
+}
+
+impl<'a> StorageAccessResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract]) -> Self {
+        Self {
+            call_resolver: CallResolver::new(contracts),
+        }
+    }
+
+    /// Walk `function`, its modifiers and its transitive internal callees as executed by
+    /// `contract_name`, and collect the state variables they read and assign.
+    pub fn resolve(&self, contract_name: &str, function: &'a EvmFunction) -> StorageAccess {
+        let state_vars = self.state_vars(contract_name);
+        let slot_vars = self.slot_vars(contract_name);
+
+        let mut reads = BTreeSet::new();
+        let mut writes = BTreeSet::new();
+
+        for body in self.call_resolver.reachable_bodies(contract_name, function) {
+            visit_body(
+                body.body_source,
+                &state_vars,
+                &slot_vars,
+                body.params.into_iter(),
+                &mut reads,
+                &mut writes,
+            );
+        }
+
+        StorageAccess {
+            reads: reads.into_iter().collect(),
+            writes: writes.into_iter().collect(),
+        }
+    }
+
+    /// State variables assigned by a single body, with the line of each assignment.
+    pub fn body_writes(
+        &self,
+        contract_name: &str,
+        body: &ReachableBody<'_>,
+    ) -> Vec<(String, usize)> {
+        let state_vars = self.state_vars(contract_name);
+        let slot_vars = self.slot_vars(contract_name);
+        let locals: HashSet<String> = body
+            .params
+            .iter()
+            .filter(|p| !p.name.is_empty())
+            .map(|p| p.name.clone())
+            .collect();
+        let first_line = body.first_line();
+
+        with_parsed_body(body.body_source, |sess, block| {
+            let mut visitor = StorageAccessVisitor {
+                state_vars: &state_vars,
+                locals,
+                storage_aliases: HashMap::new(),
+                reads: BTreeSet::new(),
+                writes: BTreeSet::new(),
+                write_spans: Vec::new(),
+            };
+            let _ = visitor.visit_block(block);
+            let mut writes: Vec<(String, usize)> = visitor
+                .write_spans
+                .into_iter()
+                .map(|(var, span)| (var, first_line + span_to_line(sess, span) - 1))
+                .collect();
+            writes.extend(
+                assembly_accesses(sess, block, first_line, &slot_vars)
+                    .into_iter()
+                    .filter(|(kind, _, _)| *kind == EvmAssemblyOpKind::Sstore)
+                    .map(|(_, var, line)| (var, line)),
+            );
+            writes.sort_by_key(|(_, line)| *line);
+            writes
+        })
+        .unwrap_or_default()
+    }
+
+    /// Constants and immutables live in bytecode, not storage
+    fn state_vars(&self, contract_name: &str) -> HashSet<String> {
+        self.call_resolver
+            .lookup_order(contract_name)
+            .iter()
+            .flat_map(|c| c.storage_variables.iter())
+            .filter(|v| !v.is_constant && !v.is_immutable)
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    /// Names inline assembly addresses storage through: state variables (`x.slot`) and
+    /// `bytes32` slot constants (`_ADMIN_SLOT`).
+    fn slot_vars(&self, contract_name: &str) -> HashSet<String> {
+        self.call_resolver
+            .lookup_order(contract_name)
+            .iter()
+            .flat_map(|c| c.storage_variables.iter())
+            .filter(|v| (!v.is_constant && !v.is_immutable) || v.type_name == "bytes32")
+            .map(|v| v.name.clone())
+            .collect()
+    }
+}
+
+/// `sload`/`sstore` of `slot_vars` in the inline assembly of `block`, with their line.
+fn assembly_accesses<'ast>(
+    sess: &solar_parse::interface::Session,
+    block: &'ast ast::Block<'ast>,
+    first_line: usize,
+    slot_vars: &HashSet<String>,
+) -> Vec<(EvmAssemblyOpKind, String, usize)> {
+    parse_assembly_ops(sess, block, first_line)
+        .into_iter()
+        .filter_map(|op| {
+            let name = op.slot_name().filter(|name| slot_vars.contains(*name))?;
+            Some((op.kind.clone(), name.to_string(), op.line))
+        })
+        .collect()
+}
+
+fn visit_body<'p>(
+    body_source: &str,
+    state_vars: &HashSet<String>,
+    slot_vars: &HashSet<String>,
+    locals: impl Iterator<Item = &'p EvmParam>,
+    reads: &mut BTreeSet<String>,
+    writes: &mut BTreeSet<String>,
+) {
+    let locals: HashSet<String> = locals
+        .filter(|p| !p.name.is_empty())
+        .map(|p| p.name.clone())
+        .collect();
+
+    let access = with_parsed_body(body_source, |sess, block| {
+        let mut visitor = StorageAccessVisitor {
+            state_vars,
+            locals,
+            storage_aliases: HashMap::new(),
+            reads: BTreeSet::new(),
+            writes: BTreeSet::new(),
+            write_spans: Vec::new(),
+        };
+        let _ = visitor.visit_block(block);
+        for (kind, var, _) in assembly_accesses(sess, block, 1, slot_vars) {
+            if kind == EvmAssemblyOpKind::Sstore {
+                visitor.writes.insert(var);
+            } else {
+                visitor.reads.insert(var);
+            }
+        }
+        (visitor.reads, visitor.writes)
+    });
+
+    if let Some((body_reads, body_writes)) = access {
+        reads.extend(body_reads);
+        writes.extend(body_writes);
+    }
+}
+
+/// AST visitor that classifies identifier uses of state variables as reads or writes.
+struct StorageAccessVisitor<'v> {
+    state_vars: &'v HashSet<String>,
+    /// Parameters and local declarations, which shadow state variables.
+    locals: HashSet<String>,
+    /// Local `storage` pointers mapped to the state variable they point into.
+    storage_aliases: HashMap<String, String>,
+    reads: BTreeSet<String>,
+    writes: BTreeSet<String>,
+    /// Every assignment in source order, for callers that need its line
+    write_spans: Vec<(String, Span)>,
+}
+
+impl StorageAccessVisitor<'_> {
+    fn state_var(&self, name: &str) -> Option<String> {
+        if self.locals.contains(name) {
+            return self.storage_aliases.get(name).cloned();
+        }
+        self.state_vars.get(name).cloned()
+    }
+
+    /// State variable at the root of `a.b[c].d`-style expressions.
+    fn root_state_var(&self, expr: &ast::Expr<'_>) -> Option<String> {
+        match &expr.kind {
+            ast::ExprKind::Ident(ident) => self.state_var(ident.as_str()),
+            ast::ExprKind::Index(base, _) | ast::ExprKind::Member(base, _) => {
+                self.root_state_var(base)
+            }
+            _ => None,
+        }
+    }
+
+    /// Record the state variable assigned through `expr`. Index expressions inside
+    /// the place (e.g. `balances[from]`) are still visited as reads.
+    fn visit_place<'ast>(&mut self, expr: &'ast ast::Expr<'ast>, also_read: bool) {
+        match &expr.kind {
+            ast::ExprKind::Ident(ident) => {
+                if let Some(var) = self.state_var(ident.as_str()) {
+                    if also_read {
+                        self.reads.insert(var.clone());
+                    }
+                    self.write_spans.push((var.clone(), ident.span));
+                    self.writes.insert(var);
+                }
+            }
+            ast::ExprKind::Index(base, index) => {
+                self.visit_place(base, also_read);
+                match index {
+                    ast::IndexKind::Index(Some(index_expr)) => {
+                        let _ = self.visit_expr(index_expr);
+                    }
+                    ast::IndexKind::Range(start, end) => {
+                        for bound in [start, end].into_iter().flatten() {
+                            let _ = self.visit_expr(bound);
+                        }
+                    }
+                    ast::IndexKind::Index(None) => {}
+                }
+            }
+            ast::ExprKind::Member(base, _) => self.visit_place(base, also_read),
+            ast::ExprKind::Tuple(elems) => {
+                for elem in elems.iter() {
+                    if let solar_parse::interface::SpannedOption::Some(e) = elem {
+                        self.visit_place(e, also_read);
+                    }
+                }
+            }
+            _ => {
+                let _ = self.visit_expr(expr);
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for StorageAccessVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_variable_definition(
+        &mut self,
+        var: &'ast ast::VariableDefinition<'ast>,
+    ) -> ControlFlow<Self::BreakValue> {
+        if let Some(init) = &var.initializer {
+            self.visit_expr(init)?;
+        }
+        if let Some(name) = var.name {
+            let name = name.as_str().to_string();
+            let alias = match (&var.data_location, &var.initializer) {
+                (Some(ast::DataLocation::Storage), Some(init)) => self.root_state_var(init),
+                _ => None,
+            };
+            if let Some(target) = alias {
+                self.storage_aliases.insert(name.clone(), target);
+            }
+            self.locals.insert(name);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        match &expr.kind {
+            ast::ExprKind::Assign(lhs, op, rhs) => {
+                self.visit_place(lhs, op.is_some());
+                self.visit_expr(rhs)
+            }
+            ast::ExprKind::Delete(target) => {
+                self.visit_place(target, false);
+                ControlFlow::Continue(())
+            }
+            ast::ExprKind::Unary(op, target)
+                if matches!(
+                    op.kind,
+                    ast::UnOpKind::PreInc
+                        | ast::UnOpKind::PreDec
+                        | ast::UnOpKind::PostInc
+                        | ast::UnOpKind::PostDec
+                ) =>
+            {
+                self.visit_place(target, true);
+                ControlFlow::Continue(())
+            }
+            ast::ExprKind::Call(callee, args) => {
+                // `array.push(x)` / `array.pop()` mutate the array in place
+                if let ast::ExprKind::Member(base, method) = &callee.kind {
+                    if matches!(method.as_str(), "push" | "pop") {
+                        self.visit_place(base, true);
+                        return self.visit_call_args(args);
+                    }
+                }
+                self.walk_expr(expr)
+            }
+            ast::ExprKind::Ident(ident) => {
+                if let Some(var) = self.state_var(ident.as_str()) {
+                    self.reads.insert(var);
+                }
+                ControlFlow::Continue(())
+            }
+            _ => self.walk_expr(expr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{self, param, state_variable};
+    use crate::batbelt::evm::types::{EvmContractType, StorageVariable};
+
+    fn function(name: &str, contract_name: &str, body: &str) -> EvmFunction {
+        fixtures::function(name, contract_name, body).with_params(vec![param("amount", "uint256")])
+    }
+
+    fn contract(
+        name: &str,
+        bases: &[&str],
+        functions: Vec<EvmFunction>,
+        vars: &[&str],
+    ) -> EvmContract {
+        EvmContract {
+            storage_variables: vars.iter().map(|v| state_variable(v, "uint256")).collect(),
+            ..fixtures::contract(name, EvmContractType::Contract, bases, functions)
+        }
+    }
+
+    #[test]
+    fn test_reads_and_writes_in_body() {
+        let contracts = vec![contract(
+            "Vault",
+            &[],
+            vec![function(
+                "deposit",
+                "Vault",
+                "    function deposit(uint256 amount) external {\n        require(!paused);\n        balances[msg.sender] += amount;\n        totalSupply = totalSupply + amount;\n        delete pending[msg.sender];\n    }",
+            )],
+            &["balances", "totalSupply", "pending", "paused"],
+        )];
+        let resolver = StorageAccessResolver::new(&contracts);
+        let access = resolver.resolve("Vault", &contracts[0].functions[0]);
+        assert_eq!(access.reads, vec!["balances", "paused", "totalSupply"]);
+        assert_eq!(access.writes, vec!["balances", "pending", "totalSupply"]);
+    }
+
+    #[test]
+    fn test_locals_shadow_and_storage_pointers_alias() {
+        let contracts = vec![contract(
+            "Vault",
+            &[],
+            vec![function(
+                "update",
+                "Vault",
+                "    function update(uint256 amount) external {\n        uint256 owner = amount;\n        Position storage p = positions[owner];\n        p.size = amount;\n        queue.push(amount);\n    }",
+            )],
+            &["owner", "positions", "queue"],
+        )];
+        let resolver = StorageAccessResolver::new(&contracts);
+        let access = resolver.resolve("Vault", &contracts[0].functions[0]);
+        assert_eq!(access.reads, vec!["positions", "queue"]);
+        assert_eq!(access.writes, vec!["positions", "queue"]);
+    }
+
+    #[test]
+    fn test_follows_internal_callees_through_inheritance() {
+        let base = contract(
+            "Base",
+            &[],
+            vec![function(
+                "_mint",
+                "Base",
+                "    function _mint(uint256 amount) internal {\n        supply += amount;\n    }",
+            )],
+            &["supply"],
+        );
+        let derived = contract(
+            "Token",
+            &["Base"],
+            vec![function(
+                "mint",
+                "Token",
+                "    function mint(uint256 amount) external {\n        _mint(amount);\n    }",
+            )],
+            &[],
+        );
+        let contracts = vec![base, derived];
+        let resolver = StorageAccessResolver::new(&contracts);
+        let access = resolver.resolve("Token", &contracts[1].functions[0]);
+        assert_eq!(access.reads, vec!["supply"]);
+        assert_eq!(access.writes, vec!["supply"]);
+    }
+
+    #[test]
+    fn test_follows_callees_of_modifiers() {
+        let guard = contract(
+            "ReentrancyGuard",
+            &[],
+            vec![function(
+                "_nonReentrantBefore",
+                "ReentrancyGuard",
+                "    function _nonReentrantBefore() private {\n        require(_status != 2);\n        _status = 2;\n    }",
+            )],
+            &["_status"],
+        );
+        let mut vault = contract(
+            "Vault",
+            &["ReentrancyGuard"],
+            vec![function(
+                "withdraw",
+                "Vault",
+                "    function withdraw(uint256 amount) external nonReentrant {\n        balance -= amount;\n    }",
+            )
+            .with_modifiers(&["nonReentrant"])],
+            &["balance"],
+        );
+        vault.modifiers.push(fixtures::modifier(
+            "nonReentrant",
+            "ReentrancyGuard",
+            "    modifier nonReentrant() {\n        _nonReentrantBefore();\n        _;\n    }",
+        ));
+        let contracts = vec![guard, vault];
+        let resolver = StorageAccessResolver::new(&contracts);
+        let access = resolver.resolve("Vault", &contracts[1].functions[0]);
+        assert_eq!(access.reads, vec!["_status", "balance"]);
+        assert_eq!(access.writes, vec!["_status", "balance"]);
+    }
+
+    #[test]
+    fn test_inline_assembly_slots() {
+        let mut vault = contract(
+            "Vault",
+            &[],
+            vec![function(
+                "setAdmin",
+                "Vault",
+                "    function setAdmin(uint256 amount) external {\n        assembly {\n            let total := sload(totalSupply.slot)\n            sstore(_ADMIN_SLOT, caller())\n            sstore(add(total, 1), amount)\n        }\n    }",
+            )],
+            &["totalSupply"],
+        );
+        vault.storage_variables.push(StorageVariable {
+            name: "_ADMIN_SLOT".to_string(),
+            type_name: "bytes32".to_string(),
+            is_constant: true,
+            ..state_variable("_ADMIN_SLOT", "uint256")
+        });
+        let contracts = vec![vault];
+        let resolver = StorageAccessResolver::new(&contracts);
+        let function = &contracts[0].functions[0];
+
+        let access = resolver.resolve("Vault", function);
+        assert_eq!(access.reads, vec!["totalSupply"]);
+        assert_eq!(access.writes, vec!["_ADMIN_SLOT"]);
+
+        let body = ReachableBody {
+            name: &function.name,
+            body_source: &function.body_source,
+            end_line: function.end_line,
+            params: function.params.iter().collect(),
+            is_modifier: false,
+        };
+        assert_eq!(
+            resolver.body_writes("Vault", &body),
+            vec![("_ADMIN_SLOT".to_string(), 4)]
+        );
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::batbelt::evm::types::{EvmContract, EvmFileDefinitions};
+
+use super::inheritance_resolver::InheritanceResolver;
+use super::type_resolver::TypeResolver;
+
+const SLOT_SIZE: u64 = 32;
+
+/// Concrete storage position of a state variable, as assigned by solc.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageSlot {
+    pub name: String,
+    /// Contract declaring the variable (may be a base of the laid out contract)
+    pub contract_name: String,
+    pub type_name: String,
+    pub slot: u64,
+    /// Byte offset inside the slot, counted from the lower-order end
+    pub offset: u64,
+    /// Size in bytes (a multiple of 32 for structs and fixed arrays)
+    pub size: u64,
+}
+
+/// Difference between two layouts that breaks an upgrade.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageLayoutChange {
+    pub kind: StorageLayoutChangeKind,
+    pub name: String,
+    pub old: Option<StorageSlot>,
+    pub new: Option<StorageSlot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StorageLayoutChangeKind {
+    /// The variable no longer exists; whatever now lives in its slot reads its data
+    Removed,
+    /// The variable moved to another slot or offset
+    Reordered,
+    /// The variable kept its position but changed its type
+    Retyped,
+}
+
+/// Minimal model of a storage type: what solc needs to know to lay it out, whatever the
+/// type is called in the source.
+#[derive(Debug, Clone, PartialEq)]
+enum LayoutType {
+    /// Packable value of `size` bytes: bool, address, integers, enums, contracts...
+    Value(u64),
+    /// Mapping or dynamic array, string or bytes: one slot, the data lives elsewhere
+    Dynamic,
+    FixedArray(Box<LayoutType>, u64),
+    Struct(Vec<LayoutType>),
+}
+
+impl LayoutType {
+    /// Size of the type and whether it must start (and end) on a slot boundary.
+    fn layout(&self) -> TypeLayout {
+        match self {
+            LayoutType::Value(size) => TypeLayout {
+                size: *size,
+                slot_aligned: false,
+            },
+            LayoutType::Dynamic => TypeLayout {
+                size: SLOT_SIZE,
+                slot_aligned: false,
+            },
+            LayoutType::FixedArray(element, length) => {
+                let element = element.layout();
+                let slots = if element.slot_aligned || element.size > SLOT_SIZE / 2 {
+                    length * element.size.div_ceil(SLOT_SIZE)
+                } else {
+                    length.div_ceil(SLOT_SIZE / element.size.max(1))
+                };
+                TypeLayout {
+                    size: slots * SLOT_SIZE,
+                    slot_aligned: true,
+                }
+            }
+            LayoutType::Struct(members) => {
+                let mut slot = 0u64;
+                let mut offset = 0u64;
+                for member in members {
+                    place(&mut slot, &mut offset, &member.layout());
+                }
+                let slots = if offset > 0 { slot + 1 } else { slot };
+                TypeLayout {
+                    size: slots.max(1) * SLOT_SIZE,
+                    slot_aligned: true,
+                }
+            }
+        }
+    }
+}
+
+/// Size of a type and whether it must start (and end) on a slot boundary.
+struct TypeLayout {
+    size: u64,
+    slot_aligned: bool,
+}
+
+/// Computes solc storage layouts over the C3 linearization of a contract.
+pub struct StorageLayoutResolver<'a> {
+    contracts_by_name: HashMap<&'a str, &'a EvmContract>,
+    inheritance_resolver: InheritanceResolver<'a>,
+    type_resolver: TypeResolver<'a>,
+}
+
+impl<'a> StorageLayoutResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract], file_definitions: &'a [EvmFileDefinitions]) -> Self {
+        Self {
+            contracts_by_name: contracts.iter().map(|c| (c.name.as_str(), c)).collect(),
+            inheritance_resolver: InheritanceResolver::new(contracts),
+            type_resolver: TypeResolver::new(contracts, file_definitions),
+        }
+    }
+
+    /// Slot and offset of every state variable of `contract_name`, most base contract
+    /// first. Constants and immutables are skipped since they are not kept in storage.
+    pub fn layout(&self, contract_name: &str) -> Vec<StorageSlot> {
+        let linearization = self.inheritance_resolver.linearize(contract_name);
+        let mut layout = Vec::new();
+        let mut slot = 0u64;
+        let mut offset = 0u64;
+
+        for name in &linearization {
+            let Some(contract) = self.contracts_by_name.get(name.as_str()) else {
+                continue;
+            };
+            for var in contract
+                .storage_variables
+                .iter()
+                .filter(|v| !v.is_constant && !v.is_immutable)
+            {
+                let type_layout = self.layout_type(&var.type_name, contract_name).layout();
+                let (var_slot, var_offset) = place(&mut slot, &mut offset, &type_layout);
+                layout.push(StorageSlot {
+                    name: var.name.clone(),
+                    contract_name: contract.name.clone(),
+                    type_name: var.type_name.clone(),
+                    slot: var_slot,
+                    offset: var_offset,
+                    size: type_layout.size,
+                });
+            }
+        }
+        layout
+    }
+
+    /// Model of `type_name` as declared in `context`, user-defined types resolved to what
+    /// they are made of.
+    fn layout_type(&self, type_name: &str, context: &str) -> LayoutType {
+        let type_name = type_name.trim();
+
+        if type_name.starts_with("mapping") || type_name.ends_with("[]") {
+            return LayoutType::Dynamic;
+        }
+        if type_name.starts_with("function") {
+            return LayoutType::Value(if type_name.contains("external") {
+                24
+            } else {
+                8
+            });
+        }
+        if let Some(pos) = type_name.rfind('[').filter(|_| type_name.ends_with(']')) {
+            let length = type_name[pos + 1..type_name.len() - 1]
+                .trim()
+                .parse::<u64>()
+                .unwrap_or(1);
+            return LayoutType::FixedArray(
+                Box::new(self.layout_type(&type_name[..pos], context)),
+                length,
+            );
+        }
+
+        let base = type_name.split_whitespace().next().unwrap_or(type_name);
+        match base {
+            "bool" => return LayoutType::Value(1),
+            "address" => return LayoutType::Value(20),
+            "string" | "bytes" => return LayoutType::Dynamic,
+            "uint" | "int" => return LayoutType::Value(SLOT_SIZE),
+            "fixed" | "ufixed" => return LayoutType::Value(16),
+            _ => {}
+        }
+        if let Some(bits) = base
+            .strip_prefix("uint")
+            .or_else(|| base.strip_prefix("int"))
+            .and_then(|b| b.parse::<u64>().ok())
+        {
+            return LayoutType::Value(bits / 8);
+        }
+        if let Some(bytes) = base
+            .strip_prefix("bytes")
+            .and_then(|b| b.parse::<u64>().ok())
+        {
+            return LayoutType::Value(bytes);
+        }
+        if let Some(bits) = base
+            .strip_prefix("ufixed")
+            .or_else(|| base.strip_prefix("fixed"))
+            .and_then(|b| b.split('x').next())
+            .and_then(|b| b.parse::<u64>().ok())
+        {
+            return LayoutType::Value(bits / 8);
+        }
+
+        if let Some(item) = self.type_resolver.find_struct(base, context) {
+            return LayoutType::Struct(
+                item.members
+                    .iter()
+                    .map(|member| self.layout_type(&member.type_name, context))
+                    .collect(),
+            );
+        }
+        if let Some(item) = self.type_resolver.find_enum(base, context) {
+            return LayoutType::Value(if item.variants.len() > 256 { 2 } else { 1 });
+        }
+        if let Some(item) = self.type_resolver.find_udvt(base, context) {
+            return self.layout_type(&item.underlying_type, context);
+        }
+        if self.type_resolver.is_contract_type(base) {
+            return LayoutType::Value(20);
+        }
+
+        // Unknown user-defined type: assume a full slot
+        LayoutType::Value(SLOT_SIZE)
+    }
+}
+
+/// Assign the next position to a type, advancing the `slot`/`offset` cursor.
+fn place(slot: &mut u64, offset: &mut u64, layout: &TypeLayout) -> (u64, u64) {
+    if *offset > 0 && (layout.slot_aligned || *offset + layout.size > SLOT_SIZE) {
+        *slot += 1;
+        *offset = 0;
+    }
+    let position = (*slot, *offset);
+    if layout.slot_aligned || layout.size >= SLOT_SIZE {
+        *slot += layout.size.div_ceil(SLOT_SIZE);
+        *offset = 0;
+    } else {
+        *offset += layout.size;
+    }
+    position
+}
+
+/// Compare an old layout with the one replacing it and report every variable that was
+/// removed, moved to another position, or retyped in place.
+pub fn compare_layouts(old: &[StorageSlot], new: &[StorageSlot]) -> Vec<StorageLayoutChange> {
+    let mut changes = Vec::new();
+    for old_var in old {
+        let Some(new_var) = new.iter().find(|v| v.name == old_var.name) else {
+            changes.push(StorageLayoutChange {
+                kind: StorageLayoutChangeKind::Removed,
+                name: old_var.name.clone(),
+                old: Some(old_var.clone()),
+                new: None,
+            });
+            continue;
+        };
+        let kind = if (new_var.slot, new_var.offset) != (old_var.slot, old_var.offset) {
+            StorageLayoutChangeKind::Reordered
+        } else if normalize_type(&new_var.type_name) != normalize_type(&old_var.type_name) {
+            StorageLayoutChangeKind::Retyped
+        } else {
+            continue;
+        };
+        changes.push(StorageLayoutChange {
+            kind,
+            name: old_var.name.clone(),
+            old: Some(old_var.clone()),
+            new: Some(new_var.clone()),
+        });
+    }
+    changes
+}
+
+fn normalize_type(type_name: &str) -> String {
+    type_name.split_whitespace().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::fixtures::{self, param, state_variable as var};
+    use crate::batbelt::evm::types::{
+        EvmContractType, EvmEnum, EvmStruct, EvmUdvt, StorageVariable,
+    };
+
+    fn contract(name: &str, bases: &[&str], vars: Vec<StorageVariable>) -> EvmContract {
+        EvmContract {
+            storage_variables: vars,
+            ..fixtures::contract(name, EvmContractType::Contract, bases, vec![])
+        }
+    }
+
+    fn positions(layout: &[StorageSlot]) -> Vec<(&str, u64, u64)> {
+        layout
+            .iter()
+            .map(|s| (s.name.as_str(), s.slot, s.offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_packs_values_and_aligns_structs_and_arrays() {
+        let mut base = contract(
+            "Base",
+            &[],
+            vec![var("owner", "address"), var("paused", "bool")],
+        );
+        let mut max_supply = var("MAX_SUPPLY", "uint256");
+        max_supply.is_constant = true;
+        base.storage_variables.push(max_supply);
+
+        let mut vault = contract(
+            "Vault",
+            &["Base"],
+            vec![
+                var("position", "Position"),
+                var("flag", "uint8"),
+                var("fees", "uint64[5]"),
+                var("status", "Status"),
+                var("balances", "mapping(address => uint256)"),
+                var("token", "IERC20"),
+                var("limit", "Limit"),
+            ],
+        );
+        vault.structs.push(EvmStruct {
+            name: "Position".to_string(),
+            members: vec![
+                param("size", "uint128"),
+                param("open", "bool"),
+                param("debt", "uint256"),
+            ],
+            line: 3,
+            end_line: 7,
+        });
+        vault.enums.push(EvmEnum {
+            name: "Status".to_string(),
+            variants: vec!["Open".to_string(), "Closed".to_string()],
+            line: 8,
+            end_line: 8,
+        });
+        let mut token = contract("IERC20", &[], vec![]);
+        token.contract_type = EvmContractType::Interface;
+
+        let file_definitions = vec![EvmFileDefinitions {
+            file_path: "src/Types.sol".to_string(),
+            udvts: vec![EvmUdvt {
+                name: "Limit".to_string(),
+                underlying_type: "uint96".to_string(),
+                line: 3,
+                end_line: 3,
+            }],
+            ..Default::default()
+        }];
+
+        let contracts = vec![base, vault, token];
+        let resolver = StorageLayoutResolver::new(&contracts, &file_definitions);
+        let layout = resolver.layout("Vault");
+
+        assert_eq!(
+            positions(&layout),
+            vec![
+                ("owner", 0, 0),
+                ("paused", 0, 20),
+                ("position", 1, 0),
+                ("flag", 3, 0),
+                ("fees", 4, 0),
+                ("status", 6, 0),
+                ("balances", 7, 0),
+                ("token", 8, 0),
+                ("limit", 8, 20),
+            ]
+        );
+        assert_eq!(layout[2].size, 64);
+    }
+
+    #[test]
+    fn test_layout_type_sizes() {
+        let position = LayoutType::Struct(vec![
+            LayoutType::Value(16),
+            LayoutType::Value(1),
+            LayoutType::Value(32),
+        ]);
+        let sizes = [
+            position.clone(),
+            LayoutType::FixedArray(Box::new(LayoutType::Value(8)), 5),
+            LayoutType::FixedArray(Box::new(position), 2),
+            LayoutType::Struct(vec![LayoutType::Dynamic, LayoutType::Value(20)]),
+        ]
+        .iter()
+        .map(|layout_type| {
+            let layout = layout_type.layout();
+            (layout.size, layout.slot_aligned)
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(64, true), (64, true), (128, true), (64, true)]);
+    }
+
+    #[test]
+    fn test_compare_layouts_flags_breaking_changes() {
+        let old_contracts = vec![contract(
+            "Vault",
+            &[],
+            vec![
+                var("owner", "address"),
+                var("total", "uint256"),
+                var("fee", "uint256"),
+                var("legacy", "bool"),
+            ],
+        )];
+        let new_contracts = vec![contract(
+            "Vault",
+            &[],
+            vec![
+                var("total", "uint256"),
+                var("owner", "address"),
+                var("fee", "int256"),
+            ],
+        )];
+        let old = StorageLayoutResolver::new(&old_contracts, &[]).layout("Vault");
+        let new = StorageLayoutResolver::new(&new_contracts, &[]).layout("Vault");
+
+        let changes: Vec<(StorageLayoutChangeKind, String)> = compare_layouts(&old, &new)
+            .into_iter()
+            .map(|c| (c.kind, c.name))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (StorageLayoutChangeKind::Reordered, "owner".to_string()),
+                (StorageLayoutChangeKind::Reordered, "total".to_string()),
+                (StorageLayoutChangeKind::Retyped, "fee".to_string()),
+                (StorageLayoutChangeKind::Removed, "legacy".to_string()),
+            ]
+        );
+    }
+}
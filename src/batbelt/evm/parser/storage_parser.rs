@@ -2,7 +2,7 @@ use solar_parse::{ast, interface::Session};
 
 use crate::batbelt::evm::types::{EvmVisibility, StorageVariable};
 
-use super::evm_file_parser::{span_to_line, type_to_string};
+use super::evm_file_parser::{expr_to_string, span_to_line, type_to_string};
 
 /// Parse a VariableDefinition into a StorageVariable.
 pub fn parse_variable_definition(
@@ -36,6 +36,11 @@ pub fn parse_variable_definition(
         .map(|m| m == ast::VarMut::Immutable)
         .unwrap_or(false);
 
+    let value = var
+        .initializer
+        .as_ref()
+        .map(|expr| expr_to_string(sess, expr));
+
     let line = var.name.map(|n| span_to_line(sess, n.span)).unwrap_or(0);
 
     Some(StorageVariable {
@@ -44,6 +49,7 @@ pub fn parse_variable_definition(
         visibility,
         is_constant,
         is_immutable,
+        value,
         line,
     })
 }
@@ -0,0 +1,386 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+use solar_parse::{
+    ast::{self, Visit},
+    interface::Session,
+};
+
+use crate::batbelt::evm::types::{
+    EvmContract, EvmContractType, EvmFunction, EvmVisibility, ExternalCallKind,
+};
+
+use super::call_resolver::CallResolver;
+use super::evm_file_parser::{expr_to_string, with_parsed_body};
+
+/// Prefixes of the functions run by `forge test`.
+const TEST_FUNCTION_PREFIXES: &[&str] = &["test", "invariant", "statefulFuzz"];
+
+/// Prefixes of the invariant tests, for which the fuzzer calls the `targetContract`s.
+const INVARIANT_FUNCTION_PREFIXES: &[&str] = &["invariant", "statefulFuzz"];
+
+/// A function of the audited contracts called by a test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestedCall {
+    /// Type of the call target (`Vault`, `IVault`...)
+    pub target_type: String,
+    /// Called function, `None` when the invariant fuzzer may call any function of the target
+    pub function: Option<String>,
+}
+
+/// Resolves the audited functions exercised by the Foundry tests, following the calls made
+/// through handler contracts of the test suite.
+pub struct TestResolver<'a> {
+    call_resolver: CallResolver<'a>,
+    contracts_by_name: HashMap<&'a str, &'a EvmContract>,
+    /// Contracts declared in the test directory: test suites, handlers and mocks
+    test_contracts: HashSet<&'a str>,
+}
+
+impl<'a> TestResolver<'a> {
+    /// `contracts` holds both the audited contracts and those parsed from `test_dir`.
+    pub fn new(contracts: &'a [EvmContract], test_dir: &Path) -> Self {
+        Self {
+            call_resolver: CallResolver::new(contracts),
+            contracts_by_name: contracts.iter().map(|c| (c.name.as_str(), c)).collect(),
+            test_contracts: contracts
+                .iter()
+                .filter(|c| Path::new(&c.file_path).starts_with(test_dir))
+                .map(|c| c.name.as_str())
+                .collect(),
+        }
+    }
+
+    /// Functions run by `forge test` (`test*`, `invariant*`, `statefulFuzz*`) of the
+    /// deployable test contracts, inherited ones included.
+    pub fn test_functions(&self) -> Vec<(&'a EvmContract, &'a EvmFunction)> {
+        let mut test_functions: Vec<(&'a EvmContract, &'a EvmFunction)> = Vec::new();
+        let mut test_contracts: Vec<&'a EvmContract> = self
+            .test_contracts
+            .iter()
+            .filter_map(|name| self.contracts_by_name.get(name).copied())
+            .filter(|c| c.contract_type == EvmContractType::Contract)
+            .collect();
+        test_contracts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for contract in test_contracts {
+            let mut seen: HashSet<&str> = HashSet::new();
+            for function in self
+                .call_resolver
+                .lookup_order(&contract.name)
+                .into_iter()
+                .flat_map(|c| c.functions.iter())
+            {
+                if is_externally_callable(function)
+                    && TEST_FUNCTION_PREFIXES
+                        .iter()
+                        .any(|prefix| function.name.starts_with(prefix))
+                    && seen.insert(&function.name)
+                {
+                    test_functions.push((contract, function));
+                }
+            }
+        }
+        test_functions
+    }
+
+    /// Audited functions called by `function` of the test contract `contract_name`, its
+    /// helpers and the handlers it calls. Invariant tests also call every function of the
+    /// contracts registered with `targetContract` in `setUp`.
+    pub fn tested_calls(&self, contract_name: &str, function: &'a EvmFunction) -> Vec<TestedCall> {
+        let mut calls: Vec<TestedCall> = Vec::new();
+        let mut visited: HashSet<(String, String)> = HashSet::new();
+        self.collect_calls(contract_name, function, &mut visited, &mut calls);
+
+        if INVARIANT_FUNCTION_PREFIXES
+            .iter()
+            .any(|prefix| function.name.starts_with(prefix))
+        {
+            for target in self.fuzz_targets(contract_name) {
+                if self.test_contracts.contains(target.as_str()) {
+                    for handler_function in self.handler_functions(&target) {
+                        self.collect_calls(&target, handler_function, &mut visited, &mut calls);
+                    }
+                } else {
+                    push_unique(
+                        &mut calls,
+                        TestedCall {
+                            target_type: target,
+                            function: None,
+                        },
+                    );
+                }
+            }
+        }
+        calls
+    }
+
+    fn collect_calls(
+        &self,
+        contract_name: &str,
+        function: &'a EvmFunction,
+        visited: &mut HashSet<(String, String)>,
+        calls: &mut Vec<TestedCall>,
+    ) {
+        if !visited.insert((contract_name.to_string(), function.name.clone())) {
+            return;
+        }
+        for call in self
+            .call_resolver
+            .resolve_external_calls(contract_name, function)
+            .into_iter()
+            .filter(|call| call.kind == ExternalCallKind::HighLevel)
+        {
+            if !self.test_contracts.contains(call.target_type.as_str()) {
+                push_unique(
+                    calls,
+                    TestedCall {
+                        target_type: call.target_type,
+                        function: Some(call.function),
+                    },
+                );
+                continue;
+            }
+            // Handler of the test suite: follow the call into its implementation
+            let handler_functions: Vec<&'a EvmFunction> = self
+                .call_resolver
+                .lookup_order(&call.target_type)
+                .into_iter()
+                .find_map(|c| {
+                    let functions: Vec<&'a EvmFunction> = c
+                        .functions
+                        .iter()
+                        .filter(|f| f.name == call.function)
+                        .collect();
+                    (!functions.is_empty()).then_some(functions)
+                })
+                .unwrap_or_default();
+            for handler_function in handler_functions {
+                self.collect_calls(&call.target_type, handler_function, visited, calls);
+            }
+        }
+    }
+
+    /// Types of the contracts registered with `targetContract(address(x))` by the `setUp`
+    /// of `contract_name`.
+    fn fuzz_targets(&self, contract_name: &str) -> Vec<String> {
+        let lookup_order = self.call_resolver.lookup_order(contract_name);
+        let Some(set_up) = lookup_order
+            .iter()
+            .find_map(|c| c.functions.iter().find(|f| f.name == "setUp"))
+        else {
+            return vec![];
+        };
+        let var_types: HashMap<&str, &str> = lookup_order
+            .iter()
+            .rev()
+            .flat_map(|c| c.storage_variables.iter())
+            .map(|v| (v.name.as_str(), v.type_name.as_str()))
+            .collect();
+
+        let mut targets: Vec<String> = Vec::new();
+        for body in self.call_resolver.reachable_bodies(contract_name, set_up) {
+            let arguments = with_parsed_body(body.body_source, |sess, block| {
+                let mut visitor = TargetContractVisitor {
+                    sess,
+                    arguments: Vec::new(),
+                };
+                let _ = visitor.visit_block(block);
+                visitor.arguments
+            })
+            .unwrap_or_default();
+            for argument in arguments {
+                let name = argument
+                    .trim_start_matches("address(")
+                    .trim_end_matches(')');
+                if let Some(type_name) = var_types.get(name) {
+                    if !targets.iter().any(|t| t == type_name) {
+                        targets.push(type_name.to_string());
+                    }
+                }
+            }
+        }
+        targets
+    }
+
+    /// Functions the invariant fuzzer may call on a handler: the public and external
+    /// functions declared by test contracts of its inheritance chain.
+    fn handler_functions(&self, contract_name: &str) -> Vec<&'a EvmFunction> {
+        self.call_resolver
+            .lookup_order(contract_name)
+            .into_iter()
+            .filter(|c| self.test_contracts.contains(c.name.as_str()))
+            .flat_map(|c| c.functions.iter())
+            .filter(|f| is_externally_callable(f))
+            .collect()
+    }
+}
+
+fn is_externally_callable(function: &EvmFunction) -> bool {
+    matches!(
+        function.visibility,
+        EvmVisibility::External | EvmVisibility::Public
+    ) && !function.is_constructor
+}
+
+fn push_unique(calls: &mut Vec<TestedCall>, call: TestedCall) {
+    if !calls.contains(&call) {
+        calls.push(call);
+    }
+}
+
+/// AST visitor that collects the arguments of the `targetContract(...)` calls.
+struct TargetContractVisitor<'v> {
+    sess: &'v Session,
+    arguments: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for TargetContractVisitor<'_> {
+    type BreakValue = ();
+
+    fn visit_expr(&mut self, expr: &'ast ast::Expr<'ast>) -> ControlFlow<Self::BreakValue> {
+        if let ast::ExprKind::Call(callee, args) = &expr.kind {
+            if let ast::ExprKind::Ident(ident) = &callee.kind {
+                if ident.as_str() == "targetContract" {
+                    self.arguments
+                        .extend(args.exprs().map(|arg| expr_to_string(self.sess, arg)));
+                }
+            }
+        }
+        self.walk_expr(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::parser::evm_file_parser::parse_sol_file;
+    use std::fs;
+
+    const VAULT: &str = r#"pragma solidity ^0.8.20;
+
+contract Vault {
+    mapping(address => uint256) public balances;
+
+    function deposit(uint256 amount) external {
+        balances[msg.sender] += amount;
+    }
+
+    function withdraw(uint256 amount) external {
+        balances[msg.sender] -= amount;
+    }
+}
+"#;
+
+    const VAULT_TEST: &str = r#"pragma solidity ^0.8.20;
+
+import {Vault} from "../src/Vault.sol";
+
+contract Handler {
+    Vault vault;
+
+    constructor(Vault _vault) {
+        vault = _vault;
+    }
+
+    function deposit(uint256 amount) external {
+        vault.deposit(amount);
+    }
+}
+
+contract VaultTest {
+    Vault vault;
+    Handler handler;
+
+    function setUp() public {
+        vault = new Vault();
+        handler = new Handler(vault);
+        targetContract(address(handler));
+    }
+
+    function test_deposit() public {
+        vault.deposit(1);
+    }
+
+    function testFuzz_withdraw(uint256 amount) public {
+        _withdraw(amount);
+    }
+
+    function invariant_solvent() public view {}
+
+    function _withdraw(uint256 amount) internal {
+        vault.withdraw(amount);
+    }
+}
+
+contract VaultTargetTest {
+    Vault vault;
+
+    function setUp() public {
+        vault = new Vault();
+        targetContract(address(vault));
+    }
+
+    function invariant_balances() public view {}
+}
+"#;
+
+    #[test]
+    fn test_tested_calls() {
+        let root = std::env::temp_dir().join(format!("bat-test-resolver-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in [("src/Vault.sol", VAULT), ("test/Vault.t.sol", VAULT_TEST)] {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let contracts: Vec<EvmContract> = ["src/Vault.sol", "test/Vault.t.sol"]
+            .iter()
+            .flat_map(|path| {
+                parse_sol_file(root.join(path).to_str().unwrap())
+                    .unwrap()
+                    .contracts
+            })
+            .collect();
+        let resolver = TestResolver::new(&contracts, &root.join("test"));
+
+        let tests: Vec<(String, Vec<TestedCall>)> = resolver
+            .test_functions()
+            .into_iter()
+            .map(|(contract, function)| {
+                (
+                    format!("{}.{}", contract.name, function.name),
+                    resolver.tested_calls(&contract.name, function),
+                )
+            })
+            .collect();
+        let call = |target: &str, function: Option<&str>| TestedCall {
+            target_type: target.to_string(),
+            function: function.map(|f| f.to_string()),
+        };
+
+        assert_eq!(
+            tests,
+            vec![
+                (
+                    "VaultTargetTest.invariant_balances".to_string(),
+                    vec![call("Vault", None)]
+                ),
+                (
+                    "VaultTest.test_deposit".to_string(),
+                    vec![call("Vault", Some("deposit"))]
+                ),
+                (
+                    "VaultTest.testFuzz_withdraw".to_string(),
+                    vec![call("Vault", Some("withdraw"))]
+                ),
+                (
+                    "VaultTest.invariant_solvent".to_string(),
+                    vec![call("Vault", Some("deposit"))]
+                ),
+            ]
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+}
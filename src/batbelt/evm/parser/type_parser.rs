@@ -0,0 +1,103 @@
+use solar_parse::{
+    ast,
+    interface::{Session, Span},
+};
+
+use crate::batbelt::evm::types::{
+    EvmCustomError, EvmEnum, EvmParam, EvmStruct, EvmUdvt, EvmUsingDirective,
+};
+
+use super::evm_file_parser::{span_to_end_line, span_to_line, type_to_string};
+
+/// Parse an ItemStruct into an EvmStruct.
+pub fn parse_struct_definition(
+    sess: &Session,
+    item: &ast::ItemStruct<'_>,
+    item_span: Span,
+) -> EvmStruct {
+    EvmStruct {
+        name: item.name.as_str().to_string(),
+        members: item
+            .fields
+            .iter()
+            .map(|field| parse_field(sess, field))
+            .collect(),
+        line: span_to_line(sess, item_span),
+        end_line: span_to_end_line(sess, item_span),
+    }
+}
+
+/// Parse an ItemEnum into an EvmEnum.
+pub fn parse_enum_definition(sess: &Session, item: &ast::ItemEnum<'_>, item_span: Span) -> EvmEnum {
+    EvmEnum {
+        name: item.name.as_str().to_string(),
+        variants: item
+            .variants
+            .iter()
+            .map(|v| v.as_str().to_string())
+            .collect(),
+        line: span_to_line(sess, item_span),
+        end_line: span_to_end_line(sess, item_span),
+    }
+}
+
+/// Parse an ItemError into an EvmCustomError.
+pub fn parse_error_definition(
+    sess: &Session,
+    item: &ast::ItemError<'_>,
+    item_span: Span,
+) -> EvmCustomError {
+    EvmCustomError {
+        name: item.name.as_str().to_string(),
+        params: item
+            .parameters
+            .iter()
+            .map(|field| parse_field(sess, field))
+            .collect(),
+        line: span_to_line(sess, item_span),
+        end_line: span_to_end_line(sess, item_span),
+    }
+}
+
+/// Parse an ItemUdvt into an EvmUdvt.
+pub fn parse_udvt_definition(sess: &Session, item: &ast::ItemUdvt<'_>, item_span: Span) -> EvmUdvt {
+    EvmUdvt {
+        name: item.name.as_str().to_string(),
+        underlying_type: type_to_string(sess, &item.ty),
+        line: span_to_line(sess, item_span),
+        end_line: span_to_end_line(sess, item_span),
+    }
+}
+
+/// Parse a UsingDirective into an EvmUsingDirective.
+pub fn parse_using_directive(
+    sess: &Session,
+    item: &ast::UsingDirective<'_>,
+    item_span: Span,
+) -> EvmUsingDirective {
+    let (library, functions) = match &item.list {
+        ast::UsingList::Single(path) => (Some(path.to_string()), vec![]),
+        ast::UsingList::Multiple(paths) => (
+            None,
+            paths.iter().map(|(path, _)| path.to_string()).collect(),
+        ),
+    };
+    EvmUsingDirective {
+        library,
+        functions,
+        type_name: item.ty.as_ref().map(|ty| type_to_string(sess, ty)),
+        global: item.global,
+        line: span_to_line(sess, item_span),
+    }
+}
+
+fn parse_field(sess: &Session, field: &ast::VariableDefinition<'_>) -> EvmParam {
+    EvmParam {
+        name: field
+            .name
+            .map(|n| n.as_str().to_string())
+            .unwrap_or_default(),
+        type_name: type_to_string(sess, &field.ty),
+        storage_location: None,
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use crate::batbelt::evm::types::{
+    EvmContract, EvmContractType, EvmEnum, EvmFileDefinitions, EvmStruct, EvmUdvt,
+};
+
+use super::inheritance_resolver::InheritanceResolver;
+
+/// Resolves user-defined type names (structs, enums, value types) to their definitions,
+/// following Solidity scoping: qualified name, inheritance chain, then file level.
+pub struct TypeResolver<'a> {
+    contracts_by_name: HashMap<&'a str, &'a EvmContract>,
+    inheritance_resolver: InheritanceResolver<'a>,
+    file_definitions: &'a [EvmFileDefinitions],
+}
+
+impl<'a> TypeResolver<'a> {
+    pub fn new(contracts: &'a [EvmContract], file_definitions: &'a [EvmFileDefinitions]) -> Self {
+        Self {
+            contracts_by_name: contracts.iter().map(|c| (c.name.as_str(), c)).collect(),
+            inheritance_resolver: InheritanceResolver::new(contracts),
+            file_definitions,
+        }
+    }
+
+    pub fn find_struct(&self, type_name: &str, context: &str) -> Option<&'a EvmStruct> {
+        let (name, scopes) = self.scopes(type_name, context);
+        scopes
+            .into_iter()
+            .find_map(|c| c.structs.iter().find(|s| s.name == name))
+            .or_else(|| {
+                self.file_definitions
+                    .iter()
+                    .find_map(|f| f.structs.iter().find(|s| s.name == name))
+            })
+    }
+
+    pub fn find_enum(&self, type_name: &str, context: &str) -> Option<&'a EvmEnum> {
+        let (name, scopes) = self.scopes(type_name, context);
+        scopes
+            .into_iter()
+            .find_map(|c| c.enums.iter().find(|e| e.name == name))
+            .or_else(|| {
+                self.file_definitions
+                    .iter()
+                    .find_map(|f| f.enums.iter().find(|e| e.name == name))
+            })
+    }
+
+    pub fn find_udvt(&self, type_name: &str, context: &str) -> Option<&'a EvmUdvt> {
+        let (name, scopes) = self.scopes(type_name, context);
+        scopes
+            .into_iter()
+            .find_map(|c| c.udvts.iter().find(|u| u.name == name))
+            .or_else(|| {
+                self.file_definitions
+                    .iter()
+                    .find_map(|f| f.udvts.iter().find(|u| u.name == name))
+            })
+    }
+
+    /// true if `type_name` names a contract or interface, which are stored as addresses.
+    pub fn is_contract_type(&self, type_name: &str) -> bool {
+        let name = type_name.rsplit('.').next().unwrap_or(type_name);
+        self.contracts_by_name
+            .get(name)
+            .is_some_and(|c| c.contract_type != EvmContractType::Library)
+    }
+
+    /// Contracts searched for a type named in `context`: the qualifier (`Lib.Type`) first,
+    /// then the linearization of `context`, then every other contract.
+    fn scopes(&self, type_name: &str, context: &str) -> (String, Vec<&'a EvmContract>) {
+        let (qualifier, name) = match type_name.rsplit_once('.') {
+            Some((qualifier, name)) => (Some(qualifier), name),
+            None => (None, type_name),
+        };
+        let mut scopes: Vec<&'a EvmContract> = Vec::new();
+        if let Some(contract) = qualifier.and_then(|q| self.contracts_by_name.get(q)) {
+            scopes.push(contract);
+        }
+        for base in self.inheritance_resolver.linearize(context).iter().rev() {
+            if let Some(contract) = self.contracts_by_name.get(base.as_str()) {
+                scopes.push(contract);
+            }
+        }
+        scopes.extend(self.contracts_by_name.values().copied());
+        (name.to_string(), scopes)
+    }
+}
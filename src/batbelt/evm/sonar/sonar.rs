@@ -2,19 +2,41 @@ use colored::Colorize;
 use dialoguer::console::Emoji;
 use error_stack::{Report, ResultExt};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::{BTreeMap, HashSet};
 use std::fs::OpenOptions;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use walkdir::WalkDir;
 
+use crate::batbelt::evm::detectors::{save_finding_candidates, EvmDetectorRunner};
 use crate::batbelt::evm::metadata::bat_metadata::{
-    EvmBatMetadata, EvmMetadataError, EvmMetadataResult, FunctionDependency,
+    evm_function_id, EvmBatMetadata, EvmMetadataError, EvmMetadataResult, FunctionDependency,
+    SelectorClashKind,
 };
-use crate::batbelt::evm::parser::call_resolver::extract_calls_from_source;
+use crate::batbelt::evm::metadata::privilege_graph::EvmPrivilegeGraph;
+use crate::batbelt::evm::metadata::reentrancy_report::EvmReentrancyReport;
+use crate::batbelt::evm::parser::access_control_resolver::AccessControlResolver;
+use crate::batbelt::evm::parser::call_resolver::CallResolver;
 use crate::batbelt::evm::parser::evm_file_parser::parse_sol_file;
 use crate::batbelt::evm::parser::import_resolver::ImportResolver;
 use crate::batbelt::evm::parser::inheritance_resolver::InheritanceResolver;
-use crate::batbelt::evm::types::EvmContract;
+use crate::batbelt::evm::parser::proxy_resolver::ProxyResolver;
+use crate::batbelt::evm::parser::test_resolver::TestResolver;
+use crate::batbelt::evm::types::{
+    EvmContract, EvmContractType, EvmDetectorKind, EvmFileDefinitions, EvmProxyKind,
+    ExternalCallKind,
+};
+use crate::config::BatConfig;
+
+/// OpenZeppelin and EIP-2535 bases whose descendants forward calls to an implementation.
+const KNOWN_PROXY_BASES: &[&str] = &[
+    "Proxy",
+    "ERC1967Proxy",
+    "TransparentUpgradeableProxy",
+    "BeaconProxy",
+    "Diamond",
+];
 
 static BAT: Emoji<'_, '_> = Emoji("🦇", "BatSonar");
 static FOLDER: Emoji<'_, '_> = Emoji("📂", "Folder");
@@ -25,6 +47,7 @@ static WAVE: Emoji<'_, '_> = Emoji("〰", "-");
 pub struct EvmSonar {
     project_root: String,
     contracts: Vec<EvmContract>,
+    file_definitions: Vec<EvmFileDefinitions>,
     error_count: usize,
 }
 
@@ -33,6 +56,7 @@ impl EvmSonar {
         Self {
             project_root: project_root.to_string(),
             contracts: Vec::new(),
+            file_definitions: Vec::new(),
             error_count: 0,
         }
     }
@@ -80,7 +104,7 @@ impl EvmSonar {
         pb.finish_and_clear();
     }
 
-    /// Run all 5 phases of the EVM sonar scan.
+    /// Run all phases of the EVM sonar scan.
     pub fn run(&mut self) -> EvmMetadataResult<EvmBatMetadata> {
         // Clear previous log
         let _ = std::fs::write("Batlog.log", "");
@@ -90,7 +114,16 @@ impl EvmSonar {
         self.phase_2_imports_and_inheritance()?;
         self.phase_3_access_control()?;
         let deps = self.phase_4_function_dependencies()?;
-        let metadata = self.phase_5_entry_points(deps)?;
+        let mut metadata = self.phase_5_entry_points(deps)?;
+        self.phase_6_selector_clashes(&mut metadata)?;
+        self.phase_7_proxy_patterns(&mut metadata);
+        let reentrancy_report = self.phase_8_reentrancy(&metadata);
+        self.phase_9_detectors(&mut metadata);
+        self.phase_10_privileges(&mut metadata);
+        self.phase_11_test_coverage(&mut metadata)?;
+        metadata.save_metadata()?;
+        reentrancy_report.save()?;
+        save_finding_candidates(&metadata.detector_hits)?;
 
         if self.error_count > 0 {
             println!(
@@ -104,66 +137,84 @@ impl EvmSonar {
         Ok(metadata)
     }
 
-    /// Phase 1: Parse all .sol files in src/ and lib/ directories.
+    /// Parse the project sources only (phase 1), e.g. to inspect another checkout.
+    /// Returns the contracts and the non-empty file-level definitions.
+    pub fn scan_sources(
+        mut self,
+    ) -> EvmMetadataResult<(Vec<EvmContract>, Vec<EvmFileDefinitions>)> {
+        self.phase_1_source_scan()?;
+        Ok((self.contracts, self.file_definitions))
+    }
+
+    /// Phase 1: Parse all .sol files in the source and dependency directories.
+    /// `node_modules/` is too large to scan, so only the files reachable through imports are parsed.
     fn phase_1_source_scan(&mut self) -> EvmMetadataResult<()> {
         let import_resolver =
             ImportResolver::new(&self.project_root).change_context(EvmMetadataError)?;
         let src_dir = import_resolver.get_src_dir().to_path_buf();
-        let lib_dir = import_resolver.get_root_dir().join("lib");
+        let lib_dirs = import_resolver.get_lib_dirs().to_vec();
 
         if !src_dir.is_dir() {
             return Err(Report::new(EvmMetadataError)
                 .attach_printable(format!("Source directory not found: {}", src_dir.display())));
         }
 
-        let src_files = Self::collect_sol_files(&src_dir);
-        let lib_files = if lib_dir.is_dir() {
-            Self::collect_sol_files(&lib_dir)
-        } else {
-            vec![]
-        };
+        let is_lib_file = |path: &Path| import_resolver.is_dependency_path(&path.to_string_lossy());
+        let src_files: Vec<String> = Self::collect_sol_files(&src_dir)
+            .into_iter()
+            .filter(|file_path| !is_lib_file(Path::new(file_path)))
+            .collect();
+        let lib_files: Vec<String> = lib_dirs
+            .iter()
+            .filter(|lib_dir| !lib_dir.ends_with("node_modules"))
+            .flat_map(|lib_dir| Self::collect_sol_files(lib_dir))
+            .collect();
 
         let total = src_files.len() + lib_files.len();
         let pb = Self::create_spinner();
         pb.set_message(format!("Source scan [0/{}]", total));
 
         let mut count = 0usize;
+        let mut parsed: HashSet<PathBuf> = HashSet::new();
+        let mut pending_imports: Vec<(String, String)> = Vec::new();
 
         // Parse src/ contracts
         for file_path in &src_files {
             count += 1;
             let short = file_path.split("/src/").last().unwrap_or(file_path);
             pb.set_message(format!("Source scan [{}/{}]: {}", count, total, short));
-            match parse_sol_file(file_path) {
-                Ok(sol_file) => {
-                    for contract in sol_file.contracts {
-                        self.contracts.push(contract);
-                    }
-                }
-                Err(e) => {
-                    self.log_error(&format!("Failed to parse [SRC] {}: {:?}", file_path, e));
-                }
-            }
+            parsed.insert(Self::canonical(file_path));
+            pending_imports.extend(self.parse_source(file_path, false));
         }
 
         // Parse lib/ contracts
         for file_path in &lib_files {
             count += 1;
-            let short = file_path.split("/lib/").last().unwrap_or(file_path);
+            let short = file_path.rsplit("/lib/").next().unwrap_or(file_path);
             pb.set_message(format!(
                 "Source scan [{}/{}]: [EXT] {}",
                 count, total, short
             ));
-            match parse_sol_file(file_path) {
-                Ok(sol_file) => {
-                    for contract in sol_file.contracts {
-                        self.contracts.push(contract);
-                    }
-                }
-                Err(e) => {
-                    self.log_error(&format!("Failed to parse [EXT] {}: {:?}", file_path, e));
-                }
+            parsed.insert(Self::canonical(file_path));
+            pending_imports.extend(self.parse_source(file_path, true));
+        }
+
+        // Follow imports into files not covered above, e.g. `node_modules/@scope/...`
+        while let Some((import_path, from_file)) = pending_imports.pop() {
+            let Some(resolved) = import_resolver.resolve(&import_path, &from_file) else {
+                continue;
+            };
+            if !parsed.insert(Self::canonical(&resolved.to_string_lossy())) {
+                continue;
             }
+            let file_path = resolved.to_string_lossy().to_string();
+            let external = is_lib_file(&resolved);
+            let short = file_path
+                .rsplit("/node_modules/")
+                .next()
+                .unwrap_or(&file_path);
+            pb.set_message(format!("Source scan [imports]: [EXT] {}", short));
+            pending_imports.extend(self.parse_source(&file_path, external));
         }
 
         let src_contracts = self.contracts.iter().filter(|c| !c.external).count();
@@ -186,9 +237,41 @@ impl EvmSonar {
         Ok(())
     }
 
+    /// Parse a single file, returning its `(import, file)` pairs so callers can follow them.
+    fn parse_source(&mut self, file_path: &str, external: bool) -> Vec<(String, String)> {
+        let tag = if external { "EXT" } else { "SRC" };
+        match parse_sol_file(file_path) {
+            Ok(sol_file) => {
+                for mut contract in sol_file.contracts {
+                    contract.external = external;
+                    self.contracts.push(contract);
+                }
+                if !sol_file.definitions.is_empty() {
+                    self.file_definitions.push(sol_file.definitions);
+                }
+                sol_file
+                    .imports
+                    .into_iter()
+                    .map(|import| (import.path, file_path.to_string()))
+                    .collect()
+            }
+            Err(e) => {
+                self.log_error(&format!("Failed to parse [{}] {}: {:?}", tag, file_path, e));
+                vec![]
+            }
+        }
+    }
+
+    fn canonical(file_path: &str) -> PathBuf {
+        Path::new(file_path)
+            .canonicalize()
+            .unwrap_or_else(|_| PathBuf::from(file_path))
+    }
+
     fn collect_sol_files(dir: &std::path::Path) -> Vec<String> {
         WalkDir::new(dir)
             .into_iter()
+            .filter_entry(|e| e.file_name() != "node_modules")
             .filter_map(|e| e.ok())
             .filter(|e| {
                 e.file_type().is_file()
@@ -207,6 +290,23 @@ impl EvmSonar {
             .collect()
     }
 
+    /// Every .sol file of the test directory, helpers and mocks included.
+    fn collect_test_files(dir: &Path) -> Vec<String> {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_entry(|e| e.file_name() != "node_modules")
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.file_type().is_file()
+                    && e.path()
+                        .extension()
+                        .map(|ext| ext == "sol")
+                        .unwrap_or(false)
+            })
+            .map(|e| e.path().to_str().unwrap().to_string())
+            .collect()
+    }
+
     /// Phase 2: Resolve imports and build inheritance graph.
     fn phase_2_imports_and_inheritance(&mut self) -> EvmMetadataResult<()> {
         let resolver = InheritanceResolver::new(&self.contracts);
@@ -245,7 +345,9 @@ impl EvmSonar {
         let pb = Self::create_spinner();
         pb.set_message(format!("Access control [0/{}]", total_modifiers));
 
+        let resolver = AccessControlResolver::new(&self.contracts);
         let mut count = 0usize;
+        let mut guards = 0usize;
         for contract in &self.contracts {
             for modifier in &contract.modifiers {
                 count += 1;
@@ -253,12 +355,18 @@ impl EvmSonar {
                     "Access control [{}/{}]: {}.{}",
                     count, total_modifiers, contract.name, modifier.name
                 ));
+                if !resolver
+                    .modifier_checks(&contract.name, modifier, &[])
+                    .is_empty()
+                {
+                    guards += 1;
+                }
             }
         }
 
         pb.finish_with_message(format!(
-            "{} Access control: {} modifiers detected",
-            SPARKLE, total_modifiers
+            "{} Access control: {} modifiers detected, {} restrict the caller",
+            SPARKLE, total_modifiers, guards
         ));
         Ok(())
     }
@@ -270,6 +378,7 @@ impl EvmSonar {
         let pb = Self::create_spinner();
         pb.set_message(format!("Function dependencies [0/{}]", total_functions));
 
+        let call_resolver = CallResolver::new(&self.contracts);
         let mut all_deps: Vec<FunctionDependency> = Vec::new();
         let mut count = 0usize;
         let mut total_calls = 0usize;
@@ -282,13 +391,16 @@ impl EvmSonar {
                     count, total_functions, contract.name, function.name
                 ));
 
-                // Use AST-based call extraction on the function body
-                let callees = extract_calls_from_source(&function.body_source);
+                // AST-based call extraction, with `using for` calls resolved to their library
+                let callees = call_resolver.callee_names(&contract.name, function);
                 total_calls += callees.len();
 
-                let func_id = format!("{}_{}_{}", contract.file_path, contract.name, function.name);
                 all_deps.push(FunctionDependency {
-                    function_metadata_id: func_id,
+                    function_metadata_id: evm_function_id(
+                        &contract.name,
+                        &function.name,
+                        &function.params,
+                    ),
                     callees,
                 });
             }
@@ -310,9 +422,13 @@ impl EvmSonar {
         let pb = Self::create_spinner();
         pb.set_message("Building entry points...");
 
-        let mut metadata = EvmBatMetadata::from_contracts(self.contracts.clone());
+        let mut metadata =
+            EvmBatMetadata::from_contracts(self.contracts.clone(), self.file_definitions.clone());
         metadata.function_dependencies = deps;
-        metadata.save_metadata()?;
+        let interface_implementations = BatConfig::get_config()
+            .change_context(EvmMetadataError)?
+            .interface_implementations;
+        let ambiguous = metadata.resolve_interface_callees(&interface_implementations);
 
         pb.finish_with_message(format!(
             "{} Entry points: {} detected across {} contracts",
@@ -320,7 +436,251 @@ impl EvmSonar {
             metadata.entry_points.len(),
             metadata.contracts.iter().filter(|c| !c.external).count()
         ));
+        for duplicate in &metadata.duplicate_contracts {
+            println!(
+                "  {} {} is declared in {}, its functions and entry points share metadata IDs and resolve to the first one",
+                "⚠".bright_yellow(),
+                duplicate.name.yellow(),
+                duplicate.file_paths.join(", ")
+            );
+        }
+        for (interface, implemented_by) in &ambiguous {
+            println!(
+                "  {} {} has several implementations ({}), pin one in Bat.toml [interface_implementations] to follow its calls",
+                "⚠".bright_yellow(),
+                interface.yellow(),
+                implemented_by.join(", ")
+            );
+        }
 
         Ok(metadata)
     }
+
+    /// Phase 6: Report selectors shared by a proxy and its possible implementations,
+    /// or by diamond facets.
+    fn phase_6_selector_clashes(&self, metadata: &mut EvmBatMetadata) -> EvmMetadataResult<()> {
+        let pb = Self::create_spinner();
+        pb.set_message("Selector clashes...");
+
+        let proxies = self.proxy_contract_names();
+        let proxy_implementations = BatConfig::get_config()
+            .change_context(EvmMetadataError)?
+            .proxy_implementations;
+        let routes =
+            ProxyResolver::new(&self.contracts).selector_routes(&proxies, &proxy_implementations);
+        metadata.selector_clashes = metadata.find_selector_clashes(&routes);
+
+        let candidates = metadata
+            .selector_clashes
+            .iter()
+            .filter(|clash| clash.kind == SelectorClashKind::ProxyImplementationCandidate)
+            .count();
+        pb.finish_with_message(format!(
+            "{} Selector clashes: {} found, {} with candidate implementations ({} proxies checked)",
+            SPARKLE,
+            metadata.selector_clashes.len() - candidates,
+            candidates,
+            proxies.len()
+        ));
+        for clash in &metadata.selector_clashes {
+            println!(
+                "  {} {} {:?}: {}",
+                "⚠".bright_yellow(),
+                clash.selector.bright_cyan(),
+                clash.kind,
+                clash.functions.join(" <> ")
+            );
+        }
+        if candidates > 0 {
+            println!(
+                "  {} Candidate implementations are not referenced by their proxy, pin them in Bat.toml [proxy_implementations] to confirm",
+                "⚠".bright_yellow(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Phase 7: Classify proxies and upgradeable implementations, flagging unsafe
+    /// initializers and upgrade authorizations.
+    fn phase_7_proxy_patterns(&self, metadata: &mut EvmBatMetadata) {
+        let pb = Self::create_spinner();
+        pb.set_message("Proxy patterns...");
+
+        let resolver = ProxyResolver::new(&self.contracts);
+        let mut upgradeable = 0usize;
+        let mut findings: Vec<String> = Vec::new();
+        for contract in metadata.contracts.iter_mut().filter(|c| !c.external) {
+            pb.set_message(format!("Proxy patterns: {}", contract.name));
+            contract.proxy = resolver.resolve(&contract.name);
+            if contract.proxy.kind != EvmProxyKind::Plain || contract.proxy.initializable {
+                upgradeable += 1;
+            }
+            for finding in &contract.proxy.findings {
+                findings.push(format!(
+                    "{} {:?}: {}.{} (line {})",
+                    contract.name,
+                    finding.kind,
+                    finding.contract_name,
+                    finding.function,
+                    finding.line
+                ));
+            }
+        }
+
+        pb.finish_with_message(format!(
+            "{} Proxy patterns: {} proxies or upgradeable contracts, {} findings",
+            SPARKLE,
+            upgradeable,
+            findings.len()
+        ));
+        for finding in &findings {
+            println!("  {} {}", "⚠".bright_yellow(), finding);
+        }
+    }
+
+    /// Phase 8: Report state writes after external calls, unguarded value transfers and
+    /// cross-function reentrancy.
+    fn phase_8_reentrancy(&self, metadata: &EvmBatMetadata) -> EvmReentrancyReport {
+        let pb = Self::create_spinner();
+        pb.set_message("Reentrancy surface...");
+
+        let report = EvmReentrancyReport::new(&self.contracts, metadata);
+
+        pb.finish_with_message(format!(
+            "{} Reentrancy surface: {} writes after calls, {} unguarded value calls, {} shared variables",
+            SPARKLE,
+            report.writes_after_calls.len(),
+            report.unguarded_value_calls.len(),
+            report.cross_function.len()
+        ));
+        report
+    }
+
+    /// Phase 9: Run the detectors over in-scope functions; hits become finding candidates.
+    fn phase_9_detectors(&self, metadata: &mut EvmBatMetadata) {
+        let pb = Self::create_spinner();
+        pb.set_message("Detectors...");
+
+        metadata.detector_hits = EvmDetectorRunner::new(&self.contracts).run();
+
+        let mut counts: BTreeMap<EvmDetectorKind, usize> = BTreeMap::new();
+        for hit in &metadata.detector_hits {
+            *counts.entry(hit.kind).or_default() += 1;
+        }
+        pb.finish_with_message(format!(
+            "{} Detectors: {} finding candidates",
+            SPARKLE,
+            metadata.detector_hits.len()
+        ));
+        for (kind, count) in counts {
+            println!("  {} {:?}: {}", "⚠".bright_yellow(), kind, count);
+        }
+    }
+
+    /// Phase 10: Build the role and privilege graph from the entry point access control.
+    fn phase_10_privileges(&self, metadata: &mut EvmBatMetadata) {
+        let pb = Self::create_spinner();
+        pb.set_message("Privilege graph...");
+
+        metadata.privileges = EvmPrivilegeGraph::new(&self.contracts, metadata);
+
+        let restricted = metadata
+            .privileges
+            .roles
+            .iter()
+            .map(|role| role.entry_points.len())
+            .sum::<usize>();
+        pb.finish_with_message(format!(
+            "{} Privileges: {} roles restricting {} entry points",
+            SPARKLE,
+            metadata.privileges.roles.len(),
+            restricted
+        ));
+    }
+
+    /// Phase 11: Record the Foundry tests calling each entry point, directly or through
+    /// handlers. Test contracts named like an audited contract (mocks) are skipped.
+    fn phase_11_test_coverage(&mut self, metadata: &mut EvmBatMetadata) -> EvmMetadataResult<()> {
+        let pb = Self::create_spinner();
+        pb.set_message("Test coverage...");
+
+        let import_resolver =
+            ImportResolver::new(&self.project_root).change_context(EvmMetadataError)?;
+        let test_dir = import_resolver.get_test_dir().to_path_buf();
+        let mut contracts = self.contracts.clone();
+        for file_path in Self::collect_test_files(&test_dir) {
+            pb.set_message(format!("Test coverage: {}", file_path));
+            match parse_sol_file(&file_path) {
+                Ok(sol_file) => {
+                    for contract in sol_file.contracts {
+                        if !contracts.iter().any(|c| c.name == contract.name) {
+                            contracts.push(contract);
+                        }
+                    }
+                }
+                Err(e) => self.log_error(&format!("Failed to parse [TEST] {}: {:?}", file_path, e)),
+            }
+        }
+
+        let resolver = TestResolver::new(&contracts, &test_dir);
+        let test_functions = resolver.test_functions();
+        for (contract, function) in &test_functions {
+            let test_name = format!("{}.{}", contract.name, function.name);
+            for call in resolver.tested_calls(&contract.name, function) {
+                for index in metadata
+                    .entry_points_called_through(&call.target_type, call.function.as_deref())
+                {
+                    let tests = &mut metadata.entry_points[index].tests;
+                    if !tests.contains(&test_name) {
+                        tests.push(test_name.clone());
+                    }
+                }
+            }
+        }
+
+        let tested = metadata
+            .entry_points
+            .iter()
+            .filter(|ep| !ep.tests.is_empty())
+            .count();
+        pb.finish_with_message(format!(
+            "{} Test coverage: {} tests, {}/{} entry points tested",
+            SPARKLE,
+            test_functions.len(),
+            tested,
+            metadata.entry_points.len()
+        ));
+        Ok(())
+    }
+
+    /// In-scope contracts inheriting a known proxy base, or whose fallback delegatecalls.
+    fn proxy_contract_names(&self) -> Vec<String> {
+        let inheritance_resolver = InheritanceResolver::new(&self.contracts);
+        let call_resolver = CallResolver::new(&self.contracts);
+
+        self.contracts
+            .iter()
+            .filter(|c| !c.external && c.contract_type == EvmContractType::Contract)
+            .filter(|c| {
+                let linearization = inheritance_resolver.linearize(&c.name);
+                if linearization
+                    .iter()
+                    .any(|name| KNOWN_PROXY_BASES.contains(&name.as_str()))
+                {
+                    return true;
+                }
+                inheritance_resolver
+                    .get_all_functions(&c.name)
+                    .into_iter()
+                    .filter(|(_, f)| f.is_fallback)
+                    .any(|(_, f)| {
+                        call_resolver
+                            .resolve_external_calls(&c.name, f)
+                            .iter()
+                            .any(|call| call.kind == ExternalCallKind::DelegateCall)
+                    })
+            })
+            .map(|c| c.name.clone())
+            .collect()
+    }
 }
@@ -1,62 +1,257 @@
+use crate::batbelt::evm::metadata::bat_metadata::{
+    ContractMetadata, EntryPointMetadata, EvmBatMetadata, FunctionMetadata,
+};
+use crate::batbelt::evm::types::{EvmProxyFindingKind, EvmProxyKind, EvmValidationKind};
+
 /// Code-overhaul template for Solidity entry points.
 /// Adapted from the Solana template to reflect EVM-specific concepts.
 pub const SOLIDITY_CO_TEMPLATE: &str = r#"# State changes:
 # Notes:
 # Access control:
-# Parameters:
+# Contract:
 # Storage layout:
 # External calls:
 # Events emitted:
 # Validations:
+# Tests:
 # Miro frame url:
 "#;
 
 /// Generate a populated code-overhaul file for a Solidity entry point.
 pub fn generate_co_content(
-    _entry_point_name: &str,
-    access_control: &[String],
-    parameters: &[String],
-    storage_vars: &[String],
-    external_calls: &[String],
-    events: &[String],
-    validations: &[String],
+    evm_metadata: &EvmBatMetadata,
+    ep: &EntryPointMetadata,
+    contract: &ContractMetadata,
+    func: &FunctionMetadata,
 ) -> String {
+    let access_control_str = ep
+        .access_control
+        .iter()
+        .map(|ac| format!("{:?}", ac))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let modifiers_str = if ep.modifiers.is_empty() {
+        "None".to_string()
+    } else {
+        ep.modifiers.join(", ")
+    };
+
     let mut content = String::new();
 
-    content.push_str("# State changes:\n");
-    content.push_str("# Notes:\n");
+    content.push_str("# State changes:\n\n");
+    content.push_str("- `COMPLETE_WITH_THE_REST_OF_STATE_CHANGES`\n\n");
+
+    content.push_str("# Notes:\n\n");
+    content.push_str("- `COMPLETE_WITH_NOTES`\n\n");
 
-    content.push_str("# Access control:\n");
-    for ac in access_control {
-        content.push_str(&format!("- {}\n", ac));
+    content.push_str("# Access control:\n\n");
+    content.push_str(&format!("- {}\n", access_control_str));
+    content.push_str(&format!("- Modifiers: {}\n\n", modifiers_str));
+
+    content.push_str("# Contract:\n\n");
+    match evm_metadata
+        .get_contract_by_name(&func.contract_name)
+        .filter(|base| base.name != contract.name)
+    {
+        Some(base) => content.push_str(&format!(
+            "- {} (inherited from {} at {}:{})\n",
+            contract.name, base.name, base.file_path, func.line
+        )),
+        None => content.push_str(&format!(
+            "- {} ({}:{})\n",
+            contract.name, contract.file_path, func.line
+        )),
     }
 
-    content.push_str("# Parameters:\n");
-    for param in parameters {
-        content.push_str(&format!("- {}\n", param));
+    if let Some(selector) = &func.selector {
+        content.push_str(&format!("- Selector: `{}`\n", selector));
     }
+    content.push_str(&proxy_lines(contract));
+    content.push('\n');
+
+    content.push_str("# Storage layout:\n\n");
+    content.push_str(&storage_layout_section(evm_metadata, ep));
+
+    content.push_str("# External calls:\n\n");
+    content.push_str(&external_calls_section(ep));
+
+    content.push_str("# Events emitted:\n\n");
+    content.push_str(&events_emitted_section(ep));
+
+    content.push_str("# Validations:\n\n");
+    content.push_str(&validations_section(ep));
+
+    content.push_str("# Tests:\n\n");
+    content.push_str(&tests_section(ep));
+
+    content.push_str("# Miro frame url:\n\n");
+    content.push_str("`COMPLETE_WITH_MIRO_FRAME_URL`\n");
 
-    content.push_str("# Storage layout:\n");
-    for var in storage_vars {
-        content.push_str(&format!("- {}\n", var));
+    content
+}
+
+fn proxy_lines(contract: &ContractMetadata) -> String {
+    let proxy = &contract.proxy;
+    if proxy.kind == EvmProxyKind::Plain && !proxy.initializable {
+        return String::new();
     }
 
-    content.push_str("# External calls:\n");
-    for call in external_calls {
-        content.push_str(&format!("- {}\n", call));
+    let mut lines = format!("- Proxy pattern: {:?}", proxy.kind);
+    if proxy.initializable {
+        lines.push_str(" (initializable)");
+    }
+    if !proxy.eip1967_slots.is_empty() {
+        lines.push_str(&format!(
+            ", EIP-1967 slots: {}",
+            proxy.eip1967_slots.join(", ")
+        ));
+    }
+    lines.push('\n');
+    for finding in &proxy.findings {
+        let description = match finding.kind {
+            EvmProxyFindingKind::MissingDisableInitializers => {
+                "constructor does not call `_disableInitializers()`"
+            }
+            EvmProxyFindingKind::UnprotectedInitialize => {
+                "callable by anyone, without an `initializer` guard"
+            }
+            EvmProxyFindingKind::UnprotectedAuthorizeUpgrade => "lets any caller upgrade",
+        };
+        lines.push_str(&format!(
+            "- ⚠ `{}.{}` at line {}: {}\n",
+            finding.contract_name, finding.function, finding.line, description
+        ));
     }
+    lines
+}
 
-    content.push_str("# Events emitted:\n");
-    for event in events {
-        content.push_str(&format!("- {}\n", event));
+fn storage_layout_section(evm_metadata: &EvmBatMetadata, ep: &EntryPointMetadata) -> String {
+    if ep.storage_reads.is_empty() && ep.storage_writes.is_empty() {
+        return "- No storage accessed\n\n".to_string();
     }
 
-    content.push_str("# Validations:\n");
-    for val in validations {
-        content.push_str(&format!("- {}\n", val));
+    let layout = evm_metadata
+        .get_contract_by_name(&ep.contract_name)
+        .map(|c| c.storage_layout.as_slice())
+        .unwrap_or_default();
+    let chain = evm_metadata.get_inheritance_chain(&ep.contract_name);
+    let describe = |name: &String| -> String {
+        if let Some(slot) = layout.iter().find(|s| &s.name == name) {
+            return format!(
+                "`{}` ({}, {}, slot {} offset {})",
+                name, slot.type_name, slot.contract_name, slot.slot, slot.offset
+            );
+        }
+        chain
+            .iter()
+            .find_map(|c| {
+                c.state_variables
+                    .iter()
+                    .find(|v| &v.name == name)
+                    .map(|v| format!("`{}` ({}, {})", name, v.type_name, c.name))
+            })
+            .unwrap_or_else(|| format!("`{}`", name))
+    };
+
+    let mut section = String::new();
+    for name in &ep.storage_writes {
+        section.push_str(&format!("- Writes: {}\n", describe(name)));
     }
+    for name in ep
+        .storage_reads
+        .iter()
+        .filter(|name| !ep.storage_writes.contains(name))
+    {
+        section.push_str(&format!("- Reads: {}\n", describe(name)));
+    }
+    section.push('\n');
+    section
+}
 
-    content.push_str("# Miro frame url:\n");
+fn external_calls_section(ep: &EntryPointMetadata) -> String {
+    if ep.external_calls.is_empty() {
+        return "- No external calls\n\n".to_string();
+    }
 
-    content
+    let mut section = String::new();
+    for call in &ep.external_calls {
+        let value = if call.forwards_value {
+            ", forwards value"
+        } else {
+            ""
+        };
+        section.push_str(&format!(
+            "- `{}.{}` ({:?}{}) in `{}` at line {}\n",
+            call.target_type, call.function, call.kind, value, call.caller, call.line
+        ));
+    }
+    section.push('\n');
+    section
+}
+
+fn validations_section(ep: &EntryPointMetadata) -> String {
+    if ep.validations.is_empty() {
+        return "- No validations detected\n\n".to_string();
+    }
+
+    let revert = |error: &Option<String>| match error.as_deref() {
+        Some(reason) if reason.starts_with('"') => format!("revert({})", reason),
+        Some(error) => format!("revert {}", error),
+        None => "revert()".to_string(),
+    };
+    let mut section = String::new();
+    for validation in &ep.validations {
+        let check = match validation.kind {
+            EvmValidationKind::Require => match &validation.error {
+                Some(error) => format!("require({}, {})", validation.condition, error),
+                None => format!("require({})", validation.condition),
+            },
+            EvmValidationKind::Assert => format!("assert({})", validation.condition),
+            EvmValidationKind::IfRevert => format!(
+                "if ({}) {}",
+                validation.condition,
+                revert(&validation.error)
+            ),
+            EvmValidationKind::Revert => revert(&validation.error),
+        };
+        section.push_str(&format!(
+            "- `{}` in `{}` at line {}\n",
+            check, validation.caller, validation.line
+        ));
+    }
+    section.push('\n');
+    section
+}
+
+fn tests_section(ep: &EntryPointMetadata) -> String {
+    if ep.tests.is_empty() {
+        return "- No tests call this entry point\n\n".to_string();
+    }
+
+    let mut section = String::new();
+    for test in &ep.tests {
+        section.push_str(&format!("- `{}`\n", test));
+    }
+    section.push('\n');
+    section
+}
+
+fn events_emitted_section(ep: &EntryPointMetadata) -> String {
+    if ep.events_emitted.is_empty() {
+        return "- No events emitted\n\n".to_string();
+    }
+
+    let mut section = String::new();
+    for event in &ep.events_emitted {
+        let declared_in = event
+            .declared_in
+            .as_deref()
+            .unwrap_or("declaration not found");
+        section.push_str(&format!(
+            "- `{}` ({}) in `{}` at line {}\n",
+            event.name, declared_in, event.caller, event.line
+        ));
+    }
+    section.push('\n');
+    section
 }
@@ -0,0 +1,724 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use inflector::Inflector;
+use lazy_regex::regex;
+
+use crate::batbelt::evm::metadata::bat_metadata::{
+    ContractMetadata, EntryPointMetadata, EvmBatMetadata, FunctionMetadata,
+};
+use crate::batbelt::evm::metadata::privilege_graph::DEFAULT_ADMIN_ROLE;
+use crate::batbelt::evm::types::{
+    canonical_type_name, AccessControlType, EvmContractType, EvmDetectorHit, EvmMutability,
+    EvmParam, EvmVisibility,
+};
+
+/// Actor deploying the contract, owner of `Ownable` contracts and holder of
+/// `DEFAULT_ADMIN_ROLE`.
+const DEPLOYER: &str = "admin";
+
+/// Actor calling unrestricted entry points.
+const USER: &str = "user";
+
+/// Pragma of the scaffolds when the one of the audited file cannot be read.
+const DEFAULT_PRAGMA: &str = "pragma solidity ^0.8.0;";
+
+/// Address constructor params receiving the deployer: `initialOwner`, `_admin`...
+const PRIVILEGED_PARAM_NAMES: &[&str] = &["owner", "admin", "governance", "governor"];
+
+/// Builds Foundry test scaffolds for a deployable contract: a PoC calling one of its entry
+/// points as the caller its access control expects, or an invariant test harness whose
+/// handler exposes every state-changing entry point.
+pub struct FoundryTestTemplate<'a> {
+    metadata: &'a EvmBatMetadata,
+    contract: &'a ContractMetadata,
+    /// Directory the test file is written to, import paths are relative to it
+    test_dir: PathBuf,
+    /// Imported symbol per source file
+    imports: BTreeMap<String, Vec<String>>,
+    actors: Vec<String>,
+    /// `grantRole` calls made by the deployer in `setUp`
+    role_grants: Vec<String>,
+}
+
+impl<'a> FoundryTestTemplate<'a> {
+    pub fn new(
+        metadata: &'a EvmBatMetadata,
+        contract: &'a ContractMetadata,
+        test_dir: &Path,
+    ) -> Self {
+        let mut template = Self {
+            metadata,
+            contract,
+            test_dir: test_dir.to_path_buf(),
+            imports: BTreeMap::new(),
+            actors: vec![DEPLOYER.to_string()],
+            role_grants: vec![],
+        };
+        template.import("Test", "forge-std/Test.sol");
+        template.import(&contract.name, &contract.file_path);
+        template
+    }
+
+    /// `VaultWithdrawPoC`, or `VaultWithdrawTxOriginAuthPoC` for a finding candidate.
+    pub fn poc_contract_name(
+        ep: &EntryPointMetadata,
+        func: &FunctionMetadata,
+        finding: Option<&EvmDetectorHit>,
+    ) -> String {
+        let finding = finding
+            .map(|hit| format!("{:?}", hit.kind))
+            .unwrap_or_default();
+        format!(
+            "{}{}{}PoC",
+            ep.contract_name,
+            func.name.to_pascal_case(),
+            finding
+        )
+    }
+
+    pub fn invariant_contract_name(contract_name: &str) -> String {
+        format!("{}InvariantTest", contract_name)
+    }
+
+    /// Test contract calling `ep` with default arguments as the caller its access control
+    /// expects. A finding candidate is described in the contract doc comment.
+    pub fn poc(mut self, ep: &EntryPointMetadata, finding: Option<&EvmDetectorHit>) -> String {
+        let Some(func) = self.metadata.get_function_by_id(&ep.function_metadata_id) else {
+            return String::new();
+        };
+        let test_contract = Self::poc_contract_name(ep, func, finding);
+        let caller = self.caller(ep);
+        let target = self.target_var();
+
+        let mut body: Vec<String> = func
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| self.local_declaration(param, index))
+            .collect();
+        let arguments = argument_names(&func.params);
+        let value = if func.mutability == EvmMutability::Payable {
+            body.push(format!("vm.deal({}, 1 ether);", caller));
+            "{value: 1 ether}"
+        } else {
+            ""
+        };
+        body.push(String::new());
+        body.push(format!("vm.prank({});", caller));
+        body.extend(invocation(&target, func, value, &arguments));
+        body.push("// COMPLETE_WITH_ASSERTIONS".to_string());
+
+        let mut doc = format!("/// PoC for `{}`, scaffolded by bat-cli", ep.name);
+        if let Some(hit) = finding {
+            doc.push_str(&format!(
+                "\n/// Finding candidate {:?} at {}:{}: `{}`",
+                hit.kind, hit.file_path, hit.line, hit.snippet
+            ));
+        }
+        let set_up = self.set_up(&[]);
+
+        let mut content = self.header();
+        content.push_str(&format!(
+            "{}\ncontract {} is Test {{\n{}\n{}\n    function test_{}() public {{\n{}    }}\n}}\n",
+            doc,
+            test_contract,
+            self.state_variables(&[]),
+            set_up,
+            func.name,
+            indent(&body, 2)
+        ));
+        content
+    }
+
+    /// Handler exposing every state-changing entry point of the contract, pranking the
+    /// caller each one expects, and an invariant test fuzzing it.
+    pub fn invariant_harness(mut self) -> String {
+        let contract_name = self.contract.name.clone();
+        let handler = format!("{}Handler", contract_name);
+        let target = self.target_var();
+        let entry_points: Vec<&EntryPointMetadata> = self
+            .metadata
+            .entry_points
+            .iter()
+            .filter(|ep| ep.contract_name == contract_name)
+            .collect();
+
+        let mut handler_functions: Vec<String> = Vec::new();
+        for ep in entry_points {
+            let Some(func) = self.metadata.get_function_by_id(&ep.function_metadata_id) else {
+                continue;
+            };
+            if matches!(func.mutability, EvmMutability::View | EvmMutability::Pure) {
+                continue;
+            }
+            let caller = self.caller(ep);
+            let mut params: Vec<String> = func
+                .params
+                .iter()
+                .enumerate()
+                .map(|(index, param)| {
+                    format!(
+                        "{} {}",
+                        self.parameter_type(param),
+                        param_name(param, index)
+                    )
+                })
+                .collect();
+            let mut body: Vec<String> = Vec::new();
+            let value = if func.mutability == EvmMutability::Payable {
+                params.push("uint256 msgValue".to_string());
+                body.push("msgValue = bound(msgValue, 0, 100 ether);".to_string());
+                body.push(format!("vm.deal({}, msgValue);", caller));
+                "{value: msgValue}"
+            } else {
+                ""
+            };
+            body.push(format!("vm.prank({});", caller));
+            body.extend(invocation(
+                &target,
+                func,
+                value,
+                &argument_names(&func.params),
+            ));
+            handler_functions.push(format!(
+                "    function {}({}) external {{\n{}    }}\n",
+                handler_function_name(func),
+                params.join(", "),
+                indent(&body, 2)
+            ));
+        }
+
+        let handler_var = format!("    {} internal handler;\n", handler);
+        let set_up = self.set_up(&[
+            format!("handler = new {}({});", handler, target),
+            "targetContract(address(handler));".to_string(),
+        ]);
+
+        let mut content = self.header();
+        content.push_str(&format!(
+            "/// Entry points of `{contract}` called by the invariant fuzzer, scaffolded by bat-cli\ncontract {handler} is Test {{\n{vars}\n    constructor({contract} _{target}) {{\n        {target} = _{target};\n    }}\n\n{functions}}}\n\n",
+            contract = contract_name,
+            handler = handler,
+            vars = self.state_variables(&[]),
+            target = target,
+            functions = handler_functions.join("\n"),
+        ));
+        content.push_str(&format!(
+            "contract {} is Test {{\n{}\n{}\n    function invariant_todo() public view {{\n        // COMPLETE_WITH_INVARIANT\n    }}\n}}\n",
+            Self::invariant_contract_name(&contract_name),
+            self.state_variables(&[handler_var]),
+            set_up
+        ));
+        content
+    }
+
+    fn header(&self) -> String {
+        let pragma = fs::read_to_string(&self.contract.file_path)
+            .ok()
+            .and_then(|source| {
+                regex!(r"pragma\s+solidity\s+[^;]+;")
+                    .find(&source)
+                    .map(|m| m.as_str().to_string())
+            })
+            .unwrap_or_else(|| DEFAULT_PRAGMA.to_string());
+        let mut header = format!("// SPDX-License-Identifier: UNLICENSED\n{}\n\n", pragma);
+        // Dependencies first, then the audited sources
+        let mut imports: Vec<(&String, &Vec<String>)> = self.imports.iter().collect();
+        imports.sort_by_key(|(path, _)| (path.starts_with('.'), path.as_str()));
+        for (path, symbols) in imports {
+            header.push_str(&format!(
+                "import {{{}}} from \"{}\";\n",
+                symbols.join(", "),
+                path
+            ));
+        }
+        header.push('\n');
+        header
+    }
+
+    fn state_variables(&self, extra: &[String]) -> String {
+        let mut vars = format!(
+            "    {} internal {};\n",
+            self.contract.name,
+            self.target_var()
+        );
+        for var in extra {
+            vars.push_str(var);
+        }
+        for actor in &self.actors {
+            vars.push_str(&format!(
+                "    address internal {} = makeAddr(\"{}\");\n",
+                actor, actor
+            ));
+        }
+        vars
+    }
+
+    /// Deploys the contract as the deployer, grants the roles the callers need, then runs
+    /// `extra`.
+    fn set_up(&mut self, extra: &[String]) -> String {
+        let constructor_params: Vec<EvmParam> = self
+            .contract
+            .functions
+            .iter()
+            .find(|f| f.is_constructor)
+            .map(|f| f.params.clone())
+            .unwrap_or_default();
+
+        let mut body: Vec<String> = Vec::new();
+        let mut arguments: Vec<String> = Vec::new();
+        for (index, param) in constructor_params.iter().enumerate() {
+            let name = param_name(param, index);
+            let privileged = canonical_type_name(&param.type_name) == "address"
+                && PRIVILEGED_PARAM_NAMES
+                    .iter()
+                    .any(|n| name.to_lowercase().contains(n));
+            if privileged {
+                arguments.push(DEPLOYER.to_string());
+            } else {
+                body.push(self.local_declaration(param, index));
+                arguments.push(name);
+            }
+        }
+        body.push(format!("vm.startPrank({});", DEPLOYER));
+        body.push(format!(
+            "{} = new {}({});",
+            self.target_var(),
+            self.contract.name,
+            arguments.join(", ")
+        ));
+        body.extend(self.role_grants.iter().cloned());
+        body.push("vm.stopPrank();".to_string());
+        body.extend(extra.iter().cloned());
+        if self.contract.proxy.initializable {
+            body.push(
+                "// COMPLETE_WITH_PROXY_DEPLOYMENT: the contract is initializable".to_string(),
+            );
+        }
+
+        format!(
+            "    function setUp() public {{\n{}    }}\n",
+            indent(&body, 2)
+        )
+    }
+
+    fn target_var(&self) -> String {
+        self.contract.name.to_camel_case()
+    }
+
+    /// Actor calling `ep`, registering the actors and role grants it needs: `user` unless
+    /// an `onlyOwner`, role or named modifier restricts the caller.
+    fn caller(&mut self, ep: &EntryPointMetadata) -> String {
+        let access_control = ep
+            .access_control
+            .iter()
+            .find(|ac| !matches!(ac, AccessControlType::None));
+        let actor = match access_control {
+            Some(AccessControlType::OnlyOwner) => DEPLOYER.to_string(),
+            Some(AccessControlType::RoleBased { role }) => {
+                if role == DEFAULT_ADMIN_ROLE || role.starts_with("getRoleAdmin(") {
+                    DEPLOYER.to_string()
+                } else {
+                    let actor = actor_name(role.trim_end_matches("_ROLE"));
+                    self.grant_role(role, &actor);
+                    actor
+                }
+            }
+            Some(AccessControlType::CustomModifier { name }) => {
+                actor_name(name.trim_start_matches("only"))
+            }
+            // Inline `msg.sender` checks and authority calls don't name an actor, the
+            // auditor picks the caller
+            Some(AccessControlType::RequireMsgSender { .. })
+            | Some(AccessControlType::Authority { .. }) => USER.to_string(),
+            None | Some(AccessControlType::None) => USER.to_string(),
+        };
+        if !self.actors.contains(&actor) {
+            self.actors.push(actor.clone());
+        }
+        actor
+    }
+
+    fn grant_role(&mut self, role: &str, actor: &str) {
+        let can_grant = self
+            .metadata
+            .find_functions_in_chain(&self.contract.name, "grantRole")
+            .is_some();
+        let grant = if !can_grant {
+            format!("// COMPLETE_WITH_ROLE_GRANT: give `{}` the {}", actor, role)
+        } else if self.has_public_state_variable(role) {
+            format!(
+                "{target}.grantRole({target}.{role}(), {actor});",
+                target = self.target_var(),
+                role = role,
+                actor = actor
+            )
+        } else {
+            format!(
+                "{}.grantRole(keccak256(\"{}\"), {});",
+                self.target_var(),
+                role,
+                actor
+            )
+        };
+        if !self.role_grants.contains(&grant) {
+            self.role_grants.push(grant);
+        }
+    }
+
+    fn has_public_state_variable(&self, name: &str) -> bool {
+        self.metadata
+            .get_inheritance_chain(&self.contract.name)
+            .iter()
+            .flat_map(|c| c.state_variables.iter())
+            .any(|v| v.name == name && v.visibility == EvmVisibility::Public)
+    }
+
+    /// `uint256 amount = 0;`, `address to = makeAddr("to");`, `Vault.Order memory order;`...
+    fn local_declaration(&mut self, param: &EvmParam, index: usize) -> String {
+        let name = param_name(param, index);
+        let declared_type = self.parameter_type(param);
+        match self.default_value(&param.type_name, &name) {
+            Some(value) => format!("{} {} = {};", declared_type, name, value),
+            None => format!("{} {};", declared_type, name),
+        }
+    }
+
+    /// Type of `param` as written in the test, with the data location of reference types.
+    fn parameter_type(&mut self, param: &EvmParam) -> String {
+        let type_name = self.qualified_type(&param.type_name);
+        let is_struct = self
+            .metadata
+            .find_struct(&self.contract.name, &param.type_name)
+            .is_some();
+        if type_name.ends_with(']') || type_name == "string" || type_name == "bytes" || is_struct {
+            format!("{} memory", type_name)
+        } else {
+            type_name
+        }
+    }
+
+    /// Initial value of a local of `type_name`, `None` when its zero value is enough.
+    fn default_value(&mut self, type_name: &str, name: &str) -> Option<String> {
+        let type_name = self.qualified_type(type_name);
+        if type_name.ends_with("[]") {
+            return Some(format!("new {}(0)", type_name));
+        }
+        match type_name.as_str() {
+            "address" => Some(format!("makeAddr(\"{}\")", name)),
+            "address payable" => Some(format!("payable(makeAddr(\"{}\"))", name)),
+            "string" | "bytes" => Some("\"\"".to_string()),
+            _ => {
+                let contract = self
+                    .metadata
+                    .get_contract_by_name(&type_name)
+                    .filter(|c| c.contract_type != EvmContractType::Library);
+                contract.map(|c| format!("{}(makeAddr(\"{}\"))", c.name, name))
+            }
+        }
+    }
+
+    /// `type_name` as reachable from the test file, importing its declaring contract or file.
+    fn qualified_type(&mut self, type_name: &str) -> String {
+        if type_name.split_whitespace().eq(["address", "payable"]) {
+            return "address payable".to_string();
+        }
+        let type_name = canonical_type_name(type_name);
+        let (element, suffix) = match type_name.find('[') {
+            Some(index) => type_name.split_at(index),
+            None => (type_name.as_str(), ""),
+        };
+        if let Some((scope, _)) = element.split_once('.') {
+            if let Some(contract) = self.metadata.get_contract_by_name(scope) {
+                let file_path = contract.file_path.clone();
+                self.import(scope, &file_path);
+            }
+            return type_name.clone();
+        }
+        if let Some(contract) = self.metadata.get_contract_by_name(element) {
+            let file_path = contract.file_path.clone();
+            self.import(element, &file_path);
+            return type_name.clone();
+        }
+
+        let declared_in = self
+            .metadata
+            .get_inheritance_chain(&self.contract.name)
+            .into_iter()
+            .chain(self.metadata.contracts.iter())
+            .find(|c| {
+                c.structs.iter().any(|s| s.name == element)
+                    || c.enums.iter().any(|e| e.name == element)
+                    || c.udvts.iter().any(|u| u.name == element)
+            })
+            .map(|c| (c.name.clone(), c.file_path.clone()));
+        if let Some((contract_name, file_path)) = declared_in {
+            self.import(&contract_name, &file_path);
+            return format!("{}.{}{}", contract_name, element, suffix);
+        }
+
+        let file_path = self
+            .metadata
+            .file_definitions
+            .iter()
+            .find(|d| {
+                d.structs.iter().any(|s| s.name == element)
+                    || d.enums.iter().any(|e| e.name == element)
+                    || d.udvts.iter().any(|u| u.name == element)
+            })
+            .map(|d| d.file_path.clone());
+        if let Some(file_path) = file_path {
+            self.import(element, &file_path);
+        }
+        type_name.clone()
+    }
+
+    fn import(&mut self, symbol: &str, file_path: &str) {
+        let path = if file_path.starts_with("forge-std/") {
+            file_path.to_string()
+        } else {
+            relative_import_path(&self.test_dir, Path::new(file_path))
+        };
+        let symbols = self.imports.entry(path).or_default();
+        if !symbols.iter().any(|s| s == symbol) {
+            symbols.push(symbol.to_string());
+        }
+    }
+}
+
+fn param_name(param: &EvmParam, index: usize) -> String {
+    if param.name.is_empty() {
+        format!("arg{}", index)
+    } else {
+        param.name.clone()
+    }
+}
+
+fn argument_names(params: &[EvmParam]) -> String {
+    params
+        .iter()
+        .enumerate()
+        .map(|(index, param)| param_name(param, index))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Call of `func` on `target`: `vault.deposit{value: 1 ether}(receiver);`, or a low-level
+/// call for `receive` and `fallback`, which can't be called by name.
+fn invocation(target: &str, func: &FunctionMetadata, value: &str, arguments: &str) -> Vec<String> {
+    if !func.is_receive && !func.is_fallback {
+        return vec![format!("{}.{}{}({});", target, func.name, value, arguments)];
+    }
+    // Calldata shorter than a selector never matches a function and skips `receive`
+    let calldata = if func.is_receive { "\"\"" } else { "hex\"01\"" };
+    vec![
+        format!(
+            "(bool success, ) = address({}).call{}({});",
+            target, value, calldata
+        ),
+        "require(success);".to_string(),
+    ]
+}
+
+/// Name of the handler function calling `func`, `receive` and `fallback` are reserved.
+fn handler_function_name(func: &FunctionMetadata) -> String {
+    if func.is_receive {
+        "receiveEther".to_string()
+    } else if func.is_fallback {
+        "callFallback".to_string()
+    } else {
+        func.name.clone()
+    }
+}
+
+/// camelCase identifier for an actor label: `MINTER` -> `minter`, `Keeper` -> `keeper`.
+fn actor_name(label: &str) -> String {
+    let name = label
+        .chars()
+        .filter(|ch| ch.is_alphanumeric() || *ch == '_')
+        .collect::<String>()
+        .to_camel_case();
+    if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+        "caller".to_string()
+    } else {
+        name
+    }
+}
+
+fn indent(lines: &[String], level: usize) -> String {
+    let prefix = "    ".repeat(level);
+    lines
+        .iter()
+        .map(|line| {
+            if line.is_empty() {
+                "\n".to_string()
+            } else {
+                format!("{}{}\n", prefix, line)
+            }
+        })
+        .collect()
+}
+
+/// Import path of `file_path` from a test file in `test_dir`, both relative to the same
+/// directory: `../src/Vault.sol`.
+fn relative_import_path(test_dir: &Path, file_path: &Path) -> String {
+    let normal = |path: &Path| -> Vec<String> {
+        path.components()
+            .filter(|c| !matches!(c, Component::CurDir))
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect()
+    };
+    let from = normal(test_dir);
+    let to = normal(file_path);
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    if parts.is_empty() {
+        parts.push(".".to_string());
+    }
+    parts.extend(to[common..].iter().cloned());
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batbelt::evm::parser::evm_file_parser::parse_sol_file;
+
+    const VAULT: &str = r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+interface IERC20 {
+    function transfer(address to, uint256 amount) external returns (bool);
+}
+
+contract Vault {
+    struct Order {
+        uint256 amount;
+        address owner;
+    }
+
+    bytes32 public constant MINTER_ROLE = keccak256("MINTER_ROLE");
+    address public owner;
+    IERC20 public token;
+
+    modifier onlyOwner() {
+        require(msg.sender == owner, "not owner");
+        _;
+    }
+
+    modifier onlyRole(bytes32 role) {
+        require(hasRole(role, msg.sender));
+        _;
+    }
+
+    constructor(IERC20 _token, address initialOwner) {
+        token = _token;
+        owner = initialOwner;
+    }
+
+    function hasRole(bytes32 role, address account) public view returns (bool) {}
+
+    function grantRole(bytes32 role, address account) external onlyOwner {}
+
+    function deposit(address receiver) external payable {}
+
+    function mint(Order memory order, uint256[] calldata ids) external onlyRole(MINTER_ROLE) {}
+
+    function setOwner(address newOwner) external onlyOwner {
+        owner = newOwner;
+    }
+
+    function sweep() external {
+        require(msg.sender == treasury, "not treasury");
+    }
+
+    address public treasury;
+
+    receive() external payable {}
+}
+"#;
+
+    #[test]
+    fn test_foundry_scaffolds() {
+        let root =
+            std::env::temp_dir().join(format!("bat-foundry-template-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        let vault_path = root.join("src/Vault.sol");
+        fs::write(&vault_path, VAULT).unwrap();
+        let contracts = parse_sol_file(vault_path.to_str().unwrap())
+            .unwrap()
+            .contracts;
+        let metadata = EvmBatMetadata::from_contracts(contracts, vec![]);
+        let contract = metadata.get_contract_by_name("Vault").unwrap();
+        let test_dir = root.join("test");
+        let entry_point = |name: &str| metadata.get_entry_point_by_name(name).unwrap();
+
+        let poc = FoundryTestTemplate::new(&metadata, contract, &test_dir)
+            .poc(entry_point("Vault.mint(Order,uint256[])"), None);
+        assert_eq!(
+            poc,
+            r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {Vault, IERC20} from "../src/Vault.sol";
+
+/// PoC for `Vault.mint(Order,uint256[])`, scaffolded by bat-cli
+contract VaultMintPoC is Test {
+    Vault internal vault;
+    address internal admin = makeAddr("admin");
+    address internal minter = makeAddr("minter");
+
+    function setUp() public {
+        IERC20 _token = IERC20(makeAddr("_token"));
+        vm.startPrank(admin);
+        vault = new Vault(_token, admin);
+        vault.grantRole(vault.MINTER_ROLE(), minter);
+        vm.stopPrank();
+    }
+
+    function test_mint() public {
+        Vault.Order memory order;
+        uint256[] memory ids = new uint256[](0);
+
+        vm.prank(minter);
+        vault.mint(order, ids);
+        // COMPLETE_WITH_ASSERTIONS
+    }
+}
+"#
+        );
+
+        let poc = FoundryTestTemplate::new(&metadata, contract, &test_dir)
+            .poc(entry_point("Vault.receive()"), None);
+        assert!(poc.contains(
+            "        vm.deal(user, 1 ether);\n\n        vm.prank(user);\n        (bool success, ) = address(vault).call{value: 1 ether}(\"\");\n        require(success);\n"
+        ));
+
+        let harness = FoundryTestTemplate::new(&metadata, contract, &test_dir).invariant_harness();
+        assert!(harness.contains(
+            "    function deposit(address receiver, uint256 msgValue) external {\n        msgValue = bound(msgValue, 0, 100 ether);\n        vm.deal(user, msgValue);\n        vm.prank(user);\n        vault.deposit{value: msgValue}(receiver);\n    }\n"
+        ));
+        assert!(harness.contains(
+            "    function setOwner(address newOwner) external {\n        vm.prank(admin);\n        vault.setOwner(newOwner);\n    }\n"
+        ));
+        assert!(harness.contains(
+            "    function sweep() external {\n        vm.prank(user);\n        vault.sweep();\n    }\n"
+        ));
+        assert!(harness.contains(
+            "    function receiveEther(uint256 msgValue) external {\n        msgValue = bound(msgValue, 0, 100 ether);\n        vm.deal(user, msgValue);\n        vm.prank(user);\n        (bool success, ) = address(vault).call{value: msgValue}(\"\");\n        require(success);\n    }\n"
+        ));
+        assert!(!harness.contains("function hasRole("));
+        assert!(harness.contains(
+            "        vm.stopPrank();\n        handler = new VaultHandler(vault);\n        targetContract(address(handler));\n"
+        ));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
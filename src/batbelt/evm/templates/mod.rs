@@ -1 +1,2 @@
 pub mod code_overhaul_template;
+pub mod foundry_test_template;
@@ -38,10 +38,262 @@ pub enum AccessControlType {
     RequireMsgSender { compared_to: String },
     /// Custom modifier with access control semantics
     CustomModifier { name: String },
+    /// Check delegated to an authority: `isAuthorized(msg.sender, msg.sig)` (solmate `Auth`,
+    /// ds-auth) or any boolean call on `msg.sender`
+    Authority { check: String },
     /// No access control detected
     None,
 }
 
+/// Kinds of calls that leave the executing contract (trust boundaries).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExternalCallKind {
+    /// Call through an interface- or contract-typed value: `IERC20(token).transfer(...)`
+    HighLevel,
+    /// `address.call(...)`
+    Call,
+    /// `address.delegatecall(...)`
+    DelegateCall,
+    /// `address.staticcall(...)`
+    StaticCall,
+    /// `payable(addr).transfer(amount)`
+    Transfer,
+    /// `payable(addr).send(amount)`
+    Send,
+    /// Call to a library function: `SafeERC20.safeTransfer(...)`
+    Library,
+}
+
+/// An external call made by an entry point or one of its internal callees.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmExternalCall {
+    pub kind: ExternalCallKind,
+    /// Type of the call target (`IERC20`, `address`, library name...)
+    pub target_type: String,
+    /// Called member (`transfer`, `call`...)
+    pub function: String,
+    /// true if ETH is forwarded (`{value: ...}`, `transfer`, `send`)
+    pub forwards_value: bool,
+    /// Function (or modifier) containing the call
+    pub caller: String,
+    pub line: usize,
+}
+
+/// A state variable assignment reached by an entry point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmStateWrite {
+    pub variable: String,
+    /// Function (or modifier) containing the assignment
+    pub caller: String,
+    pub line: usize,
+}
+
+/// An `emit` statement reached by an entry point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmEmittedEvent {
+    pub name: String,
+    /// Contract in the inheritance chain declaring the `EvmEvent`, if found
+    pub declared_in: Option<String>,
+    /// Function (or modifier) containing the `emit`
+    pub caller: String,
+    pub line: usize,
+}
+
+/// How a validation aborts execution.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EvmValidationKind {
+    /// `require(condition, "message")` or `require(condition, CustomError())`
+    Require,
+    /// `assert(condition)`
+    Assert,
+    /// `if (condition) revert ...;`, reverting when the condition holds
+    IfRevert,
+    /// Unconditional `revert CustomError()` or `revert("message")`
+    Revert,
+}
+
+/// A check reached by an entry point that reverts the transaction when it fails.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmValidation {
+    pub kind: EvmValidationKind,
+    /// Condition as written; for `IfRevert` the `if` condition, empty for `Revert`
+    pub condition: String,
+    /// Revert message or custom error (`Unauthorized(msg.sender)`), if any
+    pub error: Option<String>,
+    /// Function (or modifier) containing the check
+    pub caller: String,
+    pub line: usize,
+}
+
+/// Proxy / upgradeability pattern of a contract.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum EvmProxyKind {
+    /// `TransparentUpgradeableProxy`, or a proxy using the EIP-1967 admin slot
+    Transparent,
+    /// Implementation upgraded through itself (`UUPSUpgradeable`, `_authorizeUpgrade`),
+    /// or the `ERC1967Proxy` in front of it
+    Uups,
+    /// `BeaconProxy`, or a proxy using the EIP-1967 beacon slot
+    Beacon,
+    /// EIP-2535 diamond dispatching selectors to facets
+    Diamond,
+    /// Delegates to an implementation without following a known pattern
+    Custom,
+    /// Not a proxy nor an upgradeable implementation
+    #[default]
+    Plain,
+}
+
+/// Upgradeability issue found while classifying a proxy or implementation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EvmProxyFindingKind {
+    /// Initializable implementation whose constructor does not call `_disableInitializers()`
+    MissingDisableInitializers,
+    /// `initialize` callable by anyone, more than once
+    UnprotectedInitialize,
+    /// `_authorizeUpgrade` lets any caller upgrade the implementation
+    UnprotectedAuthorizeUpgrade,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmProxyFinding {
+    pub kind: EvmProxyFindingKind,
+    /// Function the finding points to (`constructor` if it is missing)
+    pub function: String,
+    /// Contract defining the function
+    pub contract_name: String,
+    pub line: usize,
+}
+
+/// Proxy classification of a contract, see `ProxyResolver`.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EvmProxyInfo {
+    pub kind: EvmProxyKind,
+    /// Inherits `Initializable` or uses the `initializer` modifiers
+    pub initializable: bool,
+    /// EIP-1967 slots (`implementation`, `admin`, `beacon`) declared or used by the contract
+    pub eip1967_slots: Vec<String>,
+    pub findings: Vec<EvmProxyFinding>,
+}
+
+/// Yul operation of an inline `assembly` block that the Solidity-level analysis cannot see.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EvmAssemblyOpKind {
+    /// `sload(slot)`
+    Sload,
+    /// `sstore(slot, value)`
+    Sstore,
+    /// `call(gas, target, value, ...)`
+    Call,
+    /// `delegatecall(gas, target, ...)`
+    DelegateCall,
+    /// `staticcall(gas, target, ...)`
+    StaticCall,
+    /// `mstore` to the free-memory pointer (`0x40`) or to memory it points to
+    FreeMemoryMstore,
+}
+
+/// An operation found in the inline assembly of an `EvmFunction`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmAssemblyOp {
+    pub kind: EvmAssemblyOpKind,
+    /// Arguments as written, whitespace collapsed
+    pub arguments: Vec<String>,
+    pub line: usize,
+}
+
+impl EvmAssemblyOp {
+    /// Variable naming the slot of an `sload`/`sstore`: `x.slot` -> `x`, `_ADMIN_SLOT` ->
+    /// `_ADMIN_SLOT`. `None` for computed or literal slots.
+    pub fn slot_name(&self) -> Option<&str> {
+        if !matches!(
+            self.kind,
+            EvmAssemblyOpKind::Sload | EvmAssemblyOpKind::Sstore
+        ) {
+            return None;
+        }
+        let slot = self.arguments.first()?;
+        let name = slot.strip_suffix(".slot").unwrap_or(slot);
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        is_identifier.then_some(name)
+    }
+
+    /// Address argument of a `call`, `delegatecall` or `staticcall`.
+    pub fn call_target(&self) -> Option<&str> {
+        match self.kind {
+            EvmAssemblyOpKind::Call
+            | EvmAssemblyOpKind::DelegateCall
+            | EvmAssemblyOpKind::StaticCall => self.arguments.get(1).map(|a| a.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Pattern flagged by one of the sonar detectors, see `EvmDetector`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum EvmDetectorKind {
+    /// `tx.origin` compared to an address, usually to authorize the caller
+    TxOriginAuth,
+    /// `delegatecall` to an address taken from the function parameters
+    UserDelegatecall,
+    /// `selfdestruct` (or the deprecated `suicide`)
+    Selfdestruct,
+    /// Arithmetic on function parameters inside an `unchecked` block
+    UncheckedUserArithmetic,
+    /// Inline `assembly` block
+    InlineAssembly,
+    /// Comparison involving `block.timestamp` (or `now`)
+    TimestampComparison,
+    /// Loop bounded by the length of a dynamic storage array
+    UnboundedStorageLoop,
+    /// `abi.encodePacked` with several dynamic arguments, prone to hash collisions
+    EncodePackedCollision,
+}
+
+/// A detector match inside an `EvmFunction`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmDetectorHit {
+    pub kind: EvmDetectorKind,
+    /// Canonical id of the function or modifier (`Vault.withdraw(uint256)`, `Vault.onlyOwner()`)
+    pub function_metadata_id: String,
+    pub function: String,
+    /// Contract defining the function
+    pub contract_name: String,
+    pub file_path: String,
+    pub line: usize,
+    /// Source of the flagged expression or statement, collapsed to one line
+    pub snippet: String,
+}
+
+/// Kind of change made to a role or to the ownership of a contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum EvmRoleChangeKind {
+    /// `_grantRole`, `grantRole`, `_setupRole`
+    Grant,
+    /// `_revokeRole`, `revokeRole`, `renounceRole`
+    Revoke,
+    /// `_setRoleAdmin(role, adminRole)`
+    SetAdmin,
+    /// `_transferOwnership(newOwner)`, `transferOwnership(newOwner)`
+    TransferOwnership,
+    /// `renounceOwnership()` or a transfer to `address(0)`
+    RenounceOwnership,
+}
+
+/// Call granting, revoking or administering a role, or moving the ownership of a contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmRoleChange {
+    pub kind: EvmRoleChangeKind,
+    /// Role argument as written, `None` for ownership changes
+    pub role: Option<String>,
+    /// New admin role of `SetAdmin` changes
+    pub admin_role: Option<String>,
+    /// Function or modifier making the call
+    pub caller: String,
+    pub line: usize,
+}
+
 /// Represents a Solidity parameter (function param or return).
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EvmParam {
@@ -50,6 +302,39 @@ pub struct EvmParam {
     pub storage_location: Option<String>,
 }
 
+impl EvmParam {
+    /// Type as written in a canonical signature: whitespace and `payable` dropped,
+    /// `uint`/`int` expanded to their 256-bit names.
+    pub fn canonical_type(&self) -> String {
+        canonical_type_name(&self.type_name)
+    }
+}
+
+/// See [`EvmParam::canonical_type`].
+pub fn canonical_type_name(type_name: &str) -> String {
+    let compact: String = type_name
+        .split_whitespace()
+        .filter(|token| *token != "payable")
+        .collect();
+    let (base, dims) = compact.split_at(compact.find('[').unwrap_or(compact.len()));
+    let base = match base {
+        "uint" => "uint256",
+        "int" => "int256",
+        other => other,
+    };
+    format!("{}{}", base, dims)
+}
+
+/// Canonical `name(type1,type2)` signature, used to tell overloaded functions apart.
+pub fn function_signature(name: &str, params: &[EvmParam]) -> String {
+    let types = params
+        .iter()
+        .map(|p| p.canonical_type())
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{}({})", name, types)
+}
+
 /// Storage variable info.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StorageVariable {
@@ -58,6 +343,9 @@ pub struct StorageVariable {
     pub visibility: EvmVisibility,
     pub is_constant: bool,
     pub is_immutable: bool,
+    /// Initializer expression, e.g. the slot of an EIP-1967 constant
+    #[serde(default)]
+    pub value: Option<String>,
     pub line: usize,
 }
 
@@ -70,6 +358,108 @@ pub struct EvmEvent {
     pub line: usize,
 }
 
+/// Struct definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmStruct {
+    pub name: String,
+    pub members: Vec<EvmParam>,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// Enum definition.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmEnum {
+    pub name: String,
+    pub variants: Vec<String>,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// Custom error definition (`error Unauthorized(address caller);`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmCustomError {
+    pub name: String,
+    pub params: Vec<EvmParam>,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// User-defined value type (`type Price is uint256;`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmUdvt {
+    pub name: String,
+    pub underlying_type: String,
+    pub line: usize,
+    pub end_line: usize,
+}
+
+/// `using Lib for T;` or `using {Lib.f, g} for T global;` directive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmUsingDirective {
+    /// Library attached as a whole: `using SafeERC20 for IERC20`
+    pub library: Option<String>,
+    /// Functions attached one by one, as written: `Lib.f` or a free function `g`
+    pub functions: Vec<String>,
+    /// Type the functions are attached to, `None` for `*`
+    pub type_name: Option<String>,
+    pub global: bool,
+    pub line: usize,
+}
+
+impl EvmUsingDirective {
+    /// true if the directive attaches functions to `receiver_type`; an unknown receiver
+    /// type only matches `using ... for *`.
+    pub fn applies_to(&self, receiver_type: Option<&str>) -> bool {
+        let Some(type_name) = &self.type_name else {
+            return true;
+        };
+        let Some(receiver_type) = receiver_type else {
+            return false;
+        };
+        let receiver = canonical_type_name(receiver_type);
+        let attached = canonical_type_name(type_name);
+        // `EnumerableSet.AddressSet` and `AddressSet` name the same struct
+        let unqualified = |t: &str| t.rsplit('.').next().unwrap_or(t).to_string();
+        receiver == attached || unqualified(&receiver) == unqualified(&attached)
+    }
+
+    /// Library that may provide `method` through this directive.
+    pub fn library_for(&self, method: &str) -> Option<&str> {
+        if let Some(library) = &self.library {
+            return Some(library);
+        }
+        self.functions.iter().find_map(|path| {
+            path.rsplit_once('.')
+                .filter(|(_, name)| *name == method)
+                .map(|(library, _)| library)
+        })
+    }
+}
+
+/// Structs, enums, errors and value types declared at file level, outside any contract.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct EvmFileDefinitions {
+    pub file_path: String,
+    pub structs: Vec<EvmStruct>,
+    pub enums: Vec<EvmEnum>,
+    pub errors: Vec<EvmCustomError>,
+    pub udvts: Vec<EvmUdvt>,
+    /// File-level `using for` directives, also copied into every contract of the file
+    #[serde(default)]
+    pub using_directives: Vec<EvmUsingDirective>,
+}
+
+impl EvmFileDefinitions {
+    pub fn is_empty(&self) -> bool {
+        self.structs.is_empty()
+            && self.enums.is_empty()
+            && self.errors.is_empty()
+            && self.udvts.is_empty()
+            && self.using_directives.is_empty()
+    }
+}
+
 /// Modifier definition.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EvmModifierDef {
@@ -82,6 +472,13 @@ pub struct EvmModifierDef {
     pub contract_name: String,
 }
 
+/// A modifier applied in a function header, with its arguments as written.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvmModifierInvocation {
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
 /// A parsed Solidity function.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EvmFunction {
@@ -90,6 +487,9 @@ pub struct EvmFunction {
     pub visibility: EvmVisibility,
     pub mutability: EvmMutability,
     pub modifiers: Vec<String>,
+    /// `modifiers` with their arguments: `onlyRole(MINTER_ROLE)`
+    #[serde(default)]
+    pub modifier_invocations: Vec<EvmModifierInvocation>,
     pub params: Vec<EvmParam>,
     pub returns: Vec<EvmParam>,
     pub body_source: String,
@@ -98,6 +498,9 @@ pub struct EvmFunction {
     pub is_constructor: bool,
     pub is_fallback: bool,
     pub is_receive: bool,
+    /// Storage, call and free-memory operations of the body's inline assembly
+    #[serde(default)]
+    pub assembly: Vec<EvmAssemblyOp>,
 }
 
 /// A parsed Solidity contract/interface/library.
@@ -110,6 +513,13 @@ pub struct EvmContract {
     pub modifiers: Vec<EvmModifierDef>,
     pub storage_variables: Vec<StorageVariable>,
     pub events: Vec<EvmEvent>,
+    pub structs: Vec<EvmStruct>,
+    pub enums: Vec<EvmEnum>,
+    pub errors: Vec<EvmCustomError>,
+    pub udvts: Vec<EvmUdvt>,
+    /// `using for` directives in scope in the contract: its own and the file-level ones
+    #[serde(default)]
+    pub using_directives: Vec<EvmUsingDirective>,
     pub file_path: String,
     pub line: usize,
     /// true if the contract comes from lib/ (external dependency)
@@ -122,6 +532,7 @@ pub struct EvmFile {
     pub path: String,
     pub imports: Vec<EvmImport>,
     pub contracts: Vec<EvmContract>,
+    pub definitions: EvmFileDefinitions,
     pub pragma: Option<String>,
 }
 
@@ -1,4 +1,5 @@
 use crate::batbelt::command_line::execute_command;
+use crate::batbelt::evm::metadata::reentrancy_report::EVM_REENTRANCY_FILE;
 use crate::batbelt::git::git_action::GitAction;
 use crate::batbelt::git::{GitError, GitResult};
 use crate::batbelt::metadata::BatMetadataCommit;
@@ -240,9 +241,22 @@ impl GitCommit {
                 vec![BatFile::BatToml.get_path(true).change_context(GitError)?]
             }
             GitCommit::UpdateMetadataJson { .. } => {
-                vec![BatFile::BatMetadataFile
+                let mut files = vec![BatFile::BatMetadataFile
                     .get_path(false)
-                    .change_context(GitError)?]
+                    .change_context(GitError)?];
+                // Sonar reports, written along the metadata
+                for report in [
+                    BatFile::Generic {
+                        file_path: EVM_REENTRANCY_FILE.to_string(),
+                    },
+                    BatFile::ReentrancyReport,
+                    BatFile::PdaCatalogue,
+                ] {
+                    if report.file_exists().change_context(GitError)? {
+                        files.push(report.get_path(false).change_context(GitError)?);
+                    }
+                }
+                files
             }
             GitCommit::CodeOverhaulUpdated {
                 updated_eps: file_path_vec,
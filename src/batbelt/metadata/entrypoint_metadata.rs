@@ -1,4 +1,5 @@
 use crate::batbelt::metadata::{BatMetadata, MetadataId, MetadataResult};
+use crate::batbelt::parser::cpi_parser::CpiCall;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -11,6 +12,9 @@ pub struct EntrypointMetadata {
     pub entrypoint_function_id: MetadataId,
     #[serde(default)]
     pub program_name: String,
+    /// CPIs performed by the entry point function and its dependencies
+    #[serde(default)]
+    pub cpis: Vec<CpiCall>,
 }
 
 impl EntrypointMetadata {
@@ -28,6 +32,7 @@ impl EntrypointMetadata {
             context_accounts_id,
             entrypoint_function_id,
             program_name,
+            cpis: vec![],
         }
     }
 
@@ -11,6 +11,7 @@ use error_stack::{Result, ResultExt};
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
+use std::collections::HashSet;
 use std::{fs, vec};
 use walkdir::DirEntry;
 
@@ -168,6 +169,32 @@ impl FunctionSourceCodeMetadata {
         Ok(metadata_result)
     }
 
+    /// Marks as entry points the per-instruction processors of a native `solana_program`
+    /// program, found by `syn_struct_classifier` in the instruction dispatch, which may live
+    /// in a different file. A `module::function` processor must be declared in the `module`
+    /// file or folder; a module-qualified `process` processor is named after its module, like
+    /// Pinocchio entry points.
+    pub fn mark_native_processors(functions: &mut [Self], processor_paths: &HashSet<String>) {
+        for function in functions.iter_mut() {
+            let processor_module = processor_paths.iter().find_map(|processor_path| {
+                match processor_path.split_once("::") {
+                    Some((module, name)) => (name == function.name
+                        && (derive_module_name_from_path(&function.path) == module
+                            || function.path.contains(&format!("/{}/", module))))
+                    .then_some(Some(module)),
+                    None => (*processor_path == function.name).then_some(None),
+                }
+            });
+            let Some(processor_module) = processor_module else {
+                continue;
+            };
+            function.function_type = FunctionMetadataType::EntryPoint;
+            if let (Some(module), "process") = (processor_module, function.name.as_str()) {
+                function.name = module.to_string();
+            }
+        }
+    }
+
     pub fn to_function_parser(&self) -> Result<FunctionParser, MetadataError> {
         FunctionParser::new_from_metadata(self.clone()).change_context(MetadataError)
     }
@@ -4,7 +4,10 @@ use crate::batbelt::metadata::{
     BatMetadataParser, BatMetadataType, MetadataId, SourceCodeMetadata,
 };
 
-use crate::batbelt::parser::syn_struct_classifier;
+use crate::batbelt::metadata::functions_source_code_metadata::{
+    FunctionMetadataType, FunctionSourceCodeMetadata,
+};
+use crate::batbelt::parser::{native_context_accounts_parser, syn_struct_classifier};
 use crate::batbelt::sonar::{BatSonar, SonarResultType};
 use crate::batbelt::BatEnumerator;
 use error_stack::{Result, ResultExt};
@@ -13,6 +16,7 @@ use super::MetadataError;
 use crate::batbelt::bat_dialoguer::BatDialoguer;
 use crate::batbelt::parser::parse_formatted_path;
 use colored::Colorize;
+use inflector::Inflector;
 use serde::{Deserialize, Serialize};
 use std::{fs, vec};
 use walkdir::DirEntry;
@@ -142,6 +146,37 @@ impl StructSourceCodeMetadata {
         Ok(metadata_result)
     }
 
+    /// Context accounts of the entry points of a native `solana_program` program, which
+    /// have no accounts struct: the `next_account_info` sequence of every processor,
+    /// named `<EntryPoint>Accounts`.
+    pub fn create_native_context_accounts_metadata(
+        functions: &[FunctionSourceCodeMetadata],
+    ) -> Vec<Self> {
+        functions
+            .iter()
+            .filter(|function| function.function_type == FunctionMetadataType::EntryPoint)
+            .filter_map(|function| {
+                let function_source = fs::read_to_string(&function.path)
+                    .ok()?
+                    .lines()
+                    .skip(function.start_line_index - 1)
+                    .take(function.end_line_index - function.start_line_index + 1)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let (first_line, last_line) =
+                    native_context_accounts_parser::next_account_info_lines(&function_source)?;
+                Some(StructSourceCodeMetadata::new(
+                    function.path.clone(),
+                    format!("{}Accounts", function.name.to_pascal_case()),
+                    StructMetadataType::ContextAccounts,
+                    function.start_line_index + first_line,
+                    function.start_line_index + last_line,
+                    Self::create_metadata_id(),
+                ))
+            })
+            .collect()
+    }
+
     pub fn prompt_selection() -> Result<Self, MetadataError> {
         let (metadata_vec, metadata_names) = Self::prompt_types()?;
         let prompt_text = format!("Please select the {}:", Self::metadata_name().blue());
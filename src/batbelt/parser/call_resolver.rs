@@ -9,6 +9,7 @@
 //! - `Resolution::Internal(MetadataId)` — resolved to a function in this project's metadata
 //! - `Resolution::External(String)` — resolved to a function outside the project (std, anchor, etc.)
 //! - `Resolution::Unresolved(String)` — couldn't resolve with certainty
+//! - `Resolution::Cpi(String)` — a cross-program invocation
 //!
 //! # Why "unresolved" instead of "best guess"
 //!
@@ -39,6 +40,12 @@
 //!   param_types, find impl block for that type, look up `method`.
 //!
 //! Anything else is `Unresolved`.
+//!
+//! Cross-program invocations are classified before any of the above: `invoke` and
+//! `invoke_signed` (free functions or Pinocchio instruction methods), the CPI helpers of the
+//! Anchor modules (`token::transfer`, `system_program::create_account`...) and the functions
+//! of a program's `cpi` module. They leave the program, so they are never `Internal`;
+//! `cpi_parser` records their target program, accounts and signer seeds.
 
 use std::collections::{HashMap, HashSet};
 
@@ -47,6 +54,7 @@ use syn::visit::Visit;
 use crate::batbelt::metadata::functions_source_code_metadata::FunctionSourceCodeMetadata;
 use crate::batbelt::metadata::trait_metadata::TraitMetadata;
 use crate::batbelt::metadata::MetadataId;
+use crate::batbelt::parser::cpi_parser::is_cpi_call;
 use crate::batbelt::parser::file_scope::FileScope;
 use crate::batbelt::parser::type_resolver::{ResolvedType, TypeResolver};
 
@@ -60,6 +68,8 @@ pub enum Resolution {
     External(String),
     /// Could not resolve with certainty. The string is the call site name for logging.
     Unresolved(String),
+    /// Cross-program invocation (e.g., `"invoke_signed"` or `"token::transfer"`).
+    Cpi(String),
 }
 
 #[derive(Clone, Debug)]
@@ -266,8 +276,6 @@ const FILTERED_FUNCTION_NAMES: &[&str] = &[
     "require_gte",
     "require_lt",
     "require_lte",
-    "invoke",
-    "invoke_signed",
     "system_program",
 ];
 
@@ -358,6 +366,9 @@ impl<'a> CallResolver<'a> {
         param_types: &HashMap<String, ResolvedType>,
         context_accounts_types: &HashMap<String, ResolvedType>,
     ) -> Resolution {
+        if let Some(cpi) = raw.cpi_name() {
+            return Resolution::Cpi(cpi);
+        }
         match &raw.call_type {
             RawCallType::FreeFunction => self.resolve_free_function(&raw.function_name),
             RawCallType::StaticMethod { type_name } => {
@@ -592,6 +603,23 @@ struct RawCall {
     call_type: RawCallType,
 }
 
+impl RawCall {
+    /// Call site name, if the call is a cross-program invocation.
+    fn cpi_name(&self) -> Option<String> {
+        let module = match &self.call_type {
+            RawCallType::StaticMethod { type_name } => Some(type_name.as_str()),
+            RawCallType::FreeFunction | RawCallType::MethodCall { .. } => None,
+        };
+        if !is_cpi_call(module, &self.function_name) {
+            return None;
+        }
+        Some(match module {
+            Some(module) => format!("{}::{}", module, self.function_name),
+            None => self.function_name.clone(),
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 enum RawCallType {
     FreeFunction,
@@ -791,6 +819,44 @@ mod tests {
         assert_eq!(visitor.calls.len(), 1);
     }
 
+    #[test]
+    fn test_cpi_classification() {
+        let cpi_name = |function_name: &str, call_type: RawCallType| {
+            RawCall {
+                function_name: function_name.to_string(),
+                call_type,
+            }
+            .cpi_name()
+        };
+        assert_eq!(
+            cpi_name("invoke_signed", RawCallType::FreeFunction),
+            Some("invoke_signed".to_string())
+        );
+        assert_eq!(
+            cpi_name(
+                "transfer",
+                RawCallType::StaticMethod {
+                    type_name: "token".to_string()
+                }
+            ),
+            Some("token::transfer".to_string())
+        );
+        assert_eq!(
+            cpi_name("invoke", RawCallType::MethodCall { receiver: None }),
+            Some("invoke".to_string())
+        );
+        assert_eq!(
+            cpi_name(
+                "get_associated_token_address",
+                RawCallType::StaticMethod {
+                    type_name: "associated_token".to_string()
+                }
+            ),
+            None
+        );
+        assert_eq!(cpi_name("transfer", RawCallType::FreeFunction), None);
+    }
+
     #[test]
     fn test_receiver_to_string_path() {
         let expr: syn::Expr = syn::parse_str("foo").unwrap();
@@ -0,0 +1,901 @@
+//! Detection of the cross-program invocations performed by a function.
+//!
+//! Unlike the `CallResolver`, which maps calls to project functions, this module looks for
+//! the calls that leave the program:
+//!
+//! - `invoke(&ix, &[accounts])` / `invoke_signed(&ix, &[accounts], &[seeds])`, from
+//!   `solana_program` or Pinocchio's `cpi` module. The target program is taken from the
+//!   instruction builder (`spl_token::instruction::transfer`, `system_instruction::*`) or
+//!   the `program_id` of the `Instruction`.
+//! - Anchor `CpiContext::new(program, accounts)` / `new_with_signer` / `.with_signer(seeds)`,
+//!   recorded at the helper consuming it (`token::transfer(cpi_ctx, amount)`,
+//!   `token_interface::*`, `system_program::*`, or a program's `cpi` module). The CPI helpers
+//!   of the known Anchor modules (`transfer`, `mint_to`, `create_account`...) are recorded
+//!   even when the `CpiContext` is built elsewhere; their other functions, like
+//!   `associated_token::get_associated_token_address`, are not CPIs.
+//! - Pinocchio instruction structs, `Transfer { from, to, lamports }.invoke()` or
+//!   `.invoke_signed(&signers)`. The program is known when the struct is qualified by its
+//!   crate (`pinocchio_system::instructions::Transfer`), and only the fields holding an
+//!   account are recorded.
+//!
+//! Locals are followed through their `let` bindings, so the accounts and signer seeds are
+//! resolved when built in the same function.
+
+use crate::batbelt::parser::syn_context_accounts_parser::normalize_token_str;
+use crate::batbelt::parser::ParserError;
+use error_stack::{Report, Result};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use syn::punctuated::Punctuated;
+use syn::visit::Visit;
+
+/// Functions (and Pinocchio instruction methods) performing a CPI.
+const INVOKE_FUNCTIONS: &[&str] = &[
+    "invoke",
+    "invoke_signed",
+    "invoke_unchecked",
+    "invoke_signed_unchecked",
+    "invoke_with_bounds",
+    "invoke_signed_with_bounds",
+];
+
+/// Anchor CPI helper modules and the program they invoke.
+const ANCHOR_CPI_MODULES: &[(&str, &str)] = &[
+    ("token", "token_program"),
+    ("token_interface", "token_program"),
+    ("token_2022", "token_program"),
+    ("system_program", "system_program"),
+    ("associated_token", "associated_token_program"),
+];
+
+/// Functions of the Anchor CPI modules taking a `CpiContext`.
+const ANCHOR_CPI_HELPERS: &[&str] = &[
+    "transfer",
+    "transfer_checked",
+    "mint_to",
+    "mint_to_checked",
+    "burn",
+    "burn_checked",
+    "approve",
+    "approve_checked",
+    "revoke",
+    "close_account",
+    "freeze_account",
+    "thaw_account",
+    "set_authority",
+    "sync_native",
+    "initialize_account",
+    "initialize_account3",
+    "initialize_mint",
+    "initialize_mint2",
+    "create",
+    "create_idempotent",
+    "create_account",
+    "allocate",
+    "assign",
+];
+
+/// Pinocchio instruction crates and the program their instruction structs invoke.
+const PINOCCHIO_INSTRUCTION_CRATES: &[(&str, &str)] = &[
+    ("pinocchio_system", "system_program"),
+    ("pinocchio_token", "token_program"),
+    ("pinocchio_token_2022", "token_program"),
+    (
+        "pinocchio_associated_token_account",
+        "associated_token_program",
+    ),
+];
+
+/// Conversions stripped from the accounts passed to a CPI.
+const ACCOUNT_CONVERSIONS: &[&str] = &[".clone()", ".to_account_info()", ".as_ref()", ".key()"];
+
+/// Paths stripped from the accounts passed to a CPI.
+const ACCOUNT_PREFIXES: &[&str] = &["ctx.accounts.", "self.accounts.", "self.", "accounts."];
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CpiCall {
+    /// Function performing the CPI: the entry point or one of its dependencies
+    pub function_name: String,
+    /// `invoke_signed`, `token::transfer`, `Transfer::invoke`...
+    pub call: String,
+    /// Invoked program, as named in the source code
+    pub program: String,
+    /// Accounts passed to the CPI; the account fields for instruction structs
+    pub accounts: Vec<String>,
+    /// true if the CPI is signed by a PDA of the program
+    pub pda_signed: bool,
+    /// Signer seeds, when they are built in the same function
+    pub signer_seeds: Vec<String>,
+}
+
+/// true if a call to `module::function` (or the `function` method) performs a CPI, for the
+/// calls whose arguments are not known.
+pub fn is_cpi_call(module: Option<&str>, function: &str) -> bool {
+    INVOKE_FUNCTIONS.contains(&function)
+        || module
+            .is_some_and(|module| module == "cpi" || anchor_cpi_program(module, function).is_some())
+}
+
+/// Program invoked by an Anchor CPI helper, `token_program` for `token::transfer`.
+fn anchor_cpi_program(module: &str, function: &str) -> Option<&'static str> {
+    if !ANCHOR_CPI_HELPERS.contains(&function) {
+        return None;
+    }
+    ANCHOR_CPI_MODULES
+        .iter()
+        .find(|(cpi_module, _)| *cpi_module == module)
+        .map(|(_, program)| *program)
+}
+
+/// Parses the CPIs performed by `function_source`, in order of appearance.
+pub fn parse_cpis_from_function(
+    function_name: &str,
+    function_source: &str,
+) -> Result<Vec<CpiCall>, ParserError> {
+    let item_fn = syn::parse_str::<syn::ItemFn>(function_source)
+        .or_else(|_| {
+            let wrapped = format!("fn __wrapper() {{ {} }}", function_source);
+            syn::parse_str::<syn::ItemFn>(&wrapped)
+        })
+        .map_err(|e| {
+            Report::new(ParserError)
+                .attach_printable(format!("Failed to parse Rust function: {}", e))
+        })?;
+
+    let mut binding_collector = BindingCollector {
+        bindings: HashMap::new(),
+        account_bindings: HashSet::new(),
+    };
+    for input in &item_fn.sig.inputs {
+        if let syn::FnArg::Typed(pat_type) = input {
+            let ty = pat_type.ty.to_token_stream().to_string();
+            if ty.contains("AccountInfo") || ty.contains("AccountView") {
+                pat_idents(&pat_type.pat, &mut binding_collector.account_bindings);
+            }
+        }
+    }
+    binding_collector.visit_block(&item_fn.block);
+
+    let mut visitor = CpiVisitor {
+        function_name: function_name.to_string(),
+        bindings: binding_collector.bindings,
+        account_bindings: binding_collector.account_bindings,
+        cpis: vec![],
+        contexts: vec![],
+        consumed_contexts: HashSet::new(),
+    };
+    visitor.visit_block(&item_fn.block);
+
+    // A `CpiContext` returned to the caller is recorded where the caller consumes it
+    let returns_context = match &item_fn.sig.output {
+        syn::ReturnType::Type(_, ty) => ty.to_token_stream().to_string().contains("CpiContext"),
+        syn::ReturnType::Default => false,
+    };
+    let mut cpis = visitor.cpis;
+    if !returns_context {
+        cpis.extend(
+            visitor
+                .contexts
+                .into_iter()
+                .filter(|(binding, _)| {
+                    !binding
+                        .as_ref()
+                        .is_some_and(|binding| visitor.consumed_contexts.contains(binding))
+                })
+                .map(|(_, cpi)| cpi),
+        );
+    }
+    Ok(cpis)
+}
+
+/// Collects the `let name = expr;` bindings of a function body, and the accounts
+/// destructured from the account list (`let [from, to, ..] = accounts else { ... };`).
+struct BindingCollector<'ast> {
+    bindings: HashMap<String, &'ast syn::Expr>,
+    account_bindings: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for BindingCollector<'ast> {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let Some(init) = &local.init {
+            match pat_ident(&local.pat) {
+                Some(name) => {
+                    self.bindings.insert(name, &init.expr);
+                }
+                None if expr_to_string(&init.expr).contains("accounts") => {
+                    pat_idents(&local.pat, &mut self.account_bindings);
+                }
+                None => {}
+            }
+        }
+        syn::visit::visit_local(self, local);
+    }
+}
+
+fn pat_idents(pat: &syn::Pat, idents: &mut HashSet<String>) {
+    match pat {
+        syn::Pat::Ident(pat_ident) => {
+            idents.insert(pat_ident.ident.to_string());
+        }
+        syn::Pat::Type(pat_type) => pat_idents(&pat_type.pat, idents),
+        syn::Pat::Reference(reference) => pat_idents(&reference.pat, idents),
+        syn::Pat::Slice(slice) => slice.elems.iter().for_each(|elem| pat_idents(elem, idents)),
+        syn::Pat::Tuple(tuple) => tuple.elems.iter().for_each(|elem| pat_idents(elem, idents)),
+        _ => {}
+    }
+}
+
+struct CpiVisitor<'ast> {
+    function_name: String,
+    bindings: HashMap<String, &'ast syn::Expr>,
+    /// Accounts taken as parameters or destructured from the account list
+    account_bindings: HashSet<String>,
+    cpis: Vec<CpiCall>,
+    /// `CpiContext`s not consumed where they are built, with the `let` binding holding them
+    contexts: Vec<(Option<String>, CpiCall)>,
+    /// Bindings of the `CpiContext`s passed to a helper
+    consumed_contexts: HashSet<String>,
+}
+
+impl<'ast> CpiVisitor<'ast> {
+    fn new_cpi(&self, call: &str, program: &str) -> CpiCall {
+        CpiCall {
+            function_name: self.function_name.clone(),
+            call: call.to_string(),
+            program: program.to_string(),
+            accounts: vec![],
+            pda_signed: false,
+            signer_seeds: vec![],
+        }
+    }
+
+    /// Follows references, `?`, `unwrap()` and local bindings down to the expression
+    /// building the value.
+    fn resolve<'e>(&'e self, expr: &'e syn::Expr) -> &'e syn::Expr {
+        let mut expr = expr;
+        // Bounded, since shadowing (`let a = &a;`) makes cycles
+        for _ in 0..8 {
+            expr = match expr {
+                syn::Expr::Reference(reference) => &reference.expr,
+                syn::Expr::Paren(paren) => &paren.expr,
+                syn::Expr::Try(try_expr) => &try_expr.expr,
+                syn::Expr::MethodCall(method_call)
+                    if method_call.method == "unwrap" || method_call.method == "expect" =>
+                {
+                    &method_call.receiver
+                }
+                syn::Expr::Path(path) if path.path.segments.len() == 1 => {
+                    match self.bindings.get(&path.path.segments[0].ident.to_string()) {
+                        Some(binding) => binding,
+                        None => return expr,
+                    }
+                }
+                _ => return expr,
+            };
+        }
+        expr
+    }
+
+    /// true if `expr` holds an account: one read from the accounts (`self.accounts.vault`,
+    /// `accounts[0]`, `next_account_info(...)`) or bound from them.
+    fn is_account(&self, expr: &syn::Expr) -> bool {
+        let resolved = self.resolve(expr);
+        if let syn::Expr::Path(path) = resolved {
+            if path.path.segments.len() == 1
+                && self
+                    .account_bindings
+                    .contains(&path.path.segments[0].ident.to_string())
+            {
+                return true;
+            }
+        }
+        let text = expr_to_string(resolved);
+        text.contains("accounts")
+            || ACCOUNT_CONVERSIONS
+                .iter()
+                .any(|conversion| text.ends_with(conversion) && *conversion != ".as_ref()")
+    }
+
+    /// Program invoked by the instruction passed to `invoke`.
+    fn instruction_program(&self, instruction: &syn::Expr) -> String {
+        match self.resolve(instruction) {
+            syn::Expr::Call(call) => {
+                let path = call_path(call).unwrap_or_default();
+                let segments: Vec<&str> = path.split("::").collect();
+                if segments.contains(&"system_instruction") {
+                    return "system_program".to_string();
+                }
+                match segments
+                    .iter()
+                    .position(|segment| *segment == "instruction")
+                {
+                    Some(position) if position > 0 => segments[..position].join("::"),
+                    // Instruction::new_with_bytes(program_id, ...)
+                    _ if path.contains("Instruction::new") => {
+                        call.args.first().map(clean_account).unwrap_or_default()
+                    }
+                    _ => path,
+                }
+            }
+            syn::Expr::Struct(instruction_struct) => instruction_struct
+                .fields
+                .iter()
+                .find(|field| member_name(&field.member) == "program_id")
+                .map(|field| clean_account(&field.expr))
+                .unwrap_or_default(),
+            expr => clean_account(expr),
+        }
+    }
+
+    /// Accounts of an account list, `CpiContext` accounts struct or instruction struct.
+    fn account_list(&self, accounts: &syn::Expr) -> Vec<String> {
+        match self.resolve(accounts) {
+            syn::Expr::Struct(accounts_struct) => accounts_struct
+                .fields
+                .iter()
+                .map(|field| {
+                    format!(
+                        "{}: {}",
+                        member_name(&field.member),
+                        clean_account(&field.expr)
+                    )
+                })
+                .collect(),
+            syn::Expr::Array(array) => array.elems.iter().map(clean_account).collect(),
+            syn::Expr::Macro(expr_macro) => macro_args(&expr_macro.mac)
+                .iter()
+                .map(clean_account)
+                .collect(),
+            expr => vec![clean_account(expr)],
+        }
+    }
+
+    /// Flattens the signer seeds of a CPI: nested arrays and slices, Pinocchio
+    /// `Signer::from`/`Seed::from` and the `seeds!`/`signer!` macros.
+    fn signer_seeds(&self, seeds: &syn::Expr) -> Vec<String> {
+        let mut result = vec![];
+        self.collect_seeds(seeds, &mut result, 0);
+        result
+    }
+
+    fn collect_seeds(&self, seeds: &syn::Expr, result: &mut Vec<String>, depth: usize) {
+        let resolved = self.resolve(seeds);
+        if depth > 8 {
+            result.push(expr_to_string(resolved));
+            return;
+        }
+        match resolved {
+            syn::Expr::Array(array) => {
+                for elem in &array.elems {
+                    self.collect_seeds(elem, result, depth + 1);
+                }
+            }
+            syn::Expr::Index(index) => self.collect_seeds(&index.expr, result, depth + 1),
+            syn::Expr::Call(call)
+                if call.args.len() == 1
+                    && call_path(call).is_some_and(|path| path.ends_with("::from")) =>
+            {
+                self.collect_seeds(&call.args[0], result, depth + 1)
+            }
+            syn::Expr::Macro(expr_macro)
+                if expr_macro.mac.path.segments.last().is_some_and(|segment| {
+                    segment.ident == "seeds" || segment.ident == "signer"
+                }) =>
+            {
+                for arg in macro_args(&expr_macro.mac) {
+                    self.collect_seeds(&arg, result, depth + 1);
+                }
+            }
+            _ => result.push(expr_to_string(resolved)),
+        }
+    }
+
+    /// Local binding an expression reads, through references, `?` and `unwrap()`.
+    fn binding_name(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Reference(reference) => self.binding_name(&reference.expr),
+            syn::Expr::Paren(paren) => self.binding_name(&paren.expr),
+            syn::Expr::Try(try_expr) => self.binding_name(&try_expr.expr),
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "unwrap" || method_call.method == "expect" =>
+            {
+                self.binding_name(&method_call.receiver)
+            }
+            syn::Expr::Path(path) if path.path.segments.len() == 1 => {
+                let name = path.path.segments[0].ident.to_string();
+                self.bindings.contains_key(&name).then_some(name)
+            }
+            _ => None,
+        }
+    }
+
+    /// The `CpiContext` an expression evaluates to, along with the `let` binding holding
+    /// it when it is built in this function.
+    fn cpi_context(&self, expr: &syn::Expr) -> Option<(Option<String>, CpiCall)> {
+        let binding = self.binding_name(expr);
+        match self.resolve(expr) {
+            syn::Expr::MethodCall(method_call) if method_call.method == "with_signer" => {
+                let (receiver_binding, mut cpi) = self
+                    .cpi_context(&method_call.receiver)
+                    .unwrap_or_else(|| (None, self.new_cpi("CpiContext", "")));
+                cpi.pda_signed = true;
+                if let Some(seeds) = method_call.args.first() {
+                    cpi.signer_seeds = self.signer_seeds(seeds);
+                }
+                Some((binding.or(receiver_binding), cpi))
+            }
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "with_remaining_accounts" =>
+            {
+                let (receiver_binding, cpi) = self.cpi_context(&method_call.receiver)?;
+                Some((binding.or(receiver_binding), cpi))
+            }
+            syn::Expr::Call(call) => {
+                let path = call_path(call)?;
+                is_context_constructor(&path)
+                    .then(|| (binding, self.context_constructor(call, &path)))
+            }
+            _ => None,
+        }
+    }
+
+    fn context_constructor(&self, call: &syn::ExprCall, path: &str) -> CpiCall {
+        let args: Vec<&syn::Expr> = call.args.iter().collect();
+        let mut cpi = self.new_cpi(
+            path,
+            &args
+                .first()
+                .map(|program| clean_account(program))
+                .unwrap_or_default(),
+        );
+        if let Some(accounts) = args.get(1) {
+            cpi.accounts = self.account_list(accounts);
+        }
+        if path.ends_with("new_with_signer") {
+            cpi.pda_signed = true;
+            if let Some(seeds) = args.get(2) {
+                cpi.signer_seeds = self.signer_seeds(seeds);
+            }
+        }
+        cpi
+    }
+}
+
+impl<'ast> Visit<'ast> for CpiVisitor<'ast> {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        if let (Some(name), Some(init)) = (pat_ident(&local.pat), &local.init) {
+            if let Some((receiver_binding, cpi)) = self.cpi_context(&init.expr) {
+                // `let ctx = cpi_ctx.with_signer(seeds)` replaces `cpi_ctx`
+                if let Some(receiver_binding) = receiver_binding {
+                    self.consumed_contexts.insert(receiver_binding);
+                }
+                self.contexts.push((Some(name), cpi));
+                return;
+            }
+        }
+        syn::visit::visit_local(self, local);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        if let Some(path) = call_path(node) {
+            let function = path.rsplit("::").next().unwrap_or_default();
+            let args: Vec<&syn::Expr> = node.args.iter().collect();
+            if INVOKE_FUNCTIONS.contains(&function) {
+                let program = args
+                    .first()
+                    .map(|instruction| self.instruction_program(instruction))
+                    .unwrap_or_default();
+                let mut cpi = self.new_cpi(&path, &program);
+                if let Some(accounts) = args.get(1) {
+                    cpi.accounts = self.account_list(accounts);
+                }
+                if function.contains("signed") {
+                    cpi.pda_signed = true;
+                    if let Some(seeds) = args.get(2) {
+                        cpi.signer_seeds = self.signer_seeds(seeds);
+                    }
+                }
+                self.cpis.push(cpi);
+            } else if is_context_constructor(&path) {
+                // Neither bound nor passed to a helper, e.g. returned to the caller
+                let cpi = self.context_constructor(node, &path);
+                self.contexts.push((None, cpi));
+            } else if let Some(first_arg) = args.first() {
+                let module = path.rsplit("::").nth(1).unwrap_or_default();
+                let module_program = anchor_cpi_program(module, function);
+                match self.cpi_context(first_arg) {
+                    Some((binding, mut cpi)) => {
+                        if let Some(binding) = binding {
+                            self.consumed_contexts.insert(binding);
+                        }
+                        cpi.call = path.clone();
+                        if cpi.program.is_empty() {
+                            cpi.program = module_program.unwrap_or_default().to_string();
+                        }
+                        self.cpis.push(cpi);
+                        // The constructor is consumed here, only the other arguments and
+                        // the callee are left to visit
+                        self.visit_expr(&node.func);
+                        for arg in node.args.iter().skip(1) {
+                            self.visit_expr(arg);
+                        }
+                        return;
+                    }
+                    // CpiContext built by another function
+                    None => {
+                        if let Some(program) = module_program {
+                            self.cpis.push(self.new_cpi(&path, program));
+                        }
+                    }
+                }
+            }
+        }
+        syn::visit::visit_expr_call(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        let method = node.method.to_string();
+        if INVOKE_FUNCTIONS.contains(&method.as_str()) {
+            let receiver = self.resolve(&node.receiver);
+            let mut cpi = match receiver {
+                // Pinocchio instruction struct
+                syn::Expr::Struct(instruction_struct) => {
+                    let path = path_to_string(&instruction_struct.path);
+                    let type_name = path.rsplit("::").next().unwrap_or_default();
+                    let program = PINOCCHIO_INSTRUCTION_CRATES
+                        .iter()
+                        .find(|(crate_name, _)| path.starts_with(&format!("{}::", crate_name)))
+                        .map(|(_, program)| *program)
+                        .unwrap_or_default();
+                    let mut cpi = self.new_cpi(&format!("{}::{}", type_name, method), program);
+                    cpi.accounts = instruction_struct
+                        .fields
+                        .iter()
+                        .filter(|field| self.is_account(&field.expr))
+                        .map(|field| {
+                            format!(
+                                "{}: {}",
+                                member_name(&field.member),
+                                clean_account(&field.expr)
+                            )
+                        })
+                        .collect();
+                    cpi
+                }
+                _ => {
+                    let receiver_name = clean_account(receiver);
+                    self.new_cpi(&format!("{}.{}", receiver_name, method), &receiver_name)
+                }
+            };
+            if method.contains("signed") {
+                cpi.pda_signed = true;
+                if let Some(signers) = node.args.first() {
+                    cpi.signer_seeds = self.signer_seeds(signers);
+                }
+            }
+            self.cpis.push(cpi);
+        }
+        syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+fn is_context_constructor(path: &str) -> bool {
+    path.ends_with("CpiContext::new") || path.ends_with("CpiContext::new_with_signer")
+}
+
+fn call_path(call: &syn::ExprCall) -> Option<String> {
+    match &*call.func {
+        syn::Expr::Path(expr_path) => Some(path_to_string(&expr_path.path)),
+        _ => None,
+    }
+}
+
+/// `a::b::C`, without generic arguments.
+fn path_to_string(path: &syn::Path) -> String {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn member_name(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) => ident.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+fn pat_ident(pat: &syn::Pat) -> Option<String> {
+    match pat {
+        syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        syn::Pat::Type(pat_type) => pat_ident(&pat_type.pat),
+        _ => None,
+    }
+}
+
+fn macro_args(mac: &syn::Macro) -> Vec<syn::Expr> {
+    mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+        .map(|args| args.into_iter().collect())
+        .unwrap_or_default()
+}
+
+fn expr_to_string(expr: &syn::Expr) -> String {
+    normalize_token_str(&expr.to_token_stream().to_string())
+}
+
+/// `ctx.accounts.vault.to_account_info()` → `vault`
+fn clean_account(expr: &syn::Expr) -> String {
+    let mut account = expr_to_string(expr);
+    loop {
+        let previous = account.clone();
+        account = account
+            .trim_start_matches('&')
+            .trim_start_matches('*')
+            .trim_start_matches("mut ")
+            .to_string();
+        for conversion in ACCOUNT_CONVERSIONS {
+            if let Some(stripped) = account.strip_suffix(conversion) {
+                account = stripped.to_string();
+            }
+        }
+        for prefix in ACCOUNT_PREFIXES {
+            if let Some(stripped) = account.strip_prefix(prefix) {
+                account = stripped.to_string();
+            }
+        }
+        if account == previous {
+            return account;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANCHOR_WITHDRAW: &str = r#"pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+    let user_key = ctx.accounts.user.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[b"vault", user_key.as_ref(), &[ctx.bumps.vault]]];
+    let cpi_accounts = Transfer {
+        from: ctx.accounts.vault_ata.to_account_info(),
+        to: ctx.accounts.user_ata.to_account_info(),
+        authority: ctx.accounts.vault.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        cpi_accounts,
+        signer_seeds,
+    );
+    token::transfer(cpi_ctx, amount)?;
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.user.to_account_info(),
+                to: ctx.accounts.fee_vault.to_account_info(),
+            },
+        ),
+        FEE,
+    )?;
+    token_interface::burn(ctx.accounts.burn_ctx(), amount)
+}"#;
+
+    const NATIVE_WITHDRAW: &str = r#"pub fn process_withdraw(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let vault = next_account_info(accounts_iter)?;
+    let user_ata = next_account_info(accounts_iter)?;
+    let vault_authority = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let (_, bump) = Pubkey::find_program_address(&[b"authority"], program_id);
+    let ix = spl_token::instruction::transfer(
+        token_program.key,
+        vault.key,
+        user_ata.key,
+        vault_authority.key,
+        &[],
+        amount,
+    )?;
+    invoke_signed(
+        &ix,
+        &[vault.clone(), user_ata.clone(), vault_authority.clone()],
+        &[&[b"authority", &[bump]]],
+    )?;
+    Ok(())
+}"#;
+
+    const PINOCCHIO_WITHDRAW: &str = r#"pub fn process(&mut self) -> ProgramResult {
+    let bump = [self.state.bump];
+    let seeds = [Seed::from(b"vault"), Seed::from(self.accounts.owner.address().as_ref()), Seed::from(&bump)];
+    let signers = [Signer::from(&seeds)];
+    pinocchio_system::instructions::Transfer {
+        from: self.accounts.vault,
+        to: self.accounts.owner,
+        lamports: self.amount,
+    }
+    .invoke_signed(&signers)?;
+    Ok(())
+}"#;
+
+    #[test]
+    fn test_anchor_cpis() {
+        let cpis = parse_cpis_from_function("withdraw", ANCHOR_WITHDRAW).unwrap();
+        assert_eq!(
+            cpis,
+            vec![
+                CpiCall {
+                    function_name: "withdraw".to_string(),
+                    call: "token::transfer".to_string(),
+                    program: "token_program".to_string(),
+                    accounts: vec![
+                        "from: vault_ata".to_string(),
+                        "to: user_ata".to_string(),
+                        "authority: vault".to_string(),
+                    ],
+                    pda_signed: true,
+                    signer_seeds: vec![
+                        "b\"vault\"".to_string(),
+                        "user_key.as_ref()".to_string(),
+                        "ctx.bumps.vault".to_string(),
+                    ],
+                },
+                CpiCall {
+                    function_name: "withdraw".to_string(),
+                    call: "system_program::transfer".to_string(),
+                    program: "system_program".to_string(),
+                    accounts: vec!["from: user".to_string(), "to: fee_vault".to_string()],
+                    pda_signed: false,
+                    signer_seeds: vec![],
+                },
+                CpiCall {
+                    function_name: "withdraw".to_string(),
+                    call: "token_interface::burn".to_string(),
+                    program: "token_program".to_string(),
+                    accounts: vec![],
+                    pda_signed: false,
+                    signer_seeds: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_cpi_module_functions() {
+        let source = r#"pub fn create_vault(ctx: Context<CreateVault>) -> Result<()> {
+    let ata = associated_token::get_associated_token_address(&ctx.accounts.user.key(), &ctx.accounts.mint.key());
+    let account = spl_token::state::Account::unpack(&ctx.accounts.vault_ata.data.borrow())?;
+    let program = token::ID;
+    require_keys_eq!(ata, account.owner);
+    associated_token::create(ctx.accounts.create_ata_ctx())?;
+    Ok(())
+}"#;
+        let cpis = parse_cpis_from_function("create_vault", source).unwrap();
+        assert_eq!(
+            cpis.iter()
+                .map(|cpi| (cpi.call.as_str(), cpi.program.as_str()))
+                .collect::<Vec<_>>(),
+            vec![("associated_token::create", "associated_token_program")]
+        );
+    }
+
+    #[test]
+    fn test_cpi_context_bindings() {
+        let source = r#"pub fn settle(ctx: Context<Settle>, amount: u64) -> Result<()> {
+    let cpi_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Transfer {
+            from: ctx.accounts.vault.to_account_info(),
+            to: ctx.accounts.user.to_account_info(),
+            authority: ctx.accounts.pool.to_account_info(),
+        },
+    );
+    let signed_ctx = cpi_ctx.with_signer(&[&[b"pool", &[ctx.bumps.pool]]]);
+    token::transfer(signed_ctx, amount)?;
+    let unused_ctx = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        system_program::Transfer {
+            from: ctx.accounts.user.to_account_info(),
+            to: ctx.accounts.vault.to_account_info(),
+        },
+    );
+    Ok(())
+}"#;
+        let cpis = parse_cpis_from_function("settle", source).unwrap();
+        assert_eq!(
+            cpis.iter()
+                .map(|cpi| (cpi.call.as_str(), cpi.pda_signed))
+                .collect::<Vec<_>>(),
+            vec![("token::transfer", true), ("CpiContext::new", false)]
+        );
+        assert_eq!(
+            cpis[0].accounts,
+            vec!["from: vault", "to: user", "authority: pool"]
+        );
+        assert_eq!(cpis[1].program, "system_program");
+    }
+
+    #[test]
+    fn test_is_cpi_call() {
+        assert!(is_cpi_call(None, "invoke_signed"));
+        assert!(is_cpi_call(Some("token"), "transfer"));
+        assert!(is_cpi_call(Some("cpi"), "deposit"));
+        assert!(!is_cpi_call(
+            Some("associated_token"),
+            "get_associated_token_address"
+        ));
+        assert!(!is_cpi_call(Some("Account"), "unpack"));
+        assert!(!is_cpi_call(Some("CpiContext"), "new"));
+    }
+
+    #[test]
+    fn test_native_and_pinocchio_cpis() {
+        let cpis = parse_cpis_from_function("process_withdraw", NATIVE_WITHDRAW).unwrap();
+        assert_eq!(cpis.len(), 1);
+        assert_eq!(
+            (cpis[0].call.as_str(), cpis[0].program.as_str()),
+            ("invoke_signed", "spl_token")
+        );
+        assert_eq!(
+            cpis[0].accounts,
+            vec!["vault", "user_ata", "vault_authority"]
+        );
+        assert_eq!(cpis[0].signer_seeds, vec!["b\"authority\"", "bump"]);
+
+        let cpis = parse_cpis_from_function("process", PINOCCHIO_WITHDRAW).unwrap();
+        assert_eq!(
+            cpis,
+            vec![CpiCall {
+                function_name: "process".to_string(),
+                call: "Transfer::invoke_signed".to_string(),
+                program: "system_program".to_string(),
+                accounts: vec!["from: vault".to_string(), "to: owner".to_string()],
+                pda_signed: true,
+                signer_seeds: vec![
+                    "b\"vault\"".to_string(),
+                    "self.accounts.owner.address().as_ref()".to_string(),
+                    "self.state.bump".to_string(),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pinocchio_destructured_accounts() {
+        let source = r#"pub fn process_transfer(accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let [source, destination, authority, _token_program] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    let decimals = 6;
+    Transfer {
+        from: source,
+        to: destination,
+        authority,
+        amount,
+    }
+    .invoke()?;
+    TransferChecked {
+        from: source,
+        to: destination,
+        authority,
+        amount,
+        decimals,
+    }
+    .invoke()
+}"#;
+        let cpis = parse_cpis_from_function("process_transfer", source).unwrap();
+        assert_eq!(cpis.len(), 2);
+        // Unqualified structs may come from the system or the token crate
+        assert_eq!(cpis[0].program, "");
+        for cpi in &cpis {
+            assert_eq!(
+                cpi.accounts,
+                vec!["from: source", "to: destination", "authority: authority"]
+            );
+        }
+    }
+}
@@ -15,7 +15,8 @@ use std::collections::HashSet;
 use std::fs;
 
 use crate::batbelt::metadata::entrypoint_metadata::EntrypointMetadata;
-use crate::batbelt::metadata::{BatMetadata, SourceCodeMetadata};
+use crate::batbelt::metadata::{BatMetadata, BatMetadataParser, SourceCodeMetadata};
+use crate::batbelt::parser::cpi_parser::{parse_cpis_from_function, CpiCall};
 use crate::batbelt::parser::function_parser::FunctionParser;
 
 use crate::batbelt::parser::ParserError;
@@ -123,6 +124,10 @@ impl EntrypointParser {
             // For Pinocchio: find ContextAccounts struct in the same file as the entry point.
             // Returns None if the entry point has no associated context struct (e.g. emit_event).
             Self::find_pinocchio_context_accounts(&entrypoint_function).ok()
+        } else if config.project_type == ProjectType::VanillaSolana {
+            // For native programs: the `next_account_info` sequence of the processor.
+            // Returns None if the processor loads no accounts.
+            Self::find_native_context_accounts(&entrypoint_function).ok()
         } else {
             let context_name = Self::get_context_name(entrypoint_name).unwrap();
             let structs_metadata = SourceCodeMetadata::get_filtered_structs_by_program(
@@ -149,7 +154,13 @@ impl EntrypointParser {
             )
         };
 
-        let ep_metadata = EntrypointMetadata::new(
+        // Compute dependencies for the entrypoint function
+        let _ = FunctionParser::new_from_metadata(entrypoint_function.clone());
+        let bat_metadata = BatMetadata::read_metadata().change_context(ParserError)?;
+        let dependencies =
+            Self::resolve_all_dependencies(&entrypoint_function.metadata_id, &bat_metadata);
+
+        let mut ep_metadata = EntrypointMetadata::new(
             entrypoint_name.to_string(),
             context_accounts
                 .as_ref()
@@ -159,17 +170,12 @@ impl EntrypointParser {
             BatMetadata::create_metadata_id(),
             resolved_program_name.clone(),
         );
+        ep_metadata.cpis = Self::parse_cpis(&entrypoint_function, &dependencies);
 
         ep_metadata
             .update_metadata_file()
             .change_context(ParserError)?;
 
-        // Compute dependencies for the entrypoint function
-        let _ = FunctionParser::new_from_metadata(entrypoint_function.clone());
-        let bat_metadata = BatMetadata::read_metadata().change_context(ParserError)?;
-        let dependencies =
-            Self::resolve_all_dependencies(&entrypoint_function.metadata_id, &bat_metadata);
-
         Ok(Self {
             name: entrypoint_name.to_string(),
             program_name: resolved_program_name,
@@ -179,7 +185,27 @@ impl EntrypointParser {
         })
     }
 
-    fn resolve_all_dependencies(
+    /// CPIs performed by the entry point function and its dependencies.
+    fn parse_cpis(
+        entry_point_function: &FunctionSourceCodeMetadata,
+        dependencies: &[FunctionSourceCodeMetadata],
+    ) -> Vec<CpiCall> {
+        std::iter::once(entry_point_function)
+            .chain(dependencies)
+            .flat_map(|function| {
+                let content = function
+                    .to_source_code_parser(None)
+                    .get_source_code_content();
+                parse_cpis_from_function(&function.name, &content).unwrap_or_else(|e| {
+                    log::warn!("Could not parse CPIs of '{}': {:?}", function.name, e);
+                    vec![]
+                })
+            })
+            .collect()
+    }
+
+    /// Dependencies of the entry point function, transitively and without duplicates.
+    pub fn resolve_all_dependencies(
         entrypoint_function_id: &str,
         bat_metadata: &BatMetadata,
     ) -> Vec<FunctionSourceCodeMetadata> {
@@ -228,9 +254,12 @@ impl EntrypointParser {
     ) -> Result<Vec<String>, ParserError> {
         let config = BatConfig::get_config().change_context(ParserError)?;
 
-        // For Pinocchio (and other non-Anchor), entry points are already classified
+        // For Pinocchio and native programs, entry points are already classified
         // per-file by syn_struct_classifier. Read them from BatMetadata.
-        if config.project_type == ProjectType::Pinocchio {
+        if matches!(
+            config.project_type,
+            ProjectType::Pinocchio | ProjectType::VanillaSolana
+        ) {
             let bat_metadata = BatMetadata::read_metadata().change_context(ParserError)?;
             let mut entrypoints_names: Vec<String> = bat_metadata
                 .source_code
@@ -321,6 +350,27 @@ impl EntrypointParser {
         )))
     }
 
+    /// For native entry points: find the ContextAccounts registered for the
+    /// `next_account_info` sequence inside the processor.
+    fn find_native_context_accounts(
+        entrypoint_function: &FunctionSourceCodeMetadata,
+    ) -> Result<StructSourceCodeMetadata, ParserError> {
+        SourceCodeMetadata::get_filtered_structs(None, Some(StructMetadataType::ContextAccounts))
+            .change_context(ParserError)?
+            .into_iter()
+            .find(|s| {
+                s.path == entrypoint_function.path
+                    && s.start_line_index >= entrypoint_function.start_line_index
+                    && s.end_line_index <= entrypoint_function.end_line_index
+            })
+            .ok_or_else(|| {
+                Report::new(ParserError).attach_printable(format!(
+                    "No next_account_info sequence found for native entry point {}",
+                    entrypoint_function.name
+                ))
+            })
+    }
+
     /// Extracts the struct name from `SomeStruct::try_from(accounts)` in a file.
     fn extract_try_from_struct_name(file_content: &str) -> Option<String> {
         // Look for pattern: SomeIdentifier::try_from(accounts
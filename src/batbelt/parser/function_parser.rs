@@ -258,7 +258,7 @@ impl FunctionParser {
                         dependency_function_metadata_id_vec.push(metadata_id);
                     }
                 }
-                Resolution::External(name) => {
+                Resolution::External(name) | Resolution::Cpi(name) => {
                     if !self.external_dependencies.contains(&name) {
                         self.external_dependencies.push(name);
                     }
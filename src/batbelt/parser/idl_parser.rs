@@ -0,0 +1,695 @@
+use crate::batbelt::parser::context_accounts_parser::CAAccountParser;
+use crate::batbelt::parser::solana_account_parser::SolanaAccountType;
+use crate::batbelt::parser::ParserError;
+use error_stack::{Report, Result};
+use inflector::Inflector;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+
+/// Format of an IDL file.
+#[derive(Debug, Clone, Copy, PartialEq, strum_macros::Display)]
+pub enum IdlFormat {
+    /// Anchor 0.30+ (`metadata.spec`)
+    Anchor,
+    /// Anchor before 0.30 (`isMut`/`isSigner`)
+    AnchorLegacy,
+    Codama,
+    Shank,
+}
+
+/// Program IDL, normalized across the Anchor, Codama and Shank formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Idl {
+    pub program_name: String,
+    pub format: IdlFormat,
+    pub instructions: Vec<IdlInstruction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlInstruction {
+    pub name: String,
+    /// Leaf accounts, composite accounts flattened
+    pub accounts: Vec<IdlAccount>,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlAccount {
+    pub name: String,
+    pub writable: bool,
+    /// `None` when the IDL allows both (Codama `"either"`)
+    pub signer: Option<bool>,
+    /// `None` when the IDL doesn't declare the account as a PDA
+    pub pda_seeds: Option<Vec<IdlSeed>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IdlSeed {
+    /// Constant seed, as a string when it is valid UTF-8
+    Const(String),
+    /// Key (or field) of another account of the instruction
+    Account(String),
+    /// Instruction argument
+    Arg(String),
+}
+
+impl fmt::Display for IdlSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdlSeed::Const(value) => write!(f, "b\"{}\"", value),
+            IdlSeed::Account(path) => write!(f, "{}", path),
+            IdlSeed::Arg(path) => write!(f, "{} (arg)", path),
+        }
+    }
+}
+
+impl Idl {
+    /// Loads every `*.json` IDL of `idl_dir` (usually `target/idl`), sorted by file name.
+    pub fn load_from_dir(idl_dir: &str) -> Result<Vec<Self>, ParserError> {
+        let mut paths = fs::read_dir(idl_dir)
+            .map_err(|e| {
+                Report::new(ParserError)
+                    .attach_printable(format!("Failed to read IDL folder {}: {}", idl_dir, e))
+            })?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                let content = fs::read_to_string(path).map_err(|e| {
+                    Report::new(ParserError).attach_printable(format!(
+                        "Failed to read {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                Self::from_json(&content).map_err(|report| {
+                    report.attach_printable(format!("IDL file: {}", path.display()))
+                })
+            })
+            .collect()
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, ParserError> {
+        let idl: Value = serde_json::from_str(content).map_err(|e| {
+            Report::new(ParserError).attach_printable(format!("Invalid IDL JSON: {}", e))
+        })?;
+
+        if idl["standard"] == "codama" || idl["kind"] == "rootNode" {
+            return Ok(Self::from_codama(&idl["program"]));
+        }
+
+        let format = if idl["metadata"]["spec"].is_string() {
+            IdlFormat::Anchor
+        } else if idl["metadata"]["origin"] == "shank" {
+            IdlFormat::Shank
+        } else {
+            IdlFormat::AnchorLegacy
+        };
+        let program_name = idl["metadata"]["name"]
+            .as_str()
+            .or(idl["name"].as_str())
+            .ok_or_else(|| Report::new(ParserError).attach_printable("IDL without program name"))?;
+        let instructions = json_array(&idl["instructions"])
+            .iter()
+            .map(|instruction| {
+                let mut accounts = vec![];
+                flatten_anchor_accounts(json_array(&instruction["accounts"]), &mut accounts);
+                IdlInstruction {
+                    name: json_name(instruction),
+                    accounts,
+                    args: json_array(&instruction["args"])
+                        .iter()
+                        .map(json_name)
+                        .collect(),
+                }
+            })
+            .collect();
+        Ok(Self {
+            program_name: program_name.to_string(),
+            format,
+            instructions,
+        })
+    }
+
+    fn from_codama(program: &Value) -> Self {
+        let pdas = json_array(&program["pdas"]);
+        let instructions = json_array(&program["instructions"])
+            .iter()
+            .map(|instruction| IdlInstruction {
+                name: json_name(instruction),
+                accounts: json_array(&instruction["accounts"])
+                    .iter()
+                    .map(|account| IdlAccount {
+                        name: json_name(account),
+                        writable: account["isWritable"].as_bool().unwrap_or(false),
+                        signer: account["isSigner"].as_bool(),
+                        pda_seeds: codama_pda_seeds(&account["defaultValue"], pdas),
+                    })
+                    .collect(),
+                args: json_array(&instruction["arguments"])
+                    .iter()
+                    // The discriminator is an argument with a default value
+                    .filter(|argument| argument["defaultValueStrategy"] != "omitted")
+                    .map(json_name)
+                    .collect(),
+            })
+            .collect();
+        Self {
+            program_name: program["name"].as_str().unwrap_or_default().to_string(),
+            format: IdlFormat::Codama,
+            instructions,
+        }
+    }
+
+    /// true if the IDL belongs to the sonar program `program_name`.
+    pub fn matches_program(&self, program_name: &str) -> bool {
+        self.program_name.to_snake_case() == program_name.to_snake_case()
+    }
+
+    /// Reconciles the IDL instructions with the entry points and context accounts found by
+    /// sonar. PDA seeds are only compared when `compare_seeds` is set, since only the
+    /// Anchor parser extracts them.
+    pub fn cross_check(
+        &self,
+        sonar_instructions: &[SonarInstruction],
+        compare_seeds: bool,
+    ) -> Vec<IdlDiscrepancy> {
+        let mut discrepancies = vec![];
+        for instruction in &self.instructions {
+            let discrepancy =
+                |account: Option<&str>, kind, idl: String, sonar: String| IdlDiscrepancy {
+                    program_name: self.program_name.clone(),
+                    instruction: instruction.name.clone(),
+                    account: account.map(|a| a.to_string()),
+                    kind,
+                    idl,
+                    sonar,
+                };
+            let Some(sonar_instruction) = sonar_instructions
+                .iter()
+                .find(|si| is_same_instruction(&instruction.name, &si.name))
+            else {
+                discrepancies.push(discrepancy(
+                    None,
+                    IdlDiscrepancyKind::MissingInstruction,
+                    instruction.name.clone(),
+                    "-".to_string(),
+                ));
+                continue;
+            };
+
+            if let Some(sonar_args) = &sonar_instruction.args {
+                let idl_args: Vec<String> =
+                    instruction.args.iter().map(|a| a.to_snake_case()).collect();
+                if idl_args != *sonar_args {
+                    discrepancies.push(discrepancy(
+                        None,
+                        IdlDiscrepancyKind::Args,
+                        idl_args.join(", "),
+                        sonar_args.join(", "),
+                    ));
+                }
+            }
+
+            for idl_account in &instruction.accounts {
+                let Some(sonar_account) = sonar_instruction
+                    .accounts
+                    .iter()
+                    .find(|acc| is_same_account(&idl_account.name, &acc.account_name))
+                else {
+                    discrepancies.push(discrepancy(
+                        Some(&idl_account.name),
+                        IdlDiscrepancyKind::MissingAccount,
+                        idl_account.name.clone(),
+                        "-".to_string(),
+                    ));
+                    continue;
+                };
+                let account = Some(sonar_account.account_name.as_str());
+                let sonar_signer = is_signer(sonar_account);
+                if idl_account
+                    .signer
+                    .is_some_and(|signer| signer != sonar_signer)
+                {
+                    discrepancies.push(discrepancy(
+                        account,
+                        IdlDiscrepancyKind::Signer,
+                        (!sonar_signer).to_string(),
+                        sonar_signer.to_string(),
+                    ));
+                }
+                let sonar_writable = is_writable(sonar_account);
+                if idl_account.writable != sonar_writable {
+                    discrepancies.push(discrepancy(
+                        account,
+                        IdlDiscrepancyKind::Writable,
+                        idl_account.writable.to_string(),
+                        sonar_writable.to_string(),
+                    ));
+                }
+                if let (true, Some(idl_seeds)) = (compare_seeds, &idl_account.pda_seeds) {
+                    if !seeds_match(idl_seeds, &sonar_account.seeds) {
+                        discrepancies.push(discrepancy(
+                            account,
+                            IdlDiscrepancyKind::Seeds,
+                            idl_seeds
+                                .iter()
+                                .map(|seed| seed.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                            if sonar_account.seeds.is_empty() {
+                                "-".to_string()
+                            } else {
+                                sonar_account.seeds.join(", ")
+                            },
+                        ));
+                    }
+                }
+            }
+
+            for sonar_account in sonar_instruction.accounts {
+                if !instruction
+                    .accounts
+                    .iter()
+                    .any(|acc| is_same_account(&acc.name, &sonar_account.account_name))
+                {
+                    discrepancies.push(discrepancy(
+                        Some(&sonar_account.account_name),
+                        IdlDiscrepancyKind::AccountNotInIdl,
+                        "-".to_string(),
+                        sonar_account.account_name.clone(),
+                    ));
+                }
+            }
+        }
+        discrepancies
+    }
+}
+
+/// Entry point found by sonar, as compared with an IDL instruction.
+pub struct SonarInstruction<'a> {
+    pub name: String,
+    /// Instruction arguments of the entry point function, `None` when it receives the raw
+    /// instruction data
+    pub args: Option<Vec<String>>,
+    pub accounts: &'a [CAAccountParser],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, strum_macros::Display)]
+pub enum IdlDiscrepancyKind {
+    /// Instruction of the IDL without a sonar entry point
+    MissingInstruction,
+    /// Account of the IDL missing from the parsed context accounts
+    MissingAccount,
+    /// Parsed context account the IDL doesn't declare
+    AccountNotInIdl,
+    Signer,
+    Writable,
+    Args,
+    Seeds,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdlDiscrepancy {
+    pub program_name: String,
+    pub instruction: String,
+    pub account: Option<String>,
+    pub kind: IdlDiscrepancyKind,
+    /// Value declared by the IDL
+    pub idl: String,
+    /// Value found by sonar
+    pub sonar: String,
+}
+
+fn json_array(value: &Value) -> &[Value] {
+    value.as_array().map(|a| a.as_slice()).unwrap_or_default()
+}
+
+fn json_name(value: &Value) -> String {
+    value["name"].as_str().unwrap_or_default().to_string()
+}
+
+/// Flattens the (possibly nested) accounts of an Anchor or Shank instruction.
+fn flatten_anchor_accounts(accounts: &[Value], result: &mut Vec<IdlAccount>) {
+    for account in accounts {
+        if account["accounts"].is_array() {
+            flatten_anchor_accounts(json_array(&account["accounts"]), result);
+            continue;
+        }
+        let flag = |name: &str, legacy_name: &str| {
+            account[name]
+                .as_bool()
+                .or(account[legacy_name].as_bool())
+                .unwrap_or(false)
+        };
+        result.push(IdlAccount {
+            name: json_name(account),
+            writable: flag("writable", "isMut"),
+            signer: Some(flag("signer", "isSigner")),
+            pda_seeds: account["pda"]["seeds"].as_array().map(|seeds| {
+                seeds
+                    .iter()
+                    .map(|seed| {
+                        let path = seed["path"].as_str().unwrap_or_default().to_string();
+                        match seed["kind"].as_str() {
+                            Some("const") => IdlSeed::Const(const_seed_value(&seed["value"])),
+                            Some("arg") => IdlSeed::Arg(path),
+                            _ => IdlSeed::Account(path),
+                        }
+                    })
+                    .collect()
+            }),
+        });
+    }
+}
+
+/// Seeds of a Codama account defaulting to a `pdaValueNode`: the constant seeds of the
+/// linked (or inline) PDA, and the account or argument bound to each variable seed.
+fn codama_pda_seeds(default_value: &Value, pdas: &[Value]) -> Option<Vec<IdlSeed>> {
+    if default_value["kind"] != "pdaValueNode" {
+        return None;
+    }
+    let pda = &default_value["pda"];
+    let pda = if pda["kind"] == "pdaLinkNode" {
+        pdas.iter().find(|p| p["name"] == pda["name"])?
+    } else {
+        pda
+    };
+    let bound_values = json_array(&default_value["seeds"]);
+    Some(
+        json_array(&pda["seeds"])
+            .iter()
+            .map(|seed| {
+                if seed["kind"] == "constantPdaSeedNode" {
+                    let value = &seed["value"];
+                    return IdlSeed::Const(
+                        value["string"]
+                            .as_str()
+                            .or(value["data"].as_str())
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                }
+                let name = json_name(seed);
+                let bound = bound_values
+                    .iter()
+                    .find(|v| v["name"] == seed["name"])
+                    .map(|v| &v["value"]);
+                match bound {
+                    Some(value) if value["kind"] == "argumentValueNode" => {
+                        IdlSeed::Arg(json_name(value))
+                    }
+                    Some(value) => IdlSeed::Account(json_name(value)),
+                    None => IdlSeed::Account(name),
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Anchor stores constant seeds as bytes (0.30+) or as a typed value (legacy).
+fn const_seed_value(value: &Value) -> String {
+    if let Some(value) = value.as_str() {
+        return value.to_string();
+    }
+    let bytes: Vec<u8> = json_array(value)
+        .iter()
+        .filter_map(|b| b.as_u64().map(|b| b as u8))
+        .collect();
+    String::from_utf8(bytes.clone()).unwrap_or_else(|_| format!("{:?}", bytes))
+}
+
+/// Anchor IDLs keep the function name, Shank and Codama the instruction variant; native
+/// processors are usually `process_<instruction>`.
+fn is_same_instruction(idl_name: &str, entry_point_name: &str) -> bool {
+    let idl_name = idl_name.to_snake_case();
+    let entry_point_name = entry_point_name.to_snake_case();
+    entry_point_name == idl_name || entry_point_name.trim_start_matches("process_") == idl_name
+}
+
+/// Native processors often suffix the loaded accounts with `_info`.
+fn is_same_account(idl_name: &str, sonar_name: &str) -> bool {
+    let idl_name = idl_name.to_snake_case();
+    let sonar_name = sonar_name.to_snake_case();
+    sonar_name == idl_name
+        || sonar_name
+            .trim_end_matches("_info")
+            .trim_end_matches("_account")
+            == idl_name
+}
+
+fn is_signer(account: &CAAccountParser) -> bool {
+    account.solana_account_type == SolanaAccountType::Signer
+        || account.account_wrapper_name == "Signer"
+        || account.validations.iter().any(|v| v.trim() == "signer")
+}
+
+fn is_writable(account: &CAAccountParser) -> bool {
+    account.is_mut || account.is_init || account.is_close || account.realloc.is_some()
+}
+
+/// Compares seeds one by one: constant seeds against string literals, account and
+/// argument seeds by name. Constants referenced by name are accepted as is.
+fn seeds_match(idl_seeds: &[IdlSeed], sonar_seeds: &[String]) -> bool {
+    idl_seeds.len() == sonar_seeds.len()
+        && idl_seeds
+            .iter()
+            .zip(sonar_seeds)
+            .all(|(idl_seed, sonar_seed)| match idl_seed {
+                IdlSeed::Const(value) => {
+                    !sonar_seed.contains('"') || sonar_seed.contains(&format!("\"{}\"", value))
+                }
+                IdlSeed::Account(path) | IdlSeed::Arg(path) => {
+                    let root = path.split('.').next().unwrap_or_default().to_snake_case();
+                    sonar_seed.contains(&root)
+                }
+            })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANCHOR_IDL: &str = r#"{
+  "address": "Vau1t11111111111111111111111111111111111111",
+  "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+  "instructions": [
+    {
+      "name": "deposit",
+      "discriminator": [242, 35, 198, 137, 82, 225, 242, 182],
+      "accounts": [
+        { "name": "user", "writable": true, "signer": true },
+        {
+          "name": "vault",
+          "writable": true,
+          "pda": {
+            "seeds": [
+              { "kind": "const", "value": [118, 97, 117, 108, 116] },
+              { "kind": "account", "path": "user" }
+            ]
+          }
+        },
+        { "name": "system_program", "address": "11111111111111111111111111111111" }
+      ],
+      "args": [{ "name": "amount", "type": "u64" }]
+    },
+    {
+      "name": "close",
+      "discriminator": [98, 165, 201, 177, 108, 65, 206, 96],
+      "accounts": [],
+      "args": []
+    }
+  ]
+}"#;
+
+    const CODAMA_IDL: &str = r#"{
+  "kind": "rootNode",
+  "standard": "codama",
+  "version": "1.0.0",
+  "program": {
+    "kind": "programNode",
+    "name": "vault",
+    "pdas": [
+      {
+        "kind": "pdaNode",
+        "name": "vault",
+        "seeds": [
+          { "kind": "constantPdaSeedNode", "value": { "kind": "stringValueNode", "string": "vault" } },
+          { "kind": "variablePdaSeedNode", "name": "owner" }
+        ]
+      }
+    ],
+    "instructions": [
+      {
+        "kind": "instructionNode",
+        "name": "deposit",
+        "accounts": [
+          { "kind": "instructionAccountNode", "name": "user", "isWritable": true, "isSigner": "either" },
+          {
+            "kind": "instructionAccountNode",
+            "name": "vault",
+            "isWritable": true,
+            "isSigner": false,
+            "defaultValue": {
+              "kind": "pdaValueNode",
+              "pda": { "kind": "pdaLinkNode", "name": "vault" },
+              "seeds": [
+                { "kind": "pdaSeedValueNode", "name": "owner", "value": { "kind": "accountValueNode", "name": "user" } }
+              ]
+            }
+          }
+        ],
+        "arguments": [
+          { "kind": "instructionArgumentNode", "name": "discriminator", "defaultValueStrategy": "omitted" },
+          { "kind": "instructionArgumentNode", "name": "amount" }
+        ]
+      }
+    ]
+  }
+}"#;
+
+    const SHANK_IDL: &str = r#"{
+  "version": "0.1.0",
+  "name": "vault",
+  "instructions": [
+    {
+      "name": "Deposit",
+      "accounts": [
+        { "name": "user", "isMut": true, "isSigner": true },
+        { "name": "vault", "isMut": true, "isSigner": false }
+      ],
+      "args": [{ "name": "amount", "type": "u64" }],
+      "discriminant": { "type": "u8", "value": 0 }
+    }
+  ],
+  "metadata": { "origin": "shank" }
+}"#;
+
+    fn ca_account(name: &str, signer: bool, is_mut: bool, seeds: &[&str]) -> CAAccountParser {
+        CAAccountParser {
+            content: String::new(),
+            solana_account_type: if signer {
+                SolanaAccountType::Signer
+            } else {
+                SolanaAccountType::UncheckedAccount
+            },
+            account_struct_name: String::new(),
+            account_wrapper_name: String::new(),
+            lifetime_name: String::new(),
+            account_name: name.to_string(),
+            is_pda: !seeds.is_empty(),
+            is_init: false,
+            is_mut,
+            is_close: false,
+            seeds: seeds.iter().map(|s| s.to_string()).collect(),
+            rent_exemption_account: String::new(),
+            validations: vec![],
+            owner: None,
+            token_mint: None,
+            space: None,
+            rent_exempt: false,
+            realloc: None,
+            bump: None,
+        }
+    }
+
+    #[test]
+    fn test_idl_formats() {
+        let anchor = Idl::from_json(ANCHOR_IDL).unwrap();
+        let codama = Idl::from_json(CODAMA_IDL).unwrap();
+        let shank = Idl::from_json(SHANK_IDL).unwrap();
+        assert_eq!(
+            (anchor.format, codama.format, shank.format),
+            (IdlFormat::Anchor, IdlFormat::Codama, IdlFormat::Shank)
+        );
+
+        let vault_seeds = Some(vec![
+            IdlSeed::Const("vault".to_string()),
+            IdlSeed::Account("user".to_string()),
+        ]);
+        assert_eq!(anchor.instructions[0].accounts[1].pda_seeds, vault_seeds);
+        assert_eq!(codama.instructions[0].accounts[1].pda_seeds, vault_seeds);
+        assert_eq!(codama.instructions[0].accounts[0].signer, None);
+        assert_eq!(codama.instructions[0].args, vec!["amount"]);
+        assert_eq!(
+            shank.instructions[0].accounts[0],
+            IdlAccount {
+                name: "user".to_string(),
+                writable: true,
+                signer: Some(true),
+                pda_seeds: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cross_check() {
+        let idl = Idl::from_json(ANCHOR_IDL).unwrap();
+        let accounts = vec![
+            ca_account("user", true, true, &[]),
+            ca_account(
+                "vault",
+                false,
+                false,
+                &["b\"vault\"", "user.key().as_ref()"],
+            ),
+            ca_account("system_program", false, false, &[]),
+        ];
+        let sonar_instructions = vec![SonarInstruction {
+            name: "deposit".to_string(),
+            args: Some(vec!["amount".to_string()]),
+            accounts: &accounts,
+        }];
+
+        let discrepancies: Vec<(String, Option<String>, IdlDiscrepancyKind)> = idl
+            .cross_check(&sonar_instructions, true)
+            .into_iter()
+            .map(|d| (d.instruction, d.account, d.kind))
+            .collect();
+        assert_eq!(
+            discrepancies,
+            vec![
+                (
+                    "deposit".to_string(),
+                    Some("vault".to_string()),
+                    IdlDiscrepancyKind::Writable
+                ),
+                (
+                    "close".to_string(),
+                    None,
+                    IdlDiscrepancyKind::MissingInstruction
+                ),
+            ]
+        );
+
+        // Native processor, with the raw instruction data and `_info` accounts
+        let shank = Idl::from_json(SHANK_IDL).unwrap();
+        let accounts = vec![
+            ca_account("user_info", false, true, &[]),
+            ca_account("vault_info", false, true, &[]),
+        ];
+        let discrepancies = shank.cross_check(
+            &[SonarInstruction {
+                name: "process_deposit".to_string(),
+                args: None,
+                accounts: &accounts,
+            }],
+            false,
+        );
+        assert_eq!(
+            discrepancies,
+            vec![IdlDiscrepancy {
+                program_name: "vault".to_string(),
+                instruction: "Deposit".to_string(),
+                account: Some("user_info".to_string()),
+                kind: IdlDiscrepancyKind::Signer,
+                idl: "true".to_string(),
+                sonar: "false".to_string(),
+            }]
+        );
+    }
+}
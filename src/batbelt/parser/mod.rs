@@ -7,9 +7,12 @@ use std::fmt;
 pub mod call_resolver;
 pub mod code_overhaul_parser;
 pub mod context_accounts_parser;
+pub mod cpi_parser;
 pub mod entrypoint_parser;
 pub mod file_scope;
 pub mod function_parser;
+pub mod idl_parser;
+pub mod native_context_accounts_parser;
 pub mod pinocchio_context_accounts_parser;
 pub mod solana_account_parser;
 pub mod source_code_parser;
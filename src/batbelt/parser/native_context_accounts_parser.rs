@@ -0,0 +1,359 @@
+use crate::batbelt::parser::syn_context_accounts_parser::{
+    ParsedAccount, ParsedAccountAttributes, ParsedAccountsStruct,
+};
+use crate::batbelt::parser::ParserError;
+use error_stack::{Report, Result};
+
+/// Associated functions deserializing the data of an account.
+const DESERIALIZE_METHODS: &[&str] = &[
+    "unpack",
+    "unpack_unchecked",
+    "unpack_from_slice",
+    "try_from_slice",
+    "deserialize",
+    "load",
+];
+
+/// Field and method accesses writing to an account.
+const WRITE_ACCESSES: &[&str] = &[
+    "data . borrow_mut",
+    "try_borrow_mut_data",
+    "lamports . borrow_mut",
+    "try_borrow_mut_lamports",
+    "assign",
+    "realloc",
+    "resize",
+];
+
+/// Parse the context accounts of a native `solana_program` processor: the accounts loaded
+/// in order with `let account = next_account_info(accounts_iter)?;`.
+///
+/// As with Pinocchio, account semantics are **heuristic-based**, inferred from the uses of
+/// each account in the processor body:
+/// - `account.is_signer` → `Signer`
+/// - `account.is_writable` or a write to its data or lamports → mutable
+/// - `account.owner` compared → program owned
+/// - `account.key` compared with the system or token program id → that `Program`
+/// - `State::unpack(&account.data...)` (or `try_from_slice`, `deserialize`...) → `Mint`,
+///   `TokenAccount` or the `State` program account
+pub fn parse_native_context_accounts_from_function(
+    name: &str,
+    function_source: &str,
+) -> Result<ParsedAccountsStruct, ParserError> {
+    use quote::ToTokens;
+    let item_fn: syn::ItemFn = syn::parse_str(function_source).map_err(|e| {
+        Report::new(ParserError).attach_printable(format!("Failed to parse Rust function: {}", e))
+    })?;
+
+    let mut visitor = NextAccountInfoVisitor {
+        account_names: Vec::new(),
+    };
+    syn::visit::Visit::visit_block(&mut visitor, &item_fn.block);
+
+    // to_token_stream produces a single line; split by `;` to get statements
+    let body_text = item_fn.block.to_token_stream().to_string();
+    let statements: Vec<String> = body_text
+        .split(';')
+        .map(|statement| format!(" {} ", statement.trim()))
+        .collect();
+
+    let accounts = visitor
+        .account_names
+        .iter()
+        .map(|account_name| build_parsed_account(account_name, &statements))
+        .collect();
+
+    Ok(ParsedAccountsStruct {
+        name: name.to_string(),
+        accounts,
+    })
+}
+
+/// 0-based offsets of the first and last lines of `function_source` loading an account
+/// with `next_account_info`.
+pub fn next_account_info_lines(function_source: &str) -> Option<(usize, usize)> {
+    let lines: Vec<usize> = function_source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains("next_account_info("))
+        .map(|(index, _)| index)
+        .collect();
+    Some((*lines.first()?, *lines.last()?))
+}
+
+struct NextAccountInfoVisitor {
+    account_names: Vec<String>,
+}
+
+impl<'ast> syn::visit::Visit<'ast> for NextAccountInfoVisitor {
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        use quote::ToTokens;
+        if let Some(init) = &local.init {
+            let init_text = init.expr.to_token_stream().to_string();
+            if init_text.contains("next_account_info (") {
+                if let Some(account_name) = pat_ident(&local.pat) {
+                    self.account_names.push(account_name);
+                }
+            }
+        }
+        syn::visit::visit_local(self, local);
+    }
+}
+
+fn pat_ident(pat: &syn::Pat) -> Option<String> {
+    match pat {
+        syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        syn::Pat::Type(pat_type) => pat_ident(&pat_type.pat),
+        _ => None,
+    }
+}
+
+fn build_parsed_account(account_name: &str, statements: &[String]) -> ParsedAccount {
+    let mut attrs = ParsedAccountAttributes::default();
+    let mut wrapper = "AccountInfo".to_string();
+    let mut struct_name = "AccountInfo".to_string();
+    let mut validations: Vec<String> = Vec::new();
+    let access = |member: &str| format!(" {} . {}", account_name, member);
+
+    for statement in statements
+        .iter()
+        .filter(|statement| statement.contains(&format!(" {} . ", account_name)))
+    {
+        if statement.contains(&access("is_signer")) {
+            wrapper = "Signer".to_string();
+            struct_name = "Signer".to_string();
+            push_unique(&mut validations, "is_signer");
+        }
+        if statement.contains(&access("is_writable")) {
+            attrs.is_mut = true;
+            push_unique(&mut validations, "is_writable");
+        }
+        if WRITE_ACCESSES
+            .iter()
+            .any(|write_access| statement.contains(&access(write_access)))
+        {
+            attrs.is_mut = true;
+        }
+        if compared_operand(statement, &access("owner")).is_some() {
+            if wrapper == "AccountInfo" {
+                wrapper = "Account".to_string();
+                struct_name = "ProgramAccount".to_string();
+            }
+            push_unique(&mut validations, "owner");
+        }
+        if let Some(operand) = compared_operand(statement, &access("key")) {
+            // `system_program::ID`, `spl_token::id()`: the account is the program itself
+            let modules: Vec<&str> = operand.split(" :: ").collect();
+            let modules = &modules[..modules.len() - 1];
+            if modules.contains(&"system_program") {
+                wrapper = "Program".to_string();
+                struct_name = "SystemProgram".to_string();
+            } else if modules.contains(&"spl_token") {
+                wrapper = "Program".to_string();
+                struct_name = "TokenProgram".to_string();
+            }
+            push_unique(&mut validations, "key");
+        }
+        if let Some(type_name) = deserialized_type(statement) {
+            match type_name.as_str() {
+                "Mint" => {
+                    wrapper = "Mint".to_string();
+                    struct_name = "Mint".to_string();
+                }
+                "Account" | "TokenAccount" => {
+                    wrapper = "TokenAccount".to_string();
+                    struct_name = "TokenAccount".to_string();
+                }
+                _ => {
+                    wrapper = "Account".to_string();
+                    struct_name = type_name;
+                }
+            }
+        }
+    }
+
+    // Store validations in the constraints field for later use
+    attrs.constraints = validations;
+
+    ParsedAccount {
+        field_name: account_name.to_string(),
+        account_wrapper_name: wrapper,
+        account_struct_name: struct_name,
+        lifetime_name: "'a".to_string(),
+        is_boxed: false,
+        attributes: attrs,
+        raw_type: "&AccountInfo<'a>".to_string(),
+    }
+}
+
+/// The operand `access` (` account . key`...) is compared with in `statement`, or the
+/// `module :: check_id` checking it.
+fn compared_operand(statement: &str, access: &str) -> Option<String> {
+    // Token streams print groups as `f (a , b)` and `f ()`
+    let statement = statement.replace('(', " ( ").replace(')', " ) ");
+    let tokens: Vec<&str> = statement.split_whitespace().collect();
+    let target: Vec<&str> = access.split_whitespace().collect();
+    let is_comparison = |token: &&str| *token == "==" || *token == "!=";
+    (0..(tokens.len() + 1).saturating_sub(target.len()))
+        .filter(|&index| tokens[index..index + target.len()] == target[..])
+        .find_map(|index| {
+            let after = index + target.len();
+            // `account.key == operand`
+            if tokens.get(after).is_some_and(is_comparison) {
+                return Some(path_after(&tokens[after + 1..]));
+            }
+            // `operand != *account.key`
+            let before = index - usize::from(index > 0 && tokens[index - 1] == "*");
+            if before > 0 && is_comparison(&tokens[before - 1]) {
+                return Some(path_before(&tokens[..before - 1]));
+            }
+            // `module::check_id(account.key)`
+            if index >= 2 && tokens[index - 1] == "(" && tokens[index - 2] == "check_id" {
+                return Some(path_before(&tokens[..index - 1]));
+            }
+            None
+        })
+}
+
+fn is_ident(token: &str) -> bool {
+    token.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Length of the `a :: b :: c` path starting `tokens`.
+fn path_len<'t>(mut tokens: impl Iterator<Item = &'t &'t str>) -> usize {
+    let mut len = 0;
+    while tokens.next().is_some_and(|token| is_ident(token)) {
+        len += 1;
+        if tokens.next() != Some(&"::") {
+            break;
+        }
+        len += 1;
+    }
+    // Drop a dangling `::`
+    len - usize::from(len % 2 == 0 && len > 0)
+}
+
+/// The path starting `tokens`, after any `&` or `*`.
+fn path_after(tokens: &[&str]) -> String {
+    let start = tokens
+        .iter()
+        .position(|token| *token != "&" && *token != "*")
+        .unwrap_or(tokens.len());
+    let tokens = &tokens[start..];
+    tokens[..path_len(tokens.iter())].join(" ")
+}
+
+/// The path ending `tokens`, without the `( )` of a call like `spl_token :: id ( )`.
+fn path_before(tokens: &[&str]) -> String {
+    let tokens = tokens.strip_suffix(&["(", ")"]).unwrap_or(tokens);
+    tokens[tokens.len() - path_len(tokens.iter().rev())..].join(" ")
+}
+
+/// Extracts `State` from `State :: unpack (...)`-like statements.
+fn deserialized_type(statement: &str) -> Option<String> {
+    DESERIALIZE_METHODS.iter().find_map(|method| {
+        let pos = statement.find(&format!(" :: {} (", method))?;
+        let type_name = statement[..pos].split_whitespace().last()?;
+        type_name
+            .starts_with(|c: char| c.is_uppercase())
+            .then(|| type_name.to_string())
+    })
+}
+
+fn push_unique(validations: &mut Vec<String>, validation: &str) {
+    if !validations.iter().any(|v| v == validation) {
+        validations.push(validation.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROCESS_DEPOSIT: &str = r#"pub fn process_deposit(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let depositor = next_account_info(accounts_iter)?;
+    let vault = next_account_info(accounts_iter)?;
+    let mint = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let unchecked = next_account_info(accounts_iter)?;
+
+    if !depositor.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if vault.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if *system_program.key != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let _mint = Mint::unpack(&mint.data.borrow())?;
+    let mut vault_state = VaultState::try_from_slice(&vault.data.borrow())?;
+    vault_state.balance += amount;
+    vault_state.serialize(&mut &mut vault.data.borrow_mut()[..])?;
+    Ok(())
+}"#;
+
+    #[test]
+    fn test_next_account_info_accounts() {
+        let result =
+            parse_native_context_accounts_from_function("ProcessDepositAccounts", PROCESS_DEPOSIT)
+                .unwrap();
+        let accounts: Vec<(&str, &str, &str, bool)> = result
+            .accounts
+            .iter()
+            .map(|acc| {
+                (
+                    acc.field_name.as_str(),
+                    acc.account_wrapper_name.as_str(),
+                    acc.account_struct_name.as_str(),
+                    acc.attributes.is_mut,
+                )
+            })
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                ("depositor", "Signer", "Signer", false),
+                ("vault", "Account", "VaultState", true),
+                ("mint", "Mint", "Mint", false),
+                ("system_program", "Program", "SystemProgram", false),
+                ("unchecked", "AccountInfo", "AccountInfo", false),
+            ]
+        );
+        assert_eq!(result.accounts[0].attributes.constraints, vec!["is_signer"]);
+        assert_eq!(result.accounts[1].attributes.constraints, vec!["owner"]);
+        assert_eq!(next_account_info_lines(PROCESS_DEPOSIT), Some((6, 10)));
+    }
+
+    #[test]
+    fn test_compared_operand() {
+        let statement = " if * system_program . key != system_program :: ID { return Err (e) } ";
+        assert_eq!(
+            compared_operand(statement, " system_program . key"),
+            Some("system_program :: ID".to_string())
+        );
+        let statement = " if spl_token :: id () != * token_program . key { } ";
+        assert_eq!(
+            compared_operand(statement, " token_program . key"),
+            Some("spl_token :: id".to_string())
+        );
+        let statement = " if ! spl_token :: check_id (token_program . key) { } ";
+        assert_eq!(
+            compared_operand(statement, " token_program . key"),
+            Some("spl_token :: check_id".to_string())
+        );
+        // The system program module is mentioned, but not what the key is compared with
+        let statement =
+            " if * payer . key == admin && system_program :: ID != * program . key { } ";
+        assert_eq!(
+            compared_operand(statement, " payer . key"),
+            Some("admin".to_string())
+        );
+        assert_eq!(compared_operand(statement, " payer . owner"), None);
+    }
+}
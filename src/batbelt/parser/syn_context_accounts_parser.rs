@@ -285,7 +285,7 @@ fn parse_account_attr_tokens(
 
 /// Normalize token stream string by removing extra spaces around `.`, `::`, `(`, `)`, `!`, `&`, `<`, `>`.
 /// proc_macro2 TokenStream::to_string() inserts spaces like `state . key ()` → `state.key()`.
-fn normalize_token_str(s: &str) -> String {
+pub(crate) fn normalize_token_str(s: &str) -> String {
     let s = s.trim().to_string();
     // Remove spaces around `.`
     let s = regex::Regex::new(r"\s*\.\s*")
@@ -6,6 +6,9 @@ pub struct FileClassification {
     pub solana_account_names: HashSet<String>,
     pub entrypoint_function_names: HashSet<String>,
     pub pinocchio_context_accounts_names: HashSet<String>,
+    /// Per-instruction processors of a native `solana_program` program, as called by the
+    /// instruction dispatch: `module::function` when module-qualified, `function` otherwise
+    pub native_processor_paths: HashSet<String>,
 }
 
 pub fn classify_file_from_path(path: &str) -> FileClassification {
@@ -19,6 +22,7 @@ pub fn classify_file(file_content: &str) -> FileClassification {
         solana_account_names: HashSet::new(),
         entrypoint_function_names: HashSet::new(),
         pinocchio_context_accounts_names: HashSet::new(),
+        native_processor_paths: HashSet::new(),
     };
 
     let Ok(file) = syn::parse_file(file_content) else {
@@ -64,6 +68,7 @@ pub fn classify_file(file_content: &str) -> FileClassification {
             _ => {}
         }
     }
+    classification.native_processor_paths = extract_native_processor_paths(&file);
 
     classification
 }
@@ -229,3 +234,296 @@ fn extract_entrypoint_functions(item_mod: &syn::ItemMod, classification: &mut Fi
         }
     }
 }
+
+/// Finds the per-instruction processors of a native `solana_program` program.
+///
+/// A dispatcher is a function receiving the `&[AccountInfo]` slice (or the one registered
+/// with `entrypoint!`) that matches on the variants of an `*Instruction` enum. For every
+/// arm, the first call forwarding the accounts slice is the processor. A function
+/// registered with `entrypoint!` that loads its accounts with `next_account_info` and
+/// doesn't dispatch is a single-instruction processor.
+fn extract_native_processor_paths(file: &syn::File) -> HashSet<String> {
+    let entrypoint_names: HashSet<String> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Macro(item_macro) => extract_entrypoint_macro_function(item_macro),
+            _ => None,
+        })
+        .collect();
+
+    let mut functions: Vec<(&syn::Signature, &syn::Block)> = Vec::new();
+    for item in &file.items {
+        match item {
+            syn::Item::Fn(item_fn) => functions.push((&item_fn.sig, &item_fn.block)),
+            syn::Item::Impl(item_impl) => {
+                for impl_item in &item_impl.items {
+                    if let syn::ImplItem::Fn(impl_fn) = impl_item {
+                        functions.push((&impl_fn.sig, &impl_fn.block));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut processor_paths = HashSet::new();
+    for (sig, block) in functions {
+        let is_entrypoint = entrypoint_names.contains(&sig.ident.to_string());
+        let Some(accounts_param) = native_accounts_param(sig, is_entrypoint) else {
+            continue;
+        };
+        let mut visitor = InstructionDispatchVisitor {
+            accounts_param,
+            processor_paths: Vec::new(),
+            dispatches: false,
+            loads_accounts: false,
+        };
+        syn::visit::Visit::visit_block(&mut visitor, block);
+        if visitor.dispatches {
+            processor_paths.extend(visitor.processor_paths);
+        } else if is_entrypoint && visitor.loads_accounts {
+            processor_paths.insert(sig.ident.to_string());
+        }
+    }
+    processor_paths
+}
+
+/// Extracts `process_instruction` from `entrypoint!(process_instruction);`.
+fn extract_entrypoint_macro_function(item_macro: &syn::ItemMacro) -> Option<String> {
+    let last_seg = item_macro.mac.path.segments.last()?;
+    if last_seg.ident != "entrypoint" {
+        return None;
+    }
+    let function_path: syn::Path = item_macro.mac.parse_body().ok()?;
+    Some(function_path.segments.last()?.ident.to_string())
+}
+
+/// Name of the parameter holding the accounts slice: the one typed with `AccountInfo`, or
+/// the second parameter of an `entrypoint!` function.
+fn native_accounts_param(sig: &syn::Signature, is_entrypoint: bool) -> Option<String> {
+    use quote::ToTokens;
+    let typed_params: Vec<&syn::PatType> = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::FnArg::Typed(pat_type) => Some(pat_type),
+            syn::FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let accounts_param = typed_params
+        .iter()
+        .find(|pat_type| {
+            pat_type
+                .ty
+                .to_token_stream()
+                .to_string()
+                .contains("AccountInfo")
+        })
+        .or_else(|| {
+            if is_entrypoint {
+                typed_params.get(1)
+            } else {
+                None
+            }
+        })?;
+    match &*accounts_param.pat {
+        syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+        _ => None,
+    }
+}
+
+struct InstructionDispatchVisitor {
+    accounts_param: String,
+    processor_paths: Vec<String>,
+    dispatches: bool,
+    loads_accounts: bool,
+}
+
+impl<'ast> syn::visit::Visit<'ast> for InstructionDispatchVisitor {
+    fn visit_expr_match(&mut self, expr_match: &'ast syn::ExprMatch) {
+        let is_dispatch = !expr_match.arms.is_empty()
+            && expr_match
+                .arms
+                .iter()
+                .all(|arm| is_instruction_variant_pattern(&arm.pat));
+        if !is_dispatch {
+            syn::visit::visit_expr_match(self, expr_match);
+            return;
+        }
+        self.dispatches = true;
+        for arm in &expr_match.arms {
+            let mut call_visitor = ProcessorCallVisitor {
+                accounts_param: &self.accounts_param,
+                processor_path: None,
+            };
+            syn::visit::Visit::visit_expr(&mut call_visitor, &arm.body);
+            if let Some(processor_path) = call_visitor.processor_path {
+                self.processor_paths.push(processor_path);
+            }
+        }
+    }
+
+    fn visit_expr_call(&mut self, expr_call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(expr_path) = &*expr_call.func {
+            if expr_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "next_account_info")
+            {
+                self.loads_accounts = true;
+            }
+        }
+        syn::visit::visit_expr_call(self, expr_call);
+    }
+}
+
+/// true for `MyInstruction::Variant`, `MyInstruction::Variant(..)` and
+/// `MyInstruction::Variant { .. }` patterns.
+fn is_instruction_variant_pattern(pat: &syn::Pat) -> bool {
+    let path = match pat {
+        syn::Pat::Path(pat_path) => &pat_path.path,
+        syn::Pat::TupleStruct(pat_tuple_struct) => &pat_tuple_struct.path,
+        syn::Pat::Struct(pat_struct) => &pat_struct.path,
+        syn::Pat::Or(pat_or) => {
+            return pat_or.cases.iter().all(is_instruction_variant_pattern);
+        }
+        _ => return false,
+    };
+    let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    segments.len() >= 2 && segments[segments.len() - 2].ends_with("Instruction")
+}
+
+/// Finds the first call of a dispatch arm forwarding the accounts slice.
+struct ProcessorCallVisitor<'a> {
+    accounts_param: &'a str,
+    processor_path: Option<String>,
+}
+
+impl<'ast> syn::visit::Visit<'ast> for ProcessorCallVisitor<'_> {
+    fn visit_expr_call(&mut self, expr_call: &'ast syn::ExprCall) {
+        if self.processor_path.is_some() {
+            return;
+        }
+        if let syn::Expr::Path(expr_path) = &*expr_call.func {
+            let forwards_accounts = expr_call
+                .args
+                .iter()
+                .any(|arg| expr_is_ident(arg, self.accounts_param));
+            if forwards_accounts {
+                self.processor_path = Some(processor_path(&expr_path.path));
+                return;
+            }
+        }
+        syn::visit::visit_expr_call(self, expr_call);
+    }
+}
+
+/// true for `ident` and `&ident`.
+fn expr_is_ident(expr: &syn::Expr, ident: &str) -> bool {
+    match expr {
+        syn::Expr::Path(expr_path) => expr_path.path.is_ident(ident),
+        syn::Expr::Reference(expr_ref) => expr_is_ident(&expr_ref.expr, ident),
+        _ => false,
+    }
+}
+
+/// `Self::process_x` and `Processor::process_x` → `process_x`, `deposit::process` →
+/// `deposit::process`.
+fn processor_path(path: &syn::Path) -> String {
+    let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+    let function = segments.last().cloned().unwrap_or_default();
+    match segments.len().checked_sub(2).map(|i| &segments[i]) {
+        Some(module) if module.starts_with(|c: char| c.is_lowercase()) => {
+            format!("{}::{}", module, function)
+        }
+        _ => function,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(paths: &HashSet<String>) -> Vec<&str> {
+        let mut paths: Vec<&str> = paths.iter().map(|p| p.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_native_instruction_dispatch() {
+        let source = r#"
+            entrypoint!(process_instruction);
+
+            pub fn process_instruction(
+                program_id: &Pubkey,
+                accounts: &[AccountInfo],
+                instruction_data: &[u8],
+            ) -> ProgramResult {
+                Processor::process(program_id, accounts, instruction_data)
+            }
+
+            pub struct Processor;
+
+            impl Processor {
+                pub fn process(
+                    program_id: &Pubkey,
+                    accounts: &[AccountInfo],
+                    input: &[u8],
+                ) -> ProgramResult {
+                    let instruction = VaultInstruction::try_from_slice(input)?;
+                    match instruction {
+                        VaultInstruction::Initialize => {
+                            msg!("Instruction: Initialize");
+                            Self::process_initialize(program_id, accounts)
+                        }
+                        VaultInstruction::Deposit { amount } => {
+                            let amount = u64::from_le_bytes(amount);
+                            deposit::process(program_id, &accounts, amount)
+                        }
+                        VaultInstruction::Withdraw(amount) => {
+                            instructions::process_withdraw(program_id, accounts, amount)
+                        }
+                    }
+                }
+            }
+        "#;
+        let classification = classify_file(source);
+        assert_eq!(
+            sorted(&classification.native_processor_paths),
+            vec![
+                "deposit::process",
+                "instructions::process_withdraw",
+                "process_initialize"
+            ]
+        );
+        assert!(classification.entrypoint_function_names.is_empty());
+    }
+
+    #[test]
+    fn test_native_single_instruction_entrypoint() {
+        let source = r#"
+            solana_program::entrypoint!(process_instruction);
+
+            fn process_instruction(
+                _program_id: &Pubkey,
+                accounts: &[AccountInfo],
+                _data: &[u8],
+            ) -> ProgramResult {
+                let accounts_iter = &mut accounts.iter();
+                let payer = next_account_info(accounts_iter)?;
+                match payer.is_signer {
+                    true => Ok(()),
+                    false => Err(ProgramError::MissingRequiredSignature),
+                }
+            }
+        "#;
+        let classification = classify_file(source);
+        assert_eq!(
+            sorted(&classification.native_processor_paths),
+            vec!["process_instruction"]
+        );
+    }
+}
@@ -34,6 +34,9 @@ pub enum BatFile {
     BatAnalyticsFile,
     ThreatModeling,
     FindingCandidates,
+    ReentrancyReport,
+    PrivilegeGraph,
+    PdaCatalogue,
     OpenQuestions,
     ProgramLib,
     Readme,
@@ -98,6 +101,24 @@ impl BatFile {
                     BatFolder::AuditorNotes.get_path(canonicalize)?
                 )
             }
+            BatFile::ReentrancyReport => {
+                format!(
+                    "{}/reentrancy_report.md",
+                    BatFolder::Notes.get_path(canonicalize)?
+                )
+            }
+            BatFile::PrivilegeGraph => {
+                format!(
+                    "{}/privilege_graph.md",
+                    BatFolder::Notes.get_path(canonicalize)?
+                )
+            }
+            BatFile::PdaCatalogue => {
+                format!(
+                    "{}/pda_catalogue.md",
+                    BatFolder::Notes.get_path(canonicalize)?
+                )
+            }
             BatFile::OpenQuestions => {
                 format!(
                     "{}/open_questions.md",
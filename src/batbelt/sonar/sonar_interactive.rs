@@ -1,12 +1,16 @@
-use crate::batbelt::metadata::functions_source_code_metadata::FunctionSourceCodeMetadata;
+use crate::batbelt::detectors::pda_seeds::{self, PdaCatalogue};
+use crate::batbelt::detectors::{self, account_checks};
+use crate::batbelt::metadata::functions_source_code_metadata::{
+    FunctionMetadataType, FunctionSourceCodeMetadata,
+};
 use crate::batbelt::metadata::structs_source_code_metadata::{
     StructMetadataType, StructSourceCodeMetadata,
 };
 use crate::batbelt::metadata::traits_source_code_metadata::TraitSourceCodeMetadata;
 use crate::batbelt::metadata::{BatMetadata, BatMetadataParser, SourceCodeMetadata};
-use crate::batbelt::path::BatFolder;
+use crate::batbelt::path::{BatFile, BatFolder};
 use crate::batbelt::sonar::{BatSonarError, SonarResultType};
-use crate::config::BatConfig;
+use crate::config::{BatConfig, ProjectType};
 
 use colored::Colorize;
 use dialoguer::console::{style, Emoji};
@@ -17,11 +21,14 @@ use crate::batbelt::metadata::context_accounts_metadata::ContextAccountsMetadata
 use crate::batbelt::parser::context_accounts_parser::CAAccountParser;
 use crate::batbelt::parser::entrypoint_parser::EntrypointParser;
 use crate::batbelt::parser::function_parser::FunctionParser;
+use crate::batbelt::parser::native_context_accounts_parser;
 use crate::batbelt::parser::pinocchio_context_accounts_parser;
 use crate::batbelt::parser::syn_context_accounts_parser;
+use crate::batbelt::parser::syn_struct_classifier;
 use crate::batbelt::parser::trait_parser::TraitParser;
 
 use crate::batbelt::metadata::enums_source_code_metadata::EnumSourceCodeMetadata;
+use std::collections::HashSet;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -151,6 +158,9 @@ impl BatSonarInteractive {
             .min(total_files.max(1));
         let chunk_size = total_files.div_ceil(num_threads);
         let counter = Arc::new(AtomicUsize::new(0));
+        let is_native_program = BatConfig::get_config()
+            .map(|c| c.project_type == ProjectType::VanillaSolana)
+            .unwrap_or(false);
 
         let handles: Vec<_> = program_dir_entries
             .chunks(chunk_size)
@@ -163,6 +173,7 @@ impl BatSonarInteractive {
                     let mut functions = vec![];
                     let mut traits = vec![];
                     let mut enums = vec![];
+                    let mut native_processor_paths = HashSet::new();
 
                     for entry in &entries {
                         let entry_path = entry.path().to_str().unwrap().to_string();
@@ -200,11 +211,17 @@ impl BatSonarInteractive {
                             )
                             .unwrap(),
                         );
+                        if is_native_program {
+                            native_processor_paths.extend(
+                                syn_struct_classifier::classify_file(&file_content)
+                                    .native_processor_paths,
+                            );
+                        }
 
                         pb.inc(1);
                     }
 
-                    (structs, functions, traits, enums)
+                    (structs, functions, traits, enums, native_processor_paths)
                 })
             })
             .collect();
@@ -213,13 +230,28 @@ impl BatSonarInteractive {
         let mut all_functions = vec![];
         let mut all_traits = vec![];
         let mut all_enums = vec![];
+        let mut all_native_processor_paths = HashSet::new();
 
         for h in handles {
-            let (s, f, t, e) = h.join().unwrap();
+            let (s, f, t, e, n) = h.join().unwrap();
             all_structs.extend(s);
             all_functions.extend(f);
             all_traits.extend(t);
             all_enums.extend(e);
+            all_native_processor_paths.extend(n);
+        }
+
+        // Native programs: the instruction dispatch may call processors defined in other
+        // files, so entry points and their context accounts are resolved once all files
+        // are scanned.
+        if is_native_program {
+            FunctionSourceCodeMetadata::mark_native_processors(
+                &mut all_functions,
+                &all_native_processor_paths,
+            );
+            all_structs.extend(
+                StructSourceCodeMetadata::create_native_context_accounts_metadata(&all_functions),
+            );
         }
 
         pb.finish_with_message(format!(
@@ -247,8 +279,6 @@ impl BatSonarInteractive {
         Ok(())
     }
     pub fn run_post_scan_parallel() -> Result<(), BatSonarError> {
-        use crate::config::ProjectType;
-
         let started = Instant::now();
         let project_type = BatConfig::get_config()
             .map(|c| c.project_type)
@@ -319,16 +349,68 @@ impl BatSonarInteractive {
                 Self::run_entry_points_with_pb(&pb_ep)?;
                 Self::run_pinocchio_context_accounts_with_pb(&pb_ca)?;
             }
+            ProjectType::VanillaSolana => {
+                let pb_ep = m.add(ProgressBar::new_spinner());
+                pb_ep.set_style(spinner_style.clone());
+                pb_ep.enable_steady_tick(Duration::from_millis(100));
+                pb_ep.set_message("Entry points: starting...");
+
+                let pb_ca = m.add(ProgressBar::new_spinner());
+                pb_ca.set_style(spinner_style.clone());
+                pb_ca.enable_steady_tick(Duration::from_millis(100));
+                pb_ca.set_message("Context accounts: starting...");
+
+                // Native programs: function deps + entry points + next_account_info sequences
+                Self::run_function_deps_with_pb(&pb_fd)?;
+                Self::run_entry_points_with_pb(&pb_ep)?;
+                Self::run_native_context_accounts_with_pb(&pb_ca)?;
+            }
             _ => {
                 // Generic Rust project: only resolve function dependencies.
                 Self::run_function_deps_with_pb(&pb_fd)?;
             }
         }
 
+        if matches!(
+            project_type,
+            ProjectType::Anchor | ProjectType::Pinocchio | ProjectType::VanillaSolana
+        ) {
+            Self::run_solana_detectors(&project_type)?;
+        }
+
         println!("{} Done in {}", SPARKLE, HumanDuration(started.elapsed()));
         Ok(())
     }
 
+    /// Flags the context accounts missing signer or owner checks and writes the PDA seed
+    /// catalogue, the issues of both end up as finding candidates.
+    fn run_solana_detectors(project_type: &ProjectType) -> Result<(), BatSonarError> {
+        let bat_metadata = BatMetadata::read_metadata().change_context(BatSonarError)?;
+        let hits = account_checks::detect_account_checks(&bat_metadata, project_type);
+        if !hits.is_empty() {
+            println!(
+                "  {} {} context accounts missing signer or owner checks",
+                "⚠".bright_yellow(),
+                hits.len()
+            );
+        }
+        let pda_catalogue = PdaCatalogue::new(&pda_seeds::collect_pda_usages(&bat_metadata));
+        if !pda_catalogue.issues.is_empty() {
+            println!(
+                "  {} {} PDA seed issues written to {}",
+                "⚠".bright_yellow(),
+                pda_catalogue.issues.len(),
+                BatFile::PdaCatalogue
+                    .get_path(false)
+                    .change_context(BatSonarError)?
+            );
+        }
+        BatFile::PdaCatalogue
+            .write_content(false, &pda_catalogue.to_markdown())
+            .change_context(BatSonarError)?;
+        detectors::save_finding_candidates(&hits, &pda_catalogue.issues)
+    }
+
     fn run_entry_points_with_pb(pb: &ProgressBar) -> Result<(), BatSonarError> {
         let config = BatConfig::get_config().change_context(BatSonarError)?;
         let lib_paths = if config.program_lib_paths.is_empty() {
@@ -617,6 +699,75 @@ impl BatSonarInteractive {
         ));
         Ok(())
     }
+
+    /// Parse context accounts for native `solana_program` programs.
+    /// The ContextAccounts of a native entry point point to the `next_account_info`
+    /// sequence of its processor, whose uses of every account give its semantics.
+    fn run_native_context_accounts_with_pb(pb: &ProgressBar) -> Result<(), BatSonarError> {
+        let ca_sc_metadata = SourceCodeMetadata::get_filtered_structs(
+            None,
+            Some(StructMetadataType::ContextAccounts),
+        )
+        .change_context(BatSonarError)?;
+        let entry_points = SourceCodeMetadata::get_filtered_functions(
+            None,
+            Some(FunctionMetadataType::EntryPoint),
+        )
+        .change_context(BatSonarError)?;
+        let total = ca_sc_metadata.len();
+        pb.set_message(format!("Context accounts [0/{}]", total));
+
+        for (idx, ca_sc) in ca_sc_metadata.iter().enumerate() {
+            pb.set_message(format!(
+                "Context accounts [{}/{}]: {}",
+                idx + 1,
+                total,
+                ca_sc.name,
+            ));
+            let processor = entry_points.iter().find(|ep| {
+                ep.path == ca_sc.path
+                    && ep.start_line_index <= ca_sc.start_line_index
+                    && ca_sc.end_line_index <= ep.end_line_index
+            });
+            let ca_info = match processor.map(|ep| {
+                native_context_accounts_parser::parse_native_context_accounts_from_function(
+                    &ca_sc.name,
+                    &ep.to_source_code_parser(None).get_source_code_content(),
+                )
+            }) {
+                Some(Ok(parsed)) => parsed
+                    .accounts
+                    .iter()
+                    .map(|acc| {
+                        let solana_type = acc.determine_pinocchio_solana_account_type();
+                        acc.to_pinocchio_ca_account_parser(solana_type)
+                    })
+                    .collect::<Vec<_>>(),
+                Some(Err(e)) => {
+                    log::warn!("Failed to parse native CA {}: {:?}", ca_sc.name, e);
+                    vec![]
+                }
+                None => {
+                    log::warn!("Processor of native CA {} not found", ca_sc.name);
+                    vec![]
+                }
+            };
+
+            let context_accounts_metadata = ContextAccountsMetadata::new(
+                ca_sc.name.clone(),
+                BatMetadata::create_metadata_id(),
+                ca_sc.metadata_id.clone(),
+                ca_info,
+                ca_sc.program_name.clone(),
+            );
+            context_accounts_metadata.update_metadata_file().unwrap();
+        }
+        pb.finish_with_message(format!(
+            "{} Context accounts: {} processed (native)",
+            SPARKLE, total
+        ));
+        Ok(())
+    }
 }
 
 #[cfg(test)]
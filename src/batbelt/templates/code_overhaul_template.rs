@@ -2,6 +2,7 @@ use error_stack::{Result, ResultExt};
 use inflector::Inflector;
 use serde::{Deserialize, Serialize};
 
+use crate::batbelt::detectors::account_checks;
 use crate::batbelt::git::git_action::GitAction;
 use crate::batbelt::metadata::context_accounts_metadata::ContextAccountsMetadata;
 use crate::batbelt::metadata::structs_source_code_metadata::StructMetadataType;
@@ -17,6 +18,7 @@ use crate::batbelt::templates::code_overhaul_template::CoderOverhaulTemplatePlac
 };
 use crate::batbelt::templates::{TemplateError, TemplateResult};
 use crate::batbelt::{BatEnumerator, ShareableData};
+use crate::config::{BatConfig, ProjectType};
 
 pub struct CodeOverhaulTemplate {
     pub entrypoint_name: String,
@@ -149,9 +151,116 @@ impl CodeOverhaulSection {
     }
 
     fn get_notes_content(&self, entry_point_parser: EntrypointParser) -> TemplateResult<String> {
+        let bat_metadata = BatMetadata::read_metadata().change_context(TemplateError)?;
+        let project_type = BatConfig::get_config()
+            .map(|config| config.project_type)
+            .unwrap_or(ProjectType::GenericRust);
+        let cpis_notes = Self::get_cpis_notes(&bat_metadata, &entry_point_parser);
+        let account_checks_notes =
+            Self::get_account_checks_notes(&bat_metadata, &project_type, &entry_point_parser);
+        let mut result = self.get_constraints_notes(entry_point_parser)?;
+        result.extend(account_checks_notes);
+        result.extend(cpis_notes);
+        result.push(format!("- {}", CompleteWithNotes.to_placeholder()));
+        Ok(result.join("\n"))
+    }
+
+    /// Checklist of the context accounts missing signer or owner checks.
+    fn get_account_checks_notes(
+        bat_metadata: &BatMetadata,
+        project_type: &ProjectType,
+        entry_point_parser: &EntrypointParser,
+    ) -> Vec<String> {
+        let context_accounts = match &entry_point_parser.context_accounts {
+            Some(ca) => ca,
+            None => return vec![],
+        };
+        let functions = std::iter::once(entry_point_parser.entry_point_function.clone())
+            .chain(entry_point_parser.dependencies.clone())
+            .collect::<Vec<_>>();
+        let hits = account_checks::detect_entry_point_account_checks(
+            bat_metadata,
+            &entry_point_parser.name,
+            context_accounts,
+            &functions,
+            project_type,
+        );
+        if hits.is_empty() {
+            return vec![];
+        }
+        let mut result = vec!["- [ ] check accounts:".to_string()];
+        for hit in hits {
+            result.push(format!(
+                "  - [ ] `{}` (L{}): {}",
+                hit.account_name,
+                hit.line,
+                hit.kind.description()
+            ));
+        }
+        result
+    }
+
+    /// Checklist of the CPIs performed by the entry point and its dependencies, as stored in
+    /// the entry point metadata.
+    fn get_cpis_notes(
+        bat_metadata: &BatMetadata,
+        entry_point_parser: &EntrypointParser,
+    ) -> Vec<String> {
+        let cpis = bat_metadata
+            .entry_points
+            .iter()
+            .find(|entry_point| {
+                entry_point.name == entry_point_parser.name
+                    && entry_point.program_name == entry_point_parser.program_name
+            })
+            .map(|entry_point| entry_point.cpis.clone())
+            .unwrap_or_default();
+        if cpis.is_empty() {
+            return vec![];
+        }
+        let mut result = vec!["- [ ] check CPIs:".to_string()];
+        for cpi in cpis {
+            let accounts = if cpi.accounts.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    ", accounts: {}",
+                    cpi.accounts
+                        .iter()
+                        .map(|account| format!("`{}`", account))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            let signer = match (cpi.pda_signed, cpi.signer_seeds.is_empty()) {
+                (false, _) => String::new(),
+                (true, true) => ", PDA signed".to_string(),
+                (true, false) => format!(
+                    ", PDA signed with seeds `[{}]`",
+                    cpi.signer_seeds.join(", ")
+                ),
+            };
+            // Unknown for unqualified Pinocchio instruction structs
+            let program = if cpi.program.is_empty() {
+                String::new()
+            } else {
+                format!(" to `{}`", cpi.program)
+            };
+            result.push(format!(
+                "  - [ ] `{}`{} in `{}`{}{}",
+                cpi.call, program, cpi.function_name, accounts, signer
+            ));
+        }
+        result
+    }
+
+    fn get_constraints_notes(
+        &self,
+        entry_point_parser: EntrypointParser,
+    ) -> TemplateResult<Vec<String>> {
         let context_accounts = match entry_point_parser.context_accounts {
             Some(ca) => ca,
-            None => return Ok(format!("- {}", CompleteWithNotes.to_placeholder())),
+            None => return Ok(vec![]),
         };
         let context_accounts_struct_source_code_metadata_id = context_accounts.metadata_id;
         let context_accounts_metadata =
@@ -182,7 +291,7 @@ impl CodeOverhaulSection {
                 }
             });
         if ca_info_with_validation.clone().count() == 0 {
-            return Ok(format!("- {}", CompleteWithNotes.to_placeholder()));
+            return Ok(vec![]);
         }
 
         let mut result = vec![];
@@ -222,8 +331,7 @@ impl CodeOverhaulSection {
                 ));
             }
         }
-        result.push(format!("- {}", CompleteWithNotes.to_placeholder()));
-        Ok(result.join("\n"))
+        Ok(result)
     }
 
     fn get_state_changes_content(
@@ -839,10 +947,12 @@ impl CodeOverhaulSection {
         let context_accounts_source_code = context_accounts.to_source_code_parser(None);
         let context_accounts_content = context_accounts_source_code.get_source_code_content();
 
-        // Check if this is a Pinocchio struct (fields are &'a AccountView / &'a AccountInfo).
+        // Check if this is a Pinocchio struct (fields are &'a AccountView / &'a AccountInfo)
+        // or the next_account_info sequence of a native processor.
         // BatSonar's ContextAccountsNoValidation filter only works for Anchor-style structs,
-        // so for Pinocchio we show the full struct source code without filtering.
+        // so for those we show the full source code without filtering.
         let is_pinocchio = context_accounts_content.contains("AccountView")
+            || context_accounts_content.contains("next_account_info(")
             || (context_accounts_content.contains("AccountInfo")
                 && !context_accounts_content.contains("Account<"));
 
@@ -1,8 +1,17 @@
 use crate::batbelt::path::BatFile;
 use crate::batbelt::templates::TemplateError;
 use error_stack::{IntoReport, Result, ResultExt};
+use lazy_regex::regex;
 use std::fs;
 
+/// Candidates generated by the sonar detectors are kept between these markers, so a new
+/// scan replaces them without touching the auditor's notes.
+pub const CANDIDATES_START: &str = "<!-- bat-sonar detectors: start -->";
+pub const CANDIDATES_END: &str = "<!-- bat-sonar detectors: end -->";
+const TO_REVIEW_HEADING: &str = "## to review";
+const CHECKBOXES: [&str; 3] = ["- [ ] ", "- [x] ", "- [X] "];
+const CHECKBOX_LEN: usize = 6;
+
 pub struct NoteTemplate;
 
 impl NoteTemplate {
@@ -13,6 +22,108 @@ impl NoteTemplate {
         Ok(())
     }
 
+    /// Merge `candidate_lines` into the auditor's `finding_candidates.md`, if the notes are
+    /// initialized. Shared by the Solana and EVM detectors.
+    pub fn save_generated_finding_candidates(
+        candidate_lines: &[String],
+    ) -> Result<(), TemplateError> {
+        let finding_candidates = BatFile::FindingCandidates;
+        if !finding_candidates
+            .file_exists()
+            .change_context(TemplateError)?
+        {
+            return Ok(());
+        }
+        let content = finding_candidates
+            .read_content(false)
+            .change_context(TemplateError)?;
+        finding_candidates
+            .write_content(
+                false,
+                &Self::merge_generated_finding_candidates(&content, candidate_lines),
+            )
+            .change_context(TemplateError)
+    }
+
+    /// Replace the generated block of `finding_candidates.md` with `candidate_lines`, under
+    /// `## to review`. Candidates the auditor already moved elsewhere (accepted, rejected...)
+    /// are not re-added, even if their line moved or got annotated since, and candidates
+    /// still generated keep the line the auditor ticked or annotated inside the block.
+    pub fn merge_generated_finding_candidates(content: &str, candidate_lines: &[String]) -> String {
+        let mut previous: Vec<(String, &str)> = Vec::new();
+        let mut content = match (content.find(CANDIDATES_START), content.find(CANDIDATES_END)) {
+            (Some(start), Some(end)) if start < end => {
+                previous = content[start + CANDIDATES_START.len()..end]
+                    .lines()
+                    .filter_map(|line| candidate_key(line).map(|key| (key, line)))
+                    .collect();
+                let end = end + CANDIDATES_END.len();
+                let mut end = end + content[end..].find('\n').map(|idx| idx + 1).unwrap_or(0);
+                // Drop the blank line separating the block from what follows it
+                let mut start = start;
+                if content[..start].ends_with("\n\n") {
+                    if content[end..].starts_with('\n') {
+                        end += 1;
+                    } else if content[end..].is_empty() {
+                        start -= 1;
+                    }
+                }
+                format!("{}{}", &content[..start], &content[end..])
+            }
+            _ => content.to_string(),
+        };
+
+        let reviewed: Vec<String> = content.lines().filter_map(candidate_key).collect();
+        let mut keys: Vec<String> = Vec::new();
+        let mut lines: Vec<String> = Vec::new();
+        for line in candidate_lines {
+            let key = candidate_key(line).unwrap_or_else(|| line.clone());
+            // An annotation appended by the auditor extends the key
+            if reviewed
+                .iter()
+                .any(|reviewed_key| reviewed_key.starts_with(&key))
+                || keys.contains(&key)
+            {
+                continue;
+            }
+            // Keep the auditor's checkbox and the annotation appended to the generated line
+            let kept = previous
+                .iter()
+                .filter(|_| candidate_key(line).is_some())
+                .find(|(previous_key, _)| previous_key.starts_with(&key))
+                .map(|(previous_key, previous_line)| {
+                    let checkbox = &previous_line.trim_start()[..CHECKBOX_LEN];
+                    let candidate = &line.trim_start()[CHECKBOX_LEN..];
+                    format!("{}{}{}", checkbox, candidate, &previous_key[key.len()..])
+                });
+            keys.push(key);
+            lines.push(kept.unwrap_or_else(|| line.clone()));
+        }
+        if lines.is_empty() {
+            return content;
+        }
+        let block = format!(
+            "{}\n{}\n{}\n",
+            CANDIDATES_START,
+            lines.join("\n"),
+            CANDIDATES_END
+        );
+
+        match content.find(TO_REVIEW_HEADING) {
+            Some(idx) => {
+                let insert_at = idx + TO_REVIEW_HEADING.len();
+                content.insert_str(insert_at, &format!("\n\n{}", block.trim_end()));
+            }
+            None => {
+                if !content.is_empty() && !content.ends_with('\n') {
+                    content.push('\n');
+                }
+                content.push_str(&format!("\n{}\n\n{}", TO_REVIEW_HEADING, block));
+            }
+        }
+        content
+    }
+
     fn create_finding_candidates_file() -> Result<(), TemplateError> {
         let content = r#"# Finding candidates (a.k.a Smellies)
 
@@ -87,3 +198,92 @@ impl NoteTemplate {
         Ok(())
     }
 }
+
+/// What identifies a `- [ ] ...` candidate, checked or not, once its box is stripped. Line
+/// anchors are dropped too (`src/Vault.sol#L12` → `src/Vault.sol`), since they move with
+/// unrelated edits.
+fn candidate_key(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    let candidate = CHECKBOXES
+        .iter()
+        .find_map(|checkbox| line.strip_prefix(checkbox))?;
+    Some(regex!(r"#L\d+").replace_all(candidate, "").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidate_key() {
+        assert_eq!(
+            candidate_key("  - [x] [TxOriginAuth: `tx.origin`](src/Vault.sol#L12), note"),
+            Some("[TxOriginAuth: `tx.origin`](src/Vault.sol), note".to_string())
+        );
+        assert_eq!(candidate_key("- [ ]"), None);
+        assert_eq!(candidate_key("## to review"), None);
+    }
+
+    #[test]
+    fn test_merge_keeps_short_and_manual_lines() {
+        let content = "## to review\n\n- [ ]\n- manual note\n";
+        let merged = NoteTemplate::merge_generated_finding_candidates(
+            content,
+            &["- [ ] generated".to_string(), "- [x]".to_string()],
+        );
+        assert!(merged.contains(&format!(
+            "{}\n- [ ] generated\n- [x]\n{}",
+            CANDIDATES_START, CANDIDATES_END
+        )));
+        assert!(merged.ends_with("- [ ]\n- manual note\n"));
+    }
+
+    #[test]
+    fn test_merge_keeps_ticked_candidates_in_block() {
+        let generated = vec![
+            "- [ ] [TxOriginAuth: `tx.origin`](src/Vault.sol#L12)".to_string(),
+            "- [ ] [UncheckedCall: `call`](src/Vault.sol#L30)".to_string(),
+        ];
+        let content =
+            NoteTemplate::merge_generated_finding_candidates("## to review\n", &generated).replace(
+                "- [ ] [TxOriginAuth: `tx.origin`](src/Vault.sol#L12)",
+                "- [x] [TxOriginAuth: `tx.origin`](src/Vault.sol#L12), false positive",
+            );
+
+        let rescanned = vec![
+            "- [ ] [TxOriginAuth: `tx.origin`](src/Vault.sol#L14)".to_string(),
+            "- [ ] [UncheckedCall: `call`](src/Vault.sol#L32)".to_string(),
+        ];
+        let merged = NoteTemplate::merge_generated_finding_candidates(&content, &rescanned);
+        assert_eq!(
+            merged,
+            format!(
+                "## to review\n\n{}\n{}\n{}\n{}\n",
+                CANDIDATES_START,
+                "- [x] [TxOriginAuth: `tx.origin`](src/Vault.sol#L14), false positive",
+                "- [ ] [UncheckedCall: `call`](src/Vault.sol#L32)",
+                CANDIDATES_END
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_skips_annotated_candidates_moved_to_another_section() {
+        let content = "## accepted
+
+- [x] [TxOriginAuth: `tx.origin`](src/Vault.sol#L12), confirmed
+
+## rejected
+
+- [ ] [UncheckedCall: `call`](src/Vault.sol#L30), FP: view only
+
+## to review
+";
+        let rescanned = vec![
+            "- [ ] [TxOriginAuth: `tx.origin`](src/Vault.sol#L14)".to_string(),
+            "- [ ] [UncheckedCall: `call`](src/Vault.sol#L32)".to_string(),
+        ];
+        let merged = NoteTemplate::merge_generated_finding_candidates(content, &rescanned);
+        assert_eq!(merged, content);
+    }
+}
@@ -7,7 +7,7 @@ use crate::batbelt::templates::code_overhaul_template::{
 };
 use crate::batbelt::BatEnumerator;
 use crate::commands::{BatCommandEnumerator, CommandError, CommandResult};
-use crate::config::{BatConfig, ProjectType};
+use crate::config::BatConfig;
 
 use crate::{batbelt, Suggestion};
 use clap::Subcommand;
@@ -274,7 +274,7 @@ impl CodeOverhaulCommand {
     async fn execute_start(&self) -> error_stack::Result<(), CommandError> {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
 
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             return self.execute_start_foundry().await;
         }
 
@@ -420,36 +420,13 @@ impl CodeOverhaulCommand {
             })?;
 
         // Generate EVM-specific markdown content
-        let access_control_str = ep
-            .access_control
-            .iter()
-            .map(|ac| format!("{:?}", ac))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let modifiers_str = if ep.modifiers.is_empty() {
-            "None".to_string()
-        } else {
-            ep.modifiers.join(", ")
-        };
-
-        let markdown_content = format!(
-            "# State changes:\n\n\
-            - `COMPLETE_WITH_THE_REST_OF_STATE_CHANGES`\n\n\
-            # Notes:\n\n\
-            - `COMPLETE_WITH_NOTES`\n\n\
-            # Access control:\n\n\
-            - {access_control_str}\n\
-            - Modifiers: {modifiers_str}\n\n\
-            # Contract:\n\n\
-            - {contract_name} ({file_path}:{line})\n\n\
-            # Validations:\n\n\
-            - `COMPLETE_WITH_VALIDATIONS`\n\n\
-            # Miro frame url:\n\n\
-            `COMPLETE_WITH_MIRO_FRAME_URL`\n",
-            contract_name = contract.name,
-            file_path = contract.file_path,
-            line = func.line,
-        );
+        let markdown_content =
+            crate::batbelt::evm::templates::code_overhaul_template::generate_co_content(
+                &evm_metadata,
+                ep,
+                contract,
+                func,
+            );
 
         // Move file from to-review to started
         BatFile::CodeOverhaulToReview {
@@ -30,7 +30,7 @@ use crate::batbelt::path::{BatFile, BatFolder};
 use crate::batbelt::templates::code_overhaul_template::CoderOverhaulTemplatePlaceholders;
 use crate::batbelt::BatEnumerator;
 use crate::commands::{BatCommandEnumerator, CommandResult};
-use crate::config::{BatConfig, ProjectType};
+use crate::config::BatConfig;
 use crate::{batbelt, Suggestion};
 use clap::Subcommand;
 use colored::Colorize;
@@ -93,7 +93,7 @@ impl MiroCommand {
 
     async fn entrypoint_screenshots(&self) -> Result<(), CommandError> {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
-        let selected_miro_frame = if bat_config.project_type == ProjectType::Foundry {
+        let selected_miro_frame = if bat_config.project_type.is_evm() {
             MiroFrame::prompt_select_frame_from_metadata()
                 .await
                 .change_context(CommandError)?
@@ -106,7 +106,7 @@ impl MiroCommand {
                 .change_context(CommandError)?
         };
         // get entrypoints name
-        let entrypoints_names = if bat_config.project_type == ProjectType::Foundry {
+        let entrypoints_names = if bat_config.project_type.is_evm() {
             crate::batbelt::evm::miro::get_entry_point_names().change_context(CommandError)?
         } else if bat_config.is_multi_program() {
             let program_name = bat_config
@@ -254,7 +254,7 @@ impl MiroCommand {
 
     async fn source_code_screenshots(&self) -> Result<(), CommandError> {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             let selected_miro_frame = MiroFrame::prompt_select_frame_from_metadata()
                 .await
                 .change_context(CommandError)?;
@@ -272,7 +272,7 @@ impl MiroCommand {
 
     async fn function_dependencies(&self) -> Result<(), CommandError> {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
-        let selected_miro_frame = if bat_config.project_type == ProjectType::Foundry {
+        let selected_miro_frame = if bat_config.project_type.is_evm() {
             MiroFrame::prompt_select_frame_from_metadata()
                 .await
                 .change_context(CommandError)?
@@ -500,7 +500,7 @@ impl MiroCommand {
 
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
 
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             crate::batbelt::evm::miro::deploy_co_frames()
                 .await
                 .change_context(CommandError)?;
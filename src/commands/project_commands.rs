@@ -19,7 +19,6 @@ use crate::batbelt::path::{BatFile, BatFolder};
 use crate::batbelt::templates::package_json_template::PackageJsonTemplate;
 use crate::commands::miro_commands::MiroCommand;
 use crate::commands::{BatCommandEnumerator, CommandResult};
-use crate::config::ProjectType;
 use clap::Subcommand;
 
 use crate::batbelt::git::git_action::GitAction;
@@ -157,7 +156,7 @@ impl ProjectCommands {
             "BatMetadata.json!".bright_green()
         );
 
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             // Foundry/Solidity: run EVM sonar
             let mut sol_sonar = crate::batbelt::evm::sonar::sonar::EvmSonar::new("..");
             let metadata = sol_sonar.run().change_context(CommandError)?;
@@ -367,7 +366,7 @@ mod project_commands_functions {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
 
         // For Foundry, use EVM metadata for entry point names
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             return update_co_to_review_evm();
         }
 
@@ -11,7 +11,7 @@ use error_stack::{Result, ResultExt};
 use crate::batbelt::sonar::sonar_interactive::BatSonarInteractive;
 use crate::batbelt::templates::TemplateGenerator;
 use crate::commands::{BatCommandEnumerator, CommandResult};
-use crate::config::{BatConfig, ProjectType};
+use crate::config::BatConfig;
 
 use super::CommandError;
 
@@ -45,7 +45,7 @@ impl SonarCommand {
     fn execute_run(&self) -> CommandResult<()> {
         let bat_config = BatConfig::get_config().change_context(CommandError)?;
 
-        if bat_config.project_type == ProjectType::Foundry {
+        if bat_config.project_type.is_evm() {
             return self.execute_run_foundry();
         }
 
@@ -67,6 +67,30 @@ impl SonarCommand {
         Ok(())
     }
 
+    /// Metadata keyed before signatures were used: rewrite the IDs and move the code-overhaul
+    /// files of the renamed entry points before any EVM command reads them
+    pub fn migrate_foundry_metadata() -> CommandResult<()> {
+        use crate::batbelt::evm::metadata::bat_metadata::EvmBatMetadata;
+
+        let Some(moved_files) = EvmBatMetadata::migrate_metadata().change_context(CommandError)?
+        else {
+            return Ok(());
+        };
+        GitCommit::UpdateMetadataJson {
+            bat_metadata_commit: BatMetadataCommit::UpdateMetadataVersion,
+        }
+        .create_commit(false)
+        .change_context(CommandError)?;
+        if !moved_files.is_empty() {
+            GitCommit::CodeOverhaulUpdated {
+                updated_eps: moved_files,
+            }
+            .create_commit(false)
+            .change_context(CommandError)?;
+        }
+        Ok(())
+    }
+
     fn execute_run_svm(&self) -> CommandResult<()> {
         let metadata_bat_file = BatFile::BatMetadataFile;
         // in case the file does not exist, so the BatMetadata can be read
@@ -1,5 +1,13 @@
 use crate::batbelt::bat_dialoguer::BatDialoguer;
-use crate::batbelt::command_line::CodeEditor;
+use crate::batbelt::command_line::{execute_command, CodeEditor};
+use crate::batbelt::evm::metadata::bat_metadata::{EntryPointMetadata, EvmBatMetadata};
+use crate::batbelt::evm::parser::import_resolver::ImportResolver;
+use crate::batbelt::evm::parser::storage_layout_resolver::{
+    compare_layouts, StorageLayoutChangeKind, StorageLayoutResolver, StorageSlot,
+};
+use crate::batbelt::evm::sonar::sonar::EvmSonar;
+use crate::batbelt::evm::templates::foundry_test_template::FoundryTestTemplate;
+use crate::batbelt::evm::types::{function_signature, EvmContract, EvmFileDefinitions};
 
 use crate::batbelt::path::{prettify_source_code_path, BatFile, BatFolder};
 
@@ -22,7 +30,9 @@ use crate::batbelt::templates::package_json_template::PackageJsonTemplate;
 use crate::batbelt;
 use crate::batbelt::metadata::enums_source_code_metadata::EnumSourceCodeMetadata;
 use crate::batbelt::parser::entrypoint_parser::EntrypointParser;
-use crate::config::{BatAuditorConfig, BatConfig};
+use crate::batbelt::parser::function_parser::{FunctionParameterParser, FunctionParser};
+use crate::batbelt::parser::idl_parser::{Idl, IdlDiscrepancyKind, SonarInstruction};
+use crate::config::{BatAuditorConfig, BatConfig, ProjectType};
 use log::Level;
 use tabled::{Style, Table, Tabled};
 
@@ -45,6 +55,16 @@ pub enum ToolCommand {
     ListEntryPointsPath,
     /// Shows a list of code overhaul files an the state
     ListCodeOverhaul,
+    /// Compares the storage layout of two contracts, or of a contract at two git commits
+    StorageLayoutDiff,
+    /// Shows the roles of the EVM contracts, the entry points they can call and who grants them, and writes a Mermaid diagram
+    PrivilegeGraph,
+    /// Shows the EVM entry points that no Foundry test calls, directly or through handlers
+    UntestedEntryPoints,
+    /// Writes a Foundry PoC for an EVM entry point or finding candidate, or an invariant test harness, to the test folder of the audited repo
+    FoundryScaffold,
+    /// Cross-checks the Anchor, Codama or Shank IDLs of target/idl against the entry points and context accounts found by sonar
+    IdlCheck,
 }
 
 impl BatEnumerator for ToolCommand {}
@@ -59,6 +79,11 @@ impl BatCommandEnumerator for ToolCommand {
             ToolCommand::CountCodeOverhaul => self.execute_count_co_files(),
             ToolCommand::ListEntryPointsPath => self.execute_list_entry_points(),
             ToolCommand::ListCodeOverhaul => self.execute_list_co(),
+            ToolCommand::StorageLayoutDiff => self.execute_storage_layout_diff(),
+            ToolCommand::PrivilegeGraph => self.execute_privilege_graph(),
+            ToolCommand::UntestedEntryPoints => self.execute_untested_entry_points(),
+            ToolCommand::FoundryScaffold => self.execute_foundry_scaffold(),
+            ToolCommand::IdlCheck => self.execute_idl_check(),
         }
     }
 
@@ -71,6 +96,11 @@ impl BatCommandEnumerator for ToolCommand {
             ToolCommand::CountCodeOverhaul => false,
             ToolCommand::ListEntryPointsPath => true,
             ToolCommand::ListCodeOverhaul => false,
+            ToolCommand::StorageLayoutDiff => true,
+            ToolCommand::PrivilegeGraph => true,
+            ToolCommand::UntestedEntryPoints => true,
+            ToolCommand::FoundryScaffold => true,
+            ToolCommand::IdlCheck => true,
         }
     }
 
@@ -83,6 +113,11 @@ impl BatCommandEnumerator for ToolCommand {
             ToolCommand::CountCodeOverhaul => false,
             ToolCommand::ListEntryPointsPath => false,
             ToolCommand::ListCodeOverhaul => false,
+            ToolCommand::StorageLayoutDiff => false,
+            ToolCommand::PrivilegeGraph => false,
+            ToolCommand::UntestedEntryPoints => false,
+            ToolCommand::FoundryScaffold => false,
+            ToolCommand::IdlCheck => false,
         }
     }
 }
@@ -234,6 +269,10 @@ impl ToolCommand {
     }
 
     fn execute_open_source_code(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if bat_config.project_type.is_evm() {
+            return self.execute_open_evm_source_code();
+        }
         let selected_bat_metadata_type =
             BatMetadataType::prompt_metadata_type_selection().change_context(CommandError)?;
         let (path, start_line_index) = match selected_bat_metadata_type {
@@ -275,6 +314,99 @@ impl ToolCommand {
         Ok(())
     }
 
+    fn execute_open_evm_source_code(&self) -> CommandResult<()> {
+        let evm_metadata = EvmBatMetadata::read_metadata().change_context(CommandError)?;
+        let import_resolver = ImportResolver::new("..").change_context(CommandError)?;
+        let kinds = vec!["Function", "Struct", "Enum", "Custom error", "Value type"];
+        let kind = BatDialoguer::select("Select the type of item".to_string(), kinds, None)?;
+
+        // (label, path, line) of every in-scope item of the selected kind, contract-level
+        // items qualified by their contract
+        let mut items: Vec<(String, String, usize)> = Vec::new();
+        for contract in evm_metadata.contracts.iter().filter(|c| !c.external) {
+            let qualified = |name: &str| format!("{}.{}", contract.name, name);
+            let path = &contract.file_path;
+            match kind {
+                0 => items.extend(contract.functions.iter().map(|f| {
+                    (
+                        qualified(&function_signature(&f.name, &f.params)),
+                        path.clone(),
+                        f.line,
+                    )
+                })),
+                1 => items.extend(
+                    contract
+                        .structs
+                        .iter()
+                        .map(|s| (qualified(&s.name), path.clone(), s.line)),
+                ),
+                2 => items.extend(
+                    contract
+                        .enums
+                        .iter()
+                        .map(|e| (qualified(&e.name), path.clone(), e.line)),
+                ),
+                3 => items.extend(
+                    contract
+                        .errors
+                        .iter()
+                        .map(|e| (qualified(&e.name), path.clone(), e.line)),
+                ),
+                _ => items.extend(
+                    contract
+                        .udvts
+                        .iter()
+                        .map(|u| (qualified(&u.name), path.clone(), u.line)),
+                ),
+            }
+        }
+        for definitions in evm_metadata
+            .file_definitions
+            .iter()
+            .filter(|d| !import_resolver.is_dependency_path(&d.file_path))
+        {
+            let path = &definitions.file_path;
+            match kind {
+                1 => items.extend(
+                    definitions
+                        .structs
+                        .iter()
+                        .map(|s| (s.name.clone(), path.clone(), s.line)),
+                ),
+                2 => items.extend(
+                    definitions
+                        .enums
+                        .iter()
+                        .map(|e| (e.name.clone(), path.clone(), e.line)),
+                ),
+                3 => items.extend(
+                    definitions
+                        .errors
+                        .iter()
+                        .map(|e| (e.name.clone(), path.clone(), e.line)),
+                ),
+                4 => items.extend(
+                    definitions
+                        .udvts
+                        .iter()
+                        .map(|u| (u.name.clone(), path.clone(), u.line)),
+                ),
+                _ => {}
+            }
+        }
+        if items.is_empty() {
+            println!("No items of that type found, run sonar first");
+            return Ok(());
+        }
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let labels: Vec<String> = items.iter().map(|(label, _, _)| label.clone()).collect();
+        let selection = BatDialoguer::fuzzy_select("Select the item".to_string(), labels)?;
+        let (_, path, line) = &items[selection];
+        CodeEditor::open_file_in_editor(path, Some(*line)).change_context(CommandError)?;
+        Ok(())
+    }
+
     fn execute_package_json(&self) -> CommandResult<()> {
         let prompt_text = "Select the log level:".to_string();
         let log_level_vec = vec![
@@ -520,4 +652,620 @@ impl ToolCommand {
         }
         Ok((to_review_count, started_count, finished_count))
     }
+
+    fn execute_storage_layout_diff(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if !bat_config.project_type.is_evm() {
+            return Err(Report::new(CommandError)
+                .attach_printable("Storage layout diff is only available for EVM projects"));
+        }
+        let evm_metadata = EvmBatMetadata::read_metadata().change_context(CommandError)?;
+        let contract_names: Vec<String> = evm_metadata
+            .contracts
+            .iter()
+            .filter(|c| !c.storage_layout.is_empty())
+            .map(|c| c.name.clone())
+            .collect();
+        if contract_names.is_empty() {
+            println!("No contracts with state variables found, run sonar first");
+            return Ok(());
+        }
+
+        let modes = vec!["Two contracts", "Same contract at two git commits"];
+        let mode = BatDialoguer::select("Compare".to_string(), modes, None)?;
+
+        let (old_label, old_layout, new_label, new_layout) = if mode == 0 {
+            let old_idx = BatDialoguer::fuzzy_select(
+                "Select the current (old) contract".to_string(),
+                contract_names.clone(),
+            )?;
+            let new_idx = BatDialoguer::fuzzy_select(
+                "Select the upgraded (new) contract".to_string(),
+                contract_names.clone(),
+            )?;
+            let layout_of = |name: &str| {
+                evm_metadata
+                    .get_contract_by_name(name)
+                    .map(|c| c.storage_layout.clone())
+                    .unwrap_or_default()
+            };
+            (
+                contract_names[old_idx].clone(),
+                layout_of(&contract_names[old_idx]),
+                contract_names[new_idx].clone(),
+                layout_of(&contract_names[new_idx]),
+            )
+        } else {
+            let contract_idx = BatDialoguer::fuzzy_select(
+                "Select the contract".to_string(),
+                contract_names.clone(),
+            )?;
+            let contract_name = contract_names[contract_idx].clone();
+            let old_rev = BatDialoguer::input("Old commit (hash, tag or branch)".to_string())?;
+            let new_rev = BatDialoguer::input("New commit (hash, tag or branch)".to_string())?;
+
+            // Dependencies in lib/ are usually submodules missing from a bare checkout,
+            // so the current ones are used for both commits
+            let (current_contracts, current_definitions) = EvmSonar::new("..")
+                .scan_sources()
+                .change_context(CommandError)?;
+            let import_resolver = ImportResolver::new("..").change_context(CommandError)?;
+            let layout_at = |rev: &str| -> CommandResult<Vec<StorageSlot>> {
+                let (mut contracts, mut definitions) = evm_sources_at_commit(rev)?;
+                for external in current_contracts.iter().filter(|c| c.external) {
+                    if !contracts.iter().any(|c| c.name == external.name) {
+                        contracts.push(external.clone());
+                    }
+                }
+                definitions.extend(
+                    current_definitions
+                        .iter()
+                        .filter(|d| import_resolver.is_dependency_path(&d.file_path))
+                        .cloned(),
+                );
+                if !contracts.iter().any(|c| c.name == contract_name) {
+                    return Err(Report::new(CommandError).attach_printable(format!(
+                        "Contract {} not found at {}",
+                        contract_name, rev
+                    )));
+                }
+                Ok(StorageLayoutResolver::new(&contracts, &definitions).layout(&contract_name))
+            };
+            (
+                format!("{}@{}", contract_name, old_rev),
+                layout_at(&old_rev)?,
+                format!("{}@{}", contract_name, new_rev),
+                layout_at(&new_rev)?,
+            )
+        };
+
+        let changes = compare_layouts(&old_layout, &new_layout);
+        if changes.is_empty() {
+            println!(
+                "No storage collisions between {} and {}",
+                old_label.bright_green(),
+                new_label.bright_green()
+            );
+            return Ok(());
+        }
+
+        #[derive(Tabled)]
+        struct LayoutRow {
+            #[tabled(rename = "Change")]
+            kind: ColoredString,
+            #[tabled(rename = "Variable")]
+            name: String,
+            #[tabled(rename = "Old")]
+            old: String,
+            #[tabled(rename = "New")]
+            new: String,
+        }
+
+        let describe = |slot: &Option<StorageSlot>| match slot {
+            Some(slot) => format!(
+                "slot {} offset {}: {} ({})",
+                slot.slot, slot.offset, slot.type_name, slot.contract_name
+            ),
+            None => "-".to_string(),
+        };
+        let rows: Vec<LayoutRow> = changes
+            .iter()
+            .map(|change| LayoutRow {
+                kind: match change.kind {
+                    StorageLayoutChangeKind::Removed => "Removed".bright_red(),
+                    StorageLayoutChangeKind::Reordered => "Reordered".bright_red(),
+                    StorageLayoutChangeKind::Retyped => "Retyped".bright_yellow(),
+                },
+                name: change.name.clone(),
+                old: describe(&change.old),
+                new: describe(&change.new),
+            })
+            .collect();
+
+        println!(
+            "Storage layout changes from {} to {}:\n",
+            old_label.bright_green(),
+            new_label.bright_green()
+        );
+        let mut table = Table::new(rows);
+        table.with(Style::sharp());
+        println!("{}", table);
+        Ok(())
+    }
+
+    fn execute_privilege_graph(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if !bat_config.project_type.is_evm() {
+            return Err(Report::new(CommandError)
+                .attach_printable("Privilege graph is only available for EVM projects"));
+        }
+        let evm_metadata = EvmBatMetadata::read_metadata().change_context(CommandError)?;
+        let privileges = &evm_metadata.privileges;
+        if privileges.roles.is_empty() {
+            println!("No roles found, run sonar first");
+            return Ok(());
+        }
+
+        #[derive(Tabled)]
+        struct RoleRow {
+            #[tabled(rename = "Contract")]
+            contract_name: String,
+            #[tabled(rename = "Role")]
+            name: ColoredString,
+            #[tabled(rename = "Admin")]
+            admin: String,
+            #[tabled(rename = "Entry points")]
+            entry_points: String,
+            #[tabled(rename = "Granted by")]
+            granted_by: String,
+            #[tabled(rename = "Revoked by")]
+            revoked_by: String,
+        }
+
+        let lines = |names: &[String]| {
+            if names.is_empty() {
+                "-".to_string()
+            } else {
+                names.join("\n")
+            }
+        };
+        let rows: Vec<RoleRow> = privileges
+            .roles
+            .iter()
+            .map(|role| RoleRow {
+                contract_name: role.contract_name.clone(),
+                name: role.name.bright_green(),
+                admin: role.admin.clone().unwrap_or_else(|| "-".to_string()),
+                entry_points: lines(&role.entry_points),
+                granted_by: lines(&role.granted_by),
+                revoked_by: lines(&role.revoked_by),
+            })
+            .collect();
+        let mut table = Table::new(rows);
+        table.with(Style::sharp());
+        println!("{}", table);
+
+        let bat_file = BatFile::PrivilegeGraph;
+        bat_file
+            .write_content(false, &privileges.to_markdown())
+            .change_context(CommandError)?;
+        bat_file
+            .commit_file(Some("notes: privilege graph updated".to_string()))
+            .change_context(CommandError)?;
+        println!(
+            "Mermaid diagram written to {}",
+            bat_file
+                .get_path(false)
+                .change_context(CommandError)?
+                .bright_green()
+        );
+        Ok(())
+    }
+
+    fn execute_untested_entry_points(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if !bat_config.project_type.is_evm() {
+            return Err(Report::new(CommandError)
+                .attach_printable("Test coverage is only available for EVM projects"));
+        }
+        let evm_metadata = EvmBatMetadata::read_metadata().change_context(CommandError)?;
+
+        #[derive(Tabled)]
+        struct UntestedRow {
+            #[tabled(rename = "Entry point")]
+            name: ColoredString,
+            #[tabled(rename = "Access control")]
+            access_control: String,
+            #[tabled(rename = "Path")]
+            path: String,
+        }
+
+        let rows: Vec<UntestedRow> = evm_metadata
+            .entry_points
+            .iter()
+            .filter(|ep| ep.tests.is_empty())
+            .map(|ep| {
+                let func = evm_metadata.get_function_by_id(&ep.function_metadata_id);
+                let path = func
+                    .and_then(|f| {
+                        let contract = evm_metadata.get_contract_by_name(&f.contract_name)?;
+                        Some(format!("{}:{}", contract.file_path, f.line))
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+                let access_control = if ep.access_control.is_empty() {
+                    "-".to_string()
+                } else {
+                    ep.access_control
+                        .iter()
+                        .map(|ac| format!("{:?}", ac))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                UntestedRow {
+                    name: ep.name.bright_red(),
+                    access_control,
+                    path,
+                }
+            })
+            .collect();
+
+        println!(
+            "{} of {} entry points are not called by any test",
+            rows.len().to_string().bright_red(),
+            evm_metadata.entry_points.len()
+        );
+        if rows.is_empty() {
+            return Ok(());
+        }
+        let mut table = Table::new(rows);
+        table.with(Style::sharp());
+        println!("{}", table);
+        Ok(())
+    }
+
+    fn execute_idl_check(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if bat_config.project_type.is_evm() {
+            return Err(Report::new(CommandError)
+                .attach_printable("IDL cross-check is only available for Solana projects"));
+        }
+        let idl_dir = "../target/idl";
+        let idls = if std::path::Path::new(idl_dir).is_dir() {
+            Idl::load_from_dir(idl_dir).change_context(CommandError)?
+        } else {
+            vec![]
+        };
+        if idls.is_empty() {
+            println!("No IDL found in target/idl, build the program IDL first");
+            return Ok(());
+        }
+        let bat_metadata = BatMetadata::read_metadata().change_context(CommandError)?;
+
+        // Entry point function arguments, without the accounts and program id
+        let instruction_args = |function_id: &str| -> Option<Vec<String>> {
+            let function_metadata = bat_metadata
+                .source_code
+                .get_function_by_id(function_id.to_string())
+                .ok()?;
+            let function_parser = FunctionParser::new_from_metadata(function_metadata).ok()?;
+            let args: Vec<&FunctionParameterParser> = function_parser
+                .parameters
+                .iter()
+                .filter(|param| {
+                    param.parameter_name != "program_id"
+                        && !["Context", "AccountInfo", "AccountView"]
+                            .iter()
+                            .any(|ty| param.parameter_type.contains(ty))
+                })
+                .collect();
+            // Raw instruction data, deserialized in the processor body
+            if args
+                .iter()
+                .any(|param| param.parameter_type.replace(' ', "").contains("&[u8]"))
+            {
+                return None;
+            }
+            Some(
+                args.iter()
+                    .map(|param| param.parameter_name.clone())
+                    .collect(),
+            )
+        };
+
+        let sonar_entry_points: Vec<(&str, String, Option<Vec<String>>, Vec<_>)> = bat_metadata
+            .entry_points
+            .iter()
+            .map(|ep| {
+                let accounts = bat_metadata
+                    .get_context_accounts_metadata_by_struct_source_code_metadata_id(
+                        ep.context_accounts_id.clone(),
+                    )
+                    .map(|ca| ca.context_accounts_info)
+                    .unwrap_or_default();
+                (
+                    ep.program_name.as_str(),
+                    ep.name.clone(),
+                    instruction_args(&ep.entrypoint_function_id),
+                    accounts,
+                )
+            })
+            .collect();
+
+        #[derive(Tabled)]
+        struct DiscrepancyRow {
+            #[tabled(rename = "Instruction")]
+            instruction: String,
+            #[tabled(rename = "Account")]
+            account: String,
+            #[tabled(rename = "Check")]
+            kind: ColoredString,
+            #[tabled(rename = "IDL")]
+            idl: String,
+            #[tabled(rename = "Sonar")]
+            sonar: String,
+        }
+
+        // Only the Anchor context accounts parser extracts the PDA seeds
+        let compare_seeds = bat_config.project_type == ProjectType::Anchor;
+        let single_idl = idls.len() == 1;
+        for idl in &idls {
+            let sonar_instructions: Vec<SonarInstruction> = sonar_entry_points
+                .iter()
+                .filter(|(program_name, ..)| single_idl || idl.matches_program(program_name))
+                .map(|(_, name, args, accounts)| SonarInstruction {
+                    name: name.clone(),
+                    args: args.clone(),
+                    accounts,
+                })
+                .collect();
+            let discrepancies = idl.cross_check(&sonar_instructions, compare_seeds);
+            let missing_instructions = discrepancies
+                .iter()
+                .filter(|d| d.kind == IdlDiscrepancyKind::MissingInstruction)
+                .count();
+
+            println!(
+                "{} ({} IDL): {} instructions, {} missing from sonar, {} discrepancies",
+                idl.program_name.bright_green(),
+                idl.format,
+                idl.instructions.len(),
+                missing_instructions.to_string().bright_red(),
+                (discrepancies.len() - missing_instructions)
+                    .to_string()
+                    .bright_yellow()
+            );
+            if discrepancies.is_empty() {
+                continue;
+            }
+            let rows: Vec<DiscrepancyRow> = discrepancies
+                .into_iter()
+                .map(|d| DiscrepancyRow {
+                    instruction: d.instruction,
+                    account: d.account.unwrap_or_else(|| "-".to_string()),
+                    kind: match d.kind {
+                        IdlDiscrepancyKind::MissingInstruction => d.kind.to_string().bright_red(),
+                        _ => d.kind.to_string().bright_yellow(),
+                    },
+                    idl: d.idl,
+                    sonar: d.sonar,
+                })
+                .collect();
+            let mut table = Table::new(rows);
+            table.with(Style::sharp());
+            println!("{}", table);
+        }
+        Ok(())
+    }
+
+    fn execute_foundry_scaffold(&self) -> CommandResult<()> {
+        let bat_config = BatConfig::get_config().change_context(CommandError)?;
+        if !bat_config.project_type.is_evm() {
+            return Err(Report::new(CommandError)
+                .attach_printable("Foundry scaffolds are only available for EVM projects"));
+        }
+        let evm_metadata = EvmBatMetadata::read_metadata().change_context(CommandError)?;
+        if evm_metadata.entry_points.is_empty() {
+            println!("No entry points found, run sonar first");
+            return Ok(());
+        }
+        let import_resolver = ImportResolver::new("..").change_context(CommandError)?;
+        // Hardhat projects only run forge tests through @nomicfoundation/hardhat-foundry,
+        // which adds a foundry.toml
+        if bat_config.project_type == ProjectType::Hardhat
+            && !import_resolver
+                .get_root_dir()
+                .join("foundry.toml")
+                .is_file()
+        {
+            return Err(Report::new(CommandError).attach_printable(
+                "Foundry scaffolds need a foundry.toml, set up hardhat-foundry for this Hardhat project first",
+            ));
+        }
+        let test_dir = import_resolver.get_test_dir().to_path_buf();
+
+        let modes = vec![
+            "PoC for an entry point",
+            "PoC for a finding candidate",
+            "Invariant test harness",
+        ];
+        let mode = BatDialoguer::select(
+            "Select the Foundry scaffold to generate".to_string(),
+            modes,
+            None,
+        )?;
+        let select_entry_point = |candidates: Vec<&EntryPointMetadata>| -> CommandResult<usize> {
+            let names = candidates
+                .iter()
+                .map(|ep| ep.name.clone())
+                .collect::<Vec<_>>();
+            let selection = BatDialoguer::select(
+                format!("Select the {}", "entry point".bright_green()),
+                names,
+                None,
+            )?;
+            Ok(selection)
+        };
+
+        let (contract_name, test_contract_name, content) = match mode {
+            0 | 1 => {
+                let (ep, finding) = if mode == 0 {
+                    let candidates = evm_metadata.entry_points.iter().collect::<Vec<_>>();
+                    (candidates[select_entry_point(candidates.clone())?], None)
+                } else {
+                    let hits = &evm_metadata.detector_hits;
+                    if hits.is_empty() {
+                        println!("No finding candidates found, run sonar first");
+                        return Ok(());
+                    }
+                    let hit_names = hits
+                        .iter()
+                        .map(|hit| {
+                            format!(
+                                "{:?}: {} ({}:{})",
+                                hit.kind, hit.function_metadata_id, hit.file_path, hit.line
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    let hit = &hits[BatDialoguer::select(
+                        format!("Select the {}", "finding candidate".bright_yellow()),
+                        hit_names,
+                        None,
+                    )?];
+                    // Internal functions are reached through any entry point
+                    let mut candidates = evm_metadata
+                        .entry_points
+                        .iter()
+                        .filter(|ep| ep.function_metadata_id == hit.function_metadata_id)
+                        .collect::<Vec<_>>();
+                    if candidates.is_empty() {
+                        candidates = evm_metadata.entry_points.iter().collect();
+                    }
+                    let ep = if candidates.len() == 1 {
+                        candidates[0]
+                    } else {
+                        candidates[select_entry_point(candidates.clone())?]
+                    };
+                    (ep, Some(hit))
+                };
+                let contract = evm_metadata
+                    .get_contract_by_name(&ep.contract_name)
+                    .ok_or_else(|| {
+                        Report::new(CommandError)
+                            .attach_printable(format!("Contract {} not found", ep.contract_name))
+                    })?;
+                let func = evm_metadata
+                    .get_function_by_id(&ep.function_metadata_id)
+                    .ok_or_else(|| {
+                        Report::new(CommandError).attach_printable(format!(
+                            "Function {} not found",
+                            ep.function_metadata_id
+                        ))
+                    })?;
+                (
+                    contract.name.clone(),
+                    FoundryTestTemplate::poc_contract_name(ep, func, finding),
+                    FoundryTestTemplate::new(&evm_metadata, contract, &test_dir).poc(ep, finding),
+                )
+            }
+            _ => {
+                let mut contract_names = evm_metadata
+                    .entry_points
+                    .iter()
+                    .map(|ep| ep.contract_name.clone())
+                    .collect::<Vec<_>>();
+                contract_names.sort();
+                contract_names.dedup();
+                let contract_name = contract_names[BatDialoguer::select(
+                    format!("Select the {} to fuzz", "contract".bright_green()),
+                    contract_names.clone(),
+                    None,
+                )?]
+                .clone();
+                let contract = evm_metadata
+                    .get_contract_by_name(&contract_name)
+                    .ok_or_else(|| {
+                        Report::new(CommandError)
+                            .attach_printable(format!("Contract {} not found", contract_name))
+                    })?;
+                (
+                    contract_name.clone(),
+                    FoundryTestTemplate::invariant_contract_name(&contract_name),
+                    FoundryTestTemplate::new(&evm_metadata, contract, &test_dir)
+                        .invariant_harness(),
+                )
+            }
+        };
+
+        let test_path = test_dir.join(format!("{}.t.sol", test_contract_name));
+        if test_path.is_file() {
+            let overwrite = BatDialoguer::select_yes_or_no(format!(
+                "{} already exists, overwrite it?",
+                test_path.display()
+            ))?;
+            if !overwrite {
+                return Ok(());
+            }
+        }
+        std::fs::create_dir_all(&test_dir)
+            .and_then(|_| std::fs::write(&test_path, content))
+            .map_err(|e| {
+                Report::new(CommandError).attach_printable(format!(
+                    "Cannot write {}: {}",
+                    test_path.display(),
+                    e
+                ))
+            })?;
+        println!(
+            "{} scaffold for {} written to {}, run it with {}",
+            "Foundry".bright_green(),
+            contract_name,
+            test_path.display().to_string().bright_green(),
+            format!("forge test --match-contract {}", test_contract_name).bright_yellow()
+        );
+        Ok(())
+    }
+}
+
+/// Parse the EVM sources of the audited project as of `rev`, using a temporary worktree.
+fn evm_sources_at_commit(rev: &str) -> CommandResult<(Vec<EvmContract>, Vec<EvmFileDefinitions>)> {
+    let project_prefix =
+        execute_command("git", &["-C", "..", "rev-parse", "--show-prefix"], false)?
+            .trim()
+            .to_string();
+    let worktree_path = std::env::temp_dir().join(format!(
+        "bat-storage-layout-{}-{}",
+        rev.replace('/', "-"),
+        std::process::id()
+    ));
+    let worktree = worktree_path.to_string_lossy().to_string();
+
+    execute_command(
+        "git",
+        &["-C", "..", "worktree", "add", "--detach", &worktree, rev],
+        false,
+    )?;
+    if !worktree_path.is_dir() {
+        return Err(Report::new(CommandError).attach_printable(format!(
+            "Cannot check out {} into a temporary worktree",
+            rev
+        )));
+    }
+
+    let project_root = worktree_path.join(project_prefix);
+    let project_root = project_root.to_string_lossy();
+    let sources = EvmSonar::new(&project_root)
+        .scan_sources()
+        .change_context(CommandError);
+    let import_resolver = ImportResolver::new(&project_root).change_context(CommandError);
+
+    execute_command(
+        "git",
+        &["-C", "..", "worktree", "remove", "--force", &worktree],
+        false,
+    )?;
+    // Only the in-scope definitions, dependencies are taken from the current checkout
+    let (contracts, mut definitions) = sources?;
+    let import_resolver = import_resolver?;
+    definitions.retain(|d| !import_resolver.is_dependency_path(&d.file_path));
+    Ok((contracts, definitions))
 }
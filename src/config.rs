@@ -4,12 +4,14 @@ use figment::{
 };
 use serde::{Deserialize, Serialize};
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::process::Command;
 use std::{error::Error, fmt, fs, str};
 
 use crate::batbelt::bat_dialoguer::BatDialoguer;
 use crate::batbelt::command_line::CodeEditor;
+use crate::batbelt::evm::parser::import_resolver::{ImportResolver, HARDHAT_CONFIG_FILES};
 use crate::batbelt::path::BatFile;
 use crate::batbelt::{bat_dialoguer, BatEnumerator};
 
@@ -162,10 +164,23 @@ pub enum ProjectType {
     Pinocchio,
     VanillaSolana,
     Foundry,
+    Hardhat,
+    /// Bare Solidity sources compiled with solc, without a framework config.
+    Solidity,
     #[default]
     GenericRust,
 }
 
+impl ProjectType {
+    /// true for Solidity projects, which are analyzed with the EVM sonar.
+    pub fn is_evm(&self) -> bool {
+        matches!(
+            self,
+            ProjectType::Foundry | ProjectType::Hardhat | ProjectType::Solidity
+        )
+    }
+}
+
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct BatConfig {
     pub initialized: bool,
@@ -185,6 +200,14 @@ pub struct BatConfig {
     pub project_repository_url: String,
     #[serde(default)]
     pub project_type: ProjectType,
+    /// EVM: implementation followed for calls through an interface, e.g. `IVault = "Vault"`,
+    /// when several in-scope contracts implement it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub interface_implementations: BTreeMap<String, String>,
+    /// EVM: implementation behind a proxy, e.g. `VaultProxy = "Vault"`, when the proxy
+    /// doesn't reference it
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub proxy_implementations: BTreeMap<String, String>,
 }
 
 impl BatConfig {
@@ -204,6 +227,19 @@ impl BatConfig {
                 "Foundry".green()
             );
             ProjectType::Foundry
+        } else if let Some(hardhat_config) = HARDHAT_CONFIG_FILES
+            .iter()
+            .find(|name| Path::new(name).is_file())
+        {
+            println!(
+                "Detected {} project ({} found)",
+                "Hardhat".green(),
+                hardhat_config
+            );
+            ProjectType::Hardhat
+        } else if !Path::new("Cargo.toml").is_file() && Self::has_sol_files(".") {
+            println!("Detected {} project (.sol files found)", "Solidity".green());
+            ProjectType::Solidity
         } else {
             ProjectType::GenericRust
         };
@@ -219,9 +255,14 @@ impl BatConfig {
         let (remote_https_url, owner_name, commit_hash) = Self::detect_remote_info(".")
             .unwrap_or(("".to_string(), "".to_string(), "".to_string()));
 
-        // Foundry projects: scan .sol files instead of Cargo.toml
-        if project_type == ProjectType::Foundry {
-            return Self::create_foundry_config(remote_https_url, owner_name, commit_hash);
+        // EVM projects: scan .sol files instead of Cargo.toml
+        if project_type.is_evm() {
+            return Self::create_evm_config(
+                project_type,
+                remote_https_url,
+                owner_name,
+                commit_hash,
+            );
         }
 
         // Step 1: List root-level directories that contain at least one Cargo.toml
@@ -296,7 +337,8 @@ impl BatConfig {
                 .attach_printable("No programs with Cargo.toml found in selected folders"));
         }
 
-        // Refine project type: if not Anchor, check if any Cargo.toml has pinocchio dependency
+        // Refine project type: if not Anchor, check if any Cargo.toml has a pinocchio or
+        // solana-program dependency
         if project_type == ProjectType::GenericRust {
             let has_pinocchio = cargo_programs_paths.iter().any(|prog_path| {
                 let cargo_toml_path = format!("{}/Cargo.toml", prog_path);
@@ -304,18 +346,31 @@ impl BatConfig {
                     .map(|content| content.contains("pinocchio"))
                     .unwrap_or(false)
             });
+            let has_solana_program = cargo_programs_paths.iter().any(|prog_path| {
+                let cargo_toml_path = format!("{}/Cargo.toml", prog_path);
+                fs::read_to_string(&cargo_toml_path)
+                    .map(|content| content.contains("solana-program"))
+                    .unwrap_or(false)
+            });
             if has_pinocchio {
                 println!(
                     "Detected {} project (pinocchio dependency found)",
                     "Pinocchio".green()
                 );
                 project_type = ProjectType::Pinocchio;
+            } else if has_solana_program {
+                println!(
+                    "Detected {} project (solana-program dependency found)",
+                    "native Solana".green()
+                );
+                project_type = ProjectType::VanillaSolana;
             } else {
                 println!(
-                    "{} No {} or {} dependency detected.",
+                    "{} No {}, {} or {} dependency detected.",
                     "Warning:".yellow(),
                     "Anchor.toml".green(),
                     "pinocchio".green(),
+                    "solana-program".green(),
                 );
                 println!(
                     "bat-cli will run in {} mode (no entry points or context accounts).",
@@ -480,6 +535,8 @@ impl BatConfig {
             program_lib_path: normalized_program_lib_path,
             program_lib_paths: normalized_program_lib_paths,
             project_type,
+            interface_implementations: BTreeMap::new(),
+            proxy_implementations: BTreeMap::new(),
         };
         bat_config.save().change_context(BatConfigError)?;
         Ok(bat_config)
@@ -605,24 +662,11 @@ impl BatConfig {
             .change_context(BatConfigError)
     }
 
-    /// Create BatConfig for a Foundry/Solidity project.
-    fn create_foundry_config(
-        remote_https_url: String,
-        owner_name: String,
-        commit_hash: String,
-    ) -> Result<BatConfig, BatConfigError> {
-        // Detect src directory from foundry.toml
-        let foundry_content = fs::read_to_string("foundry.toml").unwrap_or_default();
-        let src_dir = foundry_content
-            .lines()
-            .find(|l| l.trim().starts_with("src"))
-            .and_then(|l| l.split('=').nth(1))
-            .map(|v| v.trim().trim_matches('"').trim_matches('\'').to_string())
-            .unwrap_or_else(|| "src".to_string());
-
-        // Verify src directory exists and has .sol files
-        let has_sol_files = WalkDir::new(&src_dir)
+    /// true if `dir` contains .sol files outside tests, scripts and dependencies.
+    fn has_sol_files(dir: &str) -> bool {
+        WalkDir::new(dir)
             .into_iter()
+            .filter_entry(|e| e.file_name() != "node_modules" && e.file_name() != "lib")
             .filter_map(|e| e.ok())
             .any(|e| {
                 e.path()
@@ -631,16 +675,38 @@ impl BatConfig {
                     .unwrap_or(false)
                     && !e.path().to_str().unwrap_or("").contains("test")
                     && !e.path().to_str().unwrap_or("").contains("script")
-            });
+            })
+    }
+
+    /// Create BatConfig for a Foundry, Hardhat or plain solc project.
+    fn create_evm_config(
+        project_type: ProjectType,
+        remote_https_url: String,
+        owner_name: String,
+        commit_hash: String,
+    ) -> Result<BatConfig, BatConfigError> {
+        // Detect the src directory the same way the sonar does (foundry.toml, hardhat config, contracts/)
+        let import_resolver = ImportResolver::new(".").change_context(BatConfigError)?;
+        let src_dir = import_resolver
+            .get_src_dir()
+            .to_string_lossy()
+            .trim_start_matches('.')
+            .trim_start_matches('/')
+            .to_string();
 
-        if !has_sol_files {
+        // Verify src directory exists and has .sol files
+        if !Self::has_sol_files(&format!("./{}", src_dir)) {
             return Err(Report::new(BatConfigError)
                 .attach_printable(format!("No .sol files found in {}/", src_dir)));
         }
 
-        // For Foundry, program_lib_path points to the src directory.
+        // For EVM projects, program_lib_path points to the src directory.
         // Sonar will scan all .sol files; contract selection happens post-sonar.
-        let src_path = format!("../{}", src_dir.trim_start_matches("./"));
+        let src_path = if src_dir.is_empty() {
+            "..".to_string()
+        } else {
+            format!("../{}", src_dir)
+        };
         let program_name = "solidity-contracts".to_string();
         let project_name = "bat-audit".to_string();
 
@@ -737,7 +803,9 @@ impl BatConfig {
             project_repository_url,
             program_lib_path: src_path.clone(),
             program_lib_paths: vec![src_path],
-            project_type: ProjectType::Foundry,
+            project_type,
+            interface_implementations: BTreeMap::new(),
+            proxy_implementations: BTreeMap::new(),
         };
         bat_config.save().change_context(BatConfigError)?;
         Ok(bat_config)
@@ -142,9 +142,20 @@ impl BatCommands {
             //     command.check_correct_branch(),
             // ),
         };
+        if check_branch {
+            GitAction::CheckCorrectBranch
+                .execute_action()
+                .change_context(CommandError)?;
+        }
+
+        let bat_config = crate::config::BatConfig::get_config().change_context(CommandError)?;
+        // Migration commits, so it only runs on the auditor branch
+        if bat_config.project_type.is_evm() && check_branch {
+            SonarCommand::migrate_foundry_metadata()?;
+        }
+
         if check_metadata {
-            let bat_config = crate::config::BatConfig::get_config().change_context(CommandError)?;
-            if bat_config.project_type == crate::config::ProjectType::Foundry {
+            if bat_config.project_type.is_evm() {
                 // Foundry uses EvmBatMetadata, not BatMetadata (SVM)
                 crate::batbelt::evm::metadata::bat_metadata::EvmBatMetadata::read_metadata()
                     .change_context(CommandError)?;
@@ -155,12 +166,6 @@ impl BatCommands {
                     .change_context(CommandError)?;
             }
         }
-
-        if check_branch {
-            GitAction::CheckCorrectBranch
-                .execute_action()
-                .change_context(CommandError)?;
-        }
         Ok(())
     }
 
@@ -173,11 +178,12 @@ impl BatCommands {
         BatCommands::get_type_vec()
             .into_iter()
             .filter_map(|command| match command {
-                // Anchor, Pinocchio, and Foundry commands
+                // Anchor, Pinocchio, native Solana and EVM commands
                 BatCommands::CodeOverhaul(_)
                     if *project_type == ProjectType::Anchor
                         || *project_type == ProjectType::Pinocchio
-                        || *project_type == ProjectType::Foundry =>
+                        || *project_type == ProjectType::VanillaSolana
+                        || project_type.is_evm() =>
                 {
                     Some(CodeOverhaulCommand::get_bat_package_json_commands(
                         command.to_string().to_kebab_case(),